@@ -52,6 +52,12 @@ fn benchmark_files_without_partitions(c: &mut Criterion) {
                         idle_timeout_secs: None,
                         encoding: sinks::file::Encoding::Text.into(),
                         compression: sinks::file::Compression::None,
+                        max_file_size: None,
+                        rotate_interval_secs: None,
+                        rotation_suffix: Default::default(),
+                        max_files: None,
+                        fsync: Default::default(),
+                        fsync_interval_secs: None,
                     },
                 );
 
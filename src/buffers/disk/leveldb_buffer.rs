@@ -72,6 +72,13 @@ impl Clone for Writer {
     }
 }
 
+impl Writer {
+    /// The total size of the events stored in the buffer, in bytes.
+    pub fn current_size(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.current_size)
+    }
+}
+
 impl Sink for Writer {
     type SinkItem = Event;
     type SinkError = ();
@@ -41,6 +41,13 @@ pub struct Writer {
     inner: leveldb_buffer::Writer,
 }
 
+impl Writer {
+    /// The total size of the events stored in the buffer, in bytes.
+    pub fn current_size(&self) -> std::sync::Arc<std::sync::atomic::AtomicUsize> {
+        self.inner.current_size()
+    }
+}
+
 impl Sink for Writer {
     type SinkItem = Event;
     type SinkError = ();
@@ -1,5 +1,5 @@
 use crate::Event;
-use futures01::{sync::mpsc, task::AtomicTask, AsyncSink, Poll, Sink, StartSend, Stream};
+use futures01::{sync::mpsc, task::AtomicTask, Async, AsyncSink, Poll, Sink, StartSend, Stream};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::{
@@ -51,16 +51,17 @@ impl Default for WhenFull {
 }
 
 pub enum BufferInputCloner {
-    Memory(mpsc::Sender<Event>, WhenFull),
+    Memory(mpsc::Sender<Event>, WhenFull, Option<Arc<BufferUsage>>),
     #[cfg(feature = "leveldb")]
-    Disk(disk::Writer, WhenFull),
+    Disk(disk::Writer, WhenFull, Option<Arc<BufferUsage>>),
 }
 
 impl BufferInputCloner {
     pub fn get(&self) -> Box<dyn Sink<SinkItem = Event, SinkError = ()> + Send> {
         match self {
-            BufferInputCloner::Memory(tx, when_full) => {
+            BufferInputCloner::Memory(tx, when_full, usage) => {
                 let inner = tx.clone().sink_map_err(|e| error!("sender error: {:?}", e));
+                let inner = CountingSink::new(inner, usage.clone());
                 if when_full == &WhenFull::DropNewest {
                     Box::new(DropWhenFull { inner })
                 } else {
@@ -69,19 +70,110 @@ impl BufferInputCloner {
             }
 
             #[cfg(feature = "leveldb")]
-            BufferInputCloner::Disk(writer, when_full) => {
+            BufferInputCloner::Disk(writer, when_full, usage) => {
+                let inner = CountingSink::new(writer.clone(), usage.clone());
                 if when_full == &WhenFull::DropNewest {
-                    Box::new(DropWhenFull {
-                        inner: writer.clone(),
-                    })
+                    Box::new(DropWhenFull { inner })
                 } else {
-                    Box::new(writer.clone())
+                    Box::new(inner)
                 }
             }
         }
     }
 }
 
+/// Tracks the number of events held in a buffer and, for the disk
+/// buffers, their total size.
+#[derive(Debug, Default)]
+pub struct BufferUsage {
+    events: AtomicUsize,
+    byte_size: Option<Arc<AtomicUsize>>,
+}
+
+impl BufferUsage {
+    fn with_byte_size(byte_size: Arc<AtomicUsize>) -> Self {
+        Self {
+            events: AtomicUsize::new(0),
+            byte_size: Some(byte_size),
+        }
+    }
+
+    fn decrement_events(&self) {
+        // Events buffered on disk before a restart aren't counted, so don't
+        // go below zero.
+        let mut events = self.events.load(Ordering::Relaxed);
+        while events > 0 {
+            match self.events.compare_exchange_weak(
+                events,
+                events - 1,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(actual) => events = actual,
+            }
+        }
+    }
+
+    pub fn events(&self) -> usize {
+        self.events.load(Ordering::Relaxed)
+    }
+
+    pub fn byte_size(&self) -> Option<usize> {
+        self.byte_size
+            .as_ref()
+            .map(|byte_size| byte_size.load(Ordering::Relaxed))
+    }
+}
+
+/// Counts the events accepted into a buffer.
+struct CountingSink<S> {
+    inner: S,
+    usage: Option<Arc<BufferUsage>>,
+}
+
+impl<S> CountingSink<S> {
+    fn new(inner: S, usage: Option<Arc<BufferUsage>>) -> Self {
+        Self { inner, usage }
+    }
+}
+
+impl<S: Sink> Sink for CountingSink<S> {
+    type SinkItem = S::SinkItem;
+    type SinkError = S::SinkError;
+
+    fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
+        let result = self.inner.start_send(item);
+        if let (Ok(AsyncSink::Ready), Some(usage)) = (&result, &self.usage) {
+            usage.events.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        self.inner.poll_complete()
+    }
+}
+
+/// Counts the events taken out of a buffer.
+struct CountingStream<S> {
+    inner: S,
+    usage: Arc<BufferUsage>,
+}
+
+impl<S: Stream> Stream for CountingStream<S> {
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let result = self.inner.poll();
+        if let Ok(Async::Ready(Some(_))) = &result {
+            self.usage.decrement_events();
+        }
+        result
+    }
+}
+
 impl BufferConfig {
     #[inline]
     const fn memory_max_events() -> usize {
@@ -98,6 +190,7 @@ impl BufferConfig {
             BufferInputCloner,
             Box<dyn Stream<Item = Event, Error = ()> + Send>,
             Acker,
+            Arc<BufferUsage>,
         ),
         String,
     > {
@@ -107,9 +200,13 @@ impl BufferConfig {
                 when_full,
             } => {
                 let (tx, rx) = mpsc::channel(*max_events);
-                let tx = BufferInputCloner::Memory(tx, *when_full);
-                let rx = Box::new(rx);
-                Ok((tx, rx, Acker::Null))
+                let usage = Arc::new(BufferUsage::default());
+                let tx = BufferInputCloner::Memory(tx, *when_full, Some(Arc::clone(&usage)));
+                let rx = Box::new(CountingStream {
+                    inner: rx,
+                    usage: Arc::clone(&usage),
+                });
+                Ok((tx, rx, Acker::Null, usage))
             }
 
             #[cfg(feature = "leveldb")]
@@ -124,9 +221,13 @@ impl BufferConfig {
 
                 let (tx, rx, acker) = disk::open(&data_dir, buffer_dir.as_ref(), *max_size)
                     .map_err(|err| err.to_string())?;
-                let tx = BufferInputCloner::Disk(tx, *when_full);
-                let rx = Box::new(rx);
-                Ok((tx, rx, acker))
+                let usage = Arc::new(BufferUsage::with_byte_size(tx.current_size()));
+                let tx = BufferInputCloner::Disk(tx, *when_full, Some(Arc::clone(&usage)));
+                let rx = Box::new(CountingStream {
+                    inner: rx,
+                    usage: Arc::clone(&usage),
+                });
+                Ok((tx, rx, acker, usage))
             }
         }
     }
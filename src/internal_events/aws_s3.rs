@@ -0,0 +1,103 @@
+use super::InternalEvent;
+use metrics::counter;
+use rusoto_core::RusotoError;
+use rusoto_sqs::{DeleteMessageError, ReceiveMessageError};
+
+#[derive(Debug)]
+pub struct AwsS3EventReceived {
+    pub byte_size: usize,
+}
+
+impl InternalEvent for AwsS3EventReceived {
+    fn emit_logs(&self) {
+        trace!(message = "Received one event.", rate_limit_secs = 10);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", 1,
+            "component_kind" => "source",
+            "component_type" => "aws_s3",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "source",
+            "component_type" => "aws_s3",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AwsS3SqsMessageReceiveFailed<'a> {
+    pub error: RusotoError<ReceiveMessageError>,
+    pub queue_url: &'a str,
+}
+
+impl InternalEvent for AwsS3SqsMessageReceiveFailed<'_> {
+    fn emit_logs(&self) {
+        error!(
+            message = "Failed to fetch SQS messages.",
+            error = %self.error,
+            queue_url = %self.queue_url,
+            rate_limit_secs = 10,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "sqs_message_receive_failed", 1,
+            "component_kind" => "source",
+            "component_type" => "aws_s3",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AwsS3SqsMessageProcessingFailed<'a, E> {
+    pub error: E,
+    pub message_id: &'a str,
+}
+
+impl<E: std::fmt::Display> InternalEvent for AwsS3SqsMessageProcessingFailed<'_, E> {
+    fn emit_logs(&self) {
+        error!(
+            message = "Failed to process SQS message; it will be retried after the visibility timeout.",
+            message_id = %self.message_id,
+            error = %self.error,
+            rate_limit_secs = 10,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "processing_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "aws_s3",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AwsS3SqsMessageDeleteFailed<'a> {
+    pub error: RusotoError<DeleteMessageError>,
+    pub message_id: &'a str,
+}
+
+impl InternalEvent for AwsS3SqsMessageDeleteFailed<'_> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Failed to delete SQS message; its objects may be processed again.",
+            message_id = %self.message_id,
+            error = %self.error,
+            rate_limit_secs = 10,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "sqs_message_delete_failed", 1,
+            "component_kind" => "source",
+            "component_type" => "aws_s3",
+        );
+    }
+}
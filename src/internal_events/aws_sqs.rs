@@ -0,0 +1,87 @@
+use super::InternalEvent;
+use metrics::counter;
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Debug)]
+pub struct AwsSqsEventSent {
+    pub byte_size: usize,
+}
+
+impl InternalEvent for AwsSqsEventSent {
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", 1,
+            "component_kind" => "sink",
+            "component_type" => "aws_sqs",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "sink",
+            "component_type" => "aws_sqs",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AwsSqsEntriesFailed<'a> {
+    pub count: usize,
+    pub code: &'a str,
+    pub message: Option<&'a str>,
+    pub retried: bool,
+}
+
+impl InternalEvent for AwsSqsEntriesFailed<'_> {
+    fn emit_logs(&self) {
+        if self.retried {
+            warn!(
+                message = "Batch entries failed; retrying them.",
+                count = %self.count,
+                code = %self.code,
+                error = ?self.message,
+                rate_limit_secs = 10,
+            );
+        } else {
+            error!(
+                message = "Batch entries failed; dropping them.",
+                count = %self.count,
+                code = %self.code,
+                error = ?self.message,
+                rate_limit_secs = 10,
+            );
+        }
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "processing_errors", self.count as u64,
+            "component_kind" => "sink",
+            "component_type" => "aws_sqs",
+            "error_type" => "entry_failed",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct AwsSqsTemplateMissingKeys {
+    pub field: &'static str,
+    pub keys: Vec<Atom>,
+}
+
+impl InternalEvent for AwsSqsTemplateMissingKeys {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Keys do not exist on the event; dropping event.",
+            field = %self.field,
+            missing_keys = ?self.keys,
+            rate_limit_secs = 30,
+        )
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "missing_keys", 1,
+            "component_kind" => "sink",
+            "component_type" => "aws_sqs",
+        );
+    }
+}
@@ -49,3 +49,123 @@ impl InternalEvent for ElasticSearchMissingKeys {
         );
     }
 }
+
+#[derive(Debug)]
+pub struct ElasticSearchInvalidBulkAction<'a> {
+    pub action: &'a str,
+}
+
+impl InternalEvent for ElasticSearchInvalidBulkAction<'_> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Invalid bulk action; dropping event.",
+            action = %self.action,
+            rate_limit_secs = 30,
+        )
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "processing_errors", 1,
+            "component_kind" => "sink",
+            "component_type" => "elasticsearch",
+            "error_type" => "invalid_bulk_action",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct ElasticSearchBulkItemsFailed<'a> {
+    pub count: usize,
+    pub error_type: &'a str,
+    pub reason: &'a str,
+    pub retried: bool,
+    pub routed: bool,
+}
+
+impl InternalEvent for ElasticSearchBulkItemsFailed<'_> {
+    fn emit_logs(&self) {
+        if self.retried {
+            warn!(
+                message = "Bulk items failed; retrying them.",
+                count = %self.count,
+                error_type = %self.error_type,
+                reason = %self.reason,
+                rate_limit_secs = 10,
+            );
+        } else if self.routed {
+            warn!(
+                message = "Bulk items failed; sending them to the failure index.",
+                count = %self.count,
+                error_type = %self.error_type,
+                reason = %self.reason,
+                rate_limit_secs = 10,
+            );
+        } else {
+            error!(
+                message = "Bulk items failed; dropping them.",
+                count = %self.count,
+                error_type = %self.error_type,
+                reason = %self.reason,
+                rate_limit_secs = 10,
+            );
+        }
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "processing_errors", self.count as u64,
+            "component_kind" => "sink",
+            "component_type" => "elasticsearch",
+            "error_type" => self.error_type.to_owned(),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct ElasticSearchFailureIndexFailed {
+    pub count: usize,
+}
+
+impl InternalEvent for ElasticSearchFailureIndexFailed {
+    fn emit_logs(&self) {
+        error!(
+            message = "Failed to send bulk items to the failure index; dropping them.",
+            count = %self.count,
+            rate_limit_secs = 10,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "processing_errors", self.count as u64,
+            "component_kind" => "sink",
+            "component_type" => "elasticsearch",
+            "error_type" => "failure_index_failed",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct ElasticSearchInvalidResponse {
+    pub error: serde_json::Error,
+}
+
+impl InternalEvent for ElasticSearchInvalidResponse {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Could not parse the bulk response; failed items can't be retried.",
+            error = %self.error,
+            rate_limit_secs = 30,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "processing_errors", 1,
+            "component_kind" => "sink",
+            "component_type" => "elasticsearch",
+            "error_type" => "invalid_response",
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use super::InternalEvent;
-use metrics::counter;
+use metrics::{counter, gauge};
 
 #[derive(Debug)]
 pub struct KafkaEventReceived {
@@ -73,3 +73,22 @@ impl InternalEvent for KafkaKeyExtractionFailed<'_> {
         error!(message = "Failed to extract key.", key_field = %self.key_field);
     }
 }
+
+#[derive(Debug)]
+pub struct KafkaConsumerLagUpdated<'a> {
+    pub topic_id: &'a str,
+    pub partition_id: i32,
+    pub lag: i64,
+}
+
+impl InternalEvent for KafkaConsumerLagUpdated<'_> {
+    fn emit_metrics(&self) {
+        gauge!(
+            "consumer_lag", self.lag as f64,
+            "component_kind" => "source",
+            "component_type" => "kafka",
+            "topic_id" => self.topic_id.to_owned(),
+            "partition_id" => self.partition_id.to_string(),
+        );
+    }
+}
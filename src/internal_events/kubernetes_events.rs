@@ -0,0 +1,58 @@
+use super::InternalEvent;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct KubernetesEventsEventReceived;
+
+impl InternalEvent for KubernetesEventsEventReceived {
+    fn emit_logs(&self) {
+        trace!(message = "Received one event.", rate_limit_secs = 10);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", 1,
+            "component_kind" => "source",
+            "component_type" => "kubernetes_events",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct KubernetesEventsCheckpointFailed {
+    pub error: std::io::Error,
+}
+
+impl InternalEvent for KubernetesEventsCheckpointFailed {
+    fn emit_logs(&self) {
+        error!(message = "Unable to write checkpoint.", error = %self.error);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "checkpoint_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "kubernetes_events",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct KubernetesEventsEventSkipped;
+
+impl InternalEvent for KubernetesEventsEventSkipped {
+    fn emit_logs(&self) {
+        trace!(
+            message = "Skipped an already sent event.",
+            rate_limit_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_skipped", 1,
+            "component_kind" => "source",
+            "component_type" => "kubernetes_events",
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use super::InternalEvent;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct LokiOutOfOrderEventDropped;
+
+impl InternalEvent for LokiOutOfOrderEventDropped {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Received out-of-order event; dropping event.",
+            rate_limit_secs = 30,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_discarded", 1,
+            "component_kind" => "sink",
+            "component_type" => "loki",
+            "reason" => "out_of_order",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct LokiOutOfOrderEventRewritten;
+
+impl InternalEvent for LokiOutOfOrderEventRewritten {
+    fn emit_logs(&self) {
+        debug!(
+            message = "Received out-of-order event; rewriting timestamp.",
+            rate_limit_secs = 30,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "rewritten_timestamp_events", 1,
+            "component_kind" => "sink",
+            "component_type" => "loki",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct LokiLabelCardinalityExceeded<'a> {
+    pub label: &'a str,
+    pub limit: usize,
+}
+
+impl InternalEvent for LokiLabelCardinalityExceeded<'_> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Label exceeded its cardinality limit; replacing value with overflow marker.",
+            label = %self.label,
+            limit = %self.limit,
+            rate_limit_secs = 30,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "label_value_limit_exceeded", 1,
+            "component_kind" => "sink",
+            "component_type" => "loki",
+        );
+    }
+}
@@ -8,7 +8,13 @@ mod apache_metrics;
 #[cfg(feature = "api")]
 mod api;
 mod auto_concurrency;
+#[cfg(feature = "sources-aws_kinesis_firehose")]
+mod aws_kinesis_firehose;
 mod aws_kinesis_streams;
+#[cfg(feature = "sources-aws_s3")]
+mod aws_s3;
+#[cfg(feature = "sinks-aws_sqs")]
+mod aws_sqs;
 mod blackhole;
 #[cfg(feature = "transforms-coercer")]
 mod coercer;
@@ -33,16 +39,26 @@ mod journald;
 mod json_parser;
 #[cfg(feature = "sources-kafka")]
 mod kafka;
+#[cfg(feature = "sources-kubernetes-events")]
+mod kubernetes_events;
 #[cfg(feature = "sources-kubernetes-logs")]
 mod kubernetes_logs;
 #[cfg(feature = "transforms-log_to_metric")]
 mod log_to_metric;
 mod logplex;
+#[cfg(feature = "sinks-loki")]
+mod loki;
 #[cfg(feature = "transforms-lua")]
 mod lua;
+#[cfg(any(feature = "sources-mqtt", feature = "sinks-mqtt"))]
+mod mqtt;
+#[cfg(any(feature = "sources-nats", feature = "sinks-nats"))]
+mod nats;
 mod process;
 #[cfg(feature = "sources-prometheus")]
 mod prometheus;
+#[cfg(any(feature = "sources-redis", feature = "sinks-redis"))]
+mod redis;
 #[cfg(feature = "transforms-reduce")]
 mod reduce;
 #[cfg(feature = "transforms-regex_parser")]
@@ -77,6 +93,7 @@ mod tag_cardinality_limit;
 mod tcp;
 #[cfg(feature = "transforms-tokenizer")]
 mod tokenizer;
+mod topology;
 mod udp;
 mod unix;
 mod vector;
@@ -93,7 +110,13 @@ pub use self::apache_metrics::*;
 #[cfg(feature = "api")]
 pub use self::api::*;
 pub use self::auto_concurrency::*;
+#[cfg(feature = "sources-aws_kinesis_firehose")]
+pub use self::aws_kinesis_firehose::*;
 pub use self::aws_kinesis_streams::*;
+#[cfg(feature = "sources-aws_s3")]
+pub use self::aws_s3::*;
+#[cfg(feature = "sinks-aws_sqs")]
+pub use self::aws_sqs::*;
 pub use self::blackhole::*;
 #[cfg(feature = "transforms-coercer")]
 pub(crate) use self::coercer::*;
@@ -120,16 +143,26 @@ pub(crate) use self::journald::*;
 pub(crate) use self::json_parser::*;
 #[cfg(feature = "sources-kafka")]
 pub use self::kafka::*;
+#[cfg(feature = "sources-kubernetes-events")]
+pub use self::kubernetes_events::*;
 #[cfg(feature = "sources-kubernetes-logs")]
 pub use self::kubernetes_logs::*;
 #[cfg(feature = "transforms-log_to_metric")]
 pub(crate) use self::log_to_metric::*;
 pub use self::logplex::*;
+#[cfg(feature = "sinks-loki")]
+pub use self::loki::*;
 #[cfg(feature = "transforms-lua")]
 pub use self::lua::*;
+#[cfg(any(feature = "sources-mqtt", feature = "sinks-mqtt"))]
+pub use self::mqtt::*;
+#[cfg(any(feature = "sources-nats", feature = "sinks-nats"))]
+pub use self::nats::*;
 pub use self::process::*;
 #[cfg(feature = "sources-prometheus")]
 pub use self::prometheus::*;
+#[cfg(any(feature = "sources-redis", feature = "sinks-redis"))]
+pub use self::redis::*;
 #[cfg(feature = "transforms-reduce")]
 pub(crate) use self::reduce::*;
 #[cfg(feature = "transforms-regex_parser")]
@@ -160,6 +193,7 @@ pub(crate) use self::tag_cardinality_limit::*;
 pub use self::tcp::*;
 #[cfg(feature = "transforms-tokenizer")]
 pub(crate) use self::tokenizer::*;
+pub use self::topology::*;
 pub use self::udp::*;
 pub use self::unix::*;
 pub use self::vector::*;
@@ -0,0 +1,140 @@
+use super::InternalEvent;
+use metrics::counter;
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Debug)]
+pub struct MqttEventReceived<'a> {
+    pub byte_size: usize,
+    pub topic: &'a str,
+}
+
+impl InternalEvent for MqttEventReceived<'_> {
+    fn emit_logs(&self) {
+        trace!(message = "Received one event.", topic = %self.topic, rate_limit_secs = 10);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", 1,
+            "component_kind" => "source",
+            "component_type" => "mqtt",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "source",
+            "component_type" => "mqtt",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct MqttEventDecodeFailed {
+    pub error: serde_json::Error,
+}
+
+impl InternalEvent for MqttEventDecodeFailed {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Failed to decode message payload; dropping message.",
+            error = %self.error,
+            rate_limit_secs = 30,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "processing_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "mqtt",
+            "error_type" => "decode_failed",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct MqttConnectionError {
+    pub error: rumqttc::ConnectionError,
+}
+
+impl InternalEvent for MqttConnectionError {
+    fn emit_logs(&self) {
+        error!(
+            message = "MQTT connection error; reconnecting.",
+            error = %self.error,
+            rate_limit_secs = 10,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "connection_errors", 1,
+            "component_type" => "mqtt",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct MqttEventSendSuccess {
+    pub byte_size: usize,
+}
+
+impl InternalEvent for MqttEventSendSuccess {
+    fn emit_logs(&self) {
+        trace!(message = "Processed one event.", rate_limit_secs = 10);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", 1,
+            "component_kind" => "sink",
+            "component_type" => "mqtt",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "sink",
+            "component_type" => "mqtt",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct MqttEventSendFail {
+    pub error: rumqttc::ClientError,
+}
+
+impl InternalEvent for MqttEventSendFail {
+    fn emit_logs(&self) {
+        error!(message = "Failed to send message.", error = %self.error);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "send_errors", 1,
+            "component_kind" => "sink",
+            "component_type" => "mqtt",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct MqttMissingKeys<'a> {
+    pub keys: &'a [Atom],
+}
+
+impl InternalEvent for MqttMissingKeys<'_> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Keys do not exist on the event; dropping event.",
+            missing_keys = ?self.keys,
+            rate_limit_secs = 30,
+        )
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "missing_keys", 1,
+            "component_kind" => "sink",
+            "component_type" => "mqtt",
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use super::InternalEvent;
+use metrics::counter;
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Debug)]
+pub struct NatsEventReceived {
+    pub byte_size: usize,
+}
+
+impl InternalEvent for NatsEventReceived {
+    fn emit_logs(&self) {
+        trace!(message = "Received one event.", rate_limit_secs = 10);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", 1,
+            "component_kind" => "source",
+            "component_type" => "nats",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "source",
+            "component_type" => "nats",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct NatsEventDecodeFailed {
+    pub error: serde_json::Error,
+}
+
+impl InternalEvent for NatsEventDecodeFailed {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Failed to decode message; discarding event.",
+            error = %self.error,
+            rate_limit_secs = 10,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "decode_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "nats",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct NatsEventSendSuccess {
+    pub byte_size: usize,
+}
+
+impl InternalEvent for NatsEventSendSuccess {
+    fn emit_logs(&self) {
+        trace!(message = "Processed one event.", rate_limit_secs = 10);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", 1,
+            "component_kind" => "sink",
+            "component_type" => "nats",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "sink",
+            "component_type" => "nats",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct NatsEventSendFail {
+    pub error: std::io::Error,
+}
+
+impl InternalEvent for NatsEventSendFail {
+    fn emit_logs(&self) {
+        error!(message = "Failed to send message; dropping event.", error = %self.error, rate_limit_secs = 10);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "send_errors", 1,
+            "component_kind" => "sink",
+            "component_type" => "nats",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct NatsMissingKeys<'a> {
+    pub keys: &'a [Atom],
+}
+
+impl InternalEvent for NatsMissingKeys<'_> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Keys do not exist on the event; dropping event.",
+            missing_keys = ?self.keys,
+            rate_limit_secs = 30,
+        )
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "missing_keys", 1,
+            "component_kind" => "sink",
+            "component_type" => "nats",
+        );
+    }
+}
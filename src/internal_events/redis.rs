@@ -0,0 +1,93 @@
+use super::InternalEvent;
+use metrics::counter;
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Debug)]
+pub struct RedisEventReceived {
+    pub byte_size: usize,
+}
+
+impl InternalEvent for RedisEventReceived {
+    fn emit_logs(&self) {
+        trace!(message = "Received one event.", rate_limit_secs = 10);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", 1,
+            "component_kind" => "source",
+            "component_type" => "redis",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "source",
+            "component_type" => "redis",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct RedisReceiveEventFailed {
+    pub error: redis::RedisError,
+}
+
+impl InternalEvent for RedisReceiveEventFailed {
+    fn emit_logs(&self) {
+        error!(
+            message = "Failed to read message.",
+            error = %self.error,
+            rate_limit_secs = 30,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "receive_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "redis",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct RedisEventSent {
+    pub byte_size: usize,
+}
+
+impl InternalEvent for RedisEventSent {
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", 1,
+            "component_kind" => "sink",
+            "component_type" => "redis",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "sink",
+            "component_type" => "redis",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct RedisMissingKeys<'a> {
+    pub keys: &'a [Atom],
+}
+
+impl InternalEvent for RedisMissingKeys<'_> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Keys do not exist on the event; dropping event.",
+            missing_keys = ?self.keys,
+            rate_limit_secs = 30,
+        )
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "missing_keys", 1,
+            "component_kind" => "sink",
+            "component_type" => "redis",
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use super::InternalEvent;
+use metrics::{counter, gauge};
+
+#[derive(Debug)]
+pub struct EventIn;
+
+impl InternalEvent for EventIn {
+    fn emit_metrics(&self) {
+        counter!("events_in", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct EventOut;
+
+impl InternalEvent for EventOut {
+    fn emit_metrics(&self) {
+        counter!("events_out", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct BufferUsageUpdated {
+    pub events: usize,
+    pub byte_size: Option<usize>,
+}
+
+impl InternalEvent for BufferUsageUpdated {
+    fn emit_metrics(&self) {
+        gauge!("buffer_events", self.events as f64);
+        if let Some(byte_size) = self.byte_size {
+            gauge!("buffer_byte_size", byte_size as f64);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ComponentFailed;
+
+impl InternalEvent for ComponentFailed {
+    fn emit_logs(&self) {
+        error!("An error occurred that vector couldn't handle.");
+    }
+
+    fn emit_metrics(&self) {
+        counter!("processing_errors", 1, "error_type" => "component_failed");
+    }
+}
@@ -64,3 +64,47 @@ impl InternalEvent for VectorProtoDecodeError {
         );
     }
 }
+
+#[derive(Debug)]
+pub struct VectorBatchSent {
+    pub events: usize,
+    pub byte_size: usize,
+}
+
+impl InternalEvent for VectorBatchSent {
+    fn emit_logs(&self) {
+        trace!(message = "Sending batch.", events = %self.events, byte_size = %self.byte_size);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", self.events as u64,
+            "component_kind" => "sink",
+            "component_type" => "vector",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "sink",
+            "component_type" => "vector",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct VectorBatchDecodeError<'a> {
+    pub error: &'a dyn std::error::Error,
+}
+
+impl InternalEvent for VectorBatchDecodeError<'_> {
+    fn emit_logs(&self) {
+        error!(message = "Failed to decode batch.", error = %self.error, rate_limit_secs = 10);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "protobuf_decode_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "vector",
+        );
+    }
+}
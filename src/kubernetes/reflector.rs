@@ -61,6 +61,14 @@ where
             pause_between_requests,
         }
     }
+
+    /// Resume watching from the passed resource version instead of
+    /// fetching the whole state.
+    /// If the resource version is too old, the usual desync handling
+    /// applies.
+    pub fn resume_from(&mut self, resource_version: String) {
+        self.resource_version = resource_version::State::resume(resource_version);
+    }
 }
 
 impl<W, S> Reflector<W, S>
@@ -534,6 +542,52 @@ mod tests {
         drop(reflector);
     }
 
+    // Test that the resumed resource version is passed with the first
+    // invocation.
+    #[tokio::test]
+    async fn resume_from_test() {
+        trace_init();
+
+        // Prepare state.
+        let (state_events_tx, _state_events_rx) = mpsc::channel(0);
+        let (_state_actions_tx, state_actions_rx) = mpsc::channel(0);
+        let state_writer = state::mock::Writer::new(state_events_tx, state_actions_rx);
+
+        // Prepare watcher.
+        let (watcher_events_tx, mut watcher_events_rx) = mpsc::channel(0);
+        let (mut watcher_invocations_tx, watcher_invocations_rx) = mpsc::channel(0);
+        let watcher = MockWatcher::<Pod>::new(watcher_events_tx, watcher_invocations_rx);
+
+        // Prepare reflector.
+        let mut reflector =
+            Reflector::new(watcher, state_writer, None, None, Duration::from_secs(1));
+        reflector.resume_from("10".to_owned());
+
+        // Run test logic.
+        let logic = tokio::spawn(async move {
+            // Wait for watcher to request next invocation.
+            let watch_optional = match watcher_events_rx.next().await.unwrap() {
+                mock_watcher::ScenarioEvent::Invocation(val) => val,
+                _ => panic!("Unexpected event from watcher mock"),
+            };
+
+            // Assert that the resumed resource version is used.
+            assert_eq!(watch_optional.resource_version, Some("10".to_owned()));
+
+            // We're done with the test, send the error to terminate the
+            // reflector.
+            watcher_invocations_tx
+                .send(mock_watcher::ScenarioActionInvocation::ErrOther)
+                .await
+                .unwrap();
+        });
+
+        // Run the test and wait for an error.
+        let result = reflector.run().await;
+        logic.await.unwrap();
+        result.unwrap_err();
+    }
+
     /// Test that the delayed delete works accordingly.
     #[tokio::test]
     async fn test_delayed_deletes() {
@@ -13,6 +13,12 @@ impl State {
         Self(None)
     }
 
+    /// Create a new resource version [`State`] resuming from a resource
+    /// version obtained earlier, for instance from a persisted checkpoint.
+    pub fn resume(resource_version: String) -> Self {
+        Self(Some(resource_version))
+    }
+
     /// Update the resource version from a candidate obtained earlier.
     ///
     /// Returns the previous state.
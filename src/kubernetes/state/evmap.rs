@@ -16,6 +16,7 @@ where
 {
     inner: WriteHandle<String, Value<T>>,
     debounced_flush: Option<Debounce>,
+    key_fn: KeyFn,
 }
 
 impl<T> Writer<T>
@@ -24,9 +25,21 @@ where
 {
     /// Take a [`WriteHandle`], initialize it and return it wrapped with
     /// [`Self`].
+    /// Objects are stored by their `uid`.
     pub fn new(
+        inner: WriteHandle<String, Value<T>>,
+        flush_debounce_timeout: Option<Duration>,
+    ) -> Self {
+        Self::new_with_key_fn(inner, flush_debounce_timeout, key_by_uid)
+    }
+
+    /// Same as [`Self::new`], but stores objects under the key returned
+    /// by `key_fn`.
+    /// The key has to be unique across the watched objects.
+    pub fn new_with_key_fn(
         mut inner: WriteHandle<String, Value<T>>,
         flush_debounce_timeout: Option<Duration>,
+        key_fn: KeyFn,
     ) -> Self {
         // Prepare inner.
         inner.purge();
@@ -38,6 +51,7 @@ where
         Self {
             inner,
             debounced_flush,
+            key_fn,
         }
     }
 
@@ -65,21 +79,21 @@ where
     type Item = T;
 
     async fn add(&mut self, item: Self::Item) {
-        if let Some((key, value)) = kv(item) {
+        if let Some((key, value)) = kv(item, self.key_fn) {
             self.inner.insert(key, value);
             self.debounced_flush();
         }
     }
 
     async fn update(&mut self, item: Self::Item) {
-        if let Some((key, value)) = kv(item) {
+        if let Some((key, value)) = kv(item, self.key_fn) {
             self.inner.update(key, value);
             self.debounced_flush();
         }
     }
 
     async fn delete(&mut self, item: Self::Item) {
-        if let Some((key, _value)) = kv(item) {
+        if let Some((key, _value)) = kv(item, self.key_fn) {
             self.inner.empty(key);
             self.debounced_flush();
         }
@@ -118,10 +132,24 @@ where
 /// An alias to the value used at [`evmap`].
 pub type Value<T> = Box<HashValue<T>>;
 
+/// A function to select the key an object is stored under at [`evmap`].
+pub type KeyFn = fn(&ObjectMeta) -> Option<&str>;
+
+/// Store objects by their `uid`.
+pub fn key_by_uid(metadata: &ObjectMeta) -> Option<&str> {
+    metadata.uid.as_deref()
+}
+
+/// Store objects by their `name`.
+/// Only suitable for non-namespaced objects, like `Namespace`s.
+pub fn key_by_name(metadata: &ObjectMeta) -> Option<&str> {
+    metadata.name.as_deref()
+}
+
 /// Build a key value pair for using in [`evmap`].
-fn kv<T: Metadata<Ty = ObjectMeta>>(object: T) -> Option<(String, Value<T>)> {
+fn kv<T: Metadata<Ty = ObjectMeta>>(object: T, key_fn: KeyFn) -> Option<(String, Value<T>)> {
+    let key = key_fn(object.metadata())?.to_owned();
     let value = Box::new(HashValue::new(object));
-    let key = value.uid()?.to_owned();
     Some((key, value))
 }
 
@@ -144,11 +172,22 @@ mod tests {
     #[test]
     fn test_kv() {
         let pod = make_pod("uid");
-        let (key, val) = kv(pod.clone()).unwrap();
+        let (key, val) = kv(pod.clone(), key_by_uid).unwrap();
         assert_eq!(key, "uid");
         assert_eq!(val, Box::new(HashValue::new(pod)));
     }
 
+    #[test]
+    fn test_kv_by_name() {
+        let mut pod = make_pod("uid");
+        assert!(kv(pod.clone(), key_by_name).is_none());
+
+        pod.metadata.name = Some("name".to_owned());
+        let (key, val) = kv(pod.clone(), key_by_name).unwrap();
+        assert_eq!(key, "name");
+        assert_eq!(val, Box::new(HashValue::new(pod)));
+    }
+
     #[tokio::test]
     async fn test_without_debounce() {
         let (state_reader, state_writer) = evmap::new();
@@ -45,6 +45,8 @@ pub mod line_agg;
 pub mod list;
 pub mod mapping;
 pub mod metrics;
+#[cfg(feature = "rumqttc")]
+pub mod mqtt;
 pub(crate) mod pipeline;
 pub mod region;
 pub mod serde;
@@ -63,6 +65,8 @@ pub mod transforms;
 pub mod types;
 pub mod unit_test;
 pub mod validate;
+#[cfg(any(feature = "sources-vector", feature = "sinks-vector"))]
+pub mod vector_protocol;
 #[cfg(windows)]
 pub mod vector_windows;
 
@@ -0,0 +1,221 @@
+use crate::tls::TlsConfig;
+use rumqttc::{MqttOptions, QoS};
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{convert::TryFrom, path::PathBuf};
+
+#[derive(Debug, Snafu)]
+enum MqttError {
+    #[snafu(display("Could not read {} file {:?}: {}", note, filename, source))]
+    FileReadFailed {
+        note: &'static str,
+        filename: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("Must specify both TLS key_file and crt_file"))]
+    MissingCrtKeyFile,
+    #[snafu(display("Encrypted TLS keys (key_pass) are not supported"))]
+    KeyPassUnsupported,
+    #[snafu(display(
+        "No TLS ca_file was set and no system certificate authority bundle could be found"
+    ))]
+    MissingCaFile,
+    #[snafu(display("Disabling TLS {} is not supported", option))]
+    VerifyUnsupported { option: &'static str },
+    #[snafu(display("A client_id is required when clean_session is disabled"))]
+    MissingClientId,
+    #[snafu(display("Invalid QoS level {}, only 0 and 1 are supported", qos))]
+    InvalidQos { qos: u8 },
+}
+
+/// Connection settings shared by the `mqtt` source and sink.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct MqttConnectionConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub client_id: Option<String>,
+    #[serde(default = "default_keep_alive_secs")]
+    pub keep_alive_secs: u16,
+    #[serde(default = "default_clean_session")]
+    pub clean_session: bool,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub tls: Option<TlsConfig>,
+}
+
+fn default_port() -> u16 {
+    1883
+}
+
+fn default_keep_alive_secs() -> u16 {
+    60
+}
+
+fn default_clean_session() -> bool {
+    true
+}
+
+/// The MQTT quality of service levels Vector can honour. Configured as `0` or `1`.
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(try_from = "u8", into = "u8")]
+pub(crate) enum MqttQos {
+    #[derivative(Default)]
+    AtMostOnce,
+    AtLeastOnce,
+}
+
+impl TryFrom<u8> for MqttQos {
+    type Error = String;
+
+    fn try_from(qos: u8) -> Result<Self, Self::Error> {
+        match qos {
+            0 => Ok(MqttQos::AtMostOnce),
+            1 => Ok(MqttQos::AtLeastOnce),
+            qos => Err(MqttError::InvalidQos { qos }.to_string()),
+        }
+    }
+}
+
+impl From<MqttQos> for u8 {
+    fn from(qos: MqttQos) -> u8 {
+        match qos {
+            MqttQos::AtMostOnce => 0,
+            MqttQos::AtLeastOnce => 1,
+        }
+    }
+}
+
+impl From<MqttQos> for QoS {
+    fn from(qos: MqttQos) -> QoS {
+        match qos {
+            MqttQos::AtMostOnce => QoS::AtMostOnce,
+            MqttQos::AtLeastOnce => QoS::AtLeastOnce,
+        }
+    }
+}
+
+impl MqttConnectionConfig {
+    pub(crate) fn build_options(&self) -> crate::Result<MqttOptions> {
+        let client_id = match &self.client_id {
+            Some(client_id) => client_id.clone(),
+            None if self.clean_session => format!("vector-{}", uuid::Uuid::new_v4()),
+            None => return Err(MqttError::MissingClientId.into()),
+        };
+
+        let mut options = MqttOptions::new(client_id, &self.host, self.port);
+        options
+            .set_keep_alive(self.keep_alive_secs)
+            .set_clean_session(self.clean_session);
+
+        if let (Some(user), Some(password)) = (&self.user, &self.password) {
+            options.set_credentials(user, password);
+        }
+
+        let tls_enabled = self
+            .tls
+            .as_ref()
+            .and_then(|tls| tls.enabled)
+            .unwrap_or(false);
+        if tls_enabled {
+            let tls = &self.tls.as_ref().unwrap().options;
+
+            if tls.key_pass.is_some() {
+                return Err(MqttError::KeyPassUnsupported.into());
+            }
+            if tls.verify_certificate == Some(false) {
+                return Err(MqttError::VerifyUnsupported {
+                    option: "verify_certificate",
+                }
+                .into());
+            }
+            if tls.verify_hostname == Some(false) {
+                return Err(MqttError::VerifyUnsupported {
+                    option: "verify_hostname",
+                }
+                .into());
+            }
+            // The client only enables TLS once a certificate authority is
+            // set, so fall back to the system bundle when none is given.
+            let ca_file = match &tls.ca_file {
+                Some(path) => path.clone(),
+                None => openssl_probe::probe()
+                    .cert_file
+                    .ok_or(MqttError::MissingCaFile)?,
+            };
+            options.set_ca(read_file(&ca_file, "certificate authority")?);
+            match (&tls.crt_file, &tls.key_file) {
+                (Some(crt), Some(key)) => {
+                    let crt = read_file(crt, "certificate")?;
+                    let key = read_file(key, "key")?;
+                    options.set_client_auth(crt, key);
+                }
+                (None, None) => (),
+                _ => return Err(MqttError::MissingCrtKeyFile.into()),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn read_file(filename: &PathBuf, note: &'static str) -> crate::Result<Vec<u8>> {
+    std::fs::read(filename)
+        .with_context(|| FileReadFailed {
+            note,
+            filename: filename.clone(),
+        })
+        .map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_config(clean_session: bool, client_id: Option<&str>) -> MqttConnectionConfig {
+        MqttConnectionConfig {
+            host: "localhost".into(),
+            port: default_port(),
+            client_id: client_id.map(Into::into),
+            keep_alive_secs: default_keep_alive_secs(),
+            clean_session,
+            user: None,
+            password: None,
+            tls: None,
+        }
+    }
+
+    #[test]
+    fn mqtt_persistent_session_requires_client_id() {
+        assert!(make_config(false, None).build_options().is_err());
+        assert!(make_config(false, Some("vector")).build_options().is_ok());
+        assert!(make_config(true, None).build_options().is_ok());
+    }
+
+    #[test]
+    fn mqtt_tls_rejects_disabled_verification() {
+        let mut config = make_config(true, None);
+        let mut tls = TlsConfig::enabled();
+        tls.options.verify_certificate = Some(false);
+        config.tls = Some(tls);
+        assert!(config.build_options().is_err());
+
+        let mut tls = TlsConfig::enabled();
+        tls.options.verify_hostname = Some(false);
+        config.tls = Some(tls);
+        assert!(config.build_options().is_err());
+    }
+
+    #[test]
+    fn mqtt_qos_from_integer() {
+        #[derive(Deserialize)]
+        struct Config {
+            qos: MqttQos,
+        }
+
+        let config: Config = toml::from_str("qos = 1").unwrap();
+        assert_eq!(config.qos, MqttQos::AtLeastOnce);
+        assert!(toml::from_str::<Config>("qos = 2").is_err());
+    }
+}
@@ -0,0 +1,561 @@
+use crate::{
+    config::{log_schema, DataType, SinkConfig, SinkContext, SinkDescription},
+    dns::Resolver,
+    event::Event,
+    internal_events::{AwsSqsEntriesFailed, AwsSqsEventSent, AwsSqsTemplateMissingKeys},
+    region::RegionOrEndpoint,
+    sinks::util::{
+        encoding::{EncodingConfig, EncodingConfiguration},
+        retries::RetryLogic,
+        rusoto,
+        sink::Response,
+        BatchConfig, BatchSettings, EncodedLength, TowerRequestConfig, VecBuffer,
+    },
+    template::Template,
+};
+use futures::{future::BoxFuture, FutureExt};
+use futures01::{stream::iter_ok, Sink};
+use lazy_static::lazy_static;
+use rusoto_core::RusotoError;
+use rusoto_sqs::{
+    GetQueueAttributesError, GetQueueAttributesRequest, SendMessageBatchError,
+    SendMessageBatchRequest, SendMessageBatchRequestEntry, SendMessageBatchResult, Sqs, SqsClient,
+};
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{
+    collections::HashSet,
+    convert::TryInto,
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::time::{delay_for, Duration};
+use tower::Service;
+use tracing_futures::Instrument;
+
+/// The limits of a `SendMessageBatch` request.
+const MAX_BATCH_EVENTS: usize = 10;
+const MAX_BATCH_BYTES: usize = 262_144;
+
+/// How many times the failed entries of a batch are resent on their own.
+const MAX_ENTRY_RETRIES: usize = 3;
+
+#[derive(Clone)]
+pub struct SqsService {
+    client: Arc<SqsClient>,
+    config: SqsSinkConfig,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SqsSinkConfig {
+    pub queue_url: String,
+    #[serde(flatten)]
+    pub region: RegionOrEndpoint,
+    pub encoding: EncodingConfig<Encoding>,
+    /// Required by, and only allowed for, FIFO queues.
+    pub message_group_id: Option<Template>,
+    pub message_deduplication_id: Option<Template>,
+    #[serde(default)]
+    pub batch: BatchConfig,
+    #[serde(default)]
+    pub request: TowerRequestConfig,
+    pub assume_role: Option<String>,
+}
+
+lazy_static! {
+    static ref REQUEST_DEFAULTS: TowerRequestConfig = TowerRequestConfig {
+        timeout_secs: Some(30),
+        ..Default::default()
+    };
+}
+
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone, Derivative)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display(
+        "Batches can't exceed {} events and {} bytes",
+        MAX_BATCH_EVENTS,
+        MAX_BATCH_BYTES
+    ))]
+    BatchTooLarge,
+}
+
+inventory::submit! {
+    SinkDescription::new_without_default::<SqsSinkConfig>("aws_sqs")
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "aws_sqs")]
+impl SinkConfig for SqsSinkConfig {
+    async fn build(
+        &self,
+        cx: SinkContext,
+    ) -> crate::Result<(super::VectorSink, super::Healthcheck)> {
+        let client = self.create_client(cx.resolver())?;
+        let healthcheck = self.clone().healthcheck(client.clone()).boxed();
+        let sink = SqsService::new(self.clone(), client, cx)?;
+        Ok((
+            super::VectorSink::Futures01Sink(Box::new(sink)),
+            healthcheck,
+        ))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn sink_type(&self) -> &'static str {
+        "aws_sqs"
+    }
+}
+
+impl SqsSinkConfig {
+    async fn healthcheck(self, client: SqsClient) -> crate::Result<()> {
+        client
+            .get_queue_attributes(GetQueueAttributesRequest {
+                attribute_names: None,
+                queue_url: self.queue_url,
+            })
+            .await
+            .context(GetQueueAttributesFailed)?;
+
+        Ok(())
+    }
+
+    fn create_client(&self, resolver: Resolver) -> crate::Result<SqsClient> {
+        let region = (&self.region).try_into()?;
+
+        let client = rusoto::client(resolver)?;
+        let creds = rusoto::AwsCredentialsProvider::new(&region, self.assume_role.clone())?;
+
+        Ok(SqsClient::new_with(client, creds, region))
+    }
+}
+
+impl SqsService {
+    pub fn new(
+        config: SqsSinkConfig,
+        client: SqsClient,
+        cx: SinkContext,
+    ) -> crate::Result<impl Sink<SinkItem = Event, SinkError = ()>> {
+        let client = Arc::new(client);
+
+        let batch = BatchSettings::default()
+            .bytes(MAX_BATCH_BYTES as u64)
+            .events(MAX_BATCH_EVENTS)
+            .timeout(1)
+            .parse_config(config.batch)?;
+        if batch.size.events > MAX_BATCH_EVENTS || batch.size.bytes > MAX_BATCH_BYTES {
+            return Err(BuildError::BatchTooLarge.into());
+        }
+        let request = config.request.unwrap_with(&REQUEST_DEFAULTS);
+        let encoding = config.encoding.clone();
+        let message_group_id = config.message_group_id.clone();
+        let message_deduplication_id = config.message_deduplication_id.clone();
+
+        let sqs = SqsService { client, config };
+
+        let sink = request
+            .batch_sink(
+                SqsRetryLogic,
+                sqs,
+                VecBuffer::new(batch.size),
+                batch.timeout,
+                cx.acker(),
+            )
+            .sink_map_err(|e| error!("Fatal sqs sink error: {}", e))
+            .with_flat_map(move |e| {
+                iter_ok(encode_event(
+                    e,
+                    &encoding,
+                    message_group_id.as_ref(),
+                    message_deduplication_id.as_ref(),
+                ))
+            });
+
+        Ok(sink)
+    }
+}
+
+impl Service<Vec<SendMessageBatchRequestEntry>> for SqsService {
+    type Response = SendMessageBatchResult;
+    type Error = SqsError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut entries: Vec<SendMessageBatchRequestEntry>) -> Self::Future {
+        debug!(
+            message = "sending messages.",
+            events = %entries.len(),
+        );
+
+        // The ids only need to be unique within the batch.
+        for (id, entry) in entries.iter_mut().enumerate() {
+            entry.id = id.to_string();
+        }
+
+        let client = Arc::clone(&self.client);
+        let queue_url = self.config.queue_url.clone();
+
+        Box::pin(send_entries(client, queue_url, entries).instrument(info_span!("request")))
+    }
+}
+
+/// Sends the batch, resending only its entries that failed through no
+/// fault of ours.
+async fn send_entries(
+    client: Arc<SqsClient>,
+    queue_url: String,
+    mut entries: Vec<SendMessageBatchRequestEntry>,
+) -> Result<SendMessageBatchResult, SqsError> {
+    let mut backoff = Duration::from_millis(500);
+    let mut retries = 0;
+
+    loop {
+        let result = client
+            .send_message_batch(SendMessageBatchRequest {
+                entries: entries.clone(),
+                queue_url: queue_url.clone(),
+            })
+            .await
+            .context(SendMessageBatchFailed)?;
+
+        let (rejected, failed): (Vec<_>, Vec<_>) =
+            result.failed.iter().partition(|entry| entry.sender_fault);
+        if !rejected.is_empty() {
+            emit!(AwsSqsEntriesFailed {
+                count: rejected.len(),
+                code: &rejected[0].code,
+                message: rejected[0].message.as_deref(),
+                retried: false,
+            });
+        }
+        if failed.is_empty() {
+            return Ok(result);
+        }
+
+        emit!(AwsSqsEntriesFailed {
+            count: failed.len(),
+            code: &failed[0].code,
+            message: failed[0].message.as_deref(),
+            retried: retries < MAX_ENTRY_RETRIES,
+        });
+        if retries == MAX_ENTRY_RETRIES {
+            return Err(SqsError::EntriesFailed {
+                count: failed.len(),
+            });
+        }
+
+        let failed = failed
+            .into_iter()
+            .map(|entry| entry.id.as_str())
+            .collect::<HashSet<_>>();
+        entries.retain(|entry| failed.contains(entry.id.as_str()));
+
+        delay_for(backoff).await;
+        backoff *= 2;
+        retries += 1;
+    }
+}
+
+impl fmt::Debug for SqsService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqsService")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl EncodedLength for SendMessageBatchRequestEntry {
+    fn encoded_length(&self) -> usize {
+        self.message_body.len()
+    }
+}
+
+impl Response for SendMessageBatchResult {}
+
+#[derive(Debug, Snafu)]
+pub enum SqsError {
+    #[snafu(display("SendMessageBatch failed: {}", source))]
+    SendMessageBatchFailed {
+        source: RusotoError<SendMessageBatchError>,
+    },
+    #[snafu(display("{} batch entries still failed after retrying them", count))]
+    EntriesFailed { count: usize },
+}
+
+#[derive(Debug, Snafu)]
+enum HealthcheckError {
+    #[snafu(display("GetQueueAttributes failed: {}", source))]
+    GetQueueAttributesFailed {
+        source: RusotoError<GetQueueAttributesError>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct SqsRetryLogic;
+
+impl RetryLogic for SqsRetryLogic {
+    type Error = SqsError;
+    type Response = SendMessageBatchResult;
+
+    fn is_retriable_error(&self, error: &Self::Error) -> bool {
+        match error {
+            SqsError::SendMessageBatchFailed { source } => match source {
+                RusotoError::HttpDispatch(_) => true,
+                RusotoError::Unknown(res) if res.status.is_server_error() => true,
+                _ => false,
+            },
+            // Retrying the whole batch would duplicate its delivered entries.
+            SqsError::EntriesFailed { .. } => false,
+        }
+    }
+}
+
+fn render_template(
+    template: Option<&Template>,
+    event: &Event,
+    field: &'static str,
+) -> Result<Option<String>, ()> {
+    match template {
+        Some(template) => match template.render_string(event) {
+            Ok(value) => Ok(Some(value)),
+            Err(keys) => {
+                emit!(AwsSqsTemplateMissingKeys { field, keys });
+                Err(())
+            }
+        },
+        None => Ok(None),
+    }
+}
+
+fn encode_event(
+    mut event: Event,
+    encoding: &EncodingConfig<Encoding>,
+    message_group_id: Option<&Template>,
+    message_deduplication_id: Option<&Template>,
+) -> Option<SendMessageBatchRequestEntry> {
+    let message_group_id = render_template(message_group_id, &event, "message_group_id").ok()?;
+    let message_deduplication_id =
+        render_template(message_deduplication_id, &event, "message_deduplication_id").ok()?;
+
+    encoding.apply_rules(&mut event);
+
+    let log = event.into_log();
+    let message_body = match encoding.codec() {
+        Encoding::Json => serde_json::to_string(&log).expect("Error encoding event as json."),
+        Encoding::Text => log
+            .get(&log_schema().message_key())
+            .map(|v| v.to_string_lossy())
+            .unwrap_or_default(),
+    };
+
+    emit!(AwsSqsEventSent {
+        byte_size: message_body.len()
+    });
+    Some(SendMessageBatchRequestEntry {
+        message_body,
+        message_group_id,
+        message_deduplication_id,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::BTreeMap, convert::TryFrom};
+
+    #[test]
+    fn sqs_encode_event_text() {
+        let message = "hello world".to_string();
+        let entry =
+            encode_event(message.clone().into(), &Encoding::Text.into(), None, None).unwrap();
+
+        assert_eq!(entry.message_body, message);
+        assert_eq!(entry.message_group_id, None);
+        assert_eq!(entry.message_deduplication_id, None);
+    }
+
+    #[test]
+    fn sqs_encode_event_json() {
+        let message = "hello world".to_string();
+        let mut event = Event::from(message.clone());
+        event.as_mut_log().insert("key", "value");
+        let entry = encode_event(event, &Encoding::Json.into(), None, None).unwrap();
+
+        let map: BTreeMap<String, String> = serde_json::from_str(&entry.message_body).unwrap();
+
+        assert_eq!(map[&log_schema().message_key().to_string()], message);
+        assert_eq!(map["key"], "value".to_string());
+    }
+
+    #[test]
+    fn sqs_encode_event_fifo() {
+        let mut event = Event::from("hello world");
+        event.as_mut_log().insert("tenant", "acme");
+        event.as_mut_log().insert("id", "42");
+        let entry = encode_event(
+            event,
+            &Encoding::Text.into(),
+            Some(&Template::try_from("{{ tenant }}").unwrap()),
+            Some(&Template::try_from("{{ id }}").unwrap()),
+        )
+        .unwrap();
+
+        assert_eq!(entry.message_group_id, Some("acme".to_owned()));
+        assert_eq!(entry.message_deduplication_id, Some("42".to_owned()));
+    }
+
+    #[test]
+    fn sqs_encode_event_missing_group_id() {
+        let event = Event::from("hello world");
+        let entry = encode_event(
+            event,
+            &Encoding::Text.into(),
+            Some(&Template::try_from("{{ tenant }}").unwrap()),
+            None,
+        );
+
+        assert!(entry.is_none());
+    }
+
+    #[tokio::test]
+    async fn sqs_rejects_large_batches() {
+        let config: SqsSinkConfig = toml::from_str(
+            r#"
+            queue_url = "http://localhost:4566/000000000000/queue"
+            endpoint = "http://localhost:4566"
+            encoding.codec = "text"
+            batch.max_events = 11
+        "#,
+        )
+        .unwrap();
+        let cx = SinkContext::new_test();
+        let client = config.create_client(cx.resolver()).unwrap();
+
+        assert!(SqsService::new(config, client, cx).is_err());
+    }
+}
+
+#[cfg(feature = "aws-sqs-integration-tests")]
+#[cfg(test)]
+mod integration_tests {
+    use super::*;
+    use crate::{
+        config::SinkContext,
+        region::RegionOrEndpoint,
+        test_util::{random_lines_with_stream, random_string},
+    };
+    use futures::{compat::Sink01CompatExt, SinkExt, StreamExt};
+    use rusoto_core::Region;
+    use rusoto_sqs::{CreateQueueRequest, ReceiveMessageRequest};
+    use std::{collections::HashMap, convert::TryFrom};
+
+    const ENDPOINT: &str = "http://localhost:4566";
+
+    #[tokio::test]
+    async fn sqs_send_message_batch() {
+        let queue_url = ensure_queue(&format!("test-{}", random_string(10)), false).await;
+        let lines = send_lines(config(queue_url.clone(), None)).await;
+
+        assert_eq!(receive_lines(queue_url).await, lines);
+    }
+
+    #[tokio::test]
+    async fn sqs_send_message_batch_fifo() {
+        let queue_url = ensure_queue(&format!("test-{}.fifo", random_string(10)), true).await;
+        let lines = send_lines(config(
+            queue_url.clone(),
+            Some(Template::try_from("{{ message }}").unwrap()),
+        ))
+        .await;
+
+        assert_eq!(receive_lines(queue_url).await, lines);
+    }
+
+    fn config(queue_url: String, message_group_id: Option<Template>) -> SqsSinkConfig {
+        SqsSinkConfig {
+            queue_url,
+            region: RegionOrEndpoint::with_endpoint(ENDPOINT.into()),
+            encoding: Encoding::Text.into(),
+            message_deduplication_id: message_group_id.clone(),
+            message_group_id,
+            batch: Default::default(),
+            request: Default::default(),
+            assume_role: None,
+        }
+    }
+
+    async fn send_lines(config: SqsSinkConfig) -> Vec<String> {
+        let cx = SinkContext::new_test();
+        let client = config.create_client(cx.resolver()).unwrap();
+        let sink = SqsService::new(config, client, cx).unwrap();
+
+        let (mut input_lines, events) = random_lines_with_stream(100, 25);
+        let mut events = events.map(Ok);
+        let _ = sink.sink_compat().send_all(&mut events).await.unwrap();
+
+        input_lines.sort();
+        input_lines
+    }
+
+    async fn receive_lines(queue_url: String) -> Vec<String> {
+        let client = client();
+        let mut lines = Vec::new();
+        loop {
+            let response = client
+                .receive_message(ReceiveMessageRequest {
+                    queue_url: queue_url.clone(),
+                    max_number_of_messages: Some(10),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            match response.messages {
+                Some(messages) if !messages.is_empty() => {
+                    lines.extend(messages.into_iter().map(|message| message.body.unwrap()))
+                }
+                _ => break,
+            }
+        }
+        lines.sort();
+        lines
+    }
+
+    async fn ensure_queue(queue_name: &str, fifo: bool) -> String {
+        let mut attributes = HashMap::new();
+        if fifo {
+            attributes.insert("FifoQueue".to_owned(), "true".to_owned());
+        }
+
+        client()
+            .create_queue(CreateQueueRequest {
+                queue_name: queue_name.to_owned(),
+                attributes: Some(attributes),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .queue_url
+            .unwrap()
+    }
+
+    fn client() -> SqsClient {
+        SqsClient::new(Region::Custom {
+            name: "localstack".into(),
+            endpoint: ENDPOINT.into(),
+        })
+    }
+}
@@ -0,0 +1,998 @@
+use crate::{
+    config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    event::Event,
+    sinks::{
+        util::{
+            encoding::{EncodingConfig, EncodingConfiguration},
+            http::HttpClient,
+            retries::{RetryAction, RetryLogic},
+            BatchConfig, BatchSettings, Buffer, Compression, InFlightLimit, PartitionBatchSink,
+            PartitionBuffer, PartitionInnerBuffer, ServiceBuilderExt, TowerRequestConfig,
+        },
+        Healthcheck, VectorSink,
+    },
+    template::{Template, TemplateError},
+    tls::{TlsOptions, TlsSettings},
+};
+use bytes::Bytes;
+use chrono::Utc;
+use futures::{
+    future::{self, BoxFuture},
+    FutureExt, TryFutureExt,
+};
+use futures01::{stream::iter_ok, Sink};
+use http::{StatusCode, Uri};
+use hyper::{header::HeaderValue, Body, Request, Response};
+use lazy_static::lazy_static;
+use openssl::{
+    base64, hash,
+    pkey::{PKey, Private},
+    sign,
+};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::convert::TryFrom;
+use std::task::Poll;
+use tower::{Service, ServiceBuilder};
+use uuid::Uuid;
+
+const NAME: &str = "azure_blob";
+const API_VERSION: &str = "2019-12-12";
+const DEFAULT_ENDPOINTS_PROTOCOL: &str = "https";
+const DEFAULT_ENDPOINT_SUFFIX: &str = "core.windows.net";
+// The well-known account and key of the storage emulators.
+const DEVELOPMENT_ACCOUNT_NAME: &str = "devstoreaccount1";
+const DEVELOPMENT_ACCOUNT_KEY: &str =
+    "Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==";
+const DEVELOPMENT_BLOB_ENDPOINT: &str = "http://127.0.0.1:10000/devstoreaccount1";
+
+#[derive(Clone)]
+struct AzureBlobSink {
+    container_name: String,
+    container_url: String,
+    client: HttpClient,
+    credentials: Credentials,
+    settings: RequestSettings,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AzureBlobSinkConfig {
+    connection_string: Option<String>,
+    storage_account: Option<String>,
+    sas_token: Option<String>,
+    container_name: String,
+    blob_prefix: Option<String>,
+    blob_time_format: Option<String>,
+    blob_append_uuid: Option<bool>,
+    encoding: EncodingConfig<Encoding>,
+    #[serde(default)]
+    compression: Compression,
+    #[serde(default)]
+    batch: BatchConfig,
+    #[serde(default)]
+    request: TowerRequestConfig,
+    tls: Option<TlsOptions>,
+}
+
+#[cfg(test)]
+fn default_config(e: Encoding) -> AzureBlobSinkConfig {
+    AzureBlobSinkConfig {
+        connection_string: Some("UseDevelopmentStorage=true".into()),
+        storage_account: Default::default(),
+        sas_token: Default::default(),
+        container_name: "logs".into(),
+        blob_prefix: Default::default(),
+        blob_time_format: Default::default(),
+        blob_append_uuid: Default::default(),
+        encoding: e.into(),
+        compression: Compression::Gzip,
+        batch: Default::default(),
+        request: Default::default(),
+        tls: Default::default(),
+    }
+}
+
+lazy_static! {
+    static ref REQUEST_DEFAULTS: TowerRequestConfig = TowerRequestConfig {
+        in_flight_limit: InFlightLimit::Fixed(25),
+        rate_limit_num: Some(250),
+        ..Default::default()
+    };
+}
+
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum Encoding {
+    Text,
+    Ndjson,
+}
+
+impl Encoding {
+    fn content_type(&self) -> &'static str {
+        match self {
+            Self::Text => "text/plain",
+            Self::Ndjson => "application/x-ndjson",
+        }
+    }
+}
+
+inventory::submit! {
+    SinkDescription::new_without_default::<AzureBlobSinkConfig>(NAME)
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "azure_blob")]
+impl SinkConfig for AzureBlobSinkConfig {
+    async fn build(&self, cx: SinkContext) -> crate::Result<(VectorSink, Healthcheck)> {
+        let sink = AzureBlobSink::new(self, &cx)?;
+        let healthcheck = sink.clone().healthcheck().boxed();
+        let service = sink.service(self, &cx)?;
+
+        Ok((service, healthcheck))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn sink_type(&self) -> &'static str {
+        NAME
+    }
+}
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display(
+        "Either `connection_string`, or `storage_account` and `sas_token`, must be set"
+    ))]
+    MissingCredentials,
+    #[snafu(display("Invalid connection string segment: {:?}", segment))]
+    InvalidConnectionString { segment: String },
+    #[snafu(display("Connection string is missing {}", key))]
+    MissingConnectionStringKey { key: &'static str },
+    #[snafu(display("Invalid account key: {}", source))]
+    InvalidAccountKey { source: openssl::error::ErrorStack },
+    #[snafu(display("blob_prefix template parse error: {}", source))]
+    BlobPrefixTemplate { source: TemplateError },
+}
+
+#[derive(Debug, Snafu)]
+enum HealthcheckError {
+    #[snafu(display("Invalid credentials"))]
+    InvalidCredentials,
+    #[snafu(display("Unknown container: {:?}", container))]
+    UnknownContainer { container: String },
+    #[snafu(display("Unknown status code: {}", status))]
+    UnknownStatusCode { status: StatusCode },
+}
+
+impl AzureBlobSinkConfig {
+    fn storage_account(&self) -> crate::Result<StorageAccount> {
+        match (
+            &self.connection_string,
+            &self.storage_account,
+            &self.sas_token,
+        ) {
+            (Some(connection_string), None, None) => StorageAccount::parse(connection_string),
+            (None, Some(account), Some(sas_token)) => Ok(StorageAccount {
+                blob_endpoint: format!(
+                    "{}://{}.blob.{}",
+                    DEFAULT_ENDPOINTS_PROTOCOL, account, DEFAULT_ENDPOINT_SUFFIX
+                ),
+                credentials: Credentials::Sas(sas_token.trim_start_matches('?').into()),
+            }),
+            _ => Err(BuildError::MissingCredentials.into()),
+        }
+    }
+}
+
+/// The blob endpoint of a storage account, and how to authorize requests
+/// against it.
+struct StorageAccount {
+    blob_endpoint: String,
+    credentials: Credentials,
+}
+
+impl StorageAccount {
+    /// Parses an Azure Storage connection string, as found in the Azure
+    /// portal, into the account's blob endpoint and credentials.
+    fn parse(connection_string: &str) -> crate::Result<Self> {
+        let mut protocol = DEFAULT_ENDPOINTS_PROTOCOL;
+        let mut suffix = DEFAULT_ENDPOINT_SUFFIX;
+        let mut account_name = None;
+        let mut account_key = None;
+        let mut blob_endpoint = None;
+        let mut sas_token = None;
+
+        for segment in connection_string.split(';').filter(|s| !s.is_empty()) {
+            // Account keys and SAS tokens contain `=` themselves.
+            let mut parts = segment.splitn(2, '=');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key.trim(), value.trim()),
+                _ => {
+                    return Err(BuildError::InvalidConnectionString {
+                        segment: segment.into(),
+                    }
+                    .into())
+                }
+            };
+            match key {
+                "DefaultEndpointsProtocol" => protocol = value,
+                "EndpointSuffix" => suffix = value,
+                "AccountName" => account_name = Some(value),
+                "AccountKey" => account_key = Some(value),
+                "BlobEndpoint" => blob_endpoint = Some(value.trim_end_matches('/').to_owned()),
+                "SharedAccessSignature" => sas_token = Some(value.trim_start_matches('?')),
+                "UseDevelopmentStorage" if value == "true" => {
+                    account_name = Some(DEVELOPMENT_ACCOUNT_NAME);
+                    account_key = Some(DEVELOPMENT_ACCOUNT_KEY);
+                    blob_endpoint = Some(DEVELOPMENT_BLOB_ENDPOINT.to_owned());
+                }
+                // Endpoints of the other storage services are of no use here.
+                _ => (),
+            }
+        }
+
+        let blob_endpoint = match (blob_endpoint, account_name) {
+            (Some(endpoint), _) => endpoint,
+            (None, Some(account)) => format!("{}://{}.blob.{}", protocol, account, suffix),
+            (None, None) => {
+                return Err(BuildError::MissingConnectionStringKey {
+                    key: "AccountName or BlobEndpoint",
+                }
+                .into())
+            }
+        };
+
+        let credentials = match (sas_token, account_name, account_key) {
+            (Some(sas_token), _, _) => Credentials::Sas(sas_token.into()),
+            (None, Some(account), Some(key)) => Credentials::shared_key(account, key)?,
+            (None, _, _) => {
+                return Err(BuildError::MissingConnectionStringKey {
+                    key: "AccountName and AccountKey, or SharedAccessSignature",
+                }
+                .into())
+            }
+        };
+
+        Ok(Self {
+            blob_endpoint,
+            credentials,
+        })
+    }
+}
+
+#[derive(Clone)]
+enum Credentials {
+    SharedKey { account: String, key: PKey<Private> },
+    Sas(String),
+}
+
+impl Credentials {
+    fn shared_key(account: &str, key: &str) -> crate::Result<Self> {
+        let key = base64::decode_block(key).context(InvalidAccountKey)?;
+        let key = PKey::hmac(&key).context(InvalidAccountKey)?;
+        Ok(Credentials::SharedKey {
+            account: account.into(),
+            key,
+        })
+    }
+
+    fn apply(&self, request: &mut Request<Body>) {
+        match self {
+            Credentials::SharedKey { account, key } => {
+                let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+                request
+                    .headers_mut()
+                    .insert("x-ms-date", HeaderValue::from_str(&date).unwrap());
+
+                let string_to_sign = string_to_sign(account, request);
+                let mut signer = sign::Signer::new(hash::MessageDigest::sha256(), key)
+                    .expect("Failed to create signer, this is a bug!");
+                let signature = signer
+                    .sign_oneshot_to_vec(string_to_sign.as_bytes())
+                    .expect("Failed to sign request, this is a bug!");
+                let authorization =
+                    format!("SharedKey {}:{}", account, base64::encode_block(&signature));
+                request.headers_mut().insert(
+                    "authorization",
+                    HeaderValue::from_str(&authorization).unwrap(),
+                );
+            }
+            Credentials::Sas(token) => {
+                let uri = request.uri().to_string();
+                let separator = if request.uri().query().is_some() {
+                    '&'
+                } else {
+                    '?'
+                };
+                *request.uri_mut() = format!("{}{}{}", uri, separator, token)
+                    .parse::<Uri>()
+                    .unwrap();
+            }
+        }
+    }
+}
+
+/// Builds the string signed by the Shared Key authorization scheme of the
+/// Blob service.
+fn string_to_sign(account: &str, request: &Request<Body>) -> String {
+    let headers = request.headers();
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("")
+    };
+    let content_length = match header("content-length") {
+        "0" => "",
+        length => length,
+    };
+
+    let mut string = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
+        request.method(),
+        header("content-encoding"),
+        header("content-language"),
+        content_length,
+        header("content-md5"),
+        header("content-type"),
+        header("date"),
+        header("if-modified-since"),
+        header("if-match"),
+        header("if-none-match"),
+        header("if-unmodified-since"),
+        header("range"),
+    );
+
+    let mut ms_headers = headers
+        .iter()
+        .filter(|(name, _)| name.as_str().starts_with("x-ms-"))
+        .map(|(name, value)| (name.as_str(), value.to_str().unwrap_or("").trim()))
+        .collect::<Vec<_>>();
+    ms_headers.sort();
+    for (name, value) in ms_headers {
+        string.push_str(&format!("{}:{}\n", name, value));
+    }
+
+    string.push_str(&format!("/{}{}", account, request.uri().path()));
+
+    let mut params = request
+        .uri()
+        .query()
+        .unwrap_or("")
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let mut parts = param.splitn(2, '=');
+            let name = decode_query_component(parts.next().unwrap_or("")).to_lowercase();
+            (name, decode_query_component(parts.next().unwrap_or("")))
+        })
+        .collect::<Vec<_>>();
+    params.sort();
+    for (name, value) in params {
+        string.push_str(&format!("\n{}:{}", name, value));
+    }
+
+    string
+}
+
+fn decode_query_component(component: &str) -> String {
+    percent_decode_str(&component.replace('+', " "))
+        .decode_utf8_lossy()
+        .into_owned()
+}
+
+/// Characters left as they are in blob names, besides the `/` separating
+/// the virtual directories.
+const BLOB_NAME_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+fn encode_blob_name(blob_name: &str) -> String {
+    blob_name
+        .split('/')
+        .map(|segment| utf8_percent_encode(segment, BLOB_NAME_ENCODE_SET).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+impl AzureBlobSink {
+    fn new(config: &AzureBlobSinkConfig, cx: &SinkContext) -> crate::Result<Self> {
+        let account = config.storage_account()?;
+        let settings = RequestSettings::new(config)?;
+        let tls = TlsSettings::from_options(&config.tls)?;
+        let client = HttpClient::new(cx.resolver(), tls)?;
+        let container_url = format!("{}/{}", account.blob_endpoint, config.container_name);
+        Ok(AzureBlobSink {
+            container_name: config.container_name.clone(),
+            container_url,
+            client,
+            credentials: account.credentials,
+            settings,
+        })
+    }
+
+    fn service(self, config: &AzureBlobSinkConfig, cx: &SinkContext) -> crate::Result<VectorSink> {
+        let request = config.request.unwrap_with(&REQUEST_DEFAULTS);
+        let encoding = config.encoding.clone();
+
+        let batch = BatchSettings::default()
+            .bytes(bytesize::mib(10u64))
+            .timeout(300)
+            .parse_config(config.batch)?;
+
+        let blob_prefix = config.blob_prefix.as_deref().unwrap_or("blob/%F/");
+        let blob_prefix = Template::try_from(blob_prefix).context(BlobPrefixTemplate)?;
+
+        let settings = self.settings.clone();
+
+        let svc = ServiceBuilder::new()
+            .map(move |req| RequestWrapper::new(req, settings.clone()))
+            .settings(request, AzureBlobRetryLogic)
+            .service(self);
+
+        let buffer = PartitionBuffer::new(Buffer::new(batch.size, config.compression));
+
+        let sink = PartitionBatchSink::new(svc, buffer, batch.timeout, cx.acker())
+            .sink_map_err(|e| error!("Fatal azure_blob sink error: {}", e))
+            .with_flat_map(move |e| iter_ok(encode_event(e, &blob_prefix, &encoding)));
+
+        Ok(VectorSink::Futures01Sink(Box::new(sink)))
+    }
+
+    async fn healthcheck(mut self) -> crate::Result<()> {
+        let uri = format!("{}?restype=container", self.container_url).parse::<Uri>()?;
+        let mut request = Request::head(uri)
+            .header("x-ms-version", API_VERSION)
+            .body(Body::empty())?;
+        self.credentials.apply(&mut request);
+
+        let response = self.client.send(request).await?;
+        match response.status() {
+            StatusCode::OK => Ok(()),
+            StatusCode::FORBIDDEN => Err(HealthcheckError::InvalidCredentials.into()),
+            StatusCode::NOT_FOUND => Err(HealthcheckError::UnknownContainer {
+                container: self.container_name,
+            }
+            .into()),
+            status => Err(HealthcheckError::UnknownStatusCode { status }.into()),
+        }
+    }
+}
+
+impl Service<RequestWrapper> for AzureBlobSink {
+    type Response = Response<Body>;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestWrapper) -> Self::Future {
+        match self.build_request(request) {
+            Ok(request) => self.client.call(request).map_err(Into::into).boxed(),
+            Err(error) => future::err(error).boxed(),
+        }
+    }
+}
+
+impl AzureBlobSink {
+    fn build_request(&self, request: RequestWrapper) -> crate::Result<Request<Body>> {
+        let settings = request.settings;
+
+        let uri = format!(
+            "{}/{}",
+            self.container_url,
+            encode_blob_name(&request.blob_name)
+        )
+        .parse::<Uri>()?;
+        let mut builder = Request::put(uri);
+        let headers = builder.headers_mut().unwrap();
+        headers.insert("content-type", settings.content_type);
+        headers.insert(
+            "content-length",
+            HeaderValue::from_str(&format!("{}", request.body.len())).unwrap(),
+        );
+        settings
+            .content_encoding
+            .map(|ce| headers.insert("content-encoding", ce));
+        headers.insert("x-ms-blob-type", HeaderValue::from_static("BlockBlob"));
+        headers.insert("x-ms-version", HeaderValue::from_static(API_VERSION));
+
+        let mut request = builder.body(Body::from(request.body))?;
+        self.credentials.apply(&mut request);
+
+        Ok(request)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RequestWrapper {
+    body: Vec<u8>,
+    blob_name: String,
+    settings: RequestSettings,
+}
+
+impl RequestWrapper {
+    fn new(req: PartitionInnerBuffer<Vec<u8>, Bytes>, settings: RequestSettings) -> Self {
+        let (body, prefix) = req.into_parts();
+
+        let filename = {
+            let seconds = Utc::now().format(&settings.time_format);
+
+            if settings.append_uuid {
+                let uuid = Uuid::new_v4();
+                format!("{}-{}", seconds, uuid.to_hyphenated())
+            } else {
+                seconds.to_string()
+            }
+        };
+
+        let blob_name = format!(
+            "{}{}.{}",
+            String::from_utf8_lossy(&prefix[..]),
+            filename,
+            settings.extension
+        );
+
+        debug!(message = "sending events.", bytes = ?body.len(), ?blob_name);
+
+        Self {
+            body,
+            blob_name,
+            settings,
+        }
+    }
+}
+
+// Settings required to produce a request that do not change per
+// request. All possible values are pre-computed for direct use in
+// producing a request.
+#[derive(Clone, Debug)]
+struct RequestSettings {
+    content_type: HeaderValue,
+    content_encoding: Option<HeaderValue>,
+    extension: &'static str,
+    time_format: String,
+    append_uuid: bool,
+}
+
+impl RequestSettings {
+    fn new(config: &AzureBlobSinkConfig) -> crate::Result<Self> {
+        let content_type = HeaderValue::from_str(config.encoding.codec().content_type()).unwrap();
+        let content_encoding = config
+            .compression
+            .content_encoding()
+            .map(HeaderValue::from_static);
+        let extension = config.compression.extension();
+        let time_format = config
+            .blob_time_format
+            .clone()
+            .unwrap_or_else(|| "%s".into());
+        let append_uuid = config.blob_append_uuid.unwrap_or(true);
+        Ok(Self {
+            content_type,
+            content_encoding,
+            extension,
+            time_format,
+            append_uuid,
+        })
+    }
+}
+
+fn encode_event(
+    mut event: Event,
+    blob_prefix: &Template,
+    encoding: &EncodingConfig<Encoding>,
+) -> Option<PartitionInnerBuffer<Vec<u8>, Bytes>> {
+    let prefix = blob_prefix
+        .render_string(&event)
+        .map_err(|missing_keys| {
+            warn!(
+                message = "Keys do not exist on the event; dropping event.",
+                ?missing_keys,
+                rate_limit_secs = 30,
+            );
+        })
+        .ok()?;
+    encoding.apply_rules(&mut event);
+    let log = event.into_log();
+    let bytes = match encoding.codec() {
+        Encoding::Ndjson => serde_json::to_vec(&log)
+            .map(|mut b| {
+                b.push(b'\n');
+                b
+            })
+            .expect("Failed to encode event as json, this is a bug!"),
+        Encoding::Text => {
+            let mut bytes = log
+                .get(&crate::config::log_schema().message_key())
+                .map(|v| v.as_bytes().to_vec())
+                .unwrap_or_default();
+            bytes.push(b'\n');
+            bytes
+        }
+    };
+
+    Some(PartitionInnerBuffer::new(bytes, prefix.into()))
+}
+
+#[derive(Clone)]
+struct AzureBlobRetryLogic;
+
+impl RetryLogic for AzureBlobRetryLogic {
+    type Error = hyper::Error;
+    type Response = Response<Body>;
+
+    fn is_retriable_error(&self, error: &Self::Error) -> bool {
+        error.is_connect() || error.is_closed()
+    }
+
+    fn should_retry_response(&self, response: &Self::Response) -> RetryAction {
+        let status = response.status();
+
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => RetryAction::Retry("too many requests".into()),
+            StatusCode::NOT_IMPLEMENTED => {
+                RetryAction::DontRetry("endpoint not implemented".into())
+            }
+            _ if status.is_server_error() => RetryAction::Retry(format!("{}", status)),
+            _ if status.is_success() => RetryAction::Successful,
+            _ => RetryAction::DontRetry(format!("response status: {}", status)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Event;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn azure_blob_encode_event_text() {
+        let message = "hello world".to_string();
+        let blob_prefix = Template::try_from("date=%F").unwrap();
+        let bytes =
+            encode_event(message.clone().into(), &blob_prefix, &Encoding::Text.into()).unwrap();
+
+        let encoded_message = message + "\n";
+        let (bytes, _) = bytes.into_parts();
+        assert_eq!(&bytes[..], encoded_message.as_bytes());
+    }
+
+    #[test]
+    fn azure_blob_encode_event_ndjson() {
+        let message = "hello world".to_string();
+        let mut event = Event::from(message.clone());
+        event.as_mut_log().insert("key", "value");
+
+        let blob_prefix = Template::try_from("date=%F").unwrap();
+        let bytes = encode_event(event, &blob_prefix, &Encoding::Ndjson.into()).unwrap();
+
+        let (bytes, _) = bytes.into_parts();
+        let map: HashMap<String, String> = serde_json::from_slice(&bytes[..]).unwrap();
+
+        assert_eq!(
+            map.get(&crate::config::log_schema().message_key().to_string()),
+            Some(&message)
+        );
+        assert_eq!(map["key"], "value".to_string());
+    }
+
+    #[test]
+    fn azure_blob_encode_event_blob_prefix() {
+        let mut event = Event::from("hello world");
+        event.as_mut_log().insert("key", "value");
+
+        let blob_prefix = Template::try_from("key: {{ key }}/").unwrap();
+        let bytes = encode_event(event, &blob_prefix, &Encoding::Text.into()).unwrap();
+
+        let (_, prefix) = bytes.into_parts();
+        assert_eq!(prefix, "key: value/");
+    }
+
+    fn request_settings(uuid: bool, compression: Compression) -> RequestSettings {
+        RequestSettings::new(&AzureBlobSinkConfig {
+            blob_prefix: Some("blob/".into()),
+            blob_time_format: Some("date".into()),
+            blob_append_uuid: Some(uuid),
+            compression,
+            ..default_config(Encoding::Ndjson)
+        })
+        .expect("Could not create request settings")
+    }
+
+    #[test]
+    fn azure_blob_build_request() {
+        let buf = PartitionInnerBuffer::new(vec![0u8; 10], Bytes::from("blob/"));
+
+        let req = RequestWrapper::new(buf.clone(), request_settings(false, Compression::None));
+        assert_eq!(req.blob_name, "blob/date.log".to_string());
+
+        let req = RequestWrapper::new(buf.clone(), request_settings(false, Compression::Gzip));
+        assert_eq!(req.blob_name, "blob/date.log.gz".to_string());
+        assert_eq!(
+            req.settings.content_encoding,
+            Some(HeaderValue::from_static("gzip"))
+        );
+
+        let req = RequestWrapper::new(buf, request_settings(true, Compression::Gzip));
+        assert_ne!(req.blob_name, "blob/date.log.gz".to_string());
+    }
+
+    #[test]
+    fn azure_blob_encode_blob_name() {
+        assert_eq!(
+            encode_blob_name("key: value/date=2020-11-02/blob.log"),
+            "key%3A%20value/date%3D2020-11-02/blob.log"
+        );
+    }
+
+    #[test]
+    fn azure_blob_build_request_encodes_blob_name() {
+        let sink =
+            AzureBlobSink::new(&default_config(Encoding::Text), &SinkContext::new_test()).unwrap();
+        let buf = PartitionInnerBuffer::new(vec![0u8; 10], Bytes::from("key: value/"));
+        let request = RequestWrapper::new(buf, request_settings(false, Compression::None));
+
+        let request = sink.build_request(request).unwrap();
+        assert_eq!(
+            request.uri().path(),
+            "/devstoreaccount1/logs/key%3A%20value/date.log"
+        );
+    }
+
+    #[test]
+    fn azure_blob_parse_connection_string() {
+        let account = StorageAccount::parse(
+            "DefaultEndpointsProtocol=https;AccountName=vector;AccountKey=a2V5;EndpointSuffix=core.windows.net",
+        )
+        .unwrap();
+        assert_eq!(
+            account.blob_endpoint,
+            "https://vector.blob.core.windows.net"
+        );
+        assert!(
+            matches!(account.credentials, Credentials::SharedKey { ref account, .. } if account == "vector")
+        );
+
+        let account = StorageAccount::parse(
+            "BlobEndpoint=https://vector.blob.core.windows.net/;SharedAccessSignature=sv=2019-12-12&sig=abc%3D",
+        )
+        .unwrap();
+        assert_eq!(
+            account.blob_endpoint,
+            "https://vector.blob.core.windows.net"
+        );
+        assert!(
+            matches!(account.credentials, Credentials::Sas(ref token) if token == "sv=2019-12-12&sig=abc%3D")
+        );
+
+        let account = StorageAccount::parse("UseDevelopmentStorage=true").unwrap();
+        assert_eq!(account.blob_endpoint, DEVELOPMENT_BLOB_ENDPOINT);
+
+        assert!(StorageAccount::parse("AccountName=vector").is_err());
+        assert!(StorageAccount::parse("AccountKey=a2V5").is_err());
+        assert!(StorageAccount::parse("AccountName").is_err());
+    }
+
+    #[test]
+    fn azure_blob_requires_credentials() {
+        let config = AzureBlobSinkConfig {
+            connection_string: None,
+            ..default_config(Encoding::Text)
+        };
+        assert!(config.storage_account().is_err());
+
+        let config = AzureBlobSinkConfig {
+            connection_string: None,
+            storage_account: Some("vector".into()),
+            sas_token: Some("?sv=2019-12-12&sig=abc".into()),
+            ..default_config(Encoding::Text)
+        };
+        let account = config.storage_account().unwrap();
+        assert_eq!(
+            account.blob_endpoint,
+            "https://vector.blob.core.windows.net"
+        );
+        assert!(
+            matches!(account.credentials, Credentials::Sas(ref token) if token == "sv=2019-12-12&sig=abc")
+        );
+    }
+
+    #[test]
+    fn azure_blob_string_to_sign() {
+        let request = Request::put(
+            "http://127.0.0.1:10000/devstoreaccount1/logs/blob.log?comp=list&RESType=container&prefix=a%2Fb",
+        )
+        .header("content-type", "text/plain")
+        .header("content-length", "11")
+        .header("x-ms-version", API_VERSION)
+        .header("x-ms-date", "Mon, 02 Nov 2020 10:00:00 GMT")
+        .body(Body::empty())
+        .unwrap();
+
+        assert_eq!(
+            string_to_sign("devstoreaccount1", &request),
+            "PUT\n\n\n11\n\ntext/plain\n\n\n\n\n\n\n\
+             x-ms-date:Mon, 02 Nov 2020 10:00:00 GMT\n\
+             x-ms-version:2019-12-12\n\
+             /devstoreaccount1/devstoreaccount1/logs/blob.log\n\
+             comp:list\n\
+             prefix:a/b\n\
+             restype:container"
+        );
+    }
+
+    #[test]
+    fn azure_blob_sas_token_is_appended() {
+        let credentials = Credentials::Sas("sv=2019-12-12&sig=abc".into());
+
+        let mut request =
+            Request::head("https://vector.blob.core.windows.net/logs?restype=container")
+                .body(Body::empty())
+                .unwrap();
+        credentials.apply(&mut request);
+        assert_eq!(
+            request.uri().to_string(),
+            "https://vector.blob.core.windows.net/logs?restype=container&sv=2019-12-12&sig=abc"
+        );
+        assert!(request.headers().get("authorization").is_none());
+    }
+}
+
+#[cfg(feature = "azure-blob-integration-tests")]
+#[cfg(test)]
+mod integration_tests {
+    use super::*;
+    use crate::{
+        assert_downcast_matches,
+        test_util::{random_lines_with_stream, random_string},
+    };
+    use flate2::read::GzDecoder;
+    use std::io::{BufRead, BufReader};
+
+    const CONNECTION_STRING: &str = "UseDevelopmentStorage=true";
+
+    #[tokio::test]
+    async fn azure_blob_healthchecks() {
+        let config = config(Compression::None).await;
+        let sink = AzureBlobSink::new(&config, &SinkContext::new_test()).unwrap();
+        sink.healthcheck().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn azure_blob_healthcheck_unknown_container() {
+        let config = AzureBlobSinkConfig {
+            container_name: format!("unknown-{}", random_string(10).to_lowercase()),
+            ..config(Compression::None).await
+        };
+        let sink = AzureBlobSink::new(&config, &SinkContext::new_test()).unwrap();
+        assert_downcast_matches!(
+            sink.healthcheck().await.unwrap_err(),
+            HealthcheckError,
+            HealthcheckError::UnknownContainer { .. }
+        );
+    }
+
+    #[tokio::test]
+    async fn azure_blob_insert_lines() {
+        let config = config(Compression::None).await;
+        let lines = send_lines(&config).await;
+
+        let blobs = list_blobs(&config).await;
+        assert_eq!(blobs.len(), 1);
+        assert!(blobs[0].ends_with(".log"));
+
+        let body = get_blob(&config, &blobs[0]).await;
+        let received = BufReader::new(&body[..])
+            .lines()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        assert_eq!(received, lines);
+    }
+
+    #[tokio::test]
+    async fn azure_blob_insert_lines_gzip() {
+        let config = config(Compression::Gzip).await;
+        let lines = send_lines(&config).await;
+
+        let blobs = list_blobs(&config).await;
+        assert_eq!(blobs.len(), 1);
+        assert!(blobs[0].ends_with(".log.gz"));
+
+        let body = get_blob(&config, &blobs[0]).await;
+        let received = BufReader::new(GzDecoder::new(&body[..]))
+            .lines()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        assert_eq!(received, lines);
+    }
+
+    async fn config(compression: Compression) -> AzureBlobSinkConfig {
+        let config = AzureBlobSinkConfig {
+            connection_string: Some(CONNECTION_STRING.into()),
+            container_name: format!("logs-{}", random_string(10).to_lowercase()),
+            blob_prefix: Some(format!("{}/", random_string(10))),
+            compression,
+            batch: BatchConfig {
+                max_events: Some(100),
+                timeout_secs: Some(5),
+                ..Default::default()
+            },
+            ..default_config(Encoding::Text)
+        };
+        send(
+            &config,
+            Request::put(container_url(&config, "restype=container")),
+        )
+        .await;
+        config
+    }
+
+    async fn send_lines(config: &AzureBlobSinkConfig) -> Vec<String> {
+        let cx = SinkContext::new_test();
+        let (sink, _) = config.build(cx).await.unwrap();
+
+        let (lines, events) = random_lines_with_stream(100, 100);
+        sink.run(events).await.unwrap();
+
+        lines
+    }
+
+    async fn list_blobs(config: &AzureBlobSinkConfig) -> Vec<String> {
+        let query = format!(
+            "restype=container&comp=list&prefix={}",
+            config.blob_prefix.as_ref().unwrap().trim_end_matches('/')
+        );
+        let body = send(config, Request::get(container_url(config, &query))).await;
+
+        // The listing is XML; the blob names are all that's needed from it.
+        String::from_utf8(body)
+            .unwrap()
+            .split("<Name>")
+            .skip(1)
+            .map(|part| part.split("</Name>").next().unwrap().to_owned())
+            .collect()
+    }
+
+    async fn get_blob(config: &AzureBlobSinkConfig, blob_name: &str) -> Vec<u8> {
+        let uri = format!(
+            "{}/{}/{}",
+            DEVELOPMENT_BLOB_ENDPOINT, config.container_name, blob_name
+        );
+        send(config, Request::get(uri)).await
+    }
+
+    fn container_url(config: &AzureBlobSinkConfig, query: &str) -> String {
+        format!(
+            "{}/{}?{}",
+            DEVELOPMENT_BLOB_ENDPOINT, config.container_name, query
+        )
+    }
+
+    async fn send(config: &AzureBlobSinkConfig, builder: http::request::Builder) -> Vec<u8> {
+        let account = config.storage_account().unwrap();
+        let mut request = builder
+            .header("x-ms-version", API_VERSION)
+            .body(Body::empty())
+            .unwrap();
+        account.credentials.apply(&mut request);
+
+        let mut client = HttpClient::new(crate::dns::Resolver, None).unwrap();
+        let response = client.send(request).await.unwrap();
+        assert!(response.status().is_success(), "{:?}", response.status());
+        hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap()
+            .to_vec()
+    }
+}
@@ -1,25 +1,76 @@
 use crate::{
     config::{log_schema, DataType, SinkConfig, SinkContext, SinkDescription},
-    event::Event,
+    event::{Event, Value},
     sinks::{
-        util::{self, encoding::EncodingConfig, tcp::TcpSink, Encoding, StreamSinkOld, UriSerde},
-        Healthcheck, VectorSink,
+        util::{
+            encoding::{EncodingConfigWithDefault, EncodingConfiguration},
+            http::{HttpBatchService, HttpClient, HttpRetryLogic},
+            BatchConfig, BatchSettings, BoxedRawValue, Compression, JsonArrayBuffer,
+            PartitionBatchSink, PartitionBuffer, PartitionInnerBuffer, TowerRequestConfig,
+            UriSerde,
+        },
+        Healthcheck, HealthcheckError, VectorSink,
     },
-    tls::{MaybeTlsSettings, TlsConfig},
+    tls::{TlsConfig, TlsSettings},
 };
-use bytes::Bytes;
+use flate2::write::GzEncoder;
+use futures::{future, FutureExt};
 use futures01::{stream::iter_ok, Sink};
+use http::{Request, StatusCode, Uri};
+use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use std::io::Write;
+use string_cache::DefaultAtom as Atom;
 
-#[derive(Deserialize, Serialize, Debug)]
+lazy_static! {
+    static ref HOST: UriSerde = Uri::from_static("https://http-intake.logs.datadoghq.com").into();
+}
+
+const PATH: &str = "/v1/input";
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct DatadogLogsConfig {
     endpoint: Option<UriSerde>,
     api_key: String,
-    encoding: EncodingConfig<Encoding>,
+    /// The event field holding the API key to send the event with, so
+    /// that events can be routed to different organizations. Events
+    /// without the field are sent with `api_key`.
+    api_key_field: Option<Atom>,
+    #[serde(
+        skip_serializing_if = "crate::serde::skip_serializing_if_default",
+        default
+    )]
+    encoding: EncodingConfigWithDefault<Encoding>,
+    #[serde(default)]
+    compression: Compression,
+    #[serde(default)]
+    batch: BatchConfig,
+    #[serde(default)]
+    request: TowerRequestConfig,
     tls: Option<TlsConfig>,
 }
 
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display(
+        "The endpoint {:?} has no scheme, `endpoint` must be an HTTP(S) URL such as \"https://http-intake.logs.datadoghq.com\" since the TCP intake is no longer supported",
+        endpoint
+    ))]
+    TcpEndpoint { endpoint: String },
+}
+
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone, Derivative)]
+#[serde(rename_all = "snake_case")]
+#[derivative(Default)]
+pub enum Encoding {
+    #[derivative(Default)]
+    Json,
+    /// Sends only the message, as the TCP intake did.
+    Text,
+}
+
 inventory::submit! {
     SinkDescription::new_without_default::<DatadogLogsConfig>("datadog_logs")
 }
@@ -28,34 +79,39 @@ inventory::submit! {
 #[typetag::serde(name = "datadog_logs")]
 impl SinkConfig for DatadogLogsConfig {
     async fn build(&self, cx: SinkContext) -> crate::Result<(VectorSink, Healthcheck)> {
-        let (host, port) = if let Some(uri) = &self.endpoint {
-            let host = uri
-                .host()
-                .ok_or_else(|| "A host is required for endpoint".to_string())?;
-            let port = uri
-                .port_u16()
-                .ok_or_else(|| "A port is required for endpoint".to_string())?;
-
-            (host.to_string(), port)
-        } else {
-            ("intake.logs.datadoghq.com".to_string(), 10516)
-        };
+        self.uri()?;
+
+        if self.tls.as_ref().and_then(|tls| tls.enabled).is_some() {
+            warn!("The `tls.enabled` setting is deprecated, TLS is used for `https` endpoints");
+        }
+        let tls = TlsSettings::from_options(&self.tls.as_ref().map(|tls| tls.options.clone()))?;
+        let client = HttpClient::new(cx.resolver(), tls)?;
+        let healthcheck = healthcheck(self.clone(), client.clone()).boxed();
 
-        let tls_settings = MaybeTlsSettings::from_config(
-            &Some(self.tls.clone().unwrap_or_else(TlsConfig::enabled)),
-            false,
-        )?;
+        // https://docs.datadoghq.com/api/v1/logs/#send-logs
+        let batch = BatchSettings::default()
+            .bytes(5_000_000)
+            .events(1_000)
+            .timeout(5)
+            .parse_config(self.batch)?;
+        let request = self.request.unwrap_with(&TowerRequestConfig::default());
 
-        let sink = TcpSink::new(host, port, cx.resolver(), tls_settings);
-        let healthcheck = sink.healthcheck();
+        let sink = self.clone();
+        let svc = request.service(
+            HttpRetryLogic,
+            HttpBatchService::new(client, move |request| {
+                future::ready(sink.build_request(request))
+            }),
+        );
 
-        let encoding = self.encoding.clone();
-        let api_key = self.api_key.clone();
+        let buffer = PartitionBuffer::new(JsonArrayBuffer::new(batch.size));
 
-        let sink = StreamSinkOld::new(sink, cx.acker())
-            .with_flat_map(move |e| iter_ok(encode_event(e, &api_key, &encoding)));
+        let config = self.clone();
+        let svc_sink = PartitionBatchSink::new(svc, buffer, batch.timeout, cx.acker())
+            .sink_map_err(|e| error!("Fatal datadog_logs sink error: {}", e))
+            .with_flat_map(move |event| iter_ok(Some(config.encode_event(event))));
 
-        Ok((VectorSink::Futures01Sink(Box::new(sink)), healthcheck))
+        Ok((VectorSink::Futures01Sink(Box::new(svc_sink)), healthcheck))
     }
 
     fn input_type(&self) -> DataType {
@@ -67,31 +123,286 @@ impl SinkConfig for DatadogLogsConfig {
     }
 }
 
-fn encode_event(
-    mut event: Event,
-    api_key: &str,
-    encoding: &EncodingConfig<Encoding>,
-) -> Option<Bytes> {
-    let log = event.as_mut_log();
+impl DatadogLogsConfig {
+    fn uri(&self) -> crate::Result<Uri> {
+        let host: Uri = self.endpoint.clone().unwrap_or_else(|| HOST.clone()).into();
+        // Endpoints of the former TCP intake were given as `host:port`.
+        if host.scheme().is_none() {
+            return Err(BuildError::TcpEndpoint {
+                endpoint: host.to_string(),
+            }
+            .into());
+        }
+
+        format!("{}{}", host.to_string().trim_end_matches('/'), PATH)
+            .parse::<Uri>()
+            .map_err(Into::into)
+    }
+
+    /// Maps the event onto Datadog's reserved attributes, partitioned by
+    /// the API key it's to be sent with.
+    fn encode_event(&self, mut event: Event) -> PartitionInnerBuffer<serde_json::Value, String> {
+        let api_key = self
+            .api_key_field
+            .as_ref()
+            .and_then(|field| event.as_mut_log().remove(field))
+            .map(|api_key| api_key.to_string_lossy())
+            .unwrap_or_else(|| self.api_key.clone());
+
+        let log = event.as_mut_log();
+
+        if let Some(message) = log.remove(&log_schema().message_key()) {
+            log.insert("message", message);
+        }
 
-    if let Some(message) = log.remove(&log_schema().message_key()) {
-        log.insert("message", message);
+        if let Some(timestamp) = log.remove(&log_schema().timestamp_key()) {
+            log.insert("date", timestamp);
+        }
+
+        if let Some(host) = log.remove(&log_schema().host_key()) {
+            log.insert("host", host);
+        }
+
+        // Tags are sent as a comma separated list of `key:value` pairs.
+        if let Some(Value::Array(tags)) = log.remove(&"ddtags".into()) {
+            let tags = tags
+                .iter()
+                .map(Value::to_string_lossy)
+                .collect::<Vec<_>>()
+                .join(",");
+            log.insert("ddtags", tags);
+        }
+
+        self.encoding.apply_rules(&mut event);
+
+        let json = match self.encoding.codec() {
+            Encoding::Json => serde_json::to_value(event.into_log())
+                .expect("Failed to encode event as json, this is a bug!"),
+            Encoding::Text => {
+                let message = event
+                    .as_log()
+                    .get(&"message".into())
+                    .map(Value::to_string_lossy)
+                    .unwrap_or_default();
+                serde_json::json!({ "message": message })
+            }
+        };
+        PartitionInnerBuffer::new(json, api_key)
     }
 
-    if let Some(timestamp) = log.remove(&log_schema().timestamp_key()) {
-        log.insert("date", timestamp);
+    fn build_request(
+        &self,
+        events: PartitionInnerBuffer<Vec<BoxedRawValue>, String>,
+    ) -> crate::Result<Request<Vec<u8>>> {
+        let (events, api_key) = events.into_parts();
+
+        let body = serde_json::to_vec(&events)?;
+        let body = match self.compression {
+            Compression::None => body,
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&body)?;
+                encoder.finish()?
+            }
+        };
+
+        let mut builder = Request::post(self.uri()?)
+            .header("Content-Type", "application/json")
+            .header("DD-API-KEY", api_key);
+        if let Some(ce) = self.compression.content_encoding() {
+            builder = builder.header("Content-Encoding", ce);
+        }
+
+        builder.body(body).map_err(Into::into)
     }
+}
+
+async fn healthcheck(config: DatadogLogsConfig, mut client: HttpClient) -> crate::Result<()> {
+    let request = Request::post(config.uri()?)
+        .header("Content-Type", "application/json")
+        .header("DD-API-KEY", config.api_key)
+        .body(hyper::Body::from("[]"))
+        .unwrap();
 
-    if let Some(host) = log.remove(&log_schema().host_key()) {
-        log.insert("host", host);
+    let response = client.send(request).await?;
+
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        StatusCode::FORBIDDEN => Err("Invalid API key, 403 returned.".into()),
+        other => Err(HealthcheckError::UnexpectedStatus { status: other }.into()),
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::SinkConfig,
+        sinks::util::test::{build_test_server, load_sink},
+        test_util::{next_addr, random_lines, trace_init},
+    };
+    use flate2::read::GzDecoder;
+    use futures::{stream, StreamExt};
+    use serde_json::json;
+    use std::io::Read;
+
+    #[test]
+    fn encode_event_reserved_attributes() {
+        let (config, _cx) = load_sink::<DatadogLogsConfig>(
+            r#"
+            api_key = "default"
+            api_key_field = "dd_api_key"
+            encoding.except_fields = ["magic"]
+        "#,
+        )
+        .unwrap();
+
+        let mut event = Event::from("hello world");
+        event.as_mut_log().insert("host", "vector-host");
+        event.as_mut_log().insert("service", "vector");
+        event.as_mut_log().insert("ddsource", "nginx");
+        event.as_mut_log().insert("status", "warning");
+        event.as_mut_log().insert("ddtags[0]", "env:prod");
+        event.as_mut_log().insert("ddtags[1]", "team:obs");
+        event.as_mut_log().insert("magic", "vector");
+        event.as_mut_log().insert("dd_api_key", "other");
+
+        let (json, api_key) = config.encode_event(event).into_parts();
+        let json = json.as_object().unwrap();
+
+        assert_eq!(api_key, "other");
+        assert_eq!(json["message"], json!("hello world"));
+        assert_eq!(json["host"], json!("vector-host"));
+        assert_eq!(json["service"], json!("vector"));
+        assert_eq!(json["ddsource"], json!("nginx"));
+        assert_eq!(json["status"], json!("warning"));
+        assert_eq!(json["ddtags"], json!("env:prod,team:obs"));
+        assert!(json.contains_key("date"));
+        assert!(!json.contains_key("timestamp"));
+        assert!(!json.contains_key("magic"));
+        assert!(!json.contains_key("dd_api_key"));
+    }
+
+    #[test]
+    fn encode_event_default_api_key() {
+        let (config, _cx) = load_sink::<DatadogLogsConfig>(
+            r#"
+            api_key = "default"
+            api_key_field = "dd_api_key"
+        "#,
+        )
+        .unwrap();
+
+        let (_, api_key) = config.encode_event(Event::from("hello world")).into_parts();
+
+        assert_eq!(api_key, "default");
+    }
+
+    #[test]
+    fn encode_event_text() {
+        let (config, _cx) = load_sink::<DatadogLogsConfig>(
+            r#"
+            api_key = "default"
+            encoding = "text"
+            tls.enabled = true
+        "#,
+        )
+        .unwrap();
+
+        let mut event = Event::from("hello world");
+        event.as_mut_log().insert("service", "vector");
+
+        let (json, _) = config.encode_event(event).into_parts();
+
+        assert_eq!(json, json!({ "message": "hello world" }));
+    }
+
+    #[tokio::test]
+    async fn rejects_tcp_endpoint() {
+        let (config, cx) = load_sink::<DatadogLogsConfig>(
+            r#"
+            api_key = "default"
+            endpoint = "intake.logs.datadoghq.com:10516"
+        "#,
+        )
+        .unwrap();
+
+        let error = config.build(cx).await.err().unwrap();
+        assert!(error.to_string().contains("TCP intake"));
+    }
+
+    #[tokio::test]
+    async fn smoke() {
+        trace_init();
+
+        let (mut config, cx) = load_sink::<DatadogLogsConfig>(
+            r#"
+            api_key = "default"
+            api_key_field = "dd_api_key"
+            compression = "gzip"
+        "#,
+        )
+        .unwrap();
+
+        let addr = next_addr();
+        // Swap out the endpoint so we can force send it
+        // to our local server
+        let endpoint = format!("http://{}", addr).parse::<http::Uri>().unwrap();
+        config.endpoint = Some(endpoint.into());
+
+        let (sink, _) = config.build(cx).await.unwrap();
+
+        let (rx, _trigger, server) = build_test_server(addr);
+        tokio::spawn(server);
+
+        let lines = random_lines(100).take(10).collect::<Vec<_>>();
+        let events = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let mut event = Event::from(line.as_str());
+                if i % 2 == 0 {
+                    event.as_mut_log().insert("dd_api_key", "other");
+                }
+                event
+            })
+            .collect::<Vec<_>>();
+
+        sink.run(stream::iter(events)).await.unwrap();
+
+        let mut output = rx.take(2).collect::<Vec<_>>().await;
+        output.sort_by_key(|(parts, _)| {
+            parts
+                .headers
+                .get("DD-API-KEY")
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_owned()
+        });
+
+        for ((parts, body), (api_key, start)) in
+            output.iter().zip(vec![("default", 1), ("other", 0)])
+        {
+            assert_eq!(parts.uri.path(), "/v1/input");
+            assert_eq!(parts.headers.get("DD-API-KEY").unwrap(), api_key);
+            assert_eq!(parts.headers.get("Content-Encoding").unwrap(), "gzip");
+
+            let mut json = String::new();
+            GzDecoder::new(&body[..]).read_to_string(&mut json).unwrap();
+            let body: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
 
-    if let Some(bytes) = util::encode_event(event, encoding) {
-        // Prepend the api_key:
-        // {API_KEY} {EVENT_BYTES}
-        let api_key = format!("{} {}", api_key, String::from_utf8_lossy(&bytes));
-        Some(Bytes::from(api_key))
-    } else {
-        None
+            let messages = body
+                .iter()
+                .map(|log| log["message"].as_str().unwrap())
+                .collect::<Vec<_>>();
+            let expected = lines
+                .iter()
+                .skip(start)
+                .step_by(2)
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            assert_eq!(messages, expected);
+        }
     }
 }
@@ -23,7 +23,10 @@ use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashMap};
-use std::sync::atomic::{AtomicI64, Ordering::SeqCst};
+use std::sync::{
+    atomic::{AtomicI64, Ordering::SeqCst},
+    Mutex,
+};
 
 #[derive(Debug, Snafu)]
 enum BuildError {
@@ -44,6 +47,8 @@ pub struct DatadogConfig {
     #[serde(alias = "host", default = "default_endpoint")]
     pub endpoint: String,
     pub api_key: String,
+    /// The metric tag mapped into the series `host` field, `host` by default.
+    pub host_tag: Option<String>,
     #[serde(default)]
     pub batch: BatchConfig,
     #[serde(default)]
@@ -54,8 +59,17 @@ struct DatadogSink {
     config: DatadogConfig,
     /// Endpoint -> (uri_path, last_sent_timestamp)
     endpoint_data: HashMap<DatadogEndpoint, (Uri, AtomicI64)>,
+    /// When each count and rate series was last sent, so their intervals
+    /// cover the time since rather than the time since the last batch.
+    series_timestamps: Mutex<HashMap<SeriesKey, i64>>,
 }
 
+/// A series is identified by its metric name, tags and host.
+type SeriesKey = (String, Option<Vec<String>>, Option<String>);
+
+/// How long a series that isn't sent anymore is remembered for.
+const SERIES_EXPIRATION_SECS: i64 = 3600;
+
 lazy_static! {
     static ref REQUEST_DEFAULTS: TowerRequestConfig = TowerRequestConfig {
         retry_attempts: Some(5),
@@ -80,6 +94,8 @@ struct DatadogDistributionMetric {
     interval: Option<i64>,
     points: Vec<DatadogPoint<Vec<f64>>>,
     tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
@@ -89,6 +105,8 @@ struct DatadogMetric {
     interval: Option<i64>,
     points: Vec<DatadogPoint<f64>>,
     tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
@@ -167,6 +185,7 @@ impl SinkConfig for DatadogConfig {
                 .into_iter()
                 .map(|(endpoint, uri)| (endpoint, (uri, AtomicI64::new(timestamp))))
                 .collect(),
+            series_timestamps: Mutex::new(HashMap::new()),
         };
 
         let svc = request.service(
@@ -212,14 +231,26 @@ impl DatadogSink {
         let interval = now - endpoint_data.1.load(SeqCst);
         endpoint_data.1.store(now, SeqCst);
 
+        let namespace = self.config.namespace.as_deref();
+        let host_tag = self.config.host_tag.as_deref().unwrap_or("host");
         let body = match endpoint {
             DatadogEndpoint::Series => {
-                let input = encode_events(events, interval, self.config.namespace.as_deref());
+                let mut series_timestamps = self
+                    .series_timestamps
+                    .lock()
+                    .expect("Series timestamps mutex was poisoned.");
+                let input = encode_events(
+                    events,
+                    interval,
+                    namespace,
+                    host_tag,
+                    &mut series_timestamps,
+                );
+                expire_series(&mut series_timestamps, now);
                 serde_json::to_vec(&input).unwrap()
             }
             DatadogEndpoint::Distribution => {
-                let input =
-                    encode_distribution_events(events, interval, self.config.namespace.as_deref());
+                let input = encode_distribution_events(events, interval, namespace, host_tag);
                 serde_json::to_vec(&input).unwrap()
             }
         };
@@ -258,6 +289,20 @@ async fn healthcheck(config: DatadogConfig, mut client: HttpClient) -> crate::Re
     }
 }
 
+/// Splits the host tag out of the tags, into the separate `host` field.
+fn encode_tags_and_host(
+    tags: Option<BTreeMap<String, String>>,
+    host_tag: &str,
+) -> (Option<Vec<String>>, Option<String>) {
+    match tags {
+        Some(mut tags) => {
+            let host = tags.remove(host_tag);
+            (Some(encode_tags(tags)), host)
+        }
+        None => (None, None),
+    }
+}
+
 fn encode_tags(tags: BTreeMap<String, String>) -> Vec<String> {
     let mut pairs: Vec<_> = tags
         .iter()
@@ -327,10 +372,32 @@ fn stats(values: &[f64], counts: &[u32]) -> Option<DatadogStats> {
     })
 }
 
+/// The interval of a count or rate point: the time since its series was
+/// last sent, or the time since the last batch for series not seen before.
+fn series_interval(
+    series_timestamps: &mut HashMap<SeriesKey, i64>,
+    series: SeriesKey,
+    ts: i64,
+    default: i64,
+) -> i64 {
+    match series_timestamps.insert(series, ts) {
+        Some(last) if ts > last => ts - last,
+        _ => default,
+    }
+}
+
+/// Forgets the series that haven't been sent for `SERIES_EXPIRATION_SECS`,
+/// so that series which come and go don't accumulate.
+fn expire_series(series_timestamps: &mut HashMap<SeriesKey, i64>, now: i64) {
+    series_timestamps.retain(|_, last| now - *last < SERIES_EXPIRATION_SECS);
+}
+
 fn encode_events(
     events: Vec<Metric>,
     interval: i64,
     namespace: Option<&str>,
+    host_tag: &str,
+    series_timestamps: &mut HashMap<SeriesKey, i64>,
 ) -> DatadogRequest<DatadogMetric> {
     debug!(message = "series", count = events.len());
     let series = events
@@ -338,15 +405,20 @@ fn encode_events(
         .filter_map(|event| {
             let fullname = encode_namespace(namespace, '.', &event.name);
             let ts = encode_timestamp(event.timestamp);
-            let tags = event.tags.clone().map(encode_tags);
+            let (tags, host) = encode_tags_and_host(event.tags, host_tag);
+            let mut interval_of = |metric: &str| {
+                let series = (metric.to_owned(), tags.clone(), host.clone());
+                Some(series_interval(series_timestamps, series, ts, interval))
+            };
             match event.kind {
                 MetricKind::Incremental => match event.value {
                     MetricValue::Counter { value } => Some(vec![DatadogMetric {
+                        interval: interval_of(&fullname),
                         metric: fullname,
                         r#type: DatadogMetricType::Count,
-                        interval: Some(interval),
                         points: vec![DatadogPoint(ts, value)],
                         tags,
+                        host,
                     }]),
                     MetricValue::Distribution {
                         values,
@@ -355,41 +427,47 @@ fn encode_events(
                     } => {
                         // https://docs.datadoghq.com/developers/metrics/metrics_type/?tab=histogram#metric-type-definition
                         if let Some(s) = stats(&values, &sample_rates) {
+                            let count = format!("{}.count", &fullname);
                             let mut result = vec![
                                 DatadogMetric {
                                     metric: format!("{}.min", &fullname),
                                     r#type: DatadogMetricType::Gauge,
-                                    interval: Some(interval),
+                                    interval: None,
                                     points: vec![DatadogPoint(ts, s.min)],
                                     tags: tags.clone(),
+                                    host: host.clone(),
                                 },
                                 DatadogMetric {
                                     metric: format!("{}.avg", &fullname),
                                     r#type: DatadogMetricType::Gauge,
-                                    interval: Some(interval),
+                                    interval: None,
                                     points: vec![DatadogPoint(ts, s.avg)],
                                     tags: tags.clone(),
+                                    host: host.clone(),
                                 },
                                 DatadogMetric {
-                                    metric: format!("{}.count", &fullname),
+                                    interval: interval_of(&count),
+                                    metric: count,
                                     r#type: DatadogMetricType::Rate,
-                                    interval: Some(interval),
                                     points: vec![DatadogPoint(ts, s.count)],
                                     tags: tags.clone(),
+                                    host: host.clone(),
                                 },
                                 DatadogMetric {
                                     metric: format!("{}.median", &fullname),
                                     r#type: DatadogMetricType::Gauge,
-                                    interval: Some(interval),
+                                    interval: None,
                                     points: vec![DatadogPoint(ts, s.median)],
                                     tags: tags.clone(),
+                                    host: host.clone(),
                                 },
                                 DatadogMetric {
                                     metric: format!("{}.max", &fullname),
                                     r#type: DatadogMetricType::Gauge,
-                                    interval: Some(interval),
+                                    interval: None,
                                     points: vec![DatadogPoint(ts, s.max)],
                                     tags: tags.clone(),
+                                    host: host.clone(),
                                 },
                             ];
                             for (q, v) in s.quantiles {
@@ -400,9 +478,10 @@ fn encode_events(
                                         (q * 100.0) as u32
                                     ),
                                     r#type: DatadogMetricType::Gauge,
-                                    interval: Some(interval),
+                                    interval: None,
                                     points: vec![DatadogPoint(ts, v)],
                                     tags: tags.clone(),
+                                    host: host.clone(),
                                 })
                             }
                             Some(result)
@@ -416,6 +495,7 @@ fn encode_events(
                         interval: None,
                         points: vec![DatadogPoint(ts, values.len() as f64)],
                         tags,
+                        host,
                     }]),
                     _ => None,
                 },
@@ -426,6 +506,7 @@ fn encode_events(
                         interval: None,
                         points: vec![DatadogPoint(ts, value)],
                         tags,
+                        host,
                     }]),
                     _ => None,
                 },
@@ -437,10 +518,13 @@ fn encode_events(
     DatadogRequest { series }
 }
 
+// Summary distributions are sent as raw samples, from which Datadog
+// computes accurate percentiles across all hosts.
 fn encode_distribution_events(
     events: Vec<Metric>,
     interval: i64,
     namespace: Option<&str>,
+    host_tag: &str,
 ) -> DatadogRequest<DatadogDistributionMetric> {
     debug!(message = "distribution", count = events.len());
     let series = events
@@ -448,7 +532,7 @@ fn encode_distribution_events(
         .filter_map(|event| {
             let fullname = encode_namespace(namespace, '.', &event.name);
             let ts = encode_timestamp(event.timestamp);
-            let tags = event.tags.clone().map(encode_tags);
+            let (tags, host) = encode_tags_and_host(event.tags, host_tag);
             match event.kind {
                 MetricKind::Incremental => match event.value {
                     MetricValue::Distribution {
@@ -471,6 +555,7 @@ fn encode_distribution_events(
                                 interval: Some(interval),
                                 points: vec![DatadogPoint(ts, samples)],
                                 tags,
+                                host,
                             })
                         }
                     }
@@ -528,6 +613,7 @@ mod tests {
                 .into_iter()
                 .map(|(endpoint, uri)| (endpoint, (uri, AtomicI64::new(timestamp))))
                 .collect(),
+            series_timestamps: Mutex::new(HashMap::new()),
         };
 
         let events = vec![
@@ -605,7 +691,7 @@ mod tests {
                 value: MetricValue::Counter { value: 1.0 },
             },
         ];
-        let input = encode_events(events, interval, Some("ns"));
+        let input = encode_events(events, interval, Some("ns"), "host", &mut HashMap::new());
         let json = serde_json::to_string(&input).unwrap();
 
         assert_eq!(
@@ -632,7 +718,7 @@ mod tests {
                 value: MetricValue::Gauge { value: -1.1 },
             },
         ];
-        let input = encode_events(events, 60, Some(""));
+        let input = encode_events(events, 60, Some(""), "host", &mut HashMap::new());
         let json = serde_json::to_string(&input).unwrap();
 
         assert_eq!(
@@ -652,7 +738,7 @@ mod tests {
                 values: vec!["alice".into(), "bob".into()].into_iter().collect(),
             },
         }];
-        let input = encode_events(events, 60, Some(""));
+        let input = encode_events(events, 60, Some(""), "host", &mut HashMap::new());
         let json = serde_json::to_string(&input).unwrap();
 
         assert_eq!(
@@ -760,12 +846,12 @@ mod tests {
                 statistic: StatisticKind::Histogram,
             },
         }];
-        let input = encode_events(events, 60, Some(""));
+        let input = encode_events(events, 60, Some(""), "host", &mut HashMap::new());
         let json = serde_json::to_string(&input).unwrap();
 
         assert_eq!(
             json,
-            r#"{"series":[{"metric":"requests.min","type":"gauge","interval":60,"points":[[1542182950,1.0]],"tags":null},{"metric":"requests.avg","type":"gauge","interval":60,"points":[[1542182950,1.875]],"tags":null},{"metric":"requests.count","type":"rate","interval":60,"points":[[1542182950,8.0]],"tags":null},{"metric":"requests.median","type":"gauge","interval":60,"points":[[1542182950,2.0]],"tags":null},{"metric":"requests.max","type":"gauge","interval":60,"points":[[1542182950,3.0]],"tags":null},{"metric":"requests.95percentile","type":"gauge","interval":60,"points":[[1542182950,3.0]],"tags":null}]}"#
+            r#"{"series":[{"metric":"requests.min","type":"gauge","interval":null,"points":[[1542182950,1.0]],"tags":null},{"metric":"requests.avg","type":"gauge","interval":null,"points":[[1542182950,1.875]],"tags":null},{"metric":"requests.count","type":"rate","interval":60,"points":[[1542182950,8.0]],"tags":null},{"metric":"requests.median","type":"gauge","interval":null,"points":[[1542182950,2.0]],"tags":null},{"metric":"requests.max","type":"gauge","interval":null,"points":[[1542182950,3.0]],"tags":null},{"metric":"requests.95percentile","type":"gauge","interval":null,"points":[[1542182950,3.0]],"tags":null}]}"#
         );
     }
 
@@ -783,7 +869,7 @@ mod tests {
                 statistic: StatisticKind::Summary,
             },
         }];
-        let input = encode_distribution_events(events, 60, None);
+        let input = encode_distribution_events(events, 60, None, "host");
         let json = serde_json::to_string(&input).unwrap();
 
         assert_eq!(
@@ -791,4 +877,94 @@ mod tests {
             r#"{"series":[{"metric":"requests","interval":60,"points":[[1542182950,[1.0,1.0,1.0,2.0,2.0,2.0,3.0,3.0]]],"tags":null}]}"#
         );
     }
+
+    #[test]
+    fn encode_host_tag() {
+        let mut tags = tags();
+        tags.insert("hostname".to_owned(), "vector-host".to_owned());
+        let events = vec![Metric {
+            name: "volume".into(),
+            timestamp: Some(ts()),
+            tags: Some(tags.clone()),
+            kind: MetricKind::Absolute,
+            value: MetricValue::Gauge { value: -1.1 },
+        }];
+        let input = encode_events(events, 60, None, "hostname", &mut HashMap::new());
+        let json = serde_json::to_string(&input).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"series":[{"metric":"volume","type":"gauge","interval":null,"points":[[1542182950,-1.1]],"tags":["empty_tag:","normal_tag:value","true_tag:true"],"host":"vector-host"}]}"#
+        );
+
+        let events = vec![Metric {
+            name: "requests".into(),
+            timestamp: Some(ts()),
+            tags: Some(tags),
+            kind: MetricKind::Incremental,
+            value: MetricValue::Distribution {
+                values: vec![1.0],
+                sample_rates: vec![1],
+                statistic: StatisticKind::Summary,
+            },
+        }];
+        let input = encode_distribution_events(events, 60, None, "hostname");
+        let json = serde_json::to_string(&input).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"series":[{"metric":"requests","interval":60,"points":[[1542182950,[1.0]]],"tags":["empty_tag:","normal_tag:value","true_tag:true"],"host":"vector-host"}]}"#
+        );
+    }
+
+    #[test]
+    fn encode_counter_series_intervals() {
+        let counter = |name: &str, seconds: i64| Metric {
+            name: name.into(),
+            timestamp: Some(ts() + chrono::Duration::seconds(seconds)),
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Counter { value: 1.0 },
+        };
+        let intervals = |input: DatadogRequest<DatadogMetric>| {
+            input
+                .series
+                .into_iter()
+                .map(|metric| metric.interval)
+                .collect::<Vec<_>>()
+        };
+        let mut series_timestamps = HashMap::new();
+
+        let input = encode_events(
+            vec![counter("first", 0)],
+            60,
+            None,
+            "host",
+            &mut series_timestamps,
+        );
+        assert_eq!(intervals(input), vec![Some(60)]);
+
+        let input = encode_events(
+            vec![counter("first", 15), counter("second", 15)],
+            60,
+            None,
+            "host",
+            &mut series_timestamps,
+        );
+        assert_eq!(intervals(input), vec![Some(15), Some(60)]);
+    }
+
+    #[test]
+    fn expire_unseen_series() {
+        let mut series_timestamps = HashMap::new();
+        series_timestamps.insert(("old".to_owned(), None, None), 0);
+        series_timestamps.insert(("recent".to_owned(), None, None), SERIES_EXPIRATION_SECS);
+
+        expire_series(&mut series_timestamps, SERIES_EXPIRATION_SECS + 1);
+
+        assert_eq!(
+            series_timestamps.keys().collect::<Vec<_>>(),
+            vec![&("recent".to_owned(), None, None)]
+        );
+    }
 }
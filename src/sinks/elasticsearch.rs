@@ -1,21 +1,29 @@
 use crate::{
-    config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    config::{log_schema, DataType, SinkConfig, SinkContext, SinkDescription},
     emit,
     event::Event,
-    internal_events::{ElasticSearchEventReceived, ElasticSearchMissingKeys},
+    internal_events::{
+        ElasticSearchBulkItemsFailed, ElasticSearchEventReceived, ElasticSearchFailureIndexFailed,
+        ElasticSearchInvalidBulkAction, ElasticSearchInvalidResponse, ElasticSearchMissingKeys,
+    },
     region::{region_from_endpoint, RegionOrEndpoint},
     sinks::util::{
         encoding::{EncodingConfigWithDefault, EncodingConfiguration},
-        http::{BatchedHttpSink, HttpClient, HttpSink},
+        http::{HttpBatchService, HttpClient},
         retries::{RetryAction, RetryLogic},
-        rusoto, BatchConfig, BatchSettings, Buffer, Compression, TowerRequestConfig,
+        rusoto,
+        service::Svc,
+        BatchConfig, BatchSettings, BatchSink, Compression, EncodedLength, TowerRequestConfig,
+        TowerRequestSettings, VecBuffer,
     },
     template::{Template, TemplateError},
     tls::{TlsOptions, TlsSettings},
 };
 use bytes::Bytes;
-use futures::FutureExt;
-use futures01::Sink;
+use chrono::Utc;
+use flate2::write::GzEncoder;
+use futures::{future::BoxFuture, FutureExt};
+use futures01::{stream::iter_ok, Sink};
 use http::{
     header::{HeaderName, HeaderValue},
     uri::InvalidUri,
@@ -29,8 +37,21 @@ use rusoto_signature::{SignedRequest, SignedRequestPayload};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use snafu::{ResultExt, Snafu};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
+use std::io::Write;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::delay_for;
+use tower::{buffer::Buffer, Service, ServiceBuilder, ServiceExt};
+
+/// How many times the failed items of a bulk request are resent on their
+/// own.
+const MAX_ITEM_RETRIES: usize = 3;
+
+/// How many bulk requests may wait for the request service to be ready.
+const SERVICE_BUFFER_SIZE: usize = 100;
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
 #[serde(deny_unknown_fields)]
@@ -42,6 +63,13 @@ pub struct ElasticSearchConfig {
     pub doc_type: Option<String>,
     pub id_key: Option<String>,
     pub pipeline: Option<String>,
+    pub bulk_action: Option<String>,
+    #[serde(default)]
+    pub mode: ElasticSearchMode,
+    pub data_stream: Option<DataStreamConfig>,
+    /// Documents that failed for reasons retrying won't fix are indexed
+    /// here, along with the error, instead of being dropped.
+    pub failure_index: Option<String>,
 
     #[serde(default)]
     pub compression: Compression,
@@ -77,6 +105,51 @@ pub enum Encoding {
     Default,
 }
 
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone, Copy, Derivative)]
+#[serde(rename_all = "snake_case")]
+#[derivative(Default)]
+pub enum ElasticSearchMode {
+    #[derivative(Default)]
+    Normal,
+    /// Events are appended to `type-dataset-namespace` data streams, with
+    /// `create` actions.
+    DataStream,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Derivative)]
+#[serde(deny_unknown_fields)]
+#[derivative(Default)]
+pub struct DataStreamConfig {
+    #[serde(rename = "type", default = "DataStreamConfig::default_type")]
+    #[derivative(Default(value = "DataStreamConfig::default_type()"))]
+    pub dtype: String,
+    #[serde(default = "DataStreamConfig::default_dataset")]
+    #[derivative(Default(value = "DataStreamConfig::default_dataset()"))]
+    pub dataset: String,
+    #[serde(default = "DataStreamConfig::default_namespace")]
+    #[derivative(Default(value = "DataStreamConfig::default_namespace()"))]
+    pub namespace: String,
+    /// Whether to set the `data_stream` fields of events to the data
+    /// stream they're sent to.
+    #[serde(default = "crate::serde::default_true")]
+    #[derivative(Default(value = "true"))]
+    pub sync_fields: bool,
+}
+
+impl DataStreamConfig {
+    fn default_type() -> String {
+        "logs".into()
+    }
+
+    fn default_dataset() -> String {
+        "generic".into()
+    }
+
+    fn default_namespace() -> String {
+        "default".into()
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(deny_unknown_fields, rename_all = "snake_case", tag = "strategy")]
 pub enum ElasticSearchAuth {
@@ -110,24 +183,23 @@ impl SinkConfig for ElasticSearchConfig {
 
         let healthcheck = healthcheck(client.clone(), common).boxed();
 
-        let common = ElasticSearchCommon::parse_config(&self)?;
-        let compression = common.compression;
+        let common = Arc::new(ElasticSearchCommon::parse_config(&self)?);
+        // Batches used to be counted in bytes with `max_size`.
         let batch = BatchSettings::default()
             .bytes(bytesize::mib(10u64))
             .timeout(1)
-            .parse_config(self.batch)?;
+            .parse_config(self.batch.use_size_as_bytes()?)?;
         let request = self.request.unwrap_with(&REQUEST_DEFAULTS);
 
-        let sink = BatchedHttpSink::with_retry_logic(
-            common,
-            Buffer::new(batch.size, compression),
-            ElasticSearchRetryLogic,
-            request,
+        let service = ElasticSearchService::new(client, Arc::clone(&common), &request);
+        let sink = BatchSink::new(
+            service,
+            VecBuffer::new(batch.size),
             batch.timeout,
-            client,
             cx.acker(),
         )
-        .sink_map_err(|e| error!("Fatal elasticsearch sink error: {}", e));
+        .sink_map_err(|e| error!("Fatal elasticsearch sink error: {}", e))
+        .with_flat_map(move |event| iter_ok(common.encode_event(event)));
 
         Ok((
             super::VectorSink::Futures01Sink(Box::new(sink)),
@@ -151,6 +223,9 @@ pub struct ElasticSearchCommon {
     authorization: Option<String>,
     credentials: Option<rusoto::AwsCredentialsProvider>,
     index: Template,
+    bulk_action: Template,
+    data_stream: Option<DataStreamTemplates>,
+    failure_index: Option<String>,
     doc_type: String,
     tls_settings: TlsSettings,
     config: ElasticSearchConfig,
@@ -159,6 +234,104 @@ pub struct ElasticSearchCommon {
     query_params: HashMap<String, String>,
 }
 
+#[derive(Debug)]
+struct DataStreamTemplates {
+    dtype: Template,
+    dataset: Template,
+    namespace: Template,
+    sync_fields: bool,
+}
+
+impl DataStreamTemplates {
+    fn new(config: &DataStreamConfig) -> Result<Self, TemplateError> {
+        Ok(Self {
+            dtype: Template::try_from(config.dtype.as_str())?,
+            dataset: Template::try_from(config.dataset.as_str())?,
+            namespace: Template::try_from(config.namespace.as_str())?,
+            sync_fields: config.sync_fields,
+        })
+    }
+
+    /// Renders the name of the data stream the event is sent to.
+    fn index(&self, event: &mut Event) -> Option<String> {
+        let dtype = render_template(&self.dtype, event)?;
+        let dataset = render_template(&self.dataset, event)?;
+        let namespace = render_template(&self.namespace, event)?;
+        let index = format!("{}-{}-{}", dtype, dataset, namespace);
+
+        if self.sync_fields {
+            let log = event.as_mut_log();
+            log.insert("data_stream.type", dtype);
+            log.insert("data_stream.dataset", dataset);
+            log.insert("data_stream.namespace", namespace);
+        }
+
+        Some(index)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BulkAction {
+    Index,
+    Create,
+    Update,
+    Delete,
+}
+
+impl BulkAction {
+    fn parse(action: &str) -> Option<Self> {
+        match action {
+            "index" => Some(Self::Index),
+            "create" => Some(Self::Create),
+            "update" => Some(Self::Update),
+            "delete" => Some(Self::Delete),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Index => "index",
+            Self::Create => "create",
+            Self::Update => "update",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// A single action of a bulk request, kept apart so that it can be resent
+/// on its own.
+#[derive(Clone, Debug)]
+struct BulkItem {
+    index: String,
+    action: BulkAction,
+    metadata: Vec<u8>,
+    /// The document line, which `delete` actions don't have.
+    document: Option<Vec<u8>>,
+}
+
+impl BulkItem {
+    fn write(&self, body: &mut Vec<u8>) {
+        body.extend_from_slice(&self.metadata);
+        body.push(b'\n');
+        if let Some(document) = &self.document {
+            body.extend_from_slice(document);
+            body.push(b'\n');
+        }
+    }
+}
+
+impl EncodedLength for BulkItem {
+    fn encoded_length(&self) -> usize {
+        self.metadata.len()
+            + 1
+            + self
+                .document
+                .as_ref()
+                .map_or(0, |document| document.len() + 1)
+    }
+}
+
 #[derive(Debug, Snafu)]
 enum ParseError {
     #[snafu(display("Invalid host {:?}: {:?}", host, source))]
@@ -171,97 +344,158 @@ enum ParseError {
     AWSCompressionNotAllowed,
     #[snafu(display("Index template parse error: {}", source))]
     IndexTemplate { source: TemplateError },
+    #[snafu(display("Bulk action template parse error: {}", source))]
+    BulkActionTemplate { source: TemplateError },
+    #[snafu(display(
+        "Invalid bulk action {:?}, expected one of index, create, update or delete",
+        action
+    ))]
+    InvalidBulkAction { action: String },
+    #[snafu(display("Data streams only allow the create bulk action"))]
+    BulkActionNotAllowed,
+    #[snafu(display("Data stream template parse error: {}", source))]
+    DataStreamTemplate { source: TemplateError },
 }
 
-#[async_trait::async_trait]
-impl HttpSink for ElasticSearchCommon {
-    type Input = Vec<u8>;
-    type Output = Vec<u8>;
-
-    fn encode_event(&self, mut event: Event) -> Option<Self::Input> {
-        let index = self
-            .index
-            .render_string(&event)
-            .map_err(|missing_keys| {
-                emit!(ElasticSearchMissingKeys { keys: missing_keys });
-            })
-            .ok()?;
-
-        let mut action = json!({
-            "index": {
-                "_index": index,
-                "_type": self.doc_type,
-            }
-        });
-        maybe_set_id(
-            self.config.id_key.as_ref(),
-            action.pointer_mut("/index").unwrap(),
-            &mut event,
-        );
-
-        let mut body = serde_json::to_vec(&action).unwrap();
-        body.push(b'\n');
+fn render_template(template: &Template, event: &Event) -> Option<String> {
+    template
+        .render_string(event)
+        .map_err(|missing_keys| {
+            emit!(ElasticSearchMissingKeys { keys: missing_keys });
+        })
+        .ok()
+}
 
-        self.config.encoding.apply_rules(&mut event);
+type BulkService = Buffer<
+    Svc<
+        HttpBatchService<BoxFuture<'static, crate::Result<Request<Vec<u8>>>>, Vec<BulkItem>>,
+        ElasticSearchRetryLogic,
+    >,
+    Vec<BulkItem>,
+>;
 
-        serde_json::to_writer(&mut body, &event.into_log()).unwrap();
-        body.push(b'\n');
+#[derive(Clone)]
+struct ElasticSearchService {
+    common: Arc<ElasticSearchCommon>,
+    /// Sends the bulk requests with the timeout and retries of `request`,
+    /// so that every resend of failed items gets its own timeout instead of
+    /// counting against the one of the batch.
+    inner: BulkService,
+}
 
-        emit!(ElasticSearchEventReceived {
-            byte_size: body.len(),
-            index
-        });
+impl ElasticSearchService {
+    fn new(
+        client: HttpClient,
+        common: Arc<ElasticSearchCommon>,
+        request: &TowerRequestSettings,
+    ) -> Self {
+        let builder_common = Arc::clone(&common);
+        let http = HttpBatchService::new(
+            client,
+            move |items| -> BoxFuture<'static, crate::Result<Request<Vec<u8>>>> {
+                let common = Arc::clone(&builder_common);
+                Box::pin(async move { common.build_request(items).await })
+            },
+        );
+        let inner = ServiceBuilder::new()
+            .buffer(SERVICE_BUFFER_SIZE)
+            .service(request.service(ElasticSearchRetryLogic, http));
 
-        Some(body)
+        Self { common, inner }
     }
 
-    async fn build_request(&self, events: Self::Output) -> crate::Result<http::Request<Vec<u8>>> {
-        let mut builder = Request::post(&self.bulk_uri);
-
-        if let Some(credentials_provider) = &self.credentials {
-            let mut request = self.signed_request("POST", &self.bulk_uri, true);
-
-            request.add_header("Content-Type", "application/x-ndjson");
+    /// Resends the items that failed for reasons that may pass, and sends
+    /// the rest to the failure index, if there is one.
+    async fn handle_failures(&mut self, mut items: Vec<BulkItem>, mut failures: Vec<BulkFailure>) {
+        let routed = self.common.failure_index.is_some();
+        let mut backoff = Duration::from_millis(500);
+        let mut retries = 0;
+
+        while !failures.is_empty() {
+            let (mut retriable, mut failed): (Vec<_>, Vec<_>) =
+                failures.into_iter().partition(BulkFailure::is_retriable);
+            let give_up = retries == MAX_ITEM_RETRIES;
+
+            emit_failures(&failed, false, routed);
+            emit_failures(&retriable, !give_up, routed);
+            if give_up {
+                failed.append(&mut retriable);
+            }
+            self.send_to_failure_index(&items, &failed).await;
 
-            if let Some(headers) = &self.config.headers {
-                for (header, value) in headers {
-                    request.add_header(header, value);
-                }
+            if retriable.is_empty() {
+                return;
             }
+            items = retriable
+                .iter()
+                .map(|failure| items[failure.position].clone())
+                .collect();
+
+            delay_for(backoff).await;
+            backoff *= 2;
+            retries += 1;
+
+            failures = match self.inner.clone().oneshot(items.clone()).await {
+                Ok(response) if response.status().is_success() => bulk_failures(response.body()),
+                Ok(response) => {
+                    BulkFailure::request_failed(&items, response.status().as_u16(), "http_error")
+                }
+                Err(_) => BulkFailure::request_failed(&items, 503, "request_failed"),
+            };
+        }
+    }
 
-            request.set_payload(Some(events));
+    async fn send_to_failure_index(&mut self, items: &[BulkItem], failed: &[BulkFailure]) {
+        let failure_index = match &self.common.failure_index {
+            Some(failure_index) if !failed.is_empty() => failure_index,
+            _ => return,
+        };
 
-            // mut builder?
-            builder = finish_signer(&mut request, &credentials_provider, builder).await?;
+        let failed_items = failed
+            .iter()
+            .map(|failure| {
+                self.common
+                    .failure_item(failure_index, &items[failure.position], failure)
+            })
+            .collect();
 
-            // The SignedRequest ends up owning the body, so we have
-            // to play games here
-            let body = request.payload.take().unwrap();
-            match body {
-                SignedRequestPayload::Buffer(body) => {
-                    builder.body(body.to_vec()).map_err(Into::into)
-                }
-                _ => unreachable!(),
+        let sent = match self.inner.clone().oneshot(failed_items).await {
+            Ok(response) => {
+                response.status().is_success() && bulk_failures(response.body()).is_empty()
             }
-        } else {
-            builder = builder.header("Content-Type", "application/x-ndjson");
+            Err(_) => false,
+        };
+        if !sent {
+            emit!(ElasticSearchFailureIndexFailed {
+                count: failed.len()
+            });
+        }
+    }
+}
 
-            if let Some(ce) = self.compression.content_encoding() {
-                builder = builder.header("Content-Encoding", ce);
-            }
+impl Service<Vec<BulkItem>> for ElasticSearchService {
+    type Response = hyper::Response<Bytes>;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
-            if let Some(headers) = &self.config.headers {
-                for (header, value) in headers {
-                    builder = builder.header(&header[..], &value[..]);
-                }
-            }
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
 
-            if let Some(auth) = &self.authorization {
-                builder = builder.header("Authorization", &auth[..]);
+    fn call(&mut self, items: Vec<BulkItem>) -> Self::Future {
+        // Failures of the whole request are left to the retry policy, while
+        // the failed items are resent after it completed.
+        let response = self.inner.call(items.clone());
+        let mut service = self.clone();
+        Box::pin(async move {
+            let response = response.await?;
+            if response.status().is_success() {
+                let failures = bulk_failures(response.body());
+                service.handle_failures(items, failures).await;
             }
 
-            builder.body(events).map_err(Into::into)
-        }
+            Ok(response)
+        })
     }
 }
 
@@ -270,14 +504,13 @@ struct ElasticSearchRetryLogic;
 
 #[derive(Deserialize, Debug)]
 struct ESResultResponse {
-    items: Vec<ESResultItem>,
+    errors: bool,
+    /// Keyed by the item's action.
+    items: Vec<HashMap<String, ESResultItem>>,
 }
 #[derive(Deserialize, Debug)]
 struct ESResultItem {
-    index: ESIndexResult,
-}
-#[derive(Deserialize, Debug)]
-struct ESIndexResult {
+    status: u16,
     error: Option<ESErrorDetails>,
 }
 #[derive(Deserialize, Debug)]
@@ -287,6 +520,85 @@ struct ESErrorDetails {
     err_type: String,
 }
 
+/// A failed item of a bulk request, by its position in the request.
+#[derive(Debug, PartialEq)]
+struct BulkFailure {
+    position: usize,
+    status: u16,
+    error_type: String,
+    reason: String,
+}
+
+impl BulkFailure {
+    /// Rejections because of load, like `es_rejected_execution_exception`,
+    /// and failures on the server's side may pass.
+    fn is_retriable(&self) -> bool {
+        self.status == 429 || self.status >= 500
+    }
+
+    fn request_failed(items: &[BulkItem], status: u16, error_type: &str) -> Vec<Self> {
+        (0..items.len())
+            .map(|position| Self {
+                position,
+                status,
+                error_type: error_type.into(),
+                reason: "resending failed items failed".into(),
+            })
+            .collect()
+    }
+}
+
+fn bulk_failures(body: &[u8]) -> Vec<BulkFailure> {
+    if !String::from_utf8_lossy(body).contains("\"errors\":true") {
+        return Vec::new();
+    }
+
+    match serde_json::from_slice::<ESResultResponse>(body) {
+        Ok(response) if response.errors => response
+            .items
+            .into_iter()
+            .enumerate()
+            .filter_map(|(position, item)| {
+                let item = item.into_iter().next()?.1;
+                let error = item.error?;
+                Some(BulkFailure {
+                    position,
+                    status: item.status,
+                    error_type: error.err_type,
+                    reason: error.reason,
+                })
+            })
+            .collect(),
+        Ok(_) => Vec::new(),
+        Err(error) => {
+            emit!(ElasticSearchInvalidResponse { error });
+            Vec::new()
+        }
+    }
+}
+
+/// Counts the failures by their error type, giving the first reason of
+/// each.
+fn emit_failures(failures: &[BulkFailure], retried: bool, routed: bool) {
+    let mut counts = BTreeMap::new();
+    for failure in failures {
+        counts
+            .entry(failure.error_type.as_str())
+            .or_insert((0, failure.reason.as_str()))
+            .0 += 1;
+    }
+
+    for (error_type, (count, reason)) in counts {
+        emit!(ElasticSearchBulkItemsFailed {
+            count,
+            error_type,
+            reason,
+            retried,
+            routed,
+        });
+    }
+}
+
 impl RetryLogic for ElasticSearchRetryLogic {
     type Error = hyper::Error;
     type Response = hyper::Response<Bytes>;
@@ -312,33 +624,13 @@ impl RetryLogic for ElasticSearchRetryLogic {
                 let body = String::from_utf8_lossy(response.body());
                 RetryAction::DontRetry(format!("client-side error, {}: {}", status, body))
             }
-            _ if status.is_success() => {
-                let body = String::from_utf8_lossy(response.body());
-
-                if body.contains("\"errors\":true") {
-                    RetryAction::DontRetry(get_error_reason(&body))
-                } else {
-                    RetryAction::Successful
-                }
-            }
+            // Failed items are handled by the service, item by item.
+            _ if status.is_success() => RetryAction::Successful,
             _ => RetryAction::DontRetry(format!("response status: {}", status)),
         }
     }
 }
 
-fn get_error_reason(body: &str) -> String {
-    match serde_json::from_str::<ESResultResponse>(&body) {
-        Err(json_error) => format!(
-            "some messages failed, could not parse response, error: {}",
-            json_error
-        ),
-        Ok(resp) => match resp.items.into_iter().find_map(|item| item.index.error) {
-            Some(error) => format!("error type: {}, reason: {}", error.err_type, error.reason),
-            None => format!("error response: {}", body),
-        },
-    }
-}
-
 impl ElasticSearchCommon {
     pub fn parse_config(config: &ElasticSearchConfig) -> crate::Result<Self> {
         let authorization = match &config.auth {
@@ -383,6 +675,32 @@ impl ElasticSearchCommon {
         let index = config.index.as_deref().unwrap_or("vector-%Y.%m.%d");
         let index = Template::try_from(index).context(IndexTemplate)?;
 
+        let data_stream = match config.mode {
+            ElasticSearchMode::Normal => None,
+            ElasticSearchMode::DataStream => {
+                if config.bulk_action.is_some() {
+                    return Err(ParseError::BulkActionNotAllowed.into());
+                }
+                let data_stream = config.data_stream.clone().unwrap_or_default();
+                Some(DataStreamTemplates::new(&data_stream).context(DataStreamTemplate)?)
+            }
+        };
+
+        let bulk_action = match data_stream {
+            Some(_) => "create",
+            None => config.bulk_action.as_deref().unwrap_or("index"),
+        };
+        let bulk_action = Template::try_from(bulk_action).context(BulkActionTemplate)?;
+        if !bulk_action.is_dynamic() {
+            let action = String::from_utf8_lossy(bulk_action.get_ref());
+            if BulkAction::parse(&action).is_none() {
+                return Err(ParseError::InvalidBulkAction {
+                    action: action.into_owned(),
+                }
+                .into());
+            }
+        }
+
         let doc_type = config.doc_type.clone().unwrap_or_else(|| "_doc".into());
 
         let request = config.request.unwrap_with(&REQUEST_DEFAULTS);
@@ -402,6 +720,7 @@ impl ElasticSearchCommon {
         let bulk_uri = bulk_url.parse::<Uri>().unwrap();
 
         let tls_settings = TlsSettings::from_options(&config.tls)?;
+        let failure_index = config.failure_index.clone();
         let config = config.clone();
 
         Ok(Self {
@@ -410,6 +729,9 @@ impl ElasticSearchCommon {
             authorization,
             credentials,
             index,
+            bulk_action,
+            data_stream,
+            failure_index,
             doc_type,
             tls_settings,
             config,
@@ -419,6 +741,166 @@ impl ElasticSearchCommon {
         })
     }
 
+    fn encode_event(&self, mut event: Event) -> Option<BulkItem> {
+        let index = match &self.data_stream {
+            Some(data_stream) => data_stream.index(&mut event)?,
+            None => render_template(&self.index, &event)?,
+        };
+
+        let action = render_template(&self.bulk_action, &event)?;
+        let action = match BulkAction::parse(&action) {
+            Some(action) => action,
+            None => {
+                emit!(ElasticSearchInvalidBulkAction { action: &action });
+                return None;
+            }
+        };
+
+        let mut metadata = json!({ "_index": index });
+        if self.data_stream.is_none() {
+            metadata["_type"] = json!(self.doc_type);
+        }
+        maybe_set_id(self.config.id_key.as_ref(), &mut metadata, &mut event);
+
+        let mut metadata_line = serde_json::Map::new();
+        metadata_line.insert(action.as_str().into(), metadata);
+        let metadata = serde_json::to_vec(&metadata_line).unwrap();
+
+        if self.data_stream.is_some() {
+            // Data streams require documents to have an `@timestamp`.
+            let log = event.as_mut_log();
+            let timestamp_key = log_schema().timestamp_key();
+            if !log.contains("@timestamp") {
+                if let Some(timestamp) = log.remove(timestamp_key) {
+                    log.insert("@timestamp", timestamp);
+                }
+            }
+        }
+
+        self.config.encoding.apply_rules(&mut event);
+
+        let log = event.into_log();
+        let document = match action {
+            BulkAction::Delete => None,
+            BulkAction::Update => Some(json!({ "doc": log, "doc_as_upsert": true })),
+            BulkAction::Index | BulkAction::Create => Some(json!(log)),
+        };
+        let document = document.map(|document| serde_json::to_vec(&document).unwrap());
+
+        let item = BulkItem {
+            index,
+            action,
+            metadata,
+            document,
+        };
+
+        emit!(ElasticSearchEventReceived {
+            byte_size: item.encoded_length(),
+            index: item.index.clone(),
+        });
+
+        Some(item)
+    }
+
+    /// Wraps a failed item in a document for the failure index, keeping the
+    /// original document as a string so that it can't fail the same way.
+    fn failure_item(
+        &self,
+        failure_index: &str,
+        item: &BulkItem,
+        failure: &BulkFailure,
+    ) -> BulkItem {
+        let metadata = json!({
+            "index": {
+                "_index": failure_index,
+                "_type": self.doc_type,
+            }
+        });
+        let document = json!({
+            "timestamp": Utc::now(),
+            "index": item.index,
+            "action": item.action.as_str(),
+            "status": failure.status,
+            "error": {
+                "type": failure.error_type,
+                "reason": failure.reason,
+            },
+            "document": item
+                .document
+                .as_ref()
+                .map(|document| String::from_utf8_lossy(document)),
+        });
+
+        BulkItem {
+            index: failure_index.into(),
+            action: BulkAction::Index,
+            metadata: serde_json::to_vec(&metadata).unwrap(),
+            document: Some(serde_json::to_vec(&document).unwrap()),
+        }
+    }
+
+    async fn build_request(&self, items: Vec<BulkItem>) -> crate::Result<http::Request<Vec<u8>>> {
+        let mut events = Vec::new();
+        for item in &items {
+            item.write(&mut events);
+        }
+
+        let mut builder = Request::post(&self.bulk_uri);
+
+        if let Some(credentials_provider) = &self.credentials {
+            let mut request = self.signed_request("POST", &self.bulk_uri, true);
+
+            request.add_header("Content-Type", "application/x-ndjson");
+
+            if let Some(headers) = &self.config.headers {
+                for (header, value) in headers {
+                    request.add_header(header, value);
+                }
+            }
+
+            request.set_payload(Some(events));
+
+            // mut builder?
+            builder = finish_signer(&mut request, &credentials_provider, builder).await?;
+
+            // The SignedRequest ends up owning the body, so we have
+            // to play games here
+            let body = request.payload.take().unwrap();
+            match body {
+                SignedRequestPayload::Buffer(body) => {
+                    builder.body(body.to_vec()).map_err(Into::into)
+                }
+                _ => unreachable!(),
+            }
+        } else {
+            builder = builder.header("Content-Type", "application/x-ndjson");
+
+            let events = match self.compression {
+                Compression::None => events,
+                Compression::Gzip => {
+                    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                    encoder.write_all(&events)?;
+                    encoder.finish()?
+                }
+            };
+            if let Some(ce) = self.compression.content_encoding() {
+                builder = builder.header("Content-Encoding", ce);
+            }
+
+            if let Some(headers) = &self.config.headers {
+                for (header, value) in headers {
+                    builder = builder.header(&header[..], &value[..]);
+                }
+            }
+
+            if let Some(auth) = &self.authorization {
+                builder = builder.header("Authorization", &auth[..]);
+            }
+
+            builder.body(events).map_err(Into::into)
+        }
+    }
+
     fn signed_request(&self, method: &str, uri: &Uri, use_params: bool) -> SignedRequest {
         let mut request = SignedRequest::new(method, "es", &self.region, uri.path());
         if use_params {
@@ -543,10 +1025,74 @@ mod tests {
             .body(Bytes::from(json))
             .unwrap();
         let logic = ElasticSearchRetryLogic;
+        // The failed items are handled by the service instead.
         assert!(matches!(
             logic.should_retry_response(&response),
-            RetryAction::DontRetry(_)
+            RetryAction::Successful
         ));
+
+        let failures = bulk_failures(response.body());
+        assert_eq!(
+            failures,
+            vec![BulkFailure {
+                position: 0,
+                status: 400,
+                error_type: "illegal_argument_exception".into(),
+                reason:
+                    "mapper [message] of different type, current_type [long], merged_type [text]"
+                        .into(),
+            }]
+        );
+        assert!(!failures[0].is_retriable());
+    }
+
+    #[test]
+    fn finds_failed_items_by_position() {
+        let json = json!({
+            "took": 30,
+            "errors": true,
+            "items": [
+                { "create": { "_index": "logs", "status": 201 } },
+                { "create": {
+                    "_index": "logs",
+                    "status": 429,
+                    "error": { "type": "es_rejected_execution_exception", "reason": "queue full" }
+                } },
+                { "delete": {
+                    "_index": "logs",
+                    "status": 404,
+                    "result": "not_found"
+                } },
+                { "update": {
+                    "_index": "logs",
+                    "status": 400,
+                    "error": { "type": "mapper_parsing_exception", "reason": "failed to parse" }
+                } }
+            ]
+        });
+        let failures = bulk_failures(&serde_json::to_vec(&json).unwrap());
+
+        assert_eq!(
+            failures
+                .iter()
+                .map(|failure| (failure.position, failure.is_retriable()))
+                .collect::<Vec<_>>(),
+            vec![(1, true), (3, false)]
+        );
+    }
+
+    #[test]
+    fn no_failed_items_without_errors() {
+        let json =
+            r#"{"took":3,"errors":false,"items":[{"index":{"_index":"logs","status":201}}]}"#;
+
+        assert_eq!(bulk_failures(json.as_bytes()), vec![]);
+    }
+
+    fn encode(es: &ElasticSearchCommon, event: Event) -> String {
+        let mut body = Vec::new();
+        es.encode_event(event).unwrap().write(&mut body);
+        String::from_utf8(body).unwrap()
     }
 
     #[test]
@@ -567,11 +1113,150 @@ mod tests {
         event.as_mut_log().insert("foo", "bar");
         event.as_mut_log().insert("idx", "purple");
 
-        let encoded = es.encode_event(event).unwrap();
+        let encoded = encode(&es, event);
         let expected = r#"{"index":{"_index":"purple","_type":"_doc"}}
 {"foo":"bar","message":"hello there"}
 "#;
-        assert_eq!(std::str::from_utf8(&encoded).unwrap(), &expected[..]);
+        assert_eq!(encoded, expected);
+    }
+
+    #[test]
+    fn encodes_templated_bulk_actions() {
+        let config = ElasticSearchConfig {
+            index: Some(String::from("vector")),
+            bulk_action: Some(String::from("{{ action }}")),
+            id_key: Some(String::from("id")),
+            encoding: EncodingConfigWithDefault {
+                codec: Encoding::Default,
+                except_fields: Some(vec![Atom::from("action"), Atom::from("timestamp")]),
+                ..Default::default()
+            },
+            endpoint: String::from("https://example.com"),
+            ..Default::default()
+        };
+        let es = ElasticSearchCommon::parse_config(&config).unwrap();
+
+        let event = |action: &str| {
+            let mut event = Event::from("hello there");
+            event.as_mut_log().insert("action", action);
+            event.as_mut_log().insert("id", "42");
+            event
+        };
+
+        assert_eq!(
+            encode(&es, event("create")),
+            r#"{"create":{"_id":"42","_index":"vector","_type":"_doc"}}
+{"message":"hello there"}
+"#
+        );
+        assert_eq!(
+            encode(&es, event("update")),
+            r#"{"update":{"_id":"42","_index":"vector","_type":"_doc"}}
+{"doc":{"message":"hello there"},"doc_as_upsert":true}
+"#
+        );
+        assert_eq!(
+            encode(&es, event("delete")),
+            r#"{"delete":{"_id":"42","_index":"vector","_type":"_doc"}}
+"#
+        );
+        assert!(es.encode_event(event("upsert")).is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_bulk_action() {
+        let config = ElasticSearchConfig {
+            bulk_action: Some(String::from("upsert")),
+            endpoint: String::from("https://example.com"),
+            ..Default::default()
+        };
+
+        assert!(ElasticSearchCommon::parse_config(&config).is_err());
+    }
+
+    #[test]
+    fn encodes_data_stream_events() {
+        let config = ElasticSearchConfig {
+            mode: ElasticSearchMode::DataStream,
+            data_stream: Some(DataStreamConfig {
+                dataset: String::from("{{ service }}"),
+                ..Default::default()
+            }),
+            endpoint: String::from("https://example.com"),
+            ..Default::default()
+        };
+        let es = ElasticSearchCommon::parse_config(&config).unwrap();
+
+        let mut event = Event::from("hello there");
+        event.as_mut_log().insert("service", "nginx");
+        let timestamp = event.as_log()[&log_schema().timestamp_key()].clone();
+
+        let item = es.encode_event(event).unwrap();
+        assert_eq!(item.index, "logs-nginx-default");
+        assert_eq!(item.action, BulkAction::Create);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&item.metadata).unwrap(),
+            json!({ "create": { "_index": "logs-nginx-default" } })
+        );
+
+        let document =
+            serde_json::from_slice::<serde_json::Value>(item.document.as_ref().unwrap()).unwrap();
+        assert_eq!(document["@timestamp"], json!(timestamp));
+        assert_eq!(document.get("timestamp"), None);
+        assert_eq!(
+            document["data_stream"],
+            json!({ "type": "logs", "dataset": "nginx", "namespace": "default" })
+        );
+    }
+
+    #[test]
+    fn data_streams_only_allow_create() {
+        let config = ElasticSearchConfig {
+            mode: ElasticSearchMode::DataStream,
+            bulk_action: Some(String::from("index")),
+            endpoint: String::from("https://example.com"),
+            ..Default::default()
+        };
+
+        assert!(ElasticSearchCommon::parse_config(&config).is_err());
+    }
+
+    #[test]
+    fn wraps_failed_items_for_failure_index() {
+        let config = ElasticSearchConfig {
+            index: Some(String::from("vector")),
+            failure_index: Some(String::from("vector-failures")),
+            encoding: EncodingConfigWithDefault {
+                codec: Encoding::Default,
+                except_fields: Some(vec![Atom::from("timestamp")]),
+                ..Default::default()
+            },
+            endpoint: String::from("https://example.com"),
+            ..Default::default()
+        };
+        let es = ElasticSearchCommon::parse_config(&config).unwrap();
+
+        let item = es.encode_event(Event::from("hello there")).unwrap();
+        let failure = BulkFailure {
+            position: 0,
+            status: 400,
+            error_type: "mapper_parsing_exception".into(),
+            reason: "failed to parse".into(),
+        };
+        let item = es.failure_item("vector-failures", &item, &failure);
+
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&item.metadata).unwrap(),
+            json!({ "index": { "_index": "vector-failures", "_type": "_doc" } })
+        );
+
+        let document =
+            serde_json::from_slice::<serde_json::Value>(item.document.as_ref().unwrap()).unwrap();
+        assert_eq!(document["index"], "vector");
+        assert_eq!(document["action"], "index");
+        assert_eq!(document["status"], 400);
+        assert_eq!(document["error"]["type"], "mapper_parsing_exception");
+        assert_eq!(document["document"], r#"{"message":"hello there"}"#);
     }
 }
 
@@ -734,6 +1419,73 @@ mod integration_tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn insert_events_with_failure_index() {
+        trace_init();
+
+        let index = gen_index();
+        let failure_index = gen_index();
+        let config = ElasticSearchConfig {
+            endpoint: "http://localhost:9200".into(),
+            index: Some(index),
+            failure_index: Some(failure_index.clone()),
+            doc_type: Some("log_lines".into()),
+            compression: Compression::None,
+            batch: BatchConfig {
+                max_events: Some(10),
+                ..Default::default()
+            },
+            ..config()
+        };
+        let common = ElasticSearchCommon::parse_config(&config).expect("Config error");
+        let base_url = common.base_url.clone();
+
+        let cx = SinkContext::new_test();
+        let (sink, _healthcheck) = config
+            .build(cx.clone())
+            .await
+            .expect("Building config failed");
+
+        // Break every other event, which fails the whole event on its own
+        let (input, events) = random_events_with_stream(100, 100);
+        let mut doit = false;
+        sink.run(events.map(move |mut event| {
+            if doit {
+                event.as_mut_log().insert("_type", 1);
+            }
+            doit = !doit;
+            event
+        }))
+        .await
+        .expect("Sending events failed");
+
+        flush(cx.resolver(), common)
+            .await
+            .expect("Flushing writes failed");
+
+        let response = reqwest::Client::new()
+            .get(&format!("{}/{}/_search", base_url, failure_index))
+            .json(&json!({
+                "query": { "query_string": { "query": "*" } }
+            }))
+            .send()
+            .await
+            .unwrap()
+            .json::<elastic_responses::search::SearchResponse<Value>>()
+            .await
+            .unwrap();
+
+        assert_eq!(input.len() as u64 / 2, response.total());
+        for hit in response.into_hits() {
+            let document = hit.into_document().unwrap();
+            assert_eq!(document["status"], 400);
+            assert!(document["document"]
+                .as_str()
+                .unwrap()
+                .contains("\"_type\":1"));
+        }
+    }
+
     async fn run_insert_tests(mut config: ElasticSearchConfig, break_events: bool) {
         let index = gen_index();
         config.index = Some(index.clone());
@@ -9,23 +9,33 @@ use crate::{
     },
     template::Template,
 };
-use async_compression::tokio_02::write::GzipEncoder;
+use async_compression::tokio_02::write::{GzipEncoder, ZstdEncoder};
 use async_trait::async_trait;
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use futures::{
     future,
     stream::{BoxStream, StreamExt},
     FutureExt,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 use tokio::{
     fs::{self, File},
     io::AsyncWriteExt,
+    time::Interval,
 };
 
 mod bytes_path;
+mod rotation;
 use bytes_path::BytesPath;
+pub use rotation::RotationSuffix;
+
+/// The most events written before the files are synced and the events are
+/// acknowledged, when they keep arriving without a pause.
+const MAX_BATCH_EVENTS: usize = 1024;
 
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(deny_unknown_fields)]
@@ -42,6 +52,20 @@ pub struct FileSinkConfig {
         skip_serializing_if = "crate::serde::skip_serializing_if_default"
     )]
     pub compression: Compression,
+    pub max_file_size: Option<u64>,
+    pub rotate_interval_secs: Option<u64>,
+    #[serde(
+        default,
+        skip_serializing_if = "crate::serde::skip_serializing_if_default"
+    )]
+    pub rotation_suffix: RotationSuffix,
+    pub max_files: Option<usize>,
+    #[serde(
+        default,
+        skip_serializing_if = "crate::serde::skip_serializing_if_default"
+    )]
+    pub fsync: FsyncPolicy,
+    pub fsync_interval_secs: Option<u64>,
 }
 
 inventory::submit! {
@@ -65,6 +89,7 @@ impl Default for Encoding {
 #[serde(rename_all = "snake_case")]
 pub enum Compression {
     Gzip,
+    Zstd,
     None,
 }
 
@@ -74,9 +99,26 @@ impl Default for Compression {
     }
 }
 
+/// When written data is synced to the filesystem, in addition to when a
+/// file is closed.
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum FsyncPolicy {
+    Never,
+    PerBatch,
+    Interval,
+}
+
+impl Default for FsyncPolicy {
+    fn default() -> Self {
+        FsyncPolicy::Never
+    }
+}
+
 enum OutFile {
     Regular(File),
     Gzip(GzipEncoder<File>),
+    Zstd(ZstdEncoder<File>),
 }
 
 impl OutFile {
@@ -84,6 +126,7 @@ impl OutFile {
         match compression {
             Compression::None => OutFile::Regular(file),
             Compression::Gzip => OutFile::Gzip(GzipEncoder::new(file)),
+            Compression::Zstd => OutFile::Zstd(ZstdEncoder::new(file)),
         }
     }
 
@@ -91,6 +134,7 @@ impl OutFile {
         match self {
             OutFile::Regular(file) => file.sync_all().await,
             OutFile::Gzip(gzip) => gzip.get_mut().sync_all().await,
+            OutFile::Zstd(zstd) => zstd.get_mut().sync_all().await,
         }
     }
 
@@ -98,6 +142,15 @@ impl OutFile {
         match self {
             OutFile::Regular(file) => file.shutdown().await,
             OutFile::Gzip(gzip) => gzip.shutdown().await,
+            OutFile::Zstd(zstd) => zstd.shutdown().await,
+        }
+    }
+
+    async fn flush(&mut self) -> Result<(), std::io::Error> {
+        match self {
+            OutFile::Regular(file) => file.flush().await,
+            OutFile::Gzip(gzip) => gzip.flush().await,
+            OutFile::Zstd(zstd) => zstd.flush().await,
         }
     }
 
@@ -105,9 +158,17 @@ impl OutFile {
         match self {
             OutFile::Regular(file) => file.write_all(src).await,
             OutFile::Gzip(gzip) => gzip.write_all(src).await,
+            OutFile::Zstd(zstd) => zstd.write_all(src).await,
         }
     }
 
+    /// Flushes buffered data, including any pending compressed block, and
+    /// syncs it to the filesystem without ending the file.
+    async fn sync(&mut self) -> Result<(), std::io::Error> {
+        self.flush().await?;
+        self.sync_all().await
+    }
+
     /// Shutdowns by flushing data, writing headers, and syncing all of that
     /// data and metadata to the filesystem.
     async fn close(&mut self) -> Result<(), std::io::Error> {
@@ -116,6 +177,15 @@ impl OutFile {
     }
 }
 
+/// An open file, along with what is needed to decide when to rotate it.
+struct OpenFile {
+    file: OutFile,
+    /// The size of the file when it was opened, plus the data written to it
+    /// since, before compression.
+    size: u64,
+    started_at: DateTime<Utc>,
+}
+
 #[async_trait::async_trait]
 #[typetag::serde(name = "file")]
 impl SinkConfig for FileSinkConfig {
@@ -145,8 +215,19 @@ pub struct FileSink {
     path: Template,
     encoding: EncodingConfigWithDefault<Encoding>,
     idle_timeout: Duration,
-    files: ExpiringHashMap<Bytes, OutFile>,
+    files: ExpiringHashMap<Bytes, OpenFile>,
     compression: Compression,
+    max_file_size: Option<u64>,
+    rotate_interval: Option<chrono::Duration>,
+    rotation_suffix: RotationSuffix,
+    max_files: Option<usize>,
+    /// The part of `path` before anything rendered, under which all files
+    /// count towards `max_files` when `path` is templated.
+    static_prefix: BytesPath,
+    fsync: FsyncPolicy,
+    fsync_interval: Duration,
+    /// Files written to since they were last synced.
+    unsynced: HashSet<Bytes>,
 }
 
 impl FileSink {
@@ -158,6 +239,16 @@ impl FileSink {
             idle_timeout: Duration::from_secs(config.idle_timeout_secs.unwrap_or(30)),
             files: ExpiringHashMap::default(),
             compression: config.compression,
+            max_file_size: config.max_file_size,
+            rotate_interval: config
+                .rotate_interval_secs
+                .map(|secs| chrono::Duration::seconds(secs as i64)),
+            rotation_suffix: config.rotation_suffix,
+            max_files: config.max_files,
+            static_prefix: BytesPath::new(static_prefix(&config.path)),
+            fsync: config.fsync,
+            fsync_interval: Duration::from_secs(config.fsync_interval_secs.unwrap_or(1).max(1)),
+            unsynced: HashSet::new(),
         }
     }
 
@@ -184,14 +275,37 @@ impl FileSink {
             .expect("unable to compute next deadline")
     }
 
-    async fn run(&mut self, mut input: BoxStream<'_, Event>) -> crate::Result<()> {
+    async fn run(&mut self, input: BoxStream<'_, Event>) -> crate::Result<()> {
+        let mut input = input.fuse();
+        let mut sync_interval = match self.fsync {
+            FsyncPolicy::Interval => Some(tokio::time::interval(self.fsync_interval)),
+            _ => None,
+        };
+
         loop {
             tokio::select! {
                 event = input.next() => {
                     match event {
                         Some(event) => {
                             self.process_event(event).await;
-                            self.acker.ack(1);
+
+                            // Events that are already available are written
+                            // as part of the same batch.
+                            let mut count = 1;
+                            while count < MAX_BATCH_EVENTS {
+                                match input.next().now_or_never() {
+                                    Some(Some(event)) => {
+                                        self.process_event(event).await;
+                                        count += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+
+                            if self.fsync == FsyncPolicy::PerBatch {
+                                self.sync_files().await;
+                            }
+                            self.acker.ack(count);
                         },
                         None => {
                             // If we got `None` - terminate the processing.
@@ -200,7 +314,7 @@ impl FileSink {
                             // Close all the open files.
                             debug!(message = "Closing all the open files");
                             for (path, file) in self.files.iter_mut() {
-                                if let Err(error) = file.close().await {
+                                if let Err(error) = file.file.close().await {
                                     error!(message = "Failed to close file.", ?path, %error);
                                 } else{
                                     trace!(message = "Successfully closed file", ?path);
@@ -211,6 +325,9 @@ impl FileSink {
                         }
                     }
                 }
+                _ = tick(&mut sync_interval), if sync_interval.is_some() => {
+                    self.sync_files().await;
+                }
                 result = self.files.next_expired(), if !self.files.is_empty() => {
                     match result {
                         // We do not poll map when it's empty, so we should
@@ -219,7 +336,8 @@ impl FileSink {
                         Some(Ok((mut expired_file, path))) => {
                             // We got an expired file. All we really want is to
                             // flush and close it.
-                            if let Err(error) = expired_file.close().await {
+                            self.unsynced.remove(path.get_ref());
+                            if let Err(error) = expired_file.file.close().await {
                                 error!(message = "Failed to close file.", ?path, %error);
                             }
                             drop(expired_file); // ignore close error
@@ -247,36 +365,189 @@ impl FileSink {
             }
         };
 
+        let mut buf = encode_event(&self.encoding, event);
+        buf.push(b'\n');
+
         let next_deadline = self.deadline_at();
         trace!(message = "Computed next deadline.", ?next_deadline, ?path);
 
-        let file = if let Some(file) = self.files.reset_at(&path, next_deadline) {
+        if self.files.reset_at(&path, next_deadline).is_some() {
             trace!(message = "Working with an already opened file.", ?path);
-            file
-        } else {
-            trace!(message = "Opening new file.", ?path);
-            let file = match open_file(BytesPath::new(path.clone())).await {
-                Ok(file) => file,
-                Err(error) => {
-                    // We couldn't open the file for this event.
-                    // Maybe other events will work though! Just log
-                    // the error and skip this event.
-                    error!(message = "Unable to open the file.", ?path, %error);
-                    return;
-                }
-            };
+        } else if !self.open(&path, next_deadline).await {
+            return;
+        } else if self.path.is_dynamic() {
+            // A newly rendered path may leave the files of the previous
+            // ones behind, so these count towards `max_files` now.
+            if let Err(error) = self.remove_old_files(&path).await {
+                error!(message = "Failed to remove old files.", ?path, %error);
+            }
+        }
 
-            let outfile = OutFile::new(file, self.compression);
+        let needs_rotation = self
+            .files
+            .get(&path)
+            .map_or(false, |file| self.should_rotate(file, buf.len()));
+        if needs_rotation {
+            if let Some((file, _)) = self.files.remove(&path) {
+                if let Err(error) = self.rotate(&path, file).await {
+                    error!(message = "Failed to rotate file.", ?path, %error);
+                }
+            }
+            if !self.open(&path, next_deadline).await {
+                return;
+            }
+        }
 
-            self.files.insert_at(path.clone(), outfile, next_deadline);
-            self.files.get_mut(&path).unwrap()
-        };
+        let file = self.files.get_mut(&path).expect("file was just opened");
 
         trace!(message = "Writing an event to file.", ?path);
-        if let Err(error) = write_event_to_file(file, event, &self.encoding).await {
+        if let Err(error) = file.file.write_all(&buf[..]).await {
             error!(message = "Failed to write file.", ?path, %error);
+            return;
+        }
+        file.size += buf.len() as u64;
+
+        if self.fsync != FsyncPolicy::Never {
+            self.unsynced.insert(path);
+        }
+    }
+
+    /// Opens the file at `path` and tracks it until `deadline`, returning
+    /// whether that worked.
+    async fn open(&mut self, path: &Bytes, deadline: Instant) -> bool {
+        trace!(message = "Opening new file.", ?path);
+        match self.open_out_file(path).await {
+            Ok(file) => {
+                self.files.insert_at(path.clone(), file, deadline);
+                true
+            }
+            Err(error) => {
+                // We couldn't open the file for this event.
+                // Maybe other events will work though! Just log
+                // the error and skip this event.
+                error!(message = "Unable to open the file.", ?path, %error);
+                false
+            }
         }
     }
+
+    async fn open_out_file(&self, path: &Bytes) -> std::io::Result<OpenFile> {
+        let path = BytesPath::new(path.clone());
+        let file = open_file(&path).await?;
+        let size = file.metadata().await?.len();
+
+        // A file that already has data keeps its recorded start time, so
+        // that the rotation interval carries over across restarts.
+        let started_at = match self.rotate_interval {
+            Some(_) => match rotation::load_started_at(path.as_ref()).await? {
+                Some(started_at) if size > 0 => started_at,
+                _ => {
+                    let now = Utc::now();
+                    rotation::save_started_at(path.as_ref(), now).await?;
+                    now
+                }
+            },
+            None => Utc::now(),
+        };
+
+        Ok(OpenFile {
+            file: OutFile::new(file, self.compression),
+            size,
+            started_at,
+        })
+    }
+
+    /// Whether `file` has to be rotated before `len` more bytes are written
+    /// to it. Empty files are never rotated.
+    fn should_rotate(&self, file: &OpenFile, len: usize) -> bool {
+        if file.size == 0 {
+            return false;
+        }
+
+        let too_large = self
+            .max_file_size
+            .map_or(false, |max| file.size + len as u64 > max);
+        let too_old = self
+            .rotate_interval
+            .map_or(false, |interval| Utc::now() - file.started_at >= interval);
+
+        too_large || too_old
+    }
+
+    /// Closes `file`, moves it aside and removes the old files beyond
+    /// `max_files`.
+    async fn rotate(&mut self, path: &Bytes, mut file: OpenFile) -> std::io::Result<()> {
+        self.unsynced.remove(path);
+        file.file.close().await?;
+
+        let bytes_path = BytesPath::new(path.clone());
+        let rotated = rotation::rotate(bytes_path.as_ref(), self.rotation_suffix).await?;
+        debug!(message = "Rotated file.", ?rotated);
+
+        self.remove_old_files(path).await
+    }
+
+    /// Removes the oldest files beyond `max_files`. These are the rotated
+    /// files of `path` if `self.path` is static, or otherwise all of the
+    /// files under its static prefix that are not open, such as the files
+    /// of past days for a `/var/log/app-%Y-%m-%d.log` template.
+    async fn remove_old_files(&mut self, path: &Bytes) -> std::io::Result<()> {
+        let max_files = match self.max_files {
+            Some(max_files) => max_files,
+            None => return Ok(()),
+        };
+
+        let removed = if self.path.is_dynamic() {
+            let open = self
+                .files
+                .iter_mut()
+                .map(|(open_path, _)| BytesPath::new(open_path.clone()).as_ref().to_owned())
+                .collect::<HashSet<PathBuf>>();
+            rotation::remove_old_prefixed_files(self.static_prefix.as_ref(), &open, max_files)
+                .await?
+        } else {
+            let path = BytesPath::new(path.clone());
+            rotation::remove_old_files(path.as_ref(), self.rotation_suffix, max_files).await?
+        };
+        for removed in removed {
+            debug!(message = "Removed old file.", ?removed);
+        }
+
+        Ok(())
+    }
+
+    async fn sync_files(&mut self) {
+        for path in std::mem::take(&mut self.unsynced) {
+            if let Some(file) = self.files.get_mut(&path) {
+                if let Err(error) = file.file.sync().await {
+                    error!(message = "Failed to sync file.", ?path, %error);
+                }
+            }
+        }
+    }
+}
+
+/// The part of the `template` source before its first field or strftime
+/// specifier.
+fn static_prefix(template: &Template) -> Bytes {
+    let src = template.get_ref();
+    let end = src
+        .windows(2)
+        .position(|window| window == b"{{")
+        .into_iter()
+        .chain(src.iter().position(|&byte| byte == b'%'))
+        .min()
+        .unwrap_or_else(|| src.len());
+    src.slice(..end)
+}
+
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => future::pending::<()>().await,
+    }
 }
 
 async fn open_file(path: impl AsRef<std::path::Path>) -> std::io::Result<File> {
@@ -307,16 +578,6 @@ pub fn encode_event(encoding: &EncodingConfigWithDefault<Encoding>, mut event: E
     }
 }
 
-async fn write_event_to_file(
-    file: &mut OutFile,
-    event: Event,
-    encoding: &EncodingConfigWithDefault<Encoding>,
-) -> Result<(), std::io::Error> {
-    let mut buf = encode_event(encoding, event);
-    buf.push(b'\n');
-    file.write_all(&buf[..]).await
-}
-
 #[async_trait]
 impl StreamSink for FileSink {
     async fn run(&mut self, input: BoxStream<'_, Event>) -> Result<(), ()> {
@@ -334,6 +595,21 @@ mod tests {
     };
     use futures::stream;
     use std::convert::TryInto;
+    use std::path::Path;
+    use tokio::io::AsyncReadExt;
+
+    fn rotated(path: &Path, n: usize) -> String {
+        format!("{}.{}", path.display(), n)
+    }
+
+    async fn lines_from_zstd_file(path: &Path) -> Vec<String> {
+        let file = File::open(path).await.unwrap();
+        let mut decoder =
+            async_compression::tokio_02::bufread::ZstdDecoder::new(tokio::io::BufReader::new(file));
+        let mut output = String::new();
+        decoder.read_to_string(&mut output).await.unwrap();
+        output.lines().map(|s| s.to_owned()).collect()
+    }
 
     #[tokio::test]
     async fn single_partition() {
@@ -346,6 +622,12 @@ mod tests {
             idle_timeout_secs: None,
             encoding: Encoding::Text.into(),
             compression: Compression::None,
+            max_file_size: None,
+            rotate_interval_secs: None,
+            rotation_suffix: RotationSuffix::Numbered,
+            max_files: None,
+            fsync: FsyncPolicy::Never,
+            fsync_interval_secs: None,
         };
 
         let mut sink = FileSink::new(&config, Acker::Null);
@@ -371,6 +653,12 @@ mod tests {
             idle_timeout_secs: None,
             encoding: Encoding::Text.into(),
             compression: Compression::Gzip,
+            max_file_size: None,
+            rotate_interval_secs: None,
+            rotation_suffix: RotationSuffix::Numbered,
+            max_files: None,
+            fsync: FsyncPolicy::Never,
+            fsync_interval_secs: None,
         };
 
         let mut sink = FileSink::new(&config, Acker::Null);
@@ -401,6 +689,12 @@ mod tests {
             idle_timeout_secs: None,
             encoding: Encoding::Text.into(),
             compression: Compression::None,
+            max_file_size: None,
+            rotate_interval_secs: None,
+            rotation_suffix: RotationSuffix::Numbered,
+            max_files: None,
+            fsync: FsyncPolicy::Never,
+            fsync_interval_secs: None,
         };
 
         let mut sink = FileSink::new(&config, Acker::Null);
@@ -482,6 +776,12 @@ mod tests {
             idle_timeout_secs: Some(1),
             encoding: Encoding::Text.into(),
             compression: Compression::None,
+            max_file_size: None,
+            rotate_interval_secs: None,
+            rotation_suffix: RotationSuffix::Numbered,
+            max_files: None,
+            fsync: FsyncPolicy::Never,
+            fsync_interval_secs: None,
         };
 
         let mut sink = FileSink::new(&config, Acker::Null);
@@ -511,4 +811,186 @@ mod tests {
         let output = lines_from_file(template);
         assert_eq!(input, output);
     }
+
+    #[tokio::test]
+    async fn single_partition_zstd() {
+        trace_init();
+
+        let template = temp_file();
+
+        let config = FileSinkConfig {
+            path: template.clone().try_into().unwrap(),
+            idle_timeout_secs: None,
+            encoding: Encoding::Text.into(),
+            compression: Compression::Zstd,
+            max_file_size: None,
+            rotate_interval_secs: None,
+            rotation_suffix: RotationSuffix::Numbered,
+            max_files: None,
+            fsync: FsyncPolicy::Never,
+            fsync_interval_secs: None,
+        };
+
+        let mut sink = FileSink::new(&config, Acker::Null);
+        let (input, _) = random_lines_with_stream(100, 64);
+
+        let events = Box::pin(stream::iter(input.clone().into_iter().map(Event::from)));
+        sink.run(events).await.unwrap();
+
+        let output = lines_from_zstd_file(&template).await;
+        assert_eq!(input, output);
+    }
+
+    #[tokio::test]
+    async fn rotates_by_size() {
+        trace_init();
+
+        let template = temp_file();
+
+        // Three 65 byte lines fit in each file.
+        let config = FileSinkConfig {
+            path: template.clone().try_into().unwrap(),
+            idle_timeout_secs: None,
+            encoding: Encoding::Text.into(),
+            compression: Compression::None,
+            max_file_size: Some(200),
+            rotate_interval_secs: None,
+            rotation_suffix: RotationSuffix::Numbered,
+            max_files: None,
+            fsync: FsyncPolicy::PerBatch,
+            fsync_interval_secs: None,
+        };
+
+        let mut sink = FileSink::new(&config, Acker::Null);
+        let (input, _) = random_lines_with_stream(64, 10);
+
+        let events = Box::pin(stream::iter(input.clone().into_iter().map(Event::from)));
+        sink.run(events).await.unwrap();
+
+        let mut output = Vec::new();
+        for n in 1..=3 {
+            let lines = lines_from_file(rotated(&template, n));
+            assert_eq!(lines.len(), 3);
+            output.extend(lines);
+        }
+        output.extend(lines_from_file(&template));
+        assert_eq!(input, output);
+    }
+
+    #[tokio::test]
+    async fn keeps_max_files() {
+        trace_init();
+
+        let template = temp_file();
+
+        let config = FileSinkConfig {
+            path: template.clone().try_into().unwrap(),
+            idle_timeout_secs: None,
+            encoding: Encoding::Text.into(),
+            compression: Compression::None,
+            max_file_size: Some(200),
+            rotate_interval_secs: None,
+            rotation_suffix: RotationSuffix::Numbered,
+            max_files: Some(2),
+            fsync: FsyncPolicy::Never,
+            fsync_interval_secs: None,
+        };
+
+        let mut sink = FileSink::new(&config, Acker::Null);
+        let (input, _) = random_lines_with_stream(64, 10);
+
+        let events = Box::pin(stream::iter(input.clone().into_iter().map(Event::from)));
+        sink.run(events).await.unwrap();
+
+        assert!(!Path::new(&rotated(&template, 1)).exists());
+        let mut output = lines_from_file(rotated(&template, 2));
+        output.extend(lines_from_file(rotated(&template, 3)));
+        output.extend(lines_from_file(&template));
+        assert_eq!(input[3..], output[..]);
+    }
+
+    #[tokio::test]
+    async fn keeps_max_files_across_rendered_paths() {
+        trace_init();
+
+        let directory = temp_dir();
+        std::fs::create_dir_all(&directory).unwrap();
+        // The files of past days, and a file not written by the sink.
+        for day in 1..=3 {
+            std::fs::write(directory.join(format!("app-{}.log", day)), "old line\n").unwrap();
+            tokio::time::delay_for(Duration::from_millis(10)).await;
+        }
+        std::fs::write(directory.join("other.log"), "other line\n").unwrap();
+
+        let mut template = directory.to_string_lossy().to_string();
+        template.push_str("/app-{{day}}.log");
+
+        let config = FileSinkConfig {
+            path: template.try_into().unwrap(),
+            idle_timeout_secs: None,
+            encoding: Encoding::Text.into(),
+            compression: Compression::None,
+            max_file_size: None,
+            rotate_interval_secs: None,
+            rotation_suffix: RotationSuffix::Numbered,
+            max_files: Some(1),
+            fsync: FsyncPolicy::Never,
+            fsync_interval_secs: None,
+        };
+
+        let mut event = Event::from("new line");
+        event.as_mut_log().insert("day", "4");
+
+        let mut sink = FileSink::new(&config, Acker::Null);
+        sink.run(Box::pin(stream::iter(vec![event]))).await.unwrap();
+
+        assert!(!directory.join("app-1.log").exists());
+        assert!(!directory.join("app-2.log").exists());
+        assert_eq!(
+            lines_from_file(directory.join("app-3.log")),
+            vec!["old line"]
+        );
+        assert_eq!(
+            lines_from_file(directory.join("app-4.log")),
+            vec!["new line"]
+        );
+        assert!(directory.join("other.log").exists());
+    }
+
+    #[tokio::test]
+    async fn rotation_interval_survives_restart() {
+        trace_init();
+
+        let template = temp_file();
+
+        // A file left behind by a previous run, started an hour ago.
+        std::fs::write(&template, "old line\n").unwrap();
+        rotation::save_started_at(&template, Utc::now() - chrono::Duration::hours(1))
+            .await
+            .unwrap();
+
+        let config = FileSinkConfig {
+            path: template.clone().try_into().unwrap(),
+            idle_timeout_secs: None,
+            encoding: Encoding::Text.into(),
+            compression: Compression::None,
+            max_file_size: None,
+            rotate_interval_secs: Some(60),
+            rotation_suffix: RotationSuffix::Numbered,
+            max_files: None,
+            fsync: FsyncPolicy::Interval,
+            fsync_interval_secs: Some(1),
+        };
+
+        let mut sink = FileSink::new(&config, Acker::Null);
+        let events = Box::pin(stream::iter(vec![Event::from("new line")]));
+        sink.run(events).await.unwrap();
+
+        assert_eq!(lines_from_file(rotated(&template, 1)), vec!["old line"]);
+        assert_eq!(lines_from_file(&template), vec!["new line"]);
+
+        // The new file starts a new interval.
+        let started_at = rotation::load_started_at(&template).await.unwrap().unwrap();
+        assert!(Utc::now() - started_at < chrono::Duration::minutes(1));
+    }
 }
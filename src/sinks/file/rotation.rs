@@ -0,0 +1,321 @@
+//! Rotation and retention of the files written by the sink.
+//!
+//! Rotating a file renames it to its path with a suffix appended, and the
+//! next write starts a new file at the original path. The time the current
+//! file was started is recorded in a hidden `.<name>.rotation` file next to
+//! it, so that `rotate_interval_secs` carries over across restarts.
+//! Numbered suffixes are recovered by scanning the directory.
+//!
+//! Retention of a templated path covers every file under the template's
+//! static prefix, as the rendered paths change over time, e.g. once a day.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tokio::fs;
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3f";
+
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationSuffix {
+    /// `<path>.1`, `<path>.2`, ... with the most recent file numbered highest.
+    Numbered,
+    /// `<path>.20201019T120000.000`, the UTC time of the rotation.
+    Timestamp,
+}
+
+impl Default for RotationSuffix {
+    fn default() -> Self {
+        RotationSuffix::Numbered
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct RotationState {
+    started_at: DateTime<Utc>,
+}
+
+fn state_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.rotation", name))
+}
+
+/// Reads when the file at `path` was started, if that was recorded.
+pub async fn load_started_at(path: &Path) -> io::Result<Option<DateTime<Utc>>> {
+    match fs::read(state_path(path)).await {
+        Ok(data) => Ok(serde_json::from_slice::<RotationState>(&data)
+            .ok()
+            .map(|state| state.started_at)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Records when the file at `path` was started.
+pub async fn save_started_at(path: &Path, started_at: DateTime<Utc>) -> io::Result<()> {
+    let state_path = state_path(path);
+    let data = serde_json::to_vec(&RotationState { started_at }).expect("Unable to encode state.");
+
+    // Write to a temporary file first to never leave a partially
+    // written state behind.
+    let tmp_path = state_path.with_extension("tmp");
+    fs::write(&tmp_path, data).await?;
+    fs::rename(&tmp_path, &state_path).await
+}
+
+/// The rotated files of `path`, oldest first, with their sort keys.
+async fn rotated_files(path: &Path, suffix: RotationSuffix) -> io::Result<Vec<(String, PathBuf)>> {
+    let prefix = format!(
+        "{}.",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    );
+    let parent = match path.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    };
+
+    let mut files = Vec::new();
+    let mut entries = fs::read_dir(parent).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.starts_with(&prefix) {
+            continue;
+        }
+
+        let key = &name[prefix.len()..];
+        let key = match suffix {
+            // Zero-padded so that the keys sort by number.
+            RotationSuffix::Numbered => key.parse::<u64>().ok().map(|n| format!("{:020}", n)),
+            RotationSuffix::Timestamp => NaiveDateTime::parse_from_str(key, TIMESTAMP_FORMAT)
+                .ok()
+                .map(|_| key.to_owned()),
+        };
+        if let Some(key) = key {
+            files.push((key, entry.path()));
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Renames the file at `path` to its next rotated name, which is returned.
+pub async fn rotate(path: &Path, suffix: RotationSuffix) -> io::Result<PathBuf> {
+    let suffix = match suffix {
+        RotationSuffix::Numbered => {
+            let last = rotated_files(path, suffix)
+                .await?
+                .last()
+                .and_then(|(key, _)| key.parse::<u64>().ok())
+                .unwrap_or(0);
+            (last + 1).to_string()
+        }
+        RotationSuffix::Timestamp => Utc::now().format(TIMESTAMP_FORMAT).to_string(),
+    };
+
+    let mut rotated: OsString = path.as_os_str().to_owned();
+    rotated.push(".");
+    rotated.push(suffix);
+    let rotated = PathBuf::from(rotated);
+
+    fs::rename(path, &rotated).await?;
+    Ok(rotated)
+}
+
+/// Removes the oldest rotated files of `path`, keeping `max_files` of them.
+pub async fn remove_old_files(
+    path: &Path,
+    suffix: RotationSuffix,
+    max_files: usize,
+) -> io::Result<Vec<PathBuf>> {
+    let files = rotated_files(path, suffix).await?;
+    let excess = files.len().saturating_sub(max_files);
+
+    let mut removed = Vec::with_capacity(excess);
+    for (_, file) in files.into_iter().take(excess) {
+        fs::remove_file(&file).await?;
+        removed.push(file);
+    }
+    Ok(removed)
+}
+
+/// The files whose paths start with `prefix`, oldest first, with their
+/// modification times. The directories under `prefix` are included too.
+async fn prefixed_files(prefix: &Path) -> io::Result<Vec<(SystemTime, PathBuf)>> {
+    let prefix_str = prefix.to_string_lossy();
+    let root = if prefix_str.ends_with(std::path::is_separator) {
+        prefix
+    } else {
+        prefix.parent().unwrap_or_else(|| Path::new(""))
+    };
+
+    let mut files = Vec::new();
+    let mut directories = vec![root.to_owned()];
+    while let Some(directory) = directories.pop() {
+        let read_path = if directory == Path::new("") {
+            Path::new(".")
+        } else {
+            directory.as_path()
+        };
+        let mut entries = match fs::read_dir(read_path).await {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(error),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let path = directory.join(&name);
+            if !path.to_string_lossy().starts_with(&*prefix_str) {
+                continue;
+            }
+
+            let metadata = entry.metadata().await?;
+            if metadata.is_dir() {
+                directories.push(path);
+            } else if metadata.is_file() && !is_state_file(&name.to_string_lossy()) {
+                files.push((metadata.modified()?, path));
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+fn is_state_file(name: &str) -> bool {
+    name.starts_with('.') && (name.ends_with(".rotation") || name.ends_with(".tmp"))
+}
+
+/// Removes the oldest files under the static `prefix` of a templated path,
+/// keeping `max_files` of them besides the `open` ones.
+pub async fn remove_old_prefixed_files(
+    prefix: &Path,
+    open: &HashSet<PathBuf>,
+    max_files: usize,
+) -> io::Result<Vec<PathBuf>> {
+    let files = prefixed_files(prefix)
+        .await?
+        .into_iter()
+        .filter(|(_, file)| !open.contains(file))
+        .collect::<Vec<_>>();
+    let excess = files.len().saturating_sub(max_files);
+
+    let mut removed = Vec::with_capacity(excess);
+    for (_, file) in files.into_iter().take(excess) {
+        fs::remove_file(&file).await?;
+        match fs::remove_file(state_path(&file)).await {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+            _ => (),
+        }
+        removed.push(file);
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    async fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).await.unwrap();
+        fs::write(path, b"").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn numbered_rotation_continues_sequence() {
+        let directory = temp_dir();
+        let path = directory.join("app.log");
+        touch(&directory.join("app.log.9")).await;
+        touch(&directory.join("app.log.10")).await;
+        touch(&directory.join("app.log.old")).await;
+        touch(&path).await;
+
+        let rotated = rotate(&path, RotationSuffix::Numbered).await.unwrap();
+
+        assert_eq!(rotated, directory.join("app.log.11"));
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn timestamp_rotation() {
+        let directory = temp_dir();
+        let path = directory.join("app.log");
+        touch(&path).await;
+
+        let rotated = rotate(&path, RotationSuffix::Timestamp).await.unwrap();
+        let files = rotated_files(&path, RotationSuffix::Timestamp)
+            .await
+            .unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].1, rotated);
+    }
+
+    #[tokio::test]
+    async fn removes_oldest_files() {
+        let directory = temp_dir();
+        let path = directory.join("app.log");
+        for n in 1..=12 {
+            touch(&directory.join(format!("app.log.{}", n))).await;
+        }
+        touch(&path).await;
+
+        let removed = remove_old_files(&path, RotationSuffix::Numbered, 3)
+            .await
+            .unwrap();
+
+        assert_eq!(removed.len(), 9);
+        assert!(path.exists());
+        assert!(!directory.join("app.log.9").exists());
+        for n in 10..=12 {
+            assert!(directory.join(format!("app.log.{}", n)).exists());
+        }
+    }
+
+    #[tokio::test]
+    async fn removes_oldest_prefixed_files() {
+        let directory = temp_dir();
+        for day in 1..=4 {
+            touch(&directory.join(format!("app-{}.log", day))).await;
+            // Spaces out the modification times.
+            tokio::time::delay_for(std::time::Duration::from_millis(10)).await;
+        }
+        touch(&directory.join("other.log")).await;
+        let open = vec![directory.join("app-4.log")].into_iter().collect();
+
+        let removed = remove_old_prefixed_files(&directory.join("app-"), &open, 1)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            removed,
+            vec![directory.join("app-1.log"), directory.join("app-2.log")]
+        );
+        assert!(directory.join("app-3.log").exists());
+        assert!(directory.join("app-4.log").exists());
+        assert!(directory.join("other.log").exists());
+    }
+
+    #[tokio::test]
+    async fn started_at_round_trip() {
+        let directory = temp_dir();
+        let path = directory.join("app.log");
+        fs::create_dir_all(&directory).await.unwrap();
+
+        assert_eq!(load_started_at(&path).await.unwrap(), None);
+
+        let started_at = Utc::now();
+        save_started_at(&path, started_at).await.unwrap();
+
+        assert_eq!(load_started_at(&path).await.unwrap(), Some(started_at));
+        assert!(directory.join(".app.log.rotation").exists());
+    }
+}
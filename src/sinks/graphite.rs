@@ -0,0 +1,368 @@
+use crate::{
+    config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    event::Event,
+    sinks::{
+        util::{
+            encode_namespace, statistic::flatten_metric_value, tcp::TcpSink, udp::UdpSink,
+            StreamSinkOld,
+        },
+        Healthcheck, VectorSink,
+    },
+    tls::{MaybeTlsSettings, TlsConfig},
+};
+use bytes::Bytes;
+use chrono::Utc;
+use futures::{future, FutureExt};
+use futures01::{stream::iter_ok, Sink};
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("Missing host in address field"))]
+    MissingHost,
+    #[snafu(display("Missing port in address field"))]
+    MissingPort,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+// TODO: add back when serde-rs/serde#1358 is addressed
+// #[serde(deny_unknown_fields)]
+pub struct GraphiteSinkConfig {
+    pub namespace: Option<String>,
+    #[serde(flatten)]
+    pub mode: Mode,
+    #[serde(default)]
+    pub tag_format: TagFormat,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Mode {
+    Tcp {
+        address: String,
+        tls: Option<TlsConfig>,
+    },
+    Udp {
+        address: String,
+    },
+}
+
+/// How metric tags are written into the series path.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Derivative)]
+#[serde(rename_all = "snake_case")]
+#[derivative(Default)]
+pub enum TagFormat {
+    /// Graphite 1.1 tagged series, `name;key=value`.
+    #[derivative(Default)]
+    Appended,
+    /// Tags folded into the dotted path, `name.key.value`.
+    Path,
+}
+
+inventory::submit! {
+    SinkDescription::new_without_default::<GraphiteSinkConfig>("graphite")
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "graphite")]
+impl SinkConfig for GraphiteSinkConfig {
+    async fn build(&self, cx: SinkContext) -> crate::Result<(VectorSink, Healthcheck)> {
+        let namespace = self.namespace.clone();
+        let tag_format = self.tag_format;
+        let encode = move |event| iter_ok(encode_event(event, namespace.as_deref(), tag_format));
+
+        match &self.mode {
+            Mode::Tcp { address, tls } => {
+                let (host, port) = parse_address(address)?;
+                let tls = MaybeTlsSettings::from_config(tls, false)?;
+
+                let tcp = TcpSink::new(host, port, cx.resolver(), tls);
+                let healthcheck = tcp.healthcheck();
+                let sink = StreamSinkOld::new(tcp, cx.acker()).with_flat_map(encode);
+
+                Ok((VectorSink::Futures01Sink(Box::new(sink)), healthcheck))
+            }
+            Mode::Udp { address } => {
+                let (host, port) = parse_address(address)?;
+
+                let udp = UdpSink::new(host, port, cx.resolver());
+                let sink = StreamSinkOld::new(udp, cx.acker()).with_flat_map(encode);
+
+                Ok((
+                    VectorSink::Futures01Sink(Box::new(sink)),
+                    future::ok(()).boxed(),
+                ))
+            }
+        }
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn sink_type(&self) -> &'static str {
+        "graphite"
+    }
+}
+
+fn parse_address(address: &str) -> crate::Result<(String, u16)> {
+    let uri = address.parse::<http::Uri>()?;
+
+    let host = uri.host().ok_or(BuildError::MissingHost)?.to_string();
+    let port = uri.port_u16().ok_or(BuildError::MissingPort)?;
+
+    Ok((host, port))
+}
+
+/// Encodes the metric as plaintext protocol lines, one per derived series.
+/// All lines are sent as a single item, so that the event is acknowledged
+/// once they are all written.
+fn encode_event(event: Event, namespace: Option<&str>, tag_format: TagFormat) -> Option<Bytes> {
+    let metric = event.into_metric();
+    let name = encode_namespace(namespace, '.', &metric.name);
+    let timestamp = metric.timestamp.unwrap_or_else(Utc::now).timestamp();
+
+    let lines = flatten_metric_value(&metric.value)
+        .into_iter()
+        .map(|(suffix, value)| {
+            let path = encode_path(&name, suffix.as_deref(), metric.tags.as_ref(), tag_format);
+            format!("{} {} {}\n", path, value, timestamp)
+        })
+        .collect::<String>();
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.into())
+    }
+}
+
+fn encode_path(
+    name: &str,
+    suffix: Option<&str>,
+    tags: Option<&BTreeMap<String, String>>,
+    tag_format: TagFormat,
+) -> String {
+    let mut path = name.to_owned();
+    let tags = tags
+        .into_iter()
+        .flatten()
+        .filter(|(_, value)| !value.is_empty());
+
+    match tag_format {
+        TagFormat::Appended => {
+            if let Some(suffix) = suffix {
+                path.push('.');
+                path.push_str(&escape(suffix, true));
+            }
+            for (key, value) in tags {
+                path.push(';');
+                path.push_str(&escape(key, false));
+                path.push('=');
+                path.push_str(&escape(value, false));
+            }
+        }
+        TagFormat::Path => {
+            for (key, value) in tags {
+                path.push('.');
+                path.push_str(&escape(key, true));
+                path.push('.');
+                path.push_str(&escape(value, true));
+            }
+            if let Some(suffix) = suffix {
+                path.push('.');
+                path.push_str(&escape(suffix, true));
+            }
+        }
+    }
+
+    path
+}
+
+/// Replaces the characters Graphite treats as separators with `_`. Dots
+/// are only replaced in path nodes, where they would add a level.
+fn escape(value: &str, node: bool) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            ' ' | '\t' | '\n' | ';' | '=' => '_',
+            '.' if node => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::metric::{Metric, MetricKind, MetricValue, StatisticKind},
+        test_util::{next_addr, trace_init, CountReceiver},
+    };
+    use chrono::offset::TimeZone;
+    use futures::stream;
+    use pretty_assertions::assert_eq;
+
+    fn tags() -> BTreeMap<String, String> {
+        vec![
+            ("host".to_owned(), "web01.example.com".to_owned()),
+            ("region".to_owned(), "us east".to_owned()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn encode(metric: Metric, tag_format: TagFormat) -> String {
+        let bytes = encode_event(Event::Metric(metric), Some("vector"), tag_format).unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn encode_counter_appended_tags() {
+        let metric = Metric {
+            name: "requests".to_owned(),
+            timestamp: Some(Utc.ymd(2018, 11, 14).and_hms(8, 9, 10)),
+            tags: Some(tags()),
+            kind: MetricKind::Incremental,
+            value: MetricValue::Counter { value: 1.5 },
+        };
+
+        assert_eq!(
+            encode(metric, TagFormat::Appended),
+            "vector.requests;host=web01.example.com;region=us_east 1.5 1542182950\n"
+        );
+    }
+
+    #[test]
+    fn encode_gauge_path_tags() {
+        let metric = Metric {
+            name: "memory".to_owned(),
+            timestamp: Some(Utc.ymd(2018, 11, 14).and_hms(8, 9, 10)),
+            tags: Some(tags()),
+            kind: MetricKind::Absolute,
+            value: MetricValue::Gauge { value: 512.0 },
+        };
+
+        assert_eq!(
+            encode(metric, TagFormat::Path),
+            "vector.memory.host.web01_example_com.region.us_east 512 1542182950\n"
+        );
+    }
+
+    #[test]
+    fn encode_aggregated_histogram() {
+        let metric = Metric {
+            name: "latency".to_owned(),
+            timestamp: Some(Utc.ymd(2018, 11, 14).and_hms(8, 9, 10)),
+            tags: None,
+            kind: MetricKind::Absolute,
+            value: MetricValue::AggregatedHistogram {
+                buckets: vec![1.0, 2.1],
+                counts: vec![1, 2],
+                count: 3,
+                sum: 5.2,
+            },
+        };
+
+        assert_eq!(
+            encode(metric, TagFormat::Path),
+            "vector.latency.bucket_1 1 1542182950\n\
+             vector.latency.bucket_2_1 2 1542182950\n\
+             vector.latency.count 3 1542182950\n\
+             vector.latency.sum 5.2 1542182950\n"
+        );
+    }
+
+    #[test]
+    fn encode_distribution() {
+        let metric = Metric {
+            name: "latency".to_owned(),
+            timestamp: Some(Utc.ymd(2018, 11, 14).and_hms(8, 9, 10)),
+            tags: Some(tags()),
+            kind: MetricKind::Incremental,
+            value: MetricValue::Distribution {
+                values: vec![1.0, 2.0, 3.0],
+                sample_rates: vec![3, 3, 2],
+                statistic: StatisticKind::Histogram,
+            },
+        };
+
+        let lines = encode(metric, TagFormat::Appended);
+        let lines = lines.lines().collect::<Vec<_>>();
+        let suffix = ";host=web01.example.com;region=us_east";
+        assert_eq!(
+            lines,
+            vec![
+                format!("vector.latency.min{} 1 1542182950", suffix),
+                format!("vector.latency.max{} 3 1542182950", suffix),
+                format!("vector.latency.median{} 2 1542182950", suffix),
+                format!("vector.latency.avg{} 1.875 1542182950", suffix),
+                format!("vector.latency.sum{} 15 1542182950", suffix),
+                format!("vector.latency.count{} 8 1542182950", suffix),
+                format!("vector.latency.quantile_0_95{} 3 1542182950", suffix),
+            ]
+        );
+    }
+
+    #[test]
+    fn encode_empty_distribution() {
+        let metric = Metric {
+            name: "latency".to_owned(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Distribution {
+                values: vec![],
+                sample_rates: vec![],
+                statistic: StatisticKind::Histogram,
+            },
+        };
+
+        assert_eq!(
+            encode_event(Event::Metric(metric), None, TagFormat::Appended),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn tcp_smoke() {
+        trace_init();
+
+        let addr = next_addr();
+        let config = GraphiteSinkConfig {
+            namespace: None,
+            mode: Mode::Tcp {
+                address: addr.to_string(),
+                tls: None,
+            },
+            tag_format: TagFormat::Appended,
+        };
+
+        let (sink, _healthcheck) = config.build(SinkContext::new_test()).await.unwrap();
+
+        let mut receiver = CountReceiver::receive_lines(addr);
+
+        let events = (0..10)
+            .map(|i| {
+                Event::Metric(Metric {
+                    name: format!("counter_{}", i),
+                    timestamp: Some(Utc.ymd(2018, 11, 14).and_hms(8, 9, 10)),
+                    tags: None,
+                    kind: MetricKind::Absolute,
+                    value: MetricValue::Counter { value: i as f64 },
+                })
+            })
+            .collect::<Vec<_>>();
+        sink.run(stream::iter(events)).await.unwrap();
+
+        receiver.connected().await;
+
+        let output = receiver.await;
+        assert_eq!(output.len(), 10);
+        for (i, line) in output.iter().enumerate() {
+            assert_eq!(line, &format!("counter_{} {} 1542182950", i, i));
+        }
+    }
+}
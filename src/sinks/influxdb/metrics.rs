@@ -1,6 +1,6 @@
 use crate::{
     config::{DataType, SinkConfig, SinkContext, SinkDescription},
-    event::metric::{Metric, MetricValue, StatisticKind},
+    event::metric::{Metric, MetricValue},
     sinks::{
         influxdb::{
             encode_namespace, encode_timestamp, healthcheck, influx_line_protocol,
@@ -8,7 +8,7 @@ use crate::{
         },
         util::{
             http::{HttpBatchService, HttpClient, HttpRetryLogic},
-            statistic::DistributionStatistic,
+            statistic::flatten_metric_value,
             BatchConfig, BatchSettings, MetricBuffer, TowerRequestConfig,
         },
         Healthcheck, VectorSink,
@@ -267,16 +267,8 @@ fn encode_events(
                     &mut output,
                 );
             }
-            MetricValue::Distribution {
-                values,
-                sample_rates,
-                statistic,
-            } => {
-                let quantiles = match statistic {
-                    StatisticKind::Histogram => &[0.95] as &[_],
-                    StatisticKind::Summary => &[0.5, 0.75, 0.9, 0.95, 0.99] as &[_],
-                };
-                let fields = encode_distribution(&values, &sample_rates, quantiles);
+            value @ MetricValue::Distribution { .. } => {
+                let fields = encode_distribution(&value);
 
                 influx_line_protocol(
                     protocol_version,
@@ -296,31 +288,17 @@ fn encode_events(
     output
 }
 
-fn encode_distribution(
-    values: &[f64],
-    counts: &[u32],
-    quantiles: &[f64],
-) -> Option<HashMap<String, Field>> {
-    let statistic = DistributionStatistic::new(values, counts, quantiles)?;
-
-    let fields: HashMap<String, Field> = vec![
-        ("min".to_owned(), Field::Float(statistic.min)),
-        ("max".to_owned(), Field::Float(statistic.max)),
-        ("median".to_owned(), Field::Float(statistic.median)),
-        ("avg".to_owned(), Field::Float(statistic.avg)),
-        ("sum".to_owned(), Field::Float(statistic.sum)),
-        ("count".to_owned(), Field::Float(statistic.count as f64)),
-    ]
-    .into_iter()
-    .chain(
-        statistic
-            .quantiles
-            .iter()
-            .map(|&(p, val)| (format!("quantile_{:.2}", p), Field::Float(val))),
-    )
-    .collect();
-
-    Some(fields)
+fn encode_distribution(value: &MetricValue) -> Option<HashMap<String, Field>> {
+    let fields: HashMap<String, Field> = flatten_metric_value(value)
+        .into_iter()
+        .filter_map(|(suffix, value)| suffix.map(|suffix| (suffix, Field::Float(value))))
+        .collect();
+
+    if fields.is_empty() {
+        None
+    } else {
+        Some(fields)
+    }
 }
 
 fn to_fields(value: f64) -> HashMap<String, Field> {
@@ -743,9 +721,9 @@ mod tests {
                 "median=2",
                 "min=1",
                 "sum=15",
-                "quantile_0.50=2",
+                "quantile_0.5=2",
                 "quantile_0.75=2",
-                "quantile_0.90=3",
+                "quantile_0.9=3",
                 "quantile_0.95=3",
                 "quantile_0.99=3",
             ]
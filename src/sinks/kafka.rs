@@ -14,6 +14,7 @@ use futures01::{
 };
 use rdkafka::{
     consumer::{BaseConsumer, Consumer},
+    message::OwnedHeaders,
     producer::{DeliveryFuture, FutureProducer, FutureRecord},
 };
 use serde::{Deserialize, Serialize};
@@ -38,6 +39,8 @@ pub struct KafkaSinkConfig {
     bootstrap_servers: String,
     topic: String,
     key_field: Option<Atom>,
+    headers_key: Option<Atom>,
+    partition_field: Option<Atom>,
     encoding: EncodingConfigWithDefault<Encoding>,
     #[serde(default)]
     compression: KafkaCompression,
@@ -71,6 +74,8 @@ pub struct KafkaSink {
     producer: FutureProducer,
     topic: Template,
     key_field: Option<Atom>,
+    headers_key: Option<Atom>,
+    partition_field: Option<Atom>,
     encoding: EncodingConfig<Encoding>,
     in_flight: FuturesUnordered<MetadataFuture<Compat<DeliveryFuture>, usize>>,
 
@@ -133,6 +138,8 @@ impl KafkaSink {
             producer,
             topic: Template::try_from(config.topic).context(TopicTemplate)?,
             key_field: config.key_field,
+            headers_key: config.headers_key,
+            partition_field: config.partition_field,
             encoding: config.encoding.into(),
             in_flight: FuturesUnordered::new(),
             acker,
@@ -152,10 +159,27 @@ impl Sink for KafkaSink {
             error!(message = "Missing keys for topic", ?missing_keys);
         })?;
 
+        let headers = self
+            .headers_key
+            .as_ref()
+            .and_then(|headers_key| get_headers(&item, headers_key));
+        let partition = self
+            .partition_field
+            .as_ref()
+            .and_then(|partition_field| get_partition(&item, partition_field));
+
         let (key, body) = encode_event(item.clone(), &self.key_field, &self.encoding);
 
         let mut record = FutureRecord::to(&topic).key(&key).payload(&body[..]);
 
+        if let Some(headers) = headers {
+            record = record.headers(headers);
+        }
+
+        if let Some(partition) = partition {
+            record = record.partition(partition);
+        }
+
         if let Some(Value::Timestamp(timestamp)) = item.as_log().get(&log_schema().timestamp_key())
         {
             record = record.timestamp(timestamp.timestamp_millis());
@@ -254,6 +278,47 @@ async fn healthcheck(config: KafkaSinkConfig) -> crate::Result<()> {
     Ok(())
 }
 
+/// Builds message headers from the entries of the map at `headers_key`,
+/// which is the shape the kafka source reads headers back into.
+fn get_headers(event: &Event, headers_key: &Atom) -> Option<OwnedHeaders> {
+    match event.as_log().get(headers_key) {
+        Some(Value::Map(map)) => Some(map.iter().fold(
+            OwnedHeaders::new_with_capacity(map.len()),
+            |headers, (name, value)| headers.add(name.as_str(), &value.as_bytes()[..]),
+        )),
+        Some(_) => {
+            warn!(
+                message = "Headers field is not a map; sending message without headers.",
+                headers_key = %headers_key,
+                rate_limit_secs = 30,
+            );
+            None
+        }
+        None => None,
+    }
+}
+
+/// Reads the partition to send the event to from `partition_field`. Falls
+/// back to partitioning by key if the field is missing or not a partition.
+fn get_partition(event: &Event, partition_field: &Atom) -> Option<i32> {
+    let partition = match event.as_log().get(partition_field)? {
+        Value::Integer(partition) => i32::try_from(*partition).ok(),
+        value => String::from_utf8_lossy(&value.as_bytes()).parse().ok(),
+    };
+
+    match partition {
+        Some(partition) if partition >= 0 => Some(partition),
+        _ => {
+            warn!(
+                message = "Invalid partition; partitioning by key instead.",
+                partition_field = %partition_field,
+                rate_limit_secs = 30,
+            );
+            None
+        }
+    }
+}
+
 fn encode_event(
     mut event: Event,
     key_field: &Option<Atom>,
@@ -283,6 +348,7 @@ fn encode_event(
 mod tests {
     use super::*;
     use crate::event::Event;
+    use rdkafka::message::Headers;
     use std::collections::BTreeMap;
 
     #[test]
@@ -341,6 +407,43 @@ mod tests {
         assert_eq!(&key[..], b"value");
         assert!(!map.contains_key("key"));
     }
+
+    #[test]
+    fn kafka_get_headers() {
+        let mut event = Event::from("hello");
+        event.as_mut_log().insert("headers.foo", "bar");
+        event.as_mut_log().insert("headers.count", 1);
+
+        let headers = get_headers(&event, &"headers".into()).unwrap();
+
+        assert_eq!(headers.count(), 2);
+        assert_eq!(headers.get(0), Some(("count", &b"1"[..])));
+        assert_eq!(headers.get(1), Some(("foo", &b"bar"[..])));
+    }
+
+    #[test]
+    fn kafka_get_headers_not_a_map() {
+        let mut event = Event::from("hello");
+        event.as_mut_log().insert("headers", "bar");
+
+        assert!(get_headers(&event, &"headers".into()).is_none());
+        assert!(get_headers(&event, &"missing".into()).is_none());
+    }
+
+    #[test]
+    fn kafka_get_partition() {
+        let mut event = Event::from("hello");
+        event.as_mut_log().insert("int", 3);
+        event.as_mut_log().insert("string", "5");
+        event.as_mut_log().insert("negative", -1);
+        event.as_mut_log().insert("invalid", "foo");
+
+        assert_eq!(get_partition(&event, &"int".into()), Some(3));
+        assert_eq!(get_partition(&event, &"string".into()), Some(5));
+        assert_eq!(get_partition(&event, &"negative".into()), None);
+        assert_eq!(get_partition(&event, &"invalid".into()), None);
+        assert_eq!(get_partition(&event, &"missing".into()), None);
+    }
 }
 
 #[cfg(feature = "kafka-integration-tests")]
@@ -356,6 +459,7 @@ mod integration_test {
     use futures::{compat::Sink01CompatExt, future, SinkExt, StreamExt};
     use rdkafka::{
         consumer::{BaseConsumer, Consumer},
+        message::Headers,
         Message, Offset, TopicPartitionList,
     };
     use std::{thread, time::Duration};
@@ -460,6 +564,53 @@ mod integration_test {
         .await;
     }
 
+    #[tokio::test]
+    async fn kafka_headers_and_partition() {
+        let server = "localhost:9091";
+        let topic = format!("test-{}", random_string(10));
+
+        let config = KafkaSinkConfig {
+            bootstrap_servers: server.to_string(),
+            topic: "{{ topic }}".into(),
+            encoding: EncodingConfigWithDefault::from(Encoding::Text),
+            headers_key: Some("headers".into()),
+            partition_field: Some("partition".into()),
+            socket_timeout_ms: 60000,
+            message_timeout_ms: 300000,
+            ..Default::default()
+        };
+        let (acker, _) = Acker::new_for_testing();
+        let sink = KafkaSink::new(config, acker).unwrap();
+
+        let mut event = Event::from("hello");
+        event.as_mut_log().insert("topic", topic.clone());
+        event.as_mut_log().insert("partition", 0);
+        event.as_mut_log().insert("headers.foo", "bar");
+        let mut events = futures::stream::iter(vec![Ok(event)]);
+
+        let _ = sink.sink_compat().send_all(&mut events).await.unwrap();
+
+        let mut client_config = rdkafka::ClientConfig::new();
+        client_config.set("bootstrap.servers", server);
+        client_config.set("group.id", &random_string(10));
+
+        let mut tpl = TopicPartitionList::new();
+        tpl.add_partition(&topic, 0).set_offset(Offset::Beginning);
+
+        let consumer: BaseConsumer = client_config.create().unwrap();
+        consumer.assign(&tpl).unwrap();
+
+        let msg = (0..100)
+            .find_map(|_| consumer.poll(Duration::from_secs(3)).and_then(Result::ok))
+            .expect("No message received");
+
+        assert_eq!(msg.payload_view::<str>(), Some(Ok("hello")));
+        assert_eq!(msg.partition(), 0);
+        let headers = msg.headers().unwrap();
+        assert_eq!(headers.count(), 1);
+        assert_eq!(headers.get(0), Some(("foo", &b"bar"[..])));
+    }
+
     async fn kafka_happy_path(
         server: &str,
         sasl: Option<KafkaSaslConfig>,
@@ -11,15 +11,21 @@
 //!
 //! If an event produces no labels, this can happen if the template
 //! does not match, we will add a default label `{agent="vector"}`.
+//!
+//! A label name ending in `*` is expanded from an object field, producing
+//! one label per key of that object with the `*` replaced by the key.
+//!
+//! Unless out-of-order events are accepted, requests are sent one at a
+//! time so that batches of a stream reach Loki in order.
 
 use crate::{
     config::{log_schema, DataType, SinkConfig, SinkContext, SinkDescription},
     event::{self, Event, Value},
     sinks::util::{
-        buffer::loki::{LokiBuffer, LokiEvent, LokiRecord},
+        buffer::loki::{LokiBuffer, LokiEvent, LokiRecord, OutOfOrderAction},
         encoding::{EncodingConfigWithDefault, EncodingConfiguration},
         http::{Auth, BatchedHttpSink, HttpClient, HttpSink},
-        BatchConfig, BatchSettings, TowerRequestConfig, UriSerde,
+        BatchConfig, BatchSettings, InFlightLimit, TowerRequestConfig, UriSerde,
     },
     template::Template,
     tls::{TlsOptions, TlsSettings},
@@ -45,6 +51,10 @@ pub struct LokiConfig {
     #[serde(default = "crate::serde::default_true")]
     remove_timestamp: bool,
 
+    #[serde(default)]
+    out_of_order_action: OutOfOrderAction,
+    label_cardinality_limit: Option<usize>,
+
     auth: Option<Auth>,
 
     #[serde(default)]
@@ -80,7 +90,33 @@ impl SinkConfig for LokiConfig {
             return Err("`labels` must include at least one label.".into());
         }
 
-        let request_settings = self.request.unwrap_with(&TowerRequestConfig::default());
+        for (key, template) in &self.labels {
+            if is_wildcard(key)
+                && template
+                    .get_fields()
+                    .map_or(true, |fields| fields.len() != 1)
+            {
+                return Err(format!(
+                    "Wildcard label `{}` must be templated from exactly one field.",
+                    key
+                )
+                .into());
+            }
+        }
+
+        let mut request = self.request;
+        if self.out_of_order_action != OutOfOrderAction::Accept {
+            if !matches!(
+                request.in_flight_limit,
+                InFlightLimit::None | InFlightLimit::Fixed(1)
+            ) {
+                warn!(
+                    message = "Option `request.in_flight_limit` is ignored unless `out_of_order_action` is `accept`.",
+                );
+            }
+            request.in_flight_limit = InFlightLimit::Fixed(1);
+        }
+        let request_settings = request.unwrap_with(&TowerRequestConfig::default());
         let batch_settings = BatchSettings::default()
             .bytes(102_400)
             .events(100_000)
@@ -91,7 +127,9 @@ impl SinkConfig for LokiConfig {
 
         let sink = BatchedHttpSink::new(
             self.clone(),
-            LokiBuffer::new(batch_settings.size),
+            LokiBuffer::new(batch_settings.size)
+                .out_of_order_action(self.out_of_order_action)
+                .label_cardinality_limit(self.label_cardinality_limit),
             request_settings,
             batch_settings.timeout,
             client.clone(),
@@ -125,7 +163,9 @@ impl HttpSink for LokiConfig {
         let mut labels = Vec::new();
 
         for (key, template) in &self.labels {
-            if let Ok(value) = template.render_string(&event) {
+            if is_wildcard(key) {
+                labels.extend(expand_wildcard(key, template, &event));
+            } else if let Ok(value) = template.render_string(&event) {
                 labels.push((key.clone(), value));
             }
 
@@ -191,6 +231,42 @@ impl HttpSink for LokiConfig {
     }
 }
 
+fn is_wildcard(key: &str) -> bool {
+    key.ends_with('*')
+}
+
+/// Expands a `prefix_*` label into one label per key of the object field
+/// its template refers to. Fields that are missing or not objects produce
+/// no labels.
+fn expand_wildcard(key: &str, template: &Template, event: &Event) -> Vec<(String, String)> {
+    let prefix = &key[..key.len() - 1];
+    let field = match template.get_fields() {
+        Some(fields) if fields.len() == 1 => fields[0].clone(),
+        _ => return Vec::new(),
+    };
+
+    match event.as_log().get(&field) {
+        Some(Value::Map(map)) => map
+            .iter()
+            .map(|(name, value)| {
+                (
+                    format!("{}{}", prefix, sanitize_label_name(name)),
+                    value.to_string_lossy(),
+                )
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Loki label names must match `[a-zA-Z_][a-zA-Z0-9_]*`, so any other
+/// character from an expanded key is replaced with `_`.
+fn sanitize_label_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
 async fn healthcheck(config: LokiConfig, mut client: HttpClient) -> crate::Result<()> {
     let uri = format!("{}ready", config.endpoint);
 
@@ -275,6 +351,79 @@ mod tests {
 
         assert_eq!(record.labels[0], ("bar".to_string(), "bar".to_string()));
     }
+
+    #[test]
+    fn expand_wildcard_labels() {
+        let (config, _cx) = load_sink::<LokiConfig>(
+            r#"
+            endpoint = "http://localhost:3100"
+            labels = {"pod_labels_*" = "{{ kubernetes.pod_labels }}", app = "web"}
+            remove_label_fields = true
+        "#,
+        )
+        .unwrap();
+
+        let mut e1 = Event::from("hello world");
+        e1.as_mut_log()
+            .insert("kubernetes.pod_labels.tier", "frontend");
+        e1.as_mut_log()
+            .insert("kubernetes.pod_labels.release-track", "stable");
+        e1.as_mut_log().insert("kubernetes.pod_name", "web-0");
+
+        let mut record = config.encode_event(e1).unwrap();
+        record.labels.sort();
+
+        assert_eq!(
+            record.labels,
+            vec![
+                ("app".to_string(), "web".to_string()),
+                ("pod_labels_release_track".to_string(), "stable".to_string()),
+                ("pod_labels_tier".to_string(), "frontend".to_string()),
+            ]
+        );
+
+        let expected_line = serde_json::to_string(&serde_json::json!({
+            "kubernetes.pod_name": "web-0",
+            "message": "hello world",
+        }))
+        .unwrap();
+        assert_eq!(record.event.event, expected_line);
+    }
+
+    #[test]
+    fn wildcard_label_without_object_is_skipped() {
+        let (config, _cx) = load_sink::<LokiConfig>(
+            r#"
+            endpoint = "http://localhost:3100"
+            labels = {"pod_labels_*" = "{{ kubernetes.pod_labels }}"}
+        "#,
+        )
+        .unwrap();
+
+        let mut e1 = Event::from("hello world");
+        e1.as_mut_log()
+            .insert("kubernetes.pod_labels", "not an object");
+
+        let record = config.encode_event(e1).unwrap();
+
+        assert_eq!(
+            record.labels,
+            vec![("agent".to_string(), "vector".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn wildcard_label_requires_single_field() {
+        let (config, cx) = load_sink::<LokiConfig>(
+            r#"
+            endpoint = "http://localhost:3100"
+            labels = {"pod_labels_*" = "static"}
+        "#,
+        )
+        .unwrap();
+
+        assert!(config.build(cx).await.is_err());
+    }
 }
 
 #[cfg(feature = "loki-integration-tests")]
@@ -15,6 +15,10 @@ pub mod aws_kinesis_firehose;
 pub mod aws_kinesis_streams;
 #[cfg(feature = "sinks-aws_s3")]
 pub mod aws_s3;
+#[cfg(feature = "sinks-aws_sqs")]
+pub mod aws_sqs;
+#[cfg(feature = "sinks-azure_blob")]
+pub mod azure_blob;
 #[cfg(feature = "sinks-azure_monitor_logs")]
 pub mod azure_monitor_logs;
 #[cfg(feature = "sinks-blackhole")]
@@ -31,6 +35,8 @@ pub mod elasticsearch;
 pub mod file;
 #[cfg(feature = "sinks-gcp")]
 pub mod gcp;
+#[cfg(feature = "sinks-graphite")]
+pub mod graphite;
 #[cfg(feature = "sinks-honeycomb")]
 pub mod honeycomb;
 #[cfg(feature = "sinks-http")]
@@ -45,14 +51,24 @@ pub mod kafka;
 pub mod logdna;
 #[cfg(feature = "sinks-loki")]
 pub mod loki;
+#[cfg(feature = "sinks-mqtt")]
+pub mod mqtt;
+#[cfg(feature = "sinks-nats")]
+pub mod nats;
 #[cfg(feature = "sinks-new_relic_logs")]
 pub mod new_relic_logs;
+#[cfg(feature = "sinks-opentsdb")]
+pub mod opentsdb;
 #[cfg(feature = "sinks-papertrail")]
 pub mod papertrail;
 #[cfg(feature = "sinks-prometheus")]
 pub mod prometheus;
+#[cfg(feature = "sinks-prometheus_remote_write")]
+pub mod prometheus_remote_write;
 #[cfg(feature = "sinks-pulsar")]
 pub mod pulsar;
+#[cfg(feature = "sinks-redis")]
+pub mod redis;
 #[cfg(feature = "sinks-sematext_logs")]
 pub mod sematext_logs;
 #[cfg(feature = "sinks-socket")]
@@ -0,0 +1,457 @@
+use crate::{
+    buffers::Acker,
+    config::{log_schema, DataType, SinkConfig, SinkContext, SinkDescription},
+    event::Event,
+    internal_events::{
+        MqttConnectionError, MqttEventSendFail, MqttEventSendSuccess, MqttMissingKeys,
+    },
+    mqtt::{MqttConnectionConfig, MqttQos},
+    sinks::util::{
+        encoding::{EncodingConfig, EncodingConfigWithDefault, EncodingConfiguration},
+        StreamSink,
+    },
+    template::{Template, TemplateError},
+};
+use async_trait::async_trait;
+use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
+use rumqttc::{AsyncClient, Event as MqttEvent, EventLoop, Incoming, Outgoing};
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{
+    collections::VecDeque,
+    convert::TryFrom,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::{delay_for, timeout};
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("invalid topic template: {}", source))]
+    TopicTemplate { source: TemplateError },
+}
+
+#[derive(Debug, Snafu)]
+enum HealthcheckError {
+    #[snafu(display("Connection to the MQTT broker failed: {}", source))]
+    Connect { source: rumqttc::ConnectionError },
+    #[snafu(display("Timed out waiting for the MQTT broker to acknowledge the connection"))]
+    Timeout,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MqttSinkConfig {
+    #[serde(flatten)]
+    connection: MqttConnectionConfig,
+    topic: String,
+    #[serde(default)]
+    qos: MqttQos,
+    #[serde(default)]
+    retain: bool,
+    encoding: EncodingConfigWithDefault<Encoding>,
+}
+
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    #[derivative(Default)]
+    Text,
+    Json,
+}
+
+inventory::submit! {
+    SinkDescription::new_without_default::<MqttSinkConfig>("mqtt")
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "mqtt")]
+impl SinkConfig for MqttSinkConfig {
+    async fn build(
+        &self,
+        cx: SinkContext,
+    ) -> crate::Result<(super::VectorSink, super::Healthcheck)> {
+        let sink = MqttSink::new(self.clone(), cx.acker())?;
+        let healthcheck = healthcheck(self.connection.clone()).boxed();
+        Ok((super::VectorSink::Stream(Box::new(sink)), healthcheck))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn sink_type(&self) -> &'static str {
+        "mqtt"
+    }
+}
+
+fn healthcheck(connection: MqttConnectionConfig) -> BoxFuture<'static, crate::Result<()>> {
+    async move {
+        let (client, mut eventloop) = AsyncClient::new(connection.build_options()?, 1);
+
+        let connected = async {
+            loop {
+                if let MqttEvent::Incoming(Incoming::ConnAck(_)) =
+                    eventloop.poll().await.context(Connect)?
+                {
+                    return Ok::<(), HealthcheckError>(());
+                }
+            }
+        };
+        timeout(Duration::from_secs(10), connected)
+            .await
+            .map_err(|_| HealthcheckError::Timeout)??;
+
+        let _ = client.disconnect().await;
+        Ok(())
+    }
+    .boxed()
+}
+
+struct MqttSink {
+    client: AsyncClient,
+    eventloop: Option<EventLoop>,
+    topic: Template,
+    qos: MqttQos,
+    retain: bool,
+    encoding: EncodingConfig<Encoding>,
+    acker: Acker,
+    pending: Arc<Mutex<PendingAcks>>,
+}
+
+/// Events that have not been acked yet, in input order.
+///
+/// With QoS 1 an event is done once the broker's `PubAck` for it arrives.
+/// With QoS 0 the broker never acknowledges anything, so an event is done
+/// once it is written to the network, and delivery is at-most-once.
+#[derive(Debug, Default)]
+struct PendingAcks {
+    entries: VecDeque<PendingAck>,
+}
+
+#[derive(Debug, PartialEq)]
+enum PendingAck {
+    /// Handed to the client, but not yet written to the network.
+    Queued,
+    /// Written with this packet id, waiting for the `PubAck`.
+    Sent(u16),
+    Done,
+}
+
+impl PendingAcks {
+    fn push(&mut self, entry: PendingAck) {
+        self.entries.push_back(entry);
+    }
+
+    /// Publishes are written in the order they were queued, so the packet id
+    /// belongs to the oldest queued entry.
+    fn sent(&mut self, pkid: u16, qos: MqttQos) {
+        // The client resends unacknowledged publishes after reconnecting.
+        if qos == MqttQos::AtLeastOnce && self.entries.contains(&PendingAck::Sent(pkid)) {
+            return;
+        }
+
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|entry| **entry == PendingAck::Queued)
+        {
+            *entry = match qos {
+                MqttQos::AtMostOnce => PendingAck::Done,
+                MqttQos::AtLeastOnce => PendingAck::Sent(pkid),
+            };
+        }
+    }
+
+    fn acknowledged(&mut self, pkid: u16) {
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|entry| **entry == PendingAck::Sent(pkid))
+        {
+            *entry = PendingAck::Done;
+        }
+    }
+
+    /// Removes the leading done entries, returning how many can be acked.
+    fn take_done(&mut self) -> usize {
+        let mut done = 0;
+        while self.entries.front() == Some(&PendingAck::Done) {
+            self.entries.pop_front();
+            done += 1;
+        }
+        done
+    }
+}
+
+impl MqttSink {
+    fn new(config: MqttSinkConfig, acker: Acker) -> crate::Result<Self> {
+        let (client, eventloop) = AsyncClient::new(config.connection.build_options()?, 100);
+
+        Ok(MqttSink {
+            client,
+            eventloop: Some(eventloop),
+            topic: Template::try_from(config.topic).context(TopicTemplate)?,
+            qos: config.qos,
+            retain: config.retain,
+            encoding: config.encoding.into(),
+            acker,
+            pending: Arc::new(Mutex::new(PendingAcks::default())),
+        })
+    }
+}
+
+/// Drives the connection; outgoing publishes are only written to the
+/// network while the event loop is being polled.
+async fn drive_eventloop(
+    mut eventloop: EventLoop,
+    qos: MqttQos,
+    pending: Arc<Mutex<PendingAcks>>,
+    acker: Acker,
+) {
+    loop {
+        match eventloop.poll().await {
+            Ok(MqttEvent::Outgoing(Outgoing::Disconnect)) => break,
+            Ok(MqttEvent::Outgoing(Outgoing::Publish(pkid))) => {
+                let mut pending = pending.lock().unwrap();
+                pending.sent(pkid, qos);
+                acker.ack(pending.take_done());
+            }
+            Ok(MqttEvent::Incoming(Incoming::PubAck(puback))) => {
+                let mut pending = pending.lock().unwrap();
+                pending.acknowledged(puback.pkid);
+                acker.ack(pending.take_done());
+            }
+            Ok(_) => (),
+            Err(error) => {
+                emit!(MqttConnectionError { error });
+                // The event loop reconnects on the next poll.
+                delay_for(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl StreamSink for MqttSink {
+    async fn run(&mut self, mut input: BoxStream<'_, Event>) -> Result<(), ()> {
+        if let Some(eventloop) = self.eventloop.take() {
+            tokio::spawn(drive_eventloop(
+                eventloop,
+                self.qos,
+                Arc::clone(&self.pending),
+                self.acker.clone(),
+            ));
+        }
+
+        while let Some(event) = input.next().await {
+            let topic = match self.topic.render_string(&event) {
+                Ok(topic) => topic,
+                Err(missing_keys) => {
+                    emit!(MqttMissingKeys {
+                        keys: &missing_keys
+                    });
+                    let mut pending = self.pending.lock().unwrap();
+                    pending.push(PendingAck::Done);
+                    self.acker.ack(pending.take_done());
+                    continue;
+                }
+            };
+
+            let message = encode_event(event, &self.encoding);
+            let byte_size = message.len();
+
+            // Queue the entry first, the event loop may write the publish
+            // before this task resumes.
+            self.pending.lock().unwrap().push(PendingAck::Queued);
+            match self
+                .client
+                .publish(topic, self.qos.into(), self.retain, message)
+                .await
+            {
+                Ok(()) => emit!(MqttEventSendSuccess { byte_size }),
+                Err(error) => {
+                    emit!(MqttEventSendFail { error });
+                    return Err(());
+                }
+            }
+        }
+
+        // Give the broker a chance to acknowledge the last publishes before
+        // disconnecting, those left unacked are not acked to the buffer.
+        let flushed = async {
+            loop {
+                let empty = self.pending.lock().unwrap().entries.is_empty();
+                if empty {
+                    break;
+                }
+                delay_for(Duration::from_millis(100)).await;
+            }
+        };
+        let _ = timeout(Duration::from_secs(10), flushed).await;
+
+        let _ = self.client.disconnect().await;
+
+        Ok(())
+    }
+}
+
+fn encode_event(mut event: Event, encoding: &EncodingConfig<Encoding>) -> Vec<u8> {
+    encoding.apply_rules(&mut event);
+    let log = event.into_log();
+
+    match encoding.codec() {
+        Encoding::Json => serde_json::to_vec(&log).unwrap(),
+        Encoding::Text => log
+            .get(&log_schema().message_key())
+            .map(|v| v.as_bytes().to_vec())
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use string_cache::DefaultAtom as Atom;
+
+    #[test]
+    fn mqtt_encode_event_text() {
+        let message = "hello world".to_string();
+        let body = encode_event(
+            message.clone().into(),
+            &EncodingConfig::from(Encoding::Text),
+        );
+
+        assert_eq!(&body[..], message.as_bytes());
+    }
+
+    #[test]
+    fn mqtt_encode_event_apply_rules() {
+        let mut event = Event::from("hello");
+        event.as_mut_log().insert("key", "value");
+        event.as_mut_log().insert("device", "sensor-1");
+
+        let body = encode_event(
+            event,
+            &EncodingConfigWithDefault {
+                codec: Encoding::Json,
+                except_fields: Some(vec![Atom::from("key")]),
+                ..Default::default()
+            }
+            .into(),
+        );
+
+        let map: BTreeMap<String, String> = serde_json::from_slice(&body).unwrap();
+
+        assert!(!map.contains_key("key"));
+        assert_eq!(map["device"], "sensor-1".to_string());
+    }
+
+    #[test]
+    fn mqtt_pending_acks_wait_for_puback() {
+        let mut pending = PendingAcks::default();
+        pending.push(PendingAck::Queued);
+        pending.push(PendingAck::Done);
+        pending.push(PendingAck::Queued);
+
+        pending.sent(1, MqttQos::AtLeastOnce);
+        pending.sent(2, MqttQos::AtLeastOnce);
+        assert_eq!(pending.take_done(), 0);
+
+        // Resent after a reconnect.
+        pending.sent(1, MqttQos::AtLeastOnce);
+        pending.acknowledged(2);
+        assert_eq!(pending.take_done(), 0);
+
+        pending.acknowledged(1);
+        assert_eq!(pending.take_done(), 3);
+    }
+
+    #[test]
+    fn mqtt_pending_acks_at_most_once() {
+        let mut pending = PendingAcks::default();
+        pending.push(PendingAck::Queued);
+        pending.push(PendingAck::Queued);
+
+        pending.sent(0, MqttQos::AtMostOnce);
+        assert_eq!(pending.take_done(), 1);
+        pending.sent(0, MqttQos::AtMostOnce);
+        assert_eq!(pending.take_done(), 1);
+    }
+
+    #[test]
+    fn mqtt_sink_config() {
+        let config: MqttSinkConfig = toml::from_str(
+            r#"
+            host = "localhost"
+            topic = "devices/{{ device }}"
+            qos = 1
+            retain = true
+            encoding.codec = "json"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.qos, MqttQos::AtLeastOnce);
+        assert!(config.retain);
+        assert_eq!(config.connection.port, 1883);
+    }
+}
+
+#[cfg(feature = "mqtt-integration-tests")]
+#[cfg(test)]
+mod integration_tests {
+    use super::*;
+    use crate::test_util::{random_lines_with_stream, random_string, trace_init};
+    use rumqttc::QoS;
+    use std::sync::atomic::Ordering;
+
+    #[tokio::test]
+    async fn mqtt_happy() {
+        trace_init();
+
+        let topic = format!("test-{}", random_string(10));
+        let config: MqttSinkConfig = toml::from_str(&format!(
+            r#"
+            host = "127.0.0.1"
+            topic = "{}"
+            qos = 1
+            encoding.codec = "text"
+            "#,
+            topic
+        ))
+        .unwrap();
+
+        // Establish the consumer subscription.
+        let (consumer, mut eventloop) =
+            AsyncClient::new(config.connection.build_options().unwrap(), 1000);
+        consumer.subscribe(&topic, QoS::AtLeastOnce).await.unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok(event) = eventloop.poll().await {
+                if let MqttEvent::Incoming(Incoming::Publish(publish)) = event {
+                    let _ = tx.send(String::from_utf8_lossy(&publish.payload).to_string());
+                }
+            }
+        });
+        delay_for(Duration::from_secs(1)).await;
+
+        let (acker, ack_counter) = Acker::new_for_testing();
+        let mut sink = MqttSink::new(config, acker).unwrap();
+        let num_events = 100;
+        let (input, events) = random_lines_with_stream(100, num_events);
+        sink.run(Box::pin(events)).await.unwrap();
+
+        let mut output = Vec::new();
+        while output.len() < num_events {
+            match timeout(Duration::from_secs(10), rx.recv()).await {
+                Ok(Some(line)) => output.push(line),
+                _ => break,
+            }
+        }
+
+        assert_eq!(output, input);
+        assert_eq!(ack_counter.load(Ordering::Relaxed), num_events);
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{
+    buffers::Acker,
+    config::{log_schema, DataType, SinkConfig, SinkContext, SinkDescription},
+    event::Event,
+    internal_events::{NatsEventSendFail, NatsEventSendSuccess, NatsMissingKeys},
+    sinks::util::{
+        encoding::{EncodingConfig, EncodingConfigWithDefault, EncodingConfiguration},
+        StreamSink,
+    },
+    template::{Template, TemplateError},
+};
+use async_trait::async_trait;
+use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::convert::TryFrom;
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("invalid subject template: {}", source))]
+    SubjectTemplate { source: TemplateError },
+    #[snafu(display("NATS Connect Error: {}", source))]
+    Connect { source: std::io::Error },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NatsSinkConfig {
+    encoding: EncodingConfigWithDefault<Encoding>,
+    #[serde(default = "default_name")]
+    name: String,
+    subject: String,
+    url: String,
+}
+
+fn default_name() -> String {
+    String::from("vector")
+}
+
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    #[derivative(Default)]
+    Text,
+    Json,
+}
+
+inventory::submit! {
+    SinkDescription::new_without_default::<NatsSinkConfig>("nats")
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "nats")]
+impl SinkConfig for NatsSinkConfig {
+    async fn build(
+        &self,
+        cx: SinkContext,
+    ) -> crate::Result<(super::VectorSink, super::Healthcheck)> {
+        let sink = NatsSink::new(self.clone(), cx.acker()).await?;
+        let healthcheck = healthcheck(self.clone()).boxed();
+        Ok((super::VectorSink::Stream(Box::new(sink)), healthcheck))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn sink_type(&self) -> &'static str {
+        "nats"
+    }
+}
+
+impl NatsSinkConfig {
+    async fn connect(&self) -> crate::Result<nats::asynk::Connection> {
+        nats::Options::new()
+            .with_name(&self.name)
+            .connect_async(&self.url)
+            .await
+            .context(Connect)
+            .map_err(Into::into)
+    }
+}
+
+fn healthcheck(config: NatsSinkConfig) -> BoxFuture<'static, crate::Result<()>> {
+    async move { config.connect().await.map(|_| ()) }.boxed()
+}
+
+struct NatsSink {
+    encoding: EncodingConfig<Encoding>,
+    subject: Template,
+    connection: nats::asynk::Connection,
+    acker: Acker,
+}
+
+impl NatsSink {
+    async fn new(config: NatsSinkConfig, acker: Acker) -> crate::Result<Self> {
+        let connection = config.connect().await?;
+
+        Ok(NatsSink {
+            encoding: config.encoding.into(),
+            subject: Template::try_from(config.subject).context(SubjectTemplate)?,
+            connection,
+            acker,
+        })
+    }
+}
+
+#[async_trait]
+impl StreamSink for NatsSink {
+    async fn run(&mut self, mut input: BoxStream<'_, Event>) -> Result<(), ()> {
+        while let Some(event) = input.next().await {
+            let subject = match self.subject.render_string(&event) {
+                Ok(subject) => subject,
+                Err(missing_keys) => {
+                    emit!(NatsMissingKeys {
+                        keys: &missing_keys
+                    });
+                    self.acker.ack(1);
+                    continue;
+                }
+            };
+
+            let message = encode_event(event, &self.encoding);
+            let byte_size = message.len();
+
+            match self.connection.publish(&subject, message).await {
+                Ok(_) => {
+                    emit!(NatsEventSendSuccess { byte_size });
+                    self.acker.ack(1);
+                }
+                Err(error) => {
+                    // The connection reconnects on its own, so only this
+                    // event is lost.
+                    emit!(NatsEventSendFail { error });
+                    self.acker.ack(1);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn encode_event(mut event: Event, encoding: &EncodingConfig<Encoding>) -> String {
+    encoding.apply_rules(&mut event);
+    let log = event.into_log();
+
+    match encoding.codec() {
+        Encoding::Json => serde_json::to_string(&log).unwrap(),
+        Encoding::Text => log
+            .get(&log_schema().message_key())
+            .map(|v| v.to_string_lossy())
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use string_cache::DefaultAtom as Atom;
+
+    #[test]
+    fn nats_encode_event_text() {
+        let message = "hello world".to_string();
+        let body = encode_event(
+            message.clone().into(),
+            &EncodingConfig::from(Encoding::Text),
+        );
+
+        assert_eq!(&body, &message);
+    }
+
+    #[test]
+    fn nats_encode_event_json() {
+        let message = "hello world".to_string();
+        let mut event = Event::from(message.clone());
+        event.as_mut_log().insert("key", "value");
+        let body = encode_event(event, &EncodingConfig::from(Encoding::Json));
+
+        let map: BTreeMap<String, String> = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(map[&log_schema().message_key().to_string()], message);
+        assert_eq!(map["key"], "value".to_string());
+    }
+
+    #[test]
+    fn nats_encode_event_apply_rules() {
+        let mut event = Event::from("hello");
+        event.as_mut_log().insert("key", "value");
+
+        let body = encode_event(
+            event,
+            &EncodingConfigWithDefault {
+                codec: Encoding::Json,
+                except_fields: Some(vec![Atom::from("key")]),
+                ..Default::default()
+            }
+            .into(),
+        );
+
+        let map: BTreeMap<String, String> = serde_json::from_str(&body).unwrap();
+
+        assert!(!map.contains_key("key"));
+    }
+}
+
+#[cfg(feature = "nats-integration-tests")]
+#[cfg(test)]
+mod integration_tests {
+    use super::*;
+    use crate::test_util::{random_lines_with_stream, random_string, trace_init};
+    use std::sync::atomic::Ordering;
+
+    #[tokio::test]
+    async fn nats_happy() {
+        trace_init();
+
+        let subject = format!("test-{}", random_string(10));
+
+        let cnf = NatsSinkConfig {
+            encoding: EncodingConfigWithDefault::from(Encoding::Text),
+            name: "vector-test".to_owned(),
+            subject: subject.clone(),
+            url: "nats://127.0.0.1:4222".to_owned(),
+        };
+
+        // Establish the consumer subscription.
+        let consumer = cnf.clone().connect().await.unwrap();
+        let sub = consumer.subscribe(&subject).await.unwrap();
+
+        // Publish events.
+        let (acker, ack_counter) = Acker::new_for_testing();
+        let mut sink = NatsSink::new(cnf.clone(), acker).await.unwrap();
+        let num_events = 1_000;
+        let (input, events) = random_lines_with_stream(100, num_events);
+
+        let _ = sink.run(Box::pin(events)).await.unwrap();
+
+        // Unsubscribe from the channel.
+        tokio::time::delay_for(std::time::Duration::from_secs(3)).await;
+        let _ = sub.drain().await.unwrap();
+
+        let mut output: Vec<String> = Vec::new();
+        while let Some(msg) = sub.next().await {
+            output.push(String::from_utf8_lossy(&msg.data).to_string())
+        }
+
+        assert_eq!(output.len(), input.len());
+        assert_eq!(output, input);
+
+        assert_eq!(ack_counter.load(Ordering::Relaxed), num_events);
+    }
+}
@@ -0,0 +1,416 @@
+use crate::{
+    config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    event::{metric::Metric, Event},
+    sinks::{
+        util::{
+            encode_namespace,
+            http::{HttpBatchService, HttpClient, HttpRetryLogic},
+            statistic::flatten_metric_value,
+            tcp::TcpSink,
+            BatchConfig, BatchSettings, MetricBuffer, StreamSinkOld, TowerRequestConfig, UriSerde,
+        },
+        Healthcheck, HealthcheckError, VectorSink,
+    },
+    tls::{MaybeTlsSettings, TlsConfig, TlsOptions, TlsSettings},
+};
+use bytes::Bytes;
+use chrono::Utc;
+use futures::{future, FutureExt};
+use futures01::{stream::iter_ok, Sink};
+use http::{Request, Uri};
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("Missing host in address field"))]
+    MissingHost,
+    #[snafu(display("Missing port in address field"))]
+    MissingPort,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+// TODO: add back when serde-rs/serde#1358 is addressed
+// #[serde(deny_unknown_fields)]
+pub struct OpenTsdbSinkConfig {
+    pub namespace: Option<String>,
+    #[serde(flatten)]
+    pub mode: Mode,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Mode {
+    /// The telnet style `put` protocol.
+    Tcp {
+        address: String,
+        tls: Option<TlsConfig>,
+    },
+    /// The `/api/put` JSON API.
+    Http {
+        endpoint: UriSerde,
+        #[serde(default)]
+        batch: BatchConfig,
+        #[serde(default)]
+        request: TowerRequestConfig,
+        tls: Option<TlsOptions>,
+    },
+}
+
+inventory::submit! {
+    SinkDescription::new_without_default::<OpenTsdbSinkConfig>("opentsdb")
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "opentsdb")]
+impl SinkConfig for OpenTsdbSinkConfig {
+    async fn build(&self, cx: SinkContext) -> crate::Result<(VectorSink, Healthcheck)> {
+        let namespace = self.namespace.clone();
+        let hostname = crate::get_hostname()?;
+
+        match &self.mode {
+            Mode::Tcp { address, tls } => {
+                let uri = address.parse::<Uri>()?;
+                let host = uri.host().ok_or(BuildError::MissingHost)?.to_string();
+                let port = uri.port_u16().ok_or(BuildError::MissingPort)?;
+                let tls = MaybeTlsSettings::from_config(tls, false)?;
+
+                let tcp = TcpSink::new(host, port, cx.resolver(), tls);
+                let healthcheck = tcp.healthcheck();
+                let sink = StreamSinkOld::new(tcp, cx.acker()).with_flat_map(move |event| {
+                    iter_ok(encode_put_lines(event, namespace.as_deref(), &hostname))
+                });
+
+                Ok((VectorSink::Futures01Sink(Box::new(sink)), healthcheck))
+            }
+            Mode::Http {
+                endpoint,
+                batch,
+                request,
+                tls,
+            } => {
+                let tls = TlsSettings::from_options(tls)?;
+                let client = HttpClient::new(cx.resolver(), tls)?;
+                let healthcheck = healthcheck(endpoint.clone(), client.clone()).boxed();
+
+                let batch = BatchSettings::default()
+                    .events(20)
+                    .timeout(1)
+                    .parse_config(*batch)?;
+                let request = request.unwrap_with(&TowerRequestConfig::default());
+
+                let uri = api_uri(endpoint, "/api/put");
+                let service = HttpBatchService::new(client, move |metrics: Vec<Metric>| {
+                    future::ready(build_request(
+                        &uri,
+                        metrics,
+                        namespace.as_deref(),
+                        &hostname,
+                    ))
+                });
+
+                let sink = request
+                    .batch_sink(
+                        HttpRetryLogic,
+                        service,
+                        MetricBuffer::new(batch.size),
+                        batch.timeout,
+                        cx.acker(),
+                    )
+                    .sink_map_err(|e| error!("Fatal opentsdb sink error: {}", e));
+
+                Ok((VectorSink::Futures01Sink(Box::new(sink)), healthcheck))
+            }
+        }
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn sink_type(&self) -> &'static str {
+        "opentsdb"
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct DataPoint {
+    metric: String,
+    timestamp: i64,
+    value: f64,
+    tags: BTreeMap<String, String>,
+}
+
+/// Flattens the metric into one data point per derived series. OpenTSDB
+/// rejects data points without tags, so metrics without any are tagged
+/// with the `host` they are sent from.
+fn encode_data_points(metric: Metric, namespace: Option<&str>, hostname: &str) -> Vec<DataPoint> {
+    let name = encode_namespace(namespace, '.', &metric.name);
+    let timestamp = metric.timestamp.unwrap_or_else(Utc::now).timestamp_millis();
+    let mut tags = metric
+        .tags
+        .iter()
+        .flatten()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(key, value)| (escape(key), escape(value)))
+        .collect::<BTreeMap<_, _>>();
+    if tags.is_empty() {
+        tags.insert("host".to_owned(), escape(hostname));
+    }
+
+    flatten_metric_value(&metric.value)
+        .into_iter()
+        .map(|(suffix, value)| DataPoint {
+            metric: match suffix {
+                Some(suffix) => escape(&format!("{}.{}", name, suffix)),
+                None => escape(&name),
+            },
+            timestamp,
+            value,
+            tags: tags.clone(),
+        })
+        .collect()
+}
+
+/// Encodes the metric as `put` lines, sent as a single item so that the
+/// event is acknowledged once they are all written.
+fn encode_put_lines(event: Event, namespace: Option<&str>, hostname: &str) -> Option<Bytes> {
+    let lines = encode_data_points(event.into_metric(), namespace, hostname)
+        .into_iter()
+        .map(|point| {
+            let tags = point
+                .tags
+                .iter()
+                .map(|(key, value)| format!(" {}={}", key, value))
+                .collect::<String>();
+            format!(
+                "put {} {} {}{}\n",
+                point.metric, point.timestamp, point.value, tags
+            )
+        })
+        .collect::<String>();
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.into())
+    }
+}
+
+fn build_request(
+    uri: &Uri,
+    metrics: Vec<Metric>,
+    namespace: Option<&str>,
+    hostname: &str,
+) -> crate::Result<Request<Vec<u8>>> {
+    let points = metrics
+        .into_iter()
+        .flat_map(|metric| encode_data_points(metric, namespace, hostname))
+        .collect::<Vec<_>>();
+    let body = serde_json::to_vec(&points)?;
+
+    Request::post(uri.clone())
+        .header("Content-Type", "application/json")
+        .body(body)
+        .map_err(Into::into)
+}
+
+/// Replaces the characters OpenTSDB doesn't allow in metric names and
+/// tags with `_`.
+fn escape(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '-' | '_' | '.' | '/' => c,
+            c if c.is_alphanumeric() => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+fn api_uri(endpoint: &UriSerde, path: &str) -> Uri {
+    let endpoint: Uri = endpoint.clone().into();
+
+    format!("{}{}", endpoint.to_string().trim_end_matches('/'), path)
+        .parse::<Uri>()
+        .expect("This should be a valid uri")
+}
+
+async fn healthcheck(endpoint: UriSerde, mut client: HttpClient) -> crate::Result<()> {
+    let request = Request::get(api_uri(&endpoint, "/api/version"))
+        .body(hyper::Body::empty())
+        .unwrap();
+
+    let response = client.send(request).await?;
+
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        other => Err(HealthcheckError::UnexpectedStatus { status: other }.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::metric::{MetricKind, MetricValue, StatisticKind},
+        sinks::util::test::{build_test_server, load_sink},
+        test_util::{next_addr, trace_init},
+    };
+    use chrono::offset::TimeZone;
+    use futures::{stream, StreamExt};
+    use pretty_assertions::assert_eq;
+
+    fn tags() -> BTreeMap<String, String> {
+        vec![
+            ("host".to_owned(), "web01.example.com".to_owned()),
+            ("region".to_owned(), "us east".to_owned()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn encode_counter_put_line() {
+        let metric = Metric {
+            name: "requests".to_owned(),
+            timestamp: Some(Utc.ymd(2018, 11, 14).and_hms(8, 9, 10)),
+            tags: Some(tags()),
+            kind: MetricKind::Incremental,
+            value: MetricValue::Counter { value: 1.5 },
+        };
+
+        let bytes = encode_put_lines(Event::Metric(metric), Some("vector"), "vector-host").unwrap();
+        assert_eq!(
+            String::from_utf8(bytes.to_vec()).unwrap(),
+            "put vector.requests 1542182950000 1.5 host=web01.example.com region=us_east\n"
+        );
+    }
+
+    #[test]
+    fn encode_aggregated_summary_put_lines() {
+        let metric = Metric {
+            name: "latency".to_owned(),
+            timestamp: Some(Utc.ymd(2018, 11, 14).and_hms(8, 9, 10)),
+            tags: Some(tags()),
+            kind: MetricKind::Absolute,
+            value: MetricValue::AggregatedSummary {
+                quantiles: vec![0.5, 0.99],
+                values: vec![1.5, 3.0],
+                count: 6,
+                sum: 12.0,
+            },
+        };
+
+        let bytes = encode_put_lines(Event::Metric(metric), None, "vector-host").unwrap();
+        let tags = "host=web01.example.com region=us_east";
+        assert_eq!(
+            String::from_utf8(bytes.to_vec()).unwrap(),
+            format!(
+                "put latency.quantile_0.5 1542182950000 1.5 {tags}\n\
+                 put latency.quantile_0.99 1542182950000 3 {tags}\n\
+                 put latency.count 1542182950000 6 {tags}\n\
+                 put latency.sum 1542182950000 12 {tags}\n",
+                tags = tags
+            )
+        );
+    }
+
+    #[test]
+    fn encode_distribution_data_points() {
+        let metric = Metric {
+            name: "latency".to_owned(),
+            timestamp: Some(Utc.ymd(2018, 11, 14).and_hms(8, 9, 10)),
+            tags: Some(tags()),
+            kind: MetricKind::Incremental,
+            value: MetricValue::Distribution {
+                values: vec![1.0, 2.0, 3.0],
+                sample_rates: vec![3, 3, 2],
+                statistic: StatisticKind::Histogram,
+            },
+        };
+
+        let points = encode_data_points(metric, None, "vector-host")
+            .into_iter()
+            .map(|point| (point.metric, point.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            points,
+            vec![
+                ("latency.min".to_owned(), 1.0),
+                ("latency.max".to_owned(), 3.0),
+                ("latency.median".to_owned(), 2.0),
+                ("latency.avg".to_owned(), 1.875),
+                ("latency.sum".to_owned(), 15.0),
+                ("latency.count".to_owned(), 8.0),
+                ("latency.quantile_0.95".to_owned(), 3.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn encode_untagged_put_line() {
+        let metric = Metric {
+            name: "requests".to_owned(),
+            timestamp: Some(Utc.ymd(2018, 11, 14).and_hms(8, 9, 10)),
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Counter { value: 1.5 },
+        };
+
+        let bytes = encode_put_lines(Event::Metric(metric), None, "vector host").unwrap();
+        assert_eq!(
+            String::from_utf8(bytes.to_vec()).unwrap(),
+            "put requests 1542182950000 1.5 host=vector_host\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn http_smoke() {
+        trace_init();
+
+        let addr = next_addr();
+        let (config, cx) = load_sink::<OpenTsdbSinkConfig>(&format!(
+            r#"
+            mode = "http"
+            endpoint = "http://{}"
+        "#,
+            addr
+        ))
+        .unwrap();
+
+        let (sink, _) = config.build(cx).await.unwrap();
+
+        let (rx, _trigger, server) = build_test_server(addr);
+        tokio::spawn(server);
+
+        let events = (0..10)
+            .map(|i| {
+                Event::Metric(Metric {
+                    name: format!("gauge_{}", i),
+                    timestamp: Some(Utc.ymd(2018, 11, 14).and_hms(8, 9, 10)),
+                    tags: Some(tags()),
+                    kind: MetricKind::Absolute,
+                    value: MetricValue::Gauge { value: i as f64 },
+                })
+            })
+            .collect::<Vec<_>>();
+        sink.run(stream::iter(events)).await.unwrap();
+
+        let output = rx.take(1).collect::<Vec<_>>().await;
+        let (parts, body) = &output[0];
+        assert_eq!(parts.uri.path(), "/api/put");
+
+        let points: Vec<serde_json::Value> = serde_json::from_slice(&body[..]).unwrap();
+        assert_eq!(points.len(), 10);
+
+        let point = points
+            .iter()
+            .find(|point| point["metric"] == "gauge_3")
+            .unwrap();
+        assert_eq!(point["timestamp"], 1542182950000i64);
+        assert_eq!(point["value"], 3.0);
+        assert_eq!(point["tags"]["region"], "us_east");
+    }
+}
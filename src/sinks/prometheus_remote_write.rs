@@ -0,0 +1,685 @@
+//! Prometheus remote write sink
+//!
+//! This sink pushes the metrics to the backends implementing the
+//! Prometheus remote write protocol, like Cortex or Thanos.
+//!
+//! https://prometheus.io/docs/prometheus/latest/configuration/configuration/#remote_write
+//!
+//! The batches are normalised by the `MetricBuffer`, which keeps the
+//! counters absolute, as Prometheus expects them to be. The distributions
+//! are accumulated by the sink itself for the same reason, see
+//! `DistributionTotals`.
+
+use crate::{
+    config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    event::{
+        metric::{Metric, MetricKind, MetricValue, StatisticKind},
+        Event,
+    },
+    sinks::util::{
+        encode_namespace,
+        http::{Auth, BatchedHttpSink, HttpClient, HttpSink},
+        statistic::DistributionStatistic,
+        BatchConfig, BatchSettings, MetricBuffer, TowerRequestConfig, UriSerde,
+    },
+    tls::{TlsOptions, TlsSettings},
+};
+use chrono::Utc;
+use futures::{future, FutureExt};
+use futures01::Sink;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+/// The totals of distributions that haven't been seen for this long are
+/// forgotten.
+const SERIES_EXPIRATION_SECS: i64 = 3600;
+
+pub mod proto {
+    include!(concat!(env!("OUT_DIR"), "/prometheus.rs"));
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteWriteConfig {
+    endpoint: UriSerde,
+    namespace: Option<String>,
+    #[serde(default = "default_histogram_buckets")]
+    buckets: Vec<f64>,
+    #[serde(default = "default_summary_quantiles")]
+    quantiles: Vec<f64>,
+
+    tenant_id: Option<String>,
+    auth: Option<Auth>,
+
+    #[serde(default)]
+    batch: BatchConfig,
+    #[serde(default)]
+    request: TowerRequestConfig,
+
+    tls: Option<TlsOptions>,
+}
+
+fn default_histogram_buckets() -> Vec<f64> {
+    vec![
+        0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+    ]
+}
+
+fn default_summary_quantiles() -> Vec<f64> {
+    vec![0.5, 0.75, 0.9, 0.95, 0.99]
+}
+
+inventory::submit! {
+    SinkDescription::new_without_default::<RemoteWriteConfig>("prometheus_remote_write")
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "prometheus_remote_write")]
+impl SinkConfig for RemoteWriteConfig {
+    async fn build(
+        &self,
+        cx: SinkContext,
+    ) -> crate::Result<(super::VectorSink, super::Healthcheck)> {
+        if let Some(quantile) = self
+            .quantiles
+            .iter()
+            .find(|quantile| !(0.0..=1.0).contains(*quantile))
+        {
+            return Err(format!("Quantile {} must be between 0 and 1.", quantile).into());
+        }
+
+        let request_settings = self.request.unwrap_with(&TowerRequestConfig::default());
+        let batch_settings = BatchSettings::default()
+            .events(1_000)
+            .timeout(1)
+            .parse_config(self.batch)?;
+        let tls = TlsSettings::from_options(&self.tls)?;
+        let client = HttpClient::new(cx.resolver(), tls)?;
+
+        let sink = BatchedHttpSink::new(
+            RemoteWriteSink::new(self.clone()),
+            MetricBuffer::new_with_absolute_counters(batch_settings.size),
+            request_settings,
+            batch_settings.timeout,
+            client,
+            cx.acker(),
+        )
+        .sink_map_err(|e| error!("Fatal prometheus remote write sink error: {}", e));
+
+        // The remote write protocol has no endpoint to check the health with.
+        let healthcheck = future::ok(()).boxed();
+
+        Ok((
+            super::VectorSink::Futures01Sink(Box::new(sink)),
+            healthcheck,
+        ))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn sink_type(&self) -> &'static str {
+        "prometheus_remote_write"
+    }
+}
+
+struct RemoteWriteSink {
+    config: RemoteWriteConfig,
+    totals: Mutex<DistributionTotals>,
+}
+
+impl RemoteWriteSink {
+    fn new(config: RemoteWriteConfig) -> Self {
+        Self {
+            config,
+            totals: Mutex::new(DistributionTotals::default()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpSink for RemoteWriteSink {
+    type Input = Event;
+    type Output = Vec<Metric>;
+
+    fn encode_event(&self, event: Event) -> Option<Self::Input> {
+        // Accumulated here rather than when the request is built, as that
+        // happens again on every retry.
+        let metric = self.totals.lock().unwrap().accumulate(
+            event.into_metric(),
+            &self.config.buckets,
+            Utc::now().timestamp(),
+        );
+        Some(Event::Metric(metric))
+    }
+
+    async fn build_request(&self, metrics: Self::Output) -> crate::Result<http::Request<Vec<u8>>> {
+        let config = &self.config;
+        let request = {
+            let mut totals = self.totals.lock().unwrap();
+            totals.expire(Utc::now().timestamp());
+            encode_metrics(
+                config.namespace.as_deref(),
+                &config.quantiles,
+                &totals,
+                metrics,
+            )
+        };
+
+        let mut buf = Vec::with_capacity(request.encoded_len());
+        request.encode(&mut buf)?;
+        let body = snap::raw::Encoder::new().compress_vec(&buf)?;
+
+        let mut req = http::Request::post(config.endpoint.to_string())
+            .header("Content-Type", "application/x-protobuf")
+            .header("Content-Encoding", "snappy")
+            .header("X-Prometheus-Remote-Write-Version", "0.1.0");
+
+        if let Some(tenant_id) = &config.tenant_id {
+            req = req.header("X-Scope-OrgID", tenant_id);
+        }
+
+        let mut req = req.body(body)?;
+
+        if let Some(auth) = &config.auth {
+            auth.apply(&mut req);
+        }
+
+        Ok(req)
+    }
+}
+
+type SeriesKey = (String, Option<BTreeMap<String, String>>);
+
+fn series_key(metric: &Metric) -> SeriesKey {
+    (metric.name.clone(), metric.tags.clone())
+}
+
+/// The running totals of the distributions since Vector started.
+///
+/// Prometheus expects the `_bucket`, `_sum` and `_count` series to be
+/// cumulative, so they can't be computed from a single batch. Histograms
+/// are turned into absolute `AggregatedHistogram`s as the events come in.
+/// The quantiles of summaries can only be computed from the samples of a
+/// batch, so only their `_sum` and `_count` are taken from the totals.
+/// The totals of series not seen for `SERIES_EXPIRATION_SECS` are dropped,
+/// so that series which come and go don't accumulate.
+#[derive(Debug, Default)]
+struct DistributionTotals {
+    histograms: HashMap<SeriesKey, HistogramTotals>,
+    summaries: HashMap<SeriesKey, SummaryTotals>,
+}
+
+#[derive(Debug)]
+struct HistogramTotals {
+    counts: Vec<u32>,
+    count: u32,
+    sum: f64,
+    last_seen: i64,
+}
+
+#[derive(Debug, Default)]
+struct SummaryTotals {
+    count: u32,
+    sum: f64,
+    last_seen: i64,
+}
+
+impl DistributionTotals {
+    fn accumulate(&mut self, metric: Metric, buckets: &[f64], now: i64) -> Metric {
+        // Wrapping around is seen as a counter reset by Prometheus.
+        match &metric.value {
+            MetricValue::Distribution {
+                values,
+                sample_rates,
+                statistic: StatisticKind::Histogram,
+            } => {
+                let totals = self
+                    .histograms
+                    .entry(series_key(&metric))
+                    .or_insert_with(|| HistogramTotals {
+                        counts: vec![0; buckets.len()],
+                        count: 0,
+                        sum: 0.0,
+                        last_seen: now,
+                    });
+                totals.last_seen = now;
+                for (value, rate) in values.iter().zip(sample_rates.iter()) {
+                    for (bucket, count) in buckets.iter().zip(totals.counts.iter_mut()) {
+                        if value <= bucket {
+                            *count = count.wrapping_add(*rate);
+                        }
+                    }
+                    totals.count = totals.count.wrapping_add(*rate);
+                    totals.sum += value * (*rate as f64);
+                }
+
+                Metric {
+                    kind: MetricKind::Absolute,
+                    value: MetricValue::AggregatedHistogram {
+                        buckets: buckets.to_vec(),
+                        counts: totals.counts.clone(),
+                        count: totals.count,
+                        sum: totals.sum,
+                    },
+                    ..metric
+                }
+            }
+            MetricValue::Distribution {
+                values,
+                sample_rates,
+                statistic: StatisticKind::Summary,
+            } => {
+                let totals = self.summaries.entry(series_key(&metric)).or_default();
+                totals.last_seen = now;
+                for (value, rate) in values.iter().zip(sample_rates.iter()) {
+                    totals.count = totals.count.wrapping_add(*rate);
+                    totals.sum += value * (*rate as f64);
+                }
+                metric
+            }
+            _ => metric,
+        }
+    }
+
+    fn expire(&mut self, now: i64) {
+        self.histograms
+            .retain(|_, totals| now - totals.last_seen < SERIES_EXPIRATION_SECS);
+        self.summaries
+            .retain(|_, totals| now - totals.last_seen < SERIES_EXPIRATION_SECS);
+    }
+}
+
+/// Collects the time series of a batch.
+struct TimeSeries {
+    namespace: Option<String>,
+    series: Vec<proto::TimeSeries>,
+}
+
+impl TimeSeries {
+    fn push(
+        &mut self,
+        metric: &Metric,
+        suffix: &str,
+        extra_label: Option<(&str, String)>,
+        value: f64,
+    ) {
+        let name = encode_namespace(self.namespace.as_deref(), '_', &metric.name);
+        let timestamp = metric.timestamp.unwrap_or_else(Utc::now).timestamp_millis();
+
+        // The labels must be sorted by their names.
+        let mut labels = metric.tags.clone().unwrap_or_default();
+        labels.insert("__name__".into(), format!("{}{}", name, suffix));
+        if let Some((name, value)) = extra_label {
+            labels.insert(name.into(), value);
+        }
+
+        self.series.push(proto::TimeSeries {
+            labels: labels
+                .into_iter()
+                .map(|(name, value)| proto::Label { name, value })
+                .collect(),
+            samples: vec![proto::Sample { value, timestamp }],
+        });
+    }
+
+    fn push_histogram(
+        &mut self,
+        metric: &Metric,
+        buckets: &[f64],
+        counts: &[u32],
+        count: u32,
+        sum: f64,
+    ) {
+        for (bucket, count) in buckets.iter().zip(counts.iter()) {
+            self.push(
+                metric,
+                "_bucket",
+                Some(("le", bucket.to_string())),
+                *count as f64,
+            );
+        }
+        self.push(
+            metric,
+            "_bucket",
+            Some(("le", "+Inf".to_owned())),
+            count as f64,
+        );
+        self.push(metric, "_sum", None, sum);
+        self.push(metric, "_count", None, count as f64);
+    }
+
+    fn push_summary(&mut self, metric: &Metric, quantiles: &[(f64, f64)], count: u32, sum: f64) {
+        for (quantile, value) in quantiles {
+            self.push(metric, "", Some(("quantile", quantile.to_string())), *value);
+        }
+        self.push(metric, "_sum", None, sum);
+        self.push(metric, "_count", None, count as f64);
+    }
+}
+
+fn encode_metrics(
+    namespace: Option<&str>,
+    quantiles: &[f64],
+    totals: &DistributionTotals,
+    metrics: Vec<Metric>,
+) -> proto::WriteRequest {
+    let mut series = TimeSeries {
+        namespace: namespace.map(Into::into),
+        series: Vec::with_capacity(metrics.len()),
+    };
+
+    for metric in &metrics {
+        match &metric.value {
+            MetricValue::Counter { value } | MetricValue::Gauge { value } => {
+                series.push(metric, "", None, *value)
+            }
+            MetricValue::Set { values } => series.push(metric, "", None, values.len() as f64),
+            // Turned into `AggregatedHistogram`s by `DistributionTotals`.
+            MetricValue::Distribution {
+                statistic: StatisticKind::Histogram,
+                ..
+            } => (),
+            MetricValue::Distribution {
+                values,
+                sample_rates,
+                statistic: StatisticKind::Summary,
+            } => {
+                if let Some(statistic) = DistributionStatistic::new(values, sample_rates, quantiles)
+                {
+                    let (count, sum) = match totals.summaries.get(&series_key(metric)) {
+                        Some(totals) => (totals.count, totals.sum),
+                        None => (statistic.count as u32, statistic.sum),
+                    };
+                    series.push_summary(metric, &statistic.quantiles, count, sum);
+                }
+            }
+            MetricValue::AggregatedHistogram {
+                buckets,
+                counts,
+                count,
+                sum,
+            } => series.push_histogram(metric, buckets, counts, *count, *sum),
+            MetricValue::AggregatedSummary {
+                quantiles,
+                values,
+                count,
+                sum,
+            } => {
+                let quantiles = quantiles
+                    .iter()
+                    .copied()
+                    .zip(values.iter().copied())
+                    .collect::<Vec<_>>();
+                series.push_summary(metric, &quantiles, *count, *sum);
+            }
+        }
+    }
+
+    proto::WriteRequest {
+        timeseries: series.series,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{event::metric::MetricKind, sinks::util::test::load_sink};
+    use chrono::{TimeZone, Utc};
+
+    fn metric(value: MetricValue) -> Metric {
+        Metric {
+            name: "requests".into(),
+            timestamp: Some(Utc.timestamp(1_600_000_000, 0)),
+            tags: Some(
+                vec![("host".to_owned(), "web-01".to_owned())]
+                    .into_iter()
+                    .collect(),
+            ),
+            kind: MetricKind::Absolute,
+            value,
+        }
+    }
+
+    fn series(request: &proto::WriteRequest) -> Vec<(String, f64)> {
+        request
+            .timeseries
+            .iter()
+            .map(|series| {
+                let labels = series
+                    .labels
+                    .iter()
+                    .map(|label| format!("{}={}", label.name, label.value))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                (labels, series.samples[0].value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn encodes_counter() {
+        let request = encode_metrics(
+            Some("vector"),
+            &[],
+            &DistributionTotals::default(),
+            vec![metric(MetricValue::Counter { value: 42.0 })],
+        );
+
+        assert_eq!(
+            series(&request),
+            vec![("__name__=vector_requests,host=web-01".to_owned(), 42.0)]
+        );
+        assert_eq!(
+            request.timeseries[0].samples[0].timestamp,
+            1_600_000_000_000
+        );
+    }
+
+    #[test]
+    fn encodes_aggregated_histogram() {
+        let request = encode_metrics(
+            None,
+            &[],
+            &DistributionTotals::default(),
+            vec![metric(MetricValue::AggregatedHistogram {
+                buckets: vec![1.0, 2.0],
+                counts: vec![3, 5],
+                count: 6,
+                sum: 9.5,
+            })],
+        );
+
+        assert_eq!(
+            series(&request),
+            vec![
+                ("__name__=requests_bucket,host=web-01,le=1".to_owned(), 3.0),
+                ("__name__=requests_bucket,host=web-01,le=2".to_owned(), 5.0),
+                (
+                    "__name__=requests_bucket,host=web-01,le=+Inf".to_owned(),
+                    6.0
+                ),
+                ("__name__=requests_sum,host=web-01".to_owned(), 9.5),
+                ("__name__=requests_count,host=web-01".to_owned(), 6.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn encodes_aggregated_summary() {
+        let request = encode_metrics(
+            None,
+            &[],
+            &DistributionTotals::default(),
+            vec![metric(MetricValue::AggregatedSummary {
+                quantiles: vec![0.5, 0.99],
+                values: vec![1.5, 3.0],
+                count: 4,
+                sum: 7.0,
+            })],
+        );
+
+        assert_eq!(
+            series(&request),
+            vec![
+                ("__name__=requests,host=web-01,quantile=0.5".to_owned(), 1.5),
+                (
+                    "__name__=requests,host=web-01,quantile=0.99".to_owned(),
+                    3.0
+                ),
+                ("__name__=requests_sum,host=web-01".to_owned(), 7.0),
+                ("__name__=requests_count,host=web-01".to_owned(), 4.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn accumulates_histograms_across_batches() {
+        let mut totals = DistributionTotals::default();
+        let distribution = |values, sample_rates| {
+            metric(MetricValue::Distribution {
+                values,
+                sample_rates,
+                statistic: StatisticKind::Histogram,
+            })
+        };
+
+        let first = totals.accumulate(distribution(vec![0.5, 2.0], vec![2, 1]), &[1.0, 5.0], 0);
+        let request = encode_metrics(None, &[], &totals, vec![first]);
+        assert_eq!(
+            series(&request),
+            vec![
+                ("__name__=requests_bucket,host=web-01,le=1".to_owned(), 2.0),
+                ("__name__=requests_bucket,host=web-01,le=5".to_owned(), 3.0),
+                (
+                    "__name__=requests_bucket,host=web-01,le=+Inf".to_owned(),
+                    3.0
+                ),
+                ("__name__=requests_sum,host=web-01".to_owned(), 3.0),
+                ("__name__=requests_count,host=web-01".to_owned(), 3.0),
+            ]
+        );
+
+        let second = totals.accumulate(distribution(vec![4.0], vec![1]), &[1.0, 5.0], 0);
+        let request = encode_metrics(None, &[], &totals, vec![second]);
+        assert_eq!(
+            series(&request),
+            vec![
+                ("__name__=requests_bucket,host=web-01,le=1".to_owned(), 2.0),
+                ("__name__=requests_bucket,host=web-01,le=5".to_owned(), 4.0),
+                (
+                    "__name__=requests_bucket,host=web-01,le=+Inf".to_owned(),
+                    4.0
+                ),
+                ("__name__=requests_sum,host=web-01".to_owned(), 7.0),
+                ("__name__=requests_count,host=web-01".to_owned(), 4.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn accumulates_summary_totals_across_batches() {
+        let mut totals = DistributionTotals::default();
+        let distribution = |values, sample_rates| {
+            metric(MetricValue::Distribution {
+                values,
+                sample_rates,
+                statistic: StatisticKind::Summary,
+            })
+        };
+
+        let first = totals.accumulate(distribution(vec![1.0, 3.0], vec![1, 1]), &[], 0);
+        encode_metrics(None, &[0.5], &totals, vec![first]);
+
+        let second = totals.accumulate(distribution(vec![10.0], vec![1]), &[], 0);
+        let request = encode_metrics(None, &[0.5], &totals, vec![second]);
+        assert_eq!(
+            series(&request),
+            vec![
+                (
+                    "__name__=requests,host=web-01,quantile=0.5".to_owned(),
+                    10.0
+                ),
+                ("__name__=requests_sum,host=web-01".to_owned(), 14.0),
+                ("__name__=requests_count,host=web-01".to_owned(), 3.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn expires_unseen_totals() {
+        let mut totals = DistributionTotals::default();
+        let distribution = |name: &str| Metric {
+            name: name.into(),
+            ..metric(MetricValue::Distribution {
+                values: vec![1.0],
+                sample_rates: vec![1],
+                statistic: StatisticKind::Histogram,
+            })
+        };
+
+        totals.accumulate(distribution("old"), &[1.0], 0);
+        totals.accumulate(distribution("recent"), &[1.0], SERIES_EXPIRATION_SECS);
+        totals.expire(SERIES_EXPIRATION_SECS + 1);
+
+        assert_eq!(
+            totals
+                .histograms
+                .keys()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["recent"]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_quantiles() {
+        let (config, cx) = load_sink::<RemoteWriteConfig>(
+            r#"
+            endpoint = "http://localhost:9009/api/v1/push"
+            quantiles = [0.5, 1.5]
+        "#,
+        )
+        .unwrap();
+
+        assert!(config.build(cx).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn builds_request() {
+        let (config, _cx) = load_sink::<RemoteWriteConfig>(
+            r#"
+            endpoint = "http://localhost:9009/api/v1/push"
+            tenant_id = "tenant"
+            auth.strategy = "bearer"
+            auth.token = "secret"
+        "#,
+        )
+        .unwrap();
+
+        let request = RemoteWriteSink::new(config)
+            .build_request(vec![metric(MetricValue::Gauge { value: 1.0 })])
+            .await
+            .unwrap();
+
+        assert_eq!(request.uri(), "http://localhost:9009/api/v1/push");
+        let headers = request.headers();
+        assert_eq!(headers["Content-Encoding"], "snappy");
+        assert_eq!(headers["X-Scope-OrgID"], "tenant");
+        assert_eq!(headers["Authorization"], "Bearer secret");
+
+        let body = snap::raw::Decoder::new()
+            .decompress_vec(request.body())
+            .unwrap();
+        let decoded = proto::WriteRequest::decode(&body[..]).unwrap();
+        assert_eq!(
+            series(&decoded),
+            vec![("__name__=requests,host=web-01".to_owned(), 1.0)]
+        );
+    }
+}
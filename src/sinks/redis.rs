@@ -0,0 +1,440 @@
+use crate::{
+    config::{log_schema, DataType, SinkConfig, SinkContext, SinkDescription},
+    event::Event,
+    internal_events::{RedisEventSent, RedisMissingKeys},
+    sinks::util::{
+        encoding::{EncodingConfig, EncodingConfigWithDefault, EncodingConfiguration},
+        retries::RetryLogic,
+        BatchConfig, BatchSettings, EncodedLength, InFlightLimit, TowerRequestConfig, VecBuffer,
+    },
+    template::{Template, TemplateError},
+};
+use futures::{future::BoxFuture, FutureExt};
+use futures01::{stream::iter_ok, Sink};
+use lazy_static::lazy_static;
+use redis::{aio::ConnectionManager, RedisError, RedisResult};
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{
+    convert::TryFrom,
+    fmt,
+    task::{Context, Poll},
+};
+use tower::Service;
+use tracing_futures::Instrument;
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("Invalid key template: {}", source))]
+    KeyTemplate { source: TemplateError },
+    #[snafu(display("Failed to create connection: {}", source))]
+    Connection { source: RedisError },
+}
+
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RedisDataType {
+    #[derivative(Default)]
+    List,
+    Channel,
+    Stream,
+}
+
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    #[derivative(Default)]
+    Rpush,
+    Lpush,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ListOption {
+    #[serde(default)]
+    method: Method,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct StreamOption {
+    #[serde(default = "default_stream_field")]
+    field: String,
+}
+
+impl Default for StreamOption {
+    fn default() -> Self {
+        Self {
+            field: default_stream_field(),
+        }
+    }
+}
+
+fn default_stream_field() -> String {
+    "message".into()
+}
+
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    #[derivative(Default)]
+    Text,
+    Json,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RedisSinkConfig {
+    url: String,
+    key: String,
+    #[serde(default)]
+    data_type: RedisDataType,
+    list: Option<ListOption>,
+    stream: Option<StreamOption>,
+    encoding: EncodingConfigWithDefault<Encoding>,
+    #[serde(default)]
+    batch: BatchConfig,
+    #[serde(default)]
+    request: TowerRequestConfig,
+}
+
+lazy_static! {
+    static ref REQUEST_DEFAULTS: TowerRequestConfig = TowerRequestConfig {
+        in_flight_limit: InFlightLimit::Fixed(1),
+        rate_limit_num: Some(u64::max_value()),
+        ..Default::default()
+    };
+}
+
+inventory::submit! {
+    SinkDescription::new_without_default::<RedisSinkConfig>("redis")
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "redis")]
+impl SinkConfig for RedisSinkConfig {
+    async fn build(
+        &self,
+        cx: SinkContext,
+    ) -> crate::Result<(super::VectorSink, super::Healthcheck)> {
+        let conn = self.build_connection().await?;
+        let healthcheck = healthcheck(conn.clone()).boxed();
+        let sink = self.new(conn, cx)?;
+        Ok((
+            super::VectorSink::Futures01Sink(Box::new(sink)),
+            healthcheck,
+        ))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn sink_type(&self) -> &'static str {
+        "redis"
+    }
+}
+
+impl RedisSinkConfig {
+    async fn build_connection(&self) -> crate::Result<ConnectionManager> {
+        let client = redis::Client::open(self.url.as_str()).context(Connection)?;
+        let conn = ConnectionManager::new(client).await.context(Connection)?;
+        Ok(conn)
+    }
+
+    fn new(
+        &self,
+        conn: ConnectionManager,
+        cx: SinkContext,
+    ) -> crate::Result<impl Sink<SinkItem = Event, SinkError = ()>> {
+        let batch = BatchSettings::default()
+            .bytes(1_000_000)
+            .events(100)
+            .timeout(1)
+            .parse_config(self.batch)?;
+        let request = self.request.unwrap_with(&REQUEST_DEFAULTS);
+        let key = Template::try_from(self.key.as_str()).context(KeyTemplate)?;
+        let encoding = self.encoding.clone().into();
+
+        let redis = RedisSink {
+            conn,
+            command: self.command(),
+        };
+
+        let sink = request
+            .batch_sink(
+                RedisRetryLogic,
+                redis,
+                VecBuffer::new(batch.size),
+                batch.timeout,
+                cx.acker(),
+            )
+            .sink_map_err(|error| error!("Fatal redis sink error: {}", error))
+            .with_flat_map(move |event| iter_ok(encode_event(event, &key, &encoding)));
+
+        Ok(sink)
+    }
+
+    fn command(&self) -> RedisCommand {
+        match self.data_type {
+            RedisDataType::List => match self.list.clone().unwrap_or_default().method {
+                Method::Rpush => RedisCommand::Rpush,
+                Method::Lpush => RedisCommand::Lpush,
+            },
+            RedisDataType::Channel => RedisCommand::Publish,
+            RedisDataType::Stream => RedisCommand::Xadd {
+                field: self.stream.clone().unwrap_or_default().field,
+            },
+        }
+    }
+}
+
+async fn healthcheck(mut conn: ConnectionManager) -> crate::Result<()> {
+    redis::cmd("PING")
+        .query_async::<_, String>(&mut conn)
+        .await
+        .map(|_| ())
+        .map_err(Into::into)
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum RedisCommand {
+    Rpush,
+    Lpush,
+    Publish,
+    Xadd { field: String },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct RedisKvEntry {
+    key: String,
+    value: Vec<u8>,
+}
+
+impl EncodedLength for RedisKvEntry {
+    fn encoded_length(&self) -> usize {
+        self.key.len() + self.value.len()
+    }
+}
+
+#[derive(Clone)]
+struct RedisSink {
+    conn: ConnectionManager,
+    command: RedisCommand,
+}
+
+impl Service<Vec<RedisKvEntry>> for RedisSink {
+    type Response = ();
+    type Error = RedisError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, entries: Vec<RedisKvEntry>) -> Self::Future {
+        debug!(
+            message = "Sending entries.",
+            entries = %entries.len(),
+        );
+
+        // All entries of a batch are written in a single transaction, so a
+        // retried batch is never partially applied.
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for entry in entries {
+            match &self.command {
+                RedisCommand::Rpush => pipe.rpush(entry.key, entry.value),
+                RedisCommand::Lpush => pipe.lpush(entry.key, entry.value),
+                RedisCommand::Publish => pipe.publish(entry.key, entry.value),
+                RedisCommand::Xadd { field } => {
+                    pipe.xadd(entry.key, "*", &[(field.as_str(), entry.value)])
+                }
+            }
+            .ignore();
+        }
+
+        let mut conn = self.conn.clone();
+        Box::pin(
+            async move {
+                let res: RedisResult<()> = pipe.query_async(&mut conn).await;
+                res
+            }
+            .instrument(info_span!("request")),
+        )
+    }
+}
+
+impl fmt::Debug for RedisSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisSink")
+            .field("command", &self.command)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RedisRetryLogic;
+
+impl RetryLogic for RedisRetryLogic {
+    type Error = RedisError;
+    type Response = ();
+
+    fn is_retriable_error(&self, error: &Self::Error) -> bool {
+        error.is_io_error() || error.is_timeout() || error.is_connection_dropped()
+    }
+}
+
+fn encode_event(
+    mut event: Event,
+    key: &Template,
+    encoding: &EncodingConfig<Encoding>,
+) -> Option<RedisKvEntry> {
+    let key = match key.render_string(&event) {
+        Ok(key) => key,
+        Err(missing_keys) => {
+            emit!(RedisMissingKeys {
+                keys: &missing_keys
+            });
+            return None;
+        }
+    };
+
+    encoding.apply_rules(&mut event);
+
+    let log = event.into_log();
+    let value = match encoding.codec() {
+        Encoding::Json => serde_json::to_vec(&log).expect("Error encoding event as json."),
+        Encoding::Text => log
+            .get(&log_schema().message_key())
+            .map(|v| v.as_bytes().to_vec())
+            .unwrap_or_default(),
+    };
+
+    emit!(RedisEventSent {
+        byte_size: value.len()
+    });
+    Some(RedisKvEntry { key, value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use string_cache::DefaultAtom as Atom;
+
+    #[test]
+    fn redis_encode_event_text() {
+        let key = Template::try_from("key").unwrap();
+        let entry = encode_event(
+            "hello world".into(),
+            &key,
+            &EncodingConfig::from(Encoding::Text),
+        )
+        .unwrap();
+
+        assert_eq!(entry.key, "key");
+        assert_eq!(&entry.value[..], b"hello world");
+    }
+
+    #[test]
+    fn redis_encode_event_json() {
+        let key = Template::try_from("logs-{{ app }}").unwrap();
+        let mut event = Event::from("hello world");
+        event.as_mut_log().insert("app", "nginx");
+        event.as_mut_log().insert("secret", "hunter2");
+
+        let entry = encode_event(
+            event,
+            &key,
+            &EncodingConfigWithDefault {
+                codec: Encoding::Json,
+                except_fields: Some(vec![Atom::from("secret")]),
+                ..Default::default()
+            }
+            .into(),
+        )
+        .unwrap();
+
+        let map: BTreeMap<String, String> = serde_json::from_slice(&entry.value).unwrap();
+        assert_eq!(entry.key, "logs-nginx");
+        assert_eq!(map[&log_schema().message_key().to_string()], "hello world");
+        assert!(!map.contains_key("secret"));
+    }
+
+    #[test]
+    fn redis_encode_event_missing_key() {
+        let key = Template::try_from("logs-{{ app }}").unwrap();
+        let entry = encode_event(
+            "hello world".into(),
+            &key,
+            &EncodingConfig::from(Encoding::Text),
+        );
+
+        assert!(entry.is_none());
+    }
+
+    #[test]
+    fn redis_sink_command() {
+        let config: RedisSinkConfig = toml::from_str(
+            r#"
+            url = "redis://127.0.0.1:6379/0"
+            key = "vector"
+            data_type = "stream"
+            encoding.codec = "json"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.command(),
+            RedisCommand::Xadd {
+                field: "message".into()
+            }
+        );
+    }
+}
+
+#[cfg(feature = "redis-integration-tests")]
+#[cfg(test)]
+mod integration_tests {
+    use super::*;
+    use crate::test_util::{random_lines_with_stream, random_string, trace_init};
+    use futures::{compat::Sink01CompatExt, SinkExt, StreamExt};
+    use redis::AsyncCommands;
+
+    const REDIS_SERVER: &str = "redis://127.0.0.1:6379/0";
+
+    #[tokio::test]
+    async fn redis_sink_list() {
+        trace_init();
+
+        let key = format!("test-{}", random_string(10));
+        let config: RedisSinkConfig = toml::from_str(&format!(
+            r#"
+            url = "{}"
+            key = "{}"
+            data_type = "list"
+            encoding.codec = "text"
+            "#,
+            REDIS_SERVER, key
+        ))
+        .unwrap();
+
+        let conn = config.build_connection().await.unwrap();
+        let mut check = conn.clone();
+        let sink = config.new(conn, SinkContext::new_test()).unwrap();
+
+        let num_events = 100;
+        let (input, events) = random_lines_with_stream(100, num_events);
+        let mut events = events.map(Ok);
+
+        let _ = sink.sink_compat().send_all(&mut events).await.unwrap();
+
+        let output: Vec<String> = check.lrange(&key, 0, -1).await.unwrap();
+        assert_eq!(output, input);
+    }
+}
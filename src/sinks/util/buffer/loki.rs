@@ -3,20 +3,46 @@
 //! This buffer handles stream merging -- when a record is inserted into
 //! the buffer, all records having the same stream label set are grouped
 //! together for more efficient output.
+//!
+//! Loki rejects entries older than the latest one it has accepted for a
+//! stream, so the buffer also remembers the latest timestamp of every stream
+//! in finished batches and applies the configured `OutOfOrderAction` to
+//! events that would go backwards. The number of distinct values per label
+//! can be capped as well, since every new label set creates a new stream.
 
 use super::{
     err_event_too_large, json::BoxedRawValue, Batch, BatchConfig, BatchError, BatchSettings,
     BatchSize, PushResult,
 };
+use crate::internal_events::{
+    LokiLabelCardinalityExceeded, LokiOutOfOrderEventDropped, LokiOutOfOrderEventRewritten,
+};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, value::to_raw_value};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
 const WRAPPER_OVERHEAD: usize = r#"{"streams":[]}"#.len();
 const STREAM_OVERHEAD: usize = r#"{"stream":{},"values":[]}"#.len();
 const LABEL_OVERHEAD: usize = r#""":"""#.len();
 
+/// Value that replaces label values past the cardinality limit.
+pub const OVERFLOW_LABEL_VALUE: &str = "__overflow__";
+
 pub type Labels = Vec<(String, String)>;
 
+/// What to do with an event whose timestamp is older than the latest one
+/// already sent in an earlier batch of its stream.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Derivative)]
+#[serde(rename_all = "snake_case")]
+#[derivative(Default)]
+pub enum OutOfOrderAction {
+    #[derivative(Default)]
+    Drop,
+    RewriteTimestamp,
+    Accept,
+}
+
 #[derive(Clone, Debug)]
 pub struct LokiEvent {
     pub timestamp: i64,
@@ -47,12 +73,24 @@ impl From<&LokiEvent> for LokiEncodedEvent {
     }
 }
 
+/// State shared by all the buffers of one sink, so that it carries over
+/// from one batch to the next.
+#[derive(Debug, Default)]
+struct StreamState {
+    latest_timestamps: HashMap<Labels, i64>,
+    label_values: HashMap<String, HashSet<String>>,
+}
+
 #[derive(Debug)]
 pub struct LokiBuffer {
     num_bytes: usize,
     num_items: usize,
+    num_dropped: usize,
     streams: HashMap<Labels, Vec<LokiEncodedEvent>>,
     settings: BatchSize<Self>,
+    out_of_order_action: OutOfOrderAction,
+    label_cardinality_limit: Option<usize>,
+    state: Arc<Mutex<StreamState>>,
 }
 
 impl LokiBuffer {
@@ -60,8 +98,47 @@ impl LokiBuffer {
         Self {
             num_bytes: WRAPPER_OVERHEAD,
             num_items: 0,
+            num_dropped: 0,
             streams: HashMap::default(),
             settings,
+            out_of_order_action: OutOfOrderAction::default(),
+            label_cardinality_limit: None,
+            state: Arc::default(),
+        }
+    }
+
+    pub fn out_of_order_action(mut self, action: OutOfOrderAction) -> Self {
+        self.out_of_order_action = action;
+        self
+    }
+
+    pub fn label_cardinality_limit(mut self, limit: Option<usize>) -> Self {
+        self.label_cardinality_limit = limit;
+        self
+    }
+
+    /// Replaces the values of labels which already have `limit` distinct
+    /// values with `OVERFLOW_LABEL_VALUE`.
+    fn limit_cardinality(&self, state: &mut StreamState, labels: &mut Labels) {
+        let limit = match self.label_cardinality_limit {
+            Some(limit) => limit,
+            None => return,
+        };
+
+        for (name, value) in labels.iter_mut() {
+            if value == OVERFLOW_LABEL_VALUE {
+                continue;
+            }
+
+            let values = state.label_values.entry(name.clone()).or_default();
+            if !values.contains(value.as_str()) {
+                if values.len() < limit {
+                    values.insert(value.clone());
+                } else {
+                    emit!(LokiLabelCardinalityExceeded { label: name, limit });
+                    *value = OVERFLOW_LABEL_VALUE.to_owned();
+                }
+            }
         }
     }
 }
@@ -80,6 +157,37 @@ impl Batch for LokiBuffer {
     }
 
     fn push(&mut self, mut item: Self::Input) -> PushResult<Self::Input> {
+        let state = Arc::clone(&self.state);
+        let mut state = state.lock().expect("Poisoned Loki stream state lock");
+
+        // We must sort the stream labels here to ensure they hash to
+        // the same stream if the label set matches.
+        item.labels.sort();
+        self.limit_cardinality(&mut state, &mut item.labels);
+
+        let latest = match self.out_of_order_action {
+            OutOfOrderAction::Accept => None,
+            _ => state.latest_timestamps.get(&item.labels).copied(),
+        };
+        if let Some(latest) = latest {
+            if item.event.timestamp < latest {
+                match self.out_of_order_action {
+                    OutOfOrderAction::Drop => {
+                        emit!(LokiOutOfOrderEventDropped);
+                        // Dropped events are still counted so that the
+                        // batch acks them once it is sent.
+                        self.num_dropped += 1;
+                        return PushResult::Ok(false);
+                    }
+                    OutOfOrderAction::RewriteTimestamp => {
+                        emit!(LokiOutOfOrderEventRewritten);
+                        item.event.timestamp = latest;
+                    }
+                    OutOfOrderAction::Accept => {}
+                }
+            }
+        }
+
         let labels_len = item
             .labels
             .iter()
@@ -89,16 +197,15 @@ impl Batch for LokiBuffer {
         let event: LokiEncodedEvent = (&item.event).into();
         let event_len = event.encoded.get().len();
 
-        if self.is_empty() && WRAPPER_OVERHEAD + labels_len + event_len > self.settings.bytes {
+        if self.streams.is_empty()
+            && WRAPPER_OVERHEAD + labels_len + event_len > self.settings.bytes
+        {
             err_event_too_large(WRAPPER_OVERHEAD + labels_len + event_len)
         } else if self.num_items >= self.settings.events
             || self.num_bytes + event_len + 1 > self.settings.bytes
         {
             PushResult::Overflow(item)
         } else {
-            // We must sort the stream labels here to ensure they hash to
-            // the same stream if the label set matches.
-            item.labels.sort();
             let new_bytes = match self.streams.get_mut(&item.labels) {
                 // Label exists, and we checked the size, just add it
                 Some(stream) => {
@@ -126,14 +233,35 @@ impl Batch for LokiBuffer {
     }
 
     fn is_empty(&self) -> bool {
-        self.streams.is_empty()
+        self.streams.is_empty() && self.num_dropped == 0
     }
 
     fn fresh(&self) -> Self {
-        Self::new(self.settings)
+        Self {
+            num_bytes: WRAPPER_OVERHEAD,
+            num_items: 0,
+            num_dropped: 0,
+            streams: HashMap::default(),
+            settings: self.settings,
+            out_of_order_action: self.out_of_order_action,
+            label_cardinality_limit: self.label_cardinality_limit,
+            state: Arc::clone(&self.state),
+        }
     }
 
     fn finish(self) -> Self::Output {
+        // Events within a batch are sorted below, so only the batches that
+        // come after this one need to be checked against its timestamps.
+        if self.out_of_order_action != OutOfOrderAction::Accept {
+            let mut state = self.state.lock().expect("Poisoned Loki stream state lock");
+            for (labels, events) in &self.streams {
+                if let Some(max) = events.iter().map(|e| e.timestamp).max() {
+                    let latest = state.latest_timestamps.entry(labels.clone()).or_insert(max);
+                    *latest = (*latest).max(max);
+                }
+            }
+        }
+
         let streams_json = self
             .streams
             .into_iter()
@@ -141,7 +269,7 @@ impl Batch for LokiBuffer {
                 // Sort events by timestamp
                 events.sort_by_key(|e| e.timestamp);
 
-                let stream = stream.into_iter().collect::<HashMap<_, _>>();
+                let stream = stream.into_iter().collect::<BTreeMap<_, _>>();
                 let events = events.into_iter().map(|e| e.encoded).collect::<Vec<_>>();
 
                 (
@@ -175,7 +303,7 @@ impl Batch for LokiBuffer {
     }
 
     fn num_items(&self) -> usize {
-        self.num_items
+        self.num_items + self.num_dropped
     }
 }
 
@@ -266,4 +394,169 @@ mod tests {
             r#"{"streams":[{"stream":{"asdf":"value1"},"values":[["123456781","event #1"],["123456782","event #2"],["123456783","event #3"]]}]}"#,
         );
     }
+
+    fn record(labels: &[(&str, &str)], timestamp: i64, event: &str) -> LokiRecord {
+        LokiRecord {
+            labels: labels
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            event: LokiEvent {
+                timestamp,
+                event: event.into(),
+            },
+        }
+    }
+
+    #[test]
+    fn sorts_out_of_order_events_within_batch() {
+        let mut buffer = LokiBuffer::new(BatchSettings::default().size);
+        for n in &[3, 1, 2] {
+            assert!(matches!(
+                buffer.push(record(&[("asdf", "value1")], *n, &format!("event #{}", n))),
+                PushResult::Ok(false)
+            ));
+        }
+
+        assert_eq!(buffer.num_items, 3);
+        test_finish(
+            buffer,
+            r#"{"streams":[{"stream":{"asdf":"value1"},"values":[["1","event #1"],["2","event #2"],["3","event #3"]]}]}"#,
+        );
+    }
+
+    #[test]
+    fn drops_out_of_order_events_across_batches() {
+        let mut buffer = LokiBuffer::new(BatchSettings::default().size);
+        assert!(matches!(
+            buffer.push(record(&[("asdf", "value1")], 10, "first")),
+            PushResult::Ok(false)
+        ));
+        let mut next = buffer.fresh();
+        buffer.finish();
+
+        assert!(matches!(
+            next.push(record(&[("asdf", "value1")], 5, "late")),
+            PushResult::Ok(false)
+        ));
+        assert!(matches!(
+            next.push(record(&[("asdf", "value2")], 5, "other stream")),
+            PushResult::Ok(false)
+        ));
+        assert!(matches!(
+            next.push(record(&[("asdf", "value1")], 10, "same time")),
+            PushResult::Ok(false)
+        ));
+
+        assert_eq!(next.num_items, 2);
+        assert_eq!(next.num_items(), 3);
+        test_finish(
+            next,
+            r#"{"streams":[{"stream":{"asdf":"value1"},"values":[["10","same time"]]},{"stream":{"asdf":"value2"},"values":[["5","other stream"]]}]}"#,
+        );
+    }
+
+    #[test]
+    fn rewrites_out_of_order_timestamps() {
+        let mut buffer = LokiBuffer::new(BatchSettings::default().size)
+            .out_of_order_action(OutOfOrderAction::RewriteTimestamp);
+        assert!(matches!(
+            buffer.push(record(&[("asdf", "value1")], 10, "first")),
+            PushResult::Ok(false)
+        ));
+        let mut next = buffer.fresh();
+        buffer.finish();
+
+        assert!(matches!(
+            next.push(record(&[("asdf", "value1")], 5, "late")),
+            PushResult::Ok(false)
+        ));
+
+        assert_eq!(next.num_items, 1);
+        test_finish(
+            next,
+            r#"{"streams":[{"stream":{"asdf":"value1"},"values":[["10","late"]]}]}"#,
+        );
+    }
+
+    #[test]
+    fn accepts_out_of_order_events() {
+        let mut buffer = LokiBuffer::new(BatchSettings::default().size)
+            .out_of_order_action(OutOfOrderAction::Accept);
+        assert!(matches!(
+            buffer.push(record(&[("asdf", "value1")], 10, "first")),
+            PushResult::Ok(false)
+        ));
+        let mut next = buffer.fresh();
+        buffer.finish();
+
+        assert!(matches!(
+            next.push(record(&[("asdf", "value1")], 5, "late")),
+            PushResult::Ok(false)
+        ));
+
+        assert_eq!(next.num_items, 1);
+        test_finish(
+            next,
+            r#"{"streams":[{"stream":{"asdf":"value1"},"values":[["5","late"]]}]}"#,
+        );
+    }
+
+    #[test]
+    fn limits_label_cardinality() {
+        let mut buffer =
+            LokiBuffer::new(BatchSettings::default().size).label_cardinality_limit(Some(2));
+        for n in 1..5 {
+            assert!(matches!(
+                buffer.push(record(
+                    &[("pod", &format!("pod{}", n)), ("app", "web")],
+                    n,
+                    &format!("event #{}", n),
+                )),
+                PushResult::Ok(false)
+            ));
+        }
+        // Values seen before the limit was hit are still allowed.
+        assert!(matches!(
+            buffer.push(record(&[("pod", "pod1"), ("app", "web")], 5, "event #5")),
+            PushResult::Ok(false)
+        ));
+
+        assert_eq!(buffer.num_items, 5);
+        assert_eq!(buffer.streams.len(), 3);
+        test_finish(
+            buffer,
+            r#"{"streams":[{"stream":{"app":"web","pod":"__overflow__"},"values":[["3","event #3"],["4","event #4"]]},{"stream":{"app":"web","pod":"pod1"},"values":[["1","event #1"],["5","event #5"]]},{"stream":{"app":"web","pod":"pod2"},"values":[["2","event #2"]]}]}"#,
+        );
+    }
+
+    #[tokio::test]
+    async fn acks_dropped_out_of_order_events() {
+        use crate::{buffers::Acker, sinks::util::BatchSink};
+        use futures::{compat::Future01CompatExt, future};
+        use futures01::Sink;
+        use std::sync::atomic::Ordering::Relaxed;
+        use std::time::Duration;
+
+        let (acker, ack_counter) = Acker::new_for_testing();
+        let svc = tower::service_fn(|_| future::ok::<_, std::io::Error>(()));
+        let batch = BatchSettings::default().events(1).bytes(9999);
+        let buffer = LokiBuffer::new(batch.size).out_of_order_action(OutOfOrderAction::Drop);
+        let sink = BatchSink::new(svc, buffer, Duration::from_secs(10), acker);
+
+        let records = vec![
+            record(&[("asdf", "value1")], 10, "first"),
+            record(&[("asdf", "value1")], 5, "late"),
+            record(&[("asdf", "value1")], 20, "second"),
+            record(&[("asdf", "value1")], 3, "later"),
+        ];
+        let _ = sink
+            .sink_map_err(drop)
+            .send_all(futures01::stream::iter_ok(records))
+            .compat()
+            .await
+            .unwrap();
+
+        assert_eq!(ack_counter.load(Relaxed), 4);
+    }
 }
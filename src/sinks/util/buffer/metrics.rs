@@ -62,6 +62,7 @@ pub struct MetricBuffer {
     state: HashSet<MetricEntry>,
     metrics: HashSet<MetricEntry>,
     max_events: usize,
+    absolute_counters: bool,
 }
 
 impl MetricBuffer {
@@ -99,15 +100,31 @@ impl MetricBuffer {
     //   Absolute AggregatedHistogram => Absolute AggregatedHistogram
     //   Absolute AggregatedSummary   => Absolute AggregatedSummary
     //
+    // Some sinks expect the counters to stay aggregated instead, with Prometheus
+    // remote write being an example. For them the counters are normalised the
+    // same way as the gauges:
+    //   Counter                      => Absolute Counter
+    //   Absolute Counter             => Absolute Counter
+    //
     pub fn new(settings: BatchSize<Self>) -> Self {
-        Self::new_with_state(settings.events, HashSet::new())
+        Self::new_with_state(settings.events, HashSet::new(), false)
+    }
+
+    /// Creates a buffer producing absolute counters, see above.
+    pub fn new_with_absolute_counters(settings: BatchSize<Self>) -> Self {
+        Self::new_with_state(settings.events, HashSet::new(), true)
     }
 
-    fn new_with_state(max_events: usize, state: HashSet<MetricEntry>) -> Self {
+    fn new_with_state(
+        max_events: usize,
+        state: HashSet<MetricEntry>,
+        absolute_counters: bool,
+    ) -> Self {
         Self {
             state,
             metrics: HashSet::with_capacity(max_events),
             max_events,
+            absolute_counters,
         }
     }
 }
@@ -133,7 +150,9 @@ impl Batch for MetricBuffer {
             let item = item.into_metric();
 
             match &item.value {
-                MetricValue::Counter { value } if item.kind.is_absolute() => {
+                MetricValue::Counter { value }
+                    if item.kind.is_absolute() && !self.absolute_counters =>
+                {
                     let new = MetricEntry(item.clone());
                     if let Some(MetricEntry(Metric {
                         value: MetricValue::Counter { value: value0, .. },
@@ -164,7 +183,10 @@ impl Batch for MetricBuffer {
                         self.state.insert(new);
                     }
                 }
-                MetricValue::Gauge { .. } if item.kind.is_incremental() => {
+                MetricValue::Counter { .. } | MetricValue::Gauge { .. }
+                    if item.kind.is_incremental()
+                        && (item.value.is_gauge() || self.absolute_counters) =>
+                {
                     let new = MetricEntry(item.to_absolute());
                     if let Some(MetricEntry(mut existing)) = self.metrics.take(&new) {
                         existing.add(&item);
@@ -177,13 +199,9 @@ impl Batch for MetricBuffer {
                             default.0.clone()
                         } else {
                             // Otherwise we start from zero value
-                            Metric {
-                                name: item.name.to_string(),
-                                timestamp: item.timestamp,
-                                tags: item.tags.clone(),
-                                kind: MetricKind::Absolute,
-                                value: MetricValue::Gauge { value: 0.0 },
-                            }
+                            let mut initial = item.to_absolute();
+                            initial.reset();
+                            initial
                         };
                         initial.add(&item);
                         self.metrics.insert(MetricEntry(initial));
@@ -221,7 +239,7 @@ impl Batch for MetricBuffer {
             }
         }
 
-        Self::new_with_state(self.max_events, state)
+        Self::new_with_state(self.max_events, state, self.absolute_counters)
     }
 
     fn finish(self) -> Self::Output {
@@ -314,6 +332,15 @@ mod test {
     fn sink() -> (
         impl Sink<SinkItem = Event, SinkError = crate::Error>,
         Arc<Mutex<Vec<Vec<Metric>>>>,
+    ) {
+        sink_with(MetricBuffer::new)
+    }
+
+    fn sink_with(
+        buffer: impl FnOnce(BatchSize<MetricBuffer>) -> MetricBuffer,
+    ) -> (
+        impl Sink<SinkItem = Event, SinkError = crate::Error>,
+        Arc<Mutex<Vec<Vec<Metric>>>>,
     ) {
         let (acker, _) = Acker::new_for_testing();
         let sent_requests = Arc::new(Mutex::new(Vec::new()));
@@ -325,12 +352,7 @@ mod test {
             future::ok::<_, std::io::Error>(())
         });
         let batch_size = BatchSettings::default().bytes(9999).events(6).size;
-        let buffered = BatchSink::new(
-            svc,
-            MetricBuffer::new(batch_size),
-            Duration::from_secs(0),
-            acker,
-        );
+        let buffered = BatchSink::new(svc, buffer(batch_size), Duration::from_secs(0), acker);
 
         (buffered, sent_requests)
     }
@@ -531,6 +553,56 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn metric_buffer_absolute_counters() {
+        let (sink, sent_batches) = sink_with(MetricBuffer::new_with_absolute_counters);
+
+        let mut events = Vec::new();
+        for i in 0..6 {
+            let event = Event::Metric(Metric {
+                name: format!("counter-{}", i),
+                timestamp: None,
+                tags: Some(tag("staging")),
+                kind: MetricKind::Incremental,
+                value: MetricValue::Counter { value: 1.0 },
+            });
+            events.push(event);
+        }
+
+        events.push(Event::Metric(Metric {
+            name: "counter-0".into(),
+            timestamp: None,
+            tags: Some(tag("staging")),
+            kind: MetricKind::Incremental,
+            value: MetricValue::Counter { value: 2.0 },
+        }));
+
+        let _ = sink
+            .sink_map_err(drop)
+            .send_all(futures01::stream::iter_ok(events.into_iter()))
+            .compat()
+            .await
+            .unwrap();
+
+        let buffer = Arc::try_unwrap(sent_batches).unwrap().into_inner().unwrap();
+
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer[0].len(), 6);
+        assert!(buffer[0].iter().all(|metric| metric.kind.is_absolute()
+            && metric.value == MetricValue::Counter { value: 1.0 }));
+
+        assert_eq!(
+            buffer[1],
+            [Metric {
+                name: "counter-0".into(),
+                timestamp: None,
+                tags: Some(tag("staging")),
+                kind: MetricKind::Absolute,
+                value: MetricValue::Counter { value: 3.0 },
+            }]
+        );
+    }
+
     #[tokio::test]
     async fn metric_buffer_gauges() {
         let (sink, sent_batches) = sink();
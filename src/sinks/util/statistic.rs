@@ -1,3 +1,4 @@
+use crate::event::metric::{MetricValue, StatisticKind};
 use std::cmp::Ordering;
 
 pub struct DistributionStatistic {
@@ -70,3 +71,72 @@ impl DistributionStatistic {
         })
     }
 }
+
+/// Flattens a metric value into the single valued series that protocols
+/// without native histograms can carry, as `(suffix, value)` pairs. The
+/// suffixes match the field names the `influxdb_metrics` sink uses, and
+/// are `None` for values that need no derived series. Quantiles are named
+/// alike for summaries and distributions, e.g. `quantile_0.5`.
+pub fn flatten_metric_value(value: &MetricValue) -> Vec<(Option<String>, f64)> {
+    match value {
+        MetricValue::Counter { value } | MetricValue::Gauge { value } => vec![(None, *value)],
+        MetricValue::Set { values } => vec![(None, values.len() as f64)],
+        MetricValue::AggregatedHistogram {
+            buckets,
+            counts,
+            count,
+            sum,
+        } => buckets
+            .iter()
+            .zip(counts.iter())
+            .map(|(bucket, count)| (Some(format!("bucket_{}", bucket)), *count as f64))
+            .chain(vec![
+                (Some("count".to_owned()), *count as f64),
+                (Some("sum".to_owned()), *sum),
+            ])
+            .collect(),
+        MetricValue::AggregatedSummary {
+            quantiles,
+            values,
+            count,
+            sum,
+        } => quantiles
+            .iter()
+            .zip(values.iter())
+            .map(|(quantile, value)| (Some(format!("quantile_{}", quantile)), *value))
+            .chain(vec![
+                (Some("count".to_owned()), *count as f64),
+                (Some("sum".to_owned()), *sum),
+            ])
+            .collect(),
+        MetricValue::Distribution {
+            values,
+            sample_rates,
+            statistic,
+        } => {
+            let quantiles = match statistic {
+                StatisticKind::Histogram => &[0.95] as &[_],
+                StatisticKind::Summary => &[0.5, 0.75, 0.9, 0.95, 0.99] as &[_],
+            };
+            match DistributionStatistic::new(values, sample_rates, quantiles) {
+                Some(statistic) => vec![
+                    (Some("min".to_owned()), statistic.min),
+                    (Some("max".to_owned()), statistic.max),
+                    (Some("median".to_owned()), statistic.median),
+                    (Some("avg".to_owned()), statistic.avg),
+                    (Some("sum".to_owned()), statistic.sum),
+                    (Some("count".to_owned()), statistic.count as f64),
+                ]
+                .into_iter()
+                .chain(
+                    statistic
+                        .quantiles
+                        .into_iter()
+                        .map(|(p, val)| (Some(format!("quantile_{}", p)), val)),
+                )
+                .collect(),
+                None => Vec::new(),
+            }
+        }
+    }
+}
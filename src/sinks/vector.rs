@@ -1,27 +1,96 @@
 use crate::{
     config::{DataType, SinkConfig, SinkContext, SinkDescription},
     event::proto,
-    internal_events::VectorEventSent,
-    sinks::util::{tcp::TcpSink, StreamSinkOld},
-    tls::{MaybeTlsSettings, TlsConfig},
+    internal_events::{VectorBatchSent, VectorEventSent},
+    sinks::util::{
+        http::{HttpBatchService, HttpClient, HttpRetryLogic},
+        tcp::TcpSink,
+        BatchConfig, BatchSettings, EncodedLength, InFlightLimit, StreamSinkOld,
+        TowerRequestConfig, VecBuffer,
+    },
+    tls::{MaybeTlsSettings, TlsConfig, TlsSettings},
+    vector_protocol::{self, Compression, ProtocolVersion, V2_CONTENT_TYPE, V2_PATH},
     Event,
 };
 use bytes::{BufMut, Bytes, BytesMut};
+use futures::{
+    future::{self, BoxFuture},
+    FutureExt,
+};
 use futures01::{stream::iter_ok, Sink};
+use http::{Request, Uri};
+use lazy_static::lazy_static;
 use prost::Message;
 use serde::{Deserialize, Serialize};
 use snafu::Snafu;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::task::{Context, Poll};
+use tower::Service;
 
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct VectorSinkConfig {
-    pub address: String,
+    pub address: Address,
+    #[serde(default)]
+    pub version: ProtocolVersion,
     pub tls: Option<TlsConfig>,
+    /// Only used by version 2, like `request` and `compression`.
+    pub batch: Option<BatchConfig>,
+    pub request: Option<TowerRequestConfig>,
+    pub compression: Option<Compression>,
+}
+
+lazy_static! {
+    static ref V2_REQUEST_DEFAULTS: TowerRequestConfig = TowerRequestConfig {
+        in_flight_limit: InFlightLimit::Fixed(10),
+        timeout_secs: Some(30),
+        rate_limit_num: Some(u64::max_value()),
+        ..Default::default()
+    };
+}
+
+/// One address, or several that version 2 balances requests across.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Address {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Address {
+    fn as_slice(&self) -> &[String] {
+        match self {
+            Address::Single(address) => std::slice::from_ref(address),
+            Address::Multiple(addresses) => addresses,
+        }
+    }
+}
+
+impl From<String> for Address {
+    fn from(address: String) -> Self {
+        Address::Single(address)
+    }
+}
+
+impl From<Vec<String>> for Address {
+    fn from(addresses: Vec<String>) -> Self {
+        Address::Multiple(addresses)
+    }
 }
 
 impl VectorSinkConfig {
     pub fn new(address: String) -> Self {
-        Self { address, tls: None }
+        Self {
+            address: address.into(),
+            version: ProtocolVersion::V1,
+            tls: None,
+            batch: None,
+            request: None,
+            compression: None,
+        }
     }
 }
 
@@ -31,6 +100,12 @@ enum BuildError {
     MissingHost,
     #[snafu(display("Missing port in address field"))]
     MissingPort,
+    #[snafu(display("At least one address is required"))]
+    NoAddresses,
+    #[snafu(display("Multiple addresses are only supported by protocol version 2"))]
+    MultipleAddresses,
+    #[snafu(display("Option `{}` is only supported by protocol version 2", option))]
+    V2Option { option: &'static str },
 }
 
 inventory::submit! {
@@ -44,10 +119,39 @@ impl SinkConfig for VectorSinkConfig {
         &self,
         cx: SinkContext,
     ) -> crate::Result<(super::VectorSink, super::Healthcheck)> {
-        let uri = self.address.parse::<http::Uri>()?;
+        match self.version {
+            ProtocolVersion::V1 => self.build_v1(cx),
+            ProtocolVersion::V2 => self.build_v2(cx),
+        }
+    }
 
-        let host = uri.host().ok_or(BuildError::MissingHost)?.to_string();
-        let port = uri.port_u16().ok_or(BuildError::MissingPort)?;
+    fn input_type(&self) -> DataType {
+        DataType::Any
+    }
+
+    fn sink_type(&self) -> &'static str {
+        "vector"
+    }
+}
+
+impl VectorSinkConfig {
+    fn build_v1(&self, cx: SinkContext) -> crate::Result<(super::VectorSink, super::Healthcheck)> {
+        let address = match self.address.as_slice() {
+            [] => return Err(BuildError::NoAddresses.into()),
+            [address] => address,
+            _ => return Err(BuildError::MultipleAddresses.into()),
+        };
+        let (host, port) = parse_address(address)?;
+
+        for &(option, is_set) in &[
+            ("batch", self.batch.is_some()),
+            ("request", self.request.is_some()),
+            ("compression", self.compression.is_some()),
+        ] {
+            if is_set {
+                return Err(BuildError::V2Option { option }.into());
+            }
+        }
 
         let tls = MaybeTlsSettings::from_config(&self.tls, false)?;
 
@@ -62,21 +166,167 @@ impl SinkConfig for VectorSinkConfig {
         ))
     }
 
-    fn input_type(&self) -> DataType {
-        DataType::Any
-    }
+    fn build_v2(&self, cx: SinkContext) -> crate::Result<(super::VectorSink, super::Healthcheck)> {
+        let tls_enabled = self
+            .tls
+            .as_ref()
+            .and_then(|tls| tls.enabled)
+            .unwrap_or(false);
+        let scheme = if tls_enabled { "https" } else { "http" };
 
-    fn sink_type(&self) -> &'static str {
-        "vector"
+        let uris = self
+            .address
+            .as_slice()
+            .iter()
+            .map(|address| {
+                let (host, port) = parse_address(address)?;
+                Ok(format!("{}://{}:{}/{}", scheme, host, port, V2_PATH).parse::<Uri>()?)
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        if uris.is_empty() {
+            return Err(BuildError::NoAddresses.into());
+        }
+
+        let tls = TlsSettings::from_options(&self.tls.as_ref().map(|tls| tls.options.clone()))?;
+        let client = HttpClient::new(cx.resolver(), tls)?;
+        let healthcheck = healthcheck(uris.clone(), client.clone()).boxed();
+
+        let batch = BatchSettings::default()
+            .bytes(1_048_576)
+            .events(1000)
+            .timeout(1)
+            .parse_config(self.batch.unwrap_or_default())?;
+        let request = self
+            .request
+            .unwrap_or_default()
+            .unwrap_with(&V2_REQUEST_DEFAULTS);
+
+        // Each request, including retries, goes to the next address.
+        let compression = self.compression.unwrap_or_default();
+        let uris = Arc::new(uris);
+        let next = Arc::new(AtomicUsize::new(0));
+        let service = HttpBatchService::new(client, move |events: Vec<proto::EventWrapper>| {
+            let uri = uris[next.fetch_add(1, Ordering::Relaxed) % uris.len()].clone();
+            future::ready(build_request(uri, events, compression))
+        });
+        let service = BatchSentService { inner: service };
+
+        let sink = request
+            .batch_sink(
+                HttpRetryLogic,
+                service,
+                VecBuffer::new(batch.size),
+                batch.timeout,
+                cx.acker(),
+            )
+            .sink_map_err(|e| error!("Fatal vector sink error: {}", e))
+            .with_flat_map(|event| iter_ok(Some(proto::EventWrapper::from(event))));
+
+        Ok((
+            super::VectorSink::Futures01Sink(Box::new(sink)),
+            healthcheck,
+        ))
     }
 }
 
+fn parse_address(address: &str) -> crate::Result<(String, u16)> {
+    let uri = address.parse::<Uri>()?;
+
+    let host = uri.host().ok_or(BuildError::MissingHost)?.to_string();
+    let port = uri.port_u16().ok_or(BuildError::MissingPort)?;
+
+    Ok((host, port))
+}
+
 #[derive(Debug, Snafu)]
 enum HealthcheckError {
     #[snafu(display("Connect error: {}", source))]
     ConnectError { source: std::io::Error },
 }
 
+/// Checks that every address answers, using the `ping` route of the source.
+async fn healthcheck(uris: Vec<Uri>, mut client: HttpClient) -> crate::Result<()> {
+    for uri in uris {
+        let uri = format!(
+            "{}://{}/ping",
+            uri.scheme_str().unwrap_or("http"),
+            uri.authority()
+                .map(|authority| authority.as_str())
+                .unwrap_or("")
+        );
+        let request = Request::get(uri).body(hyper::Body::empty()).unwrap();
+
+        let response = client.send(request).await?;
+        if !response.status().is_success() {
+            return Err(super::HealthcheckError::UnexpectedStatus {
+                status: response.status(),
+            }
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+impl EncodedLength for proto::EventWrapper {
+    fn encoded_length(&self) -> usize {
+        self.encoded_len()
+    }
+}
+
+fn build_request(
+    uri: Uri,
+    events: Vec<proto::EventWrapper>,
+    compression: Compression,
+) -> crate::Result<Request<Vec<u8>>> {
+    let body = vector_protocol::encode_batch(events, compression)?;
+
+    let mut builder = Request::post(uri).header("Content-Type", V2_CONTENT_TYPE);
+    if let Some(encoding) = compression.content_encoding() {
+        builder = builder.header("Content-Encoding", encoding);
+    }
+
+    Ok(builder.body(body)?)
+}
+
+/// Reports the batches the source has acknowledged. Requests are built
+/// again for every retry, so this can't be done while building them.
+#[derive(Clone)]
+struct BatchSentService<S> {
+    inner: S,
+}
+
+impl<S> Service<Vec<proto::EventWrapper>> for BatchSentService<S>
+where
+    S: Service<Vec<proto::EventWrapper>, Response = http::Response<Bytes>, Error = crate::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, events: Vec<proto::EventWrapper>) -> Self::Future {
+        let count = events.len();
+        let byte_size = events.iter().map(EncodedLength::encoded_length).sum();
+        let response = self.inner.call(events);
+
+        Box::pin(async move {
+            let response = response.await?;
+            if response.status().is_success() {
+                emit!(VectorBatchSent {
+                    events: count,
+                    byte_size,
+                });
+            }
+            Ok(response)
+        })
+    }
+}
+
 fn encode_event(event: Event) -> Option<Bytes> {
     let event = proto::EventWrapper::from(event);
     let event_len = event.encoded_len();
@@ -92,3 +342,72 @@ fn encode_event(event: Event) -> Option<Bytes> {
 
     Some(out.into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sinks::util::test::load_sink;
+
+    #[test]
+    fn parses_single_and_multiple_addresses() {
+        let (config, _cx) = load_sink::<VectorSinkConfig>(
+            r#"
+            address = "127.0.0.1:6000"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.version, ProtocolVersion::V1);
+        assert_eq!(config.address.as_slice(), &["127.0.0.1:6000".to_owned()]);
+
+        let (config, _cx) = load_sink::<VectorSinkConfig>(
+            r#"
+            address = ["127.0.0.1:6000", "127.0.0.1:6001"]
+            version = "2"
+            compression = "zstd"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.version, ProtocolVersion::V2);
+        assert_eq!(config.compression, Some(Compression::Zstd));
+        assert_eq!(config.address.as_slice().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn v1_rejects_multiple_addresses() {
+        let (config, cx) = load_sink::<VectorSinkConfig>(
+            r#"
+            address = ["127.0.0.1:6000", "127.0.0.1:6001"]
+        "#,
+        )
+        .unwrap();
+
+        assert!(config.build(cx).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn v1_rejects_v2_options() {
+        let (config, cx) = load_sink::<VectorSinkConfig>(
+            r#"
+            address = "127.0.0.1:6000"
+            compression = "gzip"
+        "#,
+        )
+        .unwrap();
+
+        assert!(config.build(cx).await.is_err());
+    }
+
+    #[test]
+    fn builds_compressed_request() {
+        let uri = "http://127.0.0.1:6000/v2/events".parse::<Uri>().unwrap();
+        let events = vec![proto::EventWrapper::from(Event::from("hello"))];
+
+        let request = build_request(uri, events, Compression::Gzip).unwrap();
+
+        assert_eq!(request.method(), http::Method::POST);
+        assert_eq!(request.headers()["Content-Encoding"], "gzip");
+        assert_eq!(request.headers()["Content-Type"], V2_CONTENT_TYPE);
+        let events = vector_protocol::decode_batch(request.body(), Compression::Gzip).unwrap();
+        assert_eq!(events, vec![Event::from("hello")]);
+    }
+}
@@ -0,0 +1,427 @@
+use crate::{
+    config::{log_schema, DataType, GlobalOptions, SourceConfig, SourceDescription},
+    event::Event,
+    internal_events::{AwsKinesisFirehoseRequestError, AwsKinesisFirehoseRequestReceived},
+    shutdown::ShutdownSignal,
+    sources::util::{ErrorMessage, HttpSource},
+    tls::TlsConfig,
+    Pipeline,
+};
+use bytes::{buf::BufExt, Bytes};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::{io::Read, net::SocketAddr};
+use warp::{
+    http::{HeaderMap, StatusCode},
+    reply::{Reply, Response},
+};
+
+mod models;
+
+use models::{
+    CloudwatchLogsMessageType, CloudwatchLogsSubscription, FirehoseRequest, FirehoseResponse,
+};
+
+const REQUEST_ID_HEADER: &str = "X-Amz-Firehose-Request-Id";
+const ACCESS_KEY_HEADER: &str = "X-Amz-Firehose-Access-Key";
+const SOURCE_ARN_HEADER: &str = "X-Amz-Firehose-Source-Arn";
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AwsKinesisFirehoseConfig {
+    address: SocketAddr,
+    access_key: Option<String>,
+    tls: Option<TlsConfig>,
+}
+
+inventory::submit! {
+    SourceDescription::new_without_default::<AwsKinesisFirehoseConfig>("aws_kinesis_firehose")
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "aws_kinesis_firehose")]
+impl SourceConfig for AwsKinesisFirehoseConfig {
+    async fn build(
+        &self,
+        _: &str,
+        _: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: Pipeline,
+    ) -> crate::Result<super::Source> {
+        let source = FirehoseSource {
+            access_key: self.access_key.clone(),
+        };
+        source.run(self.address, "", &self.tls, out, shutdown)
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn source_type(&self) -> &'static str {
+        "aws_kinesis_firehose"
+    }
+}
+
+#[derive(Clone)]
+struct FirehoseSource {
+    access_key: Option<String>,
+}
+
+impl HttpSource for FirehoseSource {
+    fn build_event(&self, body: Bytes, header_map: HeaderMap) -> Result<Vec<Event>, ErrorMessage> {
+        self.decode_request(body, &header_map).map_err(|error| {
+            emit!(AwsKinesisFirehoseRequestError {
+                request_id: get_header(&header_map, REQUEST_ID_HEADER),
+                error: error.message(),
+            });
+            error
+        })
+    }
+
+    fn build_response(&self, header_map: &HeaderMap) -> Response {
+        // Firehose only considers a batch delivered if the reply echoes its request id.
+        let response = FirehoseResponse {
+            request_id: get_header(header_map, REQUEST_ID_HEADER)
+                .unwrap_or_default()
+                .to_owned(),
+            timestamp: Utc::now(),
+            error_message: None,
+        };
+        warp::reply::json(&response).into_response()
+    }
+}
+
+impl FirehoseSource {
+    fn decode_request(
+        &self,
+        body: Bytes,
+        header_map: &HeaderMap,
+    ) -> Result<Vec<Event>, ErrorMessage> {
+        if let Some(access_key) = &self.access_key {
+            if get_header(header_map, ACCESS_KEY_HEADER) != Some(access_key.as_str()) {
+                return Err(ErrorMessage::new(
+                    StatusCode::UNAUTHORIZED,
+                    "Invalid access key".to_owned(),
+                ));
+            }
+        }
+
+        let body = match get_header(header_map, "Content-Encoding") {
+            Some("gzip") => gunzip(&body).map_err(|error| {
+                ErrorMessage::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Could not decompress request body: {}", error),
+                )
+            })?,
+            _ => body,
+        };
+
+        let request: FirehoseRequest = serde_json::from_reader(body.reader()).map_err(|error| {
+            ErrorMessage::new(
+                StatusCode::BAD_REQUEST,
+                format!("Could not parse request body: {}", error),
+            )
+        })?;
+
+        if let Some(request_id) = get_header(header_map, REQUEST_ID_HEADER) {
+            if request_id != request.request_id {
+                return Err(ErrorMessage::new(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "Request id {:?} does not match header value {:?}",
+                        request.request_id, request_id
+                    ),
+                ));
+            }
+        }
+
+        let source_arn = get_header(header_map, SOURCE_ARN_HEADER);
+
+        emit!(AwsKinesisFirehoseRequestReceived {
+            request_id: &request.request_id,
+            source_arn,
+            record_count: request.records.len(),
+        });
+
+        decode_records(request, source_arn.map(Into::into))
+    }
+}
+
+fn get_header<'a>(header_map: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    header_map.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn gunzip(data: &[u8]) -> std::io::Result<Bytes> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut decoded)?;
+    Ok(decoded.into())
+}
+
+fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&[0x1f, 0x8b])
+}
+
+fn decode_records(
+    request: FirehoseRequest,
+    source_arn: Option<String>,
+) -> Result<Vec<Event>, ErrorMessage> {
+    let mut events = Vec::new();
+
+    for record in request.records {
+        let data = base64::decode(&record.data).map_err(|error| {
+            ErrorMessage::new(
+                StatusCode::BAD_REQUEST,
+                format!("Could not decode record data: {}", error),
+            )
+        })?;
+        let data = Bytes::from(data);
+
+        // CloudWatch Logs subscriptions always deliver gzipped records.
+        let data = if is_gzip(&data) {
+            gunzip(&data).map_err(|error| {
+                ErrorMessage::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Could not decompress record data: {}", error),
+                )
+            })?
+        } else {
+            data
+        };
+
+        match serde_json::from_slice::<CloudwatchLogsSubscription>(&data) {
+            Ok(subscription) => events.extend(unroll_subscription(subscription)),
+            Err(_) => events.push(record_event(data, request.timestamp)),
+        }
+    }
+
+    for event in events.iter_mut() {
+        let log = event.as_mut_log();
+        log.insert("request_id", request.request_id.clone());
+        if let Some(source_arn) = &source_arn {
+            log.insert("source_arn", source_arn.clone());
+        }
+        log.try_insert(
+            log_schema().source_type_key(),
+            Bytes::from("aws_kinesis_firehose"),
+        );
+    }
+
+    Ok(events)
+}
+
+fn record_event(data: Bytes, timestamp: DateTime<Utc>) -> Event {
+    let mut event = Event::from(data);
+    event
+        .as_mut_log()
+        .insert(log_schema().timestamp_key().clone(), timestamp);
+    event
+}
+
+fn unroll_subscription(subscription: CloudwatchLogsSubscription) -> Vec<Event> {
+    if subscription.message_type == CloudwatchLogsMessageType::ControlMessage {
+        return Vec::new();
+    }
+
+    let CloudwatchLogsSubscription {
+        owner,
+        log_group,
+        log_stream,
+        subscription_filters,
+        log_events,
+        ..
+    } = subscription;
+
+    log_events
+        .into_iter()
+        .map(|log_event| {
+            let mut event = Event::from(log_event.message);
+            let log = event.as_mut_log();
+            log.insert(log_schema().timestamp_key().clone(), log_event.timestamp);
+            log.insert("id", log_event.id);
+            log.insert("owner", owner.clone());
+            log.insert("log_group", log_group.clone());
+            log.insert("log_stream", log_stream.clone());
+            log.insert("subscription_filters", subscription_filters.clone());
+            event
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{collect_n, next_addr, trace_init, wait_for_tcp};
+    use chrono::TimeZone;
+    use flate2::{write::GzEncoder, Compression};
+    use futures::compat::Future01CompatExt;
+    use futures01::sync::mpsc;
+    use pretty_assertions::assert_eq;
+    use std::io::Write;
+
+    async fn source(access_key: Option<&str>) -> (mpsc::Receiver<Event>, SocketAddr) {
+        let (sender, recv) = Pipeline::new_test();
+        let address = next_addr();
+        let access_key = access_key.map(Into::into);
+        tokio::spawn(async move {
+            AwsKinesisFirehoseConfig {
+                address,
+                access_key,
+                tls: None,
+            }
+            .build(
+                "default",
+                &GlobalOptions::default(),
+                ShutdownSignal::noop(),
+                sender,
+            )
+            .await
+            .unwrap()
+            .compat()
+            .await
+            .unwrap()
+        });
+        wait_for_tcp(address).await;
+        (recv, address)
+    }
+
+    async fn send(
+        address: SocketAddr,
+        records: Vec<&[u8]>,
+        access_key: Option<&str>,
+    ) -> reqwest::Response {
+        let request = serde_json::json!({
+            "requestId": "e17265d6-97af-4938-982e-90d5614c4242",
+            "timestamp": 1600110760138u64,
+            "records": records
+                .into_iter()
+                .map(|data| serde_json::json!({ "data": base64::encode(data) }))
+                .collect::<Vec<_>>(),
+        });
+
+        let mut builder = reqwest::Client::new()
+            .post(&format!("http://{}", address))
+            .header(REQUEST_ID_HEADER, "e17265d6-97af-4938-982e-90d5614c4242")
+            .header(
+                SOURCE_ARN_HEADER,
+                "arn:aws:firehose:us-east-1:111111111111:deliverystream/test",
+            )
+            .json(&request);
+        if let Some(access_key) = access_key {
+            builder = builder.header(ACCESS_KEY_HEADER, access_key);
+        }
+        builder.send().await.unwrap()
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[tokio::test]
+    async fn aws_kinesis_firehose_forwards_records() {
+        trace_init();
+
+        let (rx, addr) = source(Some("secret")).await;
+
+        let response = send(addr, vec![b"hello", b"world"], Some("secret")).await;
+        assert_eq!(200, response.status().as_u16());
+        let response: FirehoseResponse = response.json().await.unwrap();
+        assert_eq!(response.request_id, "e17265d6-97af-4938-982e-90d5614c4242");
+
+        let events = collect_n(rx, 2).await.unwrap();
+        let log = events[0].as_log();
+        assert_eq!(log[&log_schema().message_key()], "hello".into());
+        assert_eq!(
+            log[&log_schema().timestamp_key()],
+            Utc.timestamp_millis(1600110760138).into()
+        );
+        assert_eq!(
+            log[&"request_id".into()],
+            "e17265d6-97af-4938-982e-90d5614c4242".into()
+        );
+        assert_eq!(
+            log[&"source_arn".into()],
+            "arn:aws:firehose:us-east-1:111111111111:deliverystream/test".into()
+        );
+        assert_eq!(
+            log[log_schema().source_type_key()],
+            "aws_kinesis_firehose".into()
+        );
+        assert_eq!(
+            events[1].as_log()[&log_schema().message_key()],
+            "world".into()
+        );
+    }
+
+    #[tokio::test]
+    async fn aws_kinesis_firehose_rejects_invalid_access_key() {
+        trace_init();
+
+        let (_rx, addr) = source(Some("secret")).await;
+
+        let response = send(addr, vec![b"hello"], Some("wrong")).await;
+        assert_eq!(401, response.status().as_u16());
+    }
+
+    #[test]
+    fn aws_kinesis_firehose_unrolls_cloudwatch_logs() {
+        let subscription = serde_json::json!({
+            "messageType": "DATA_MESSAGE",
+            "owner": "111111111111",
+            "logGroup": "test",
+            "logStream": "test-stream",
+            "subscriptionFilters": ["Destination"],
+            "logEvents": [
+                { "id": "35683658089614582423604394983260738922885519999578275840", "timestamp": 1600110569039u64, "message": "first" },
+                { "id": "35683658089659183914001456229543810359430816722590236673", "timestamp": 1600110569041u64, "message": "second" }
+            ]
+        });
+        let request = FirehoseRequest {
+            request_id: "request".into(),
+            timestamp: Utc.timestamp_millis(1600110760138),
+            records: vec![models::FirehoseRecord {
+                data: base64::encode(gzip(subscription.to_string().as_bytes())),
+            }],
+        };
+
+        let events = decode_records(request, None).unwrap();
+
+        assert_eq!(events.len(), 2);
+        let log = events[1].as_log();
+        assert_eq!(log[&log_schema().message_key()], "second".into());
+        assert_eq!(
+            log[&log_schema().timestamp_key()],
+            Utc.timestamp_millis(1600110569041).into()
+        );
+        assert_eq!(log[&"log_group".into()], "test".into());
+        assert_eq!(log[&"log_stream".into()], "test-stream".into());
+        assert_eq!(log[&"owner".into()], "111111111111".into());
+        assert_eq!(log[&"request_id".into()], "request".into());
+    }
+
+    #[test]
+    fn aws_kinesis_firehose_skips_cloudwatch_control_messages() {
+        let subscription = serde_json::json!({
+            "messageType": "CONTROL_MESSAGE",
+            "owner": "CloudwatchLogs",
+            "logGroup": "",
+            "logStream": "",
+            "subscriptionFilters": [],
+            "logEvents": [
+                { "id": "", "timestamp": 1600110569039u64, "message": "CWL CONTROL MESSAGE: Checking health of destination Firehose." }
+            ]
+        });
+        let request = FirehoseRequest {
+            request_id: "request".into(),
+            timestamp: Utc.timestamp_millis(1600110760138),
+            records: vec![models::FirehoseRecord {
+                data: base64::encode(gzip(subscription.to_string().as_bytes())),
+            }],
+        };
+
+        assert!(decode_records(request, None).unwrap().is_empty());
+    }
+}
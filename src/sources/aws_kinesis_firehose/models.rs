@@ -0,0 +1,62 @@
+use chrono::{serde::ts_milliseconds, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A batch of records delivered by Firehose.
+///
+/// https://docs.aws.amazon.com/firehose/latest/dev/httpdeliveryrequestresponse.html
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FirehoseRequest {
+    pub request_id: String,
+    #[serde(with = "ts_milliseconds")]
+    pub timestamp: DateTime<Utc>,
+    pub records: Vec<FirehoseRecord>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FirehoseRecord {
+    /// Base64 encoded record data.
+    pub data: String,
+}
+
+/// The acknowledgement Firehose expects in reply to a request.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FirehoseResponse {
+    pub request_id: String,
+    #[serde(with = "ts_milliseconds")]
+    pub timestamp: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_message: Option<String>,
+}
+
+/// The payload of a record written by a CloudWatch Logs subscription filter.
+///
+/// https://docs.aws.amazon.com/AmazonCloudWatch/latest/logs/SubscriptionFilters.html
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudwatchLogsSubscription {
+    pub owner: String,
+    pub log_group: String,
+    pub log_stream: String,
+    pub subscription_filters: Vec<String>,
+    pub message_type: CloudwatchLogsMessageType,
+    pub log_events: Vec<CloudwatchLogEvent>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CloudwatchLogsMessageType {
+    /// Sent when the subscription is set up to check the destination is reachable.
+    ControlMessage,
+    DataMessage,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CloudwatchLogEvent {
+    pub id: String,
+    #[serde(with = "ts_milliseconds")]
+    pub timestamp: DateTime<Utc>,
+    pub message: String,
+}
@@ -0,0 +1,368 @@
+use crate::{
+    config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
+    dns::Resolver,
+    line_agg,
+    region::RegionOrEndpoint,
+    shutdown::ShutdownSignal,
+    sinks::util::rusoto,
+    Pipeline,
+};
+use futures::{FutureExt, TryFutureExt};
+use rusoto_core::Region;
+use rusoto_s3::S3Client;
+use rusoto_sqs::SqsClient;
+use serde::{Deserialize, Serialize};
+use std::convert::{TryFrom, TryInto};
+
+use super::util::MultilineConfig;
+
+mod sqs;
+
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// Decompress objects stored with `Content-Encoding: gzip`, a gzip
+    /// content type or a `.gz` extension; read anything else as is.
+    #[derivative(Default)]
+    Auto,
+    None,
+    Gzip,
+}
+
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "lowercase")]
+enum Strategy {
+    #[derivative(Default)]
+    Sqs,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct AwsS3Config {
+    #[serde(flatten)]
+    region: RegionOrEndpoint,
+    #[serde(default)]
+    compression: Compression,
+    #[serde(default)]
+    strategy: Strategy,
+    sqs: Option<sqs::Config>,
+    assume_role: Option<String>,
+    multiline: Option<MultilineConfig>,
+}
+
+inventory::submit! {
+    SourceDescription::new_without_default::<AwsS3Config>("aws_s3")
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "aws_s3")]
+impl SourceConfig for AwsS3Config {
+    async fn build(
+        &self,
+        _name: &str,
+        _globals: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: Pipeline,
+    ) -> crate::Result<super::Source> {
+        let multiline_config: Option<line_agg::Config> = self
+            .multiline
+            .as_ref()
+            .map(|config| config.try_into())
+            .transpose()?;
+
+        match self.strategy {
+            Strategy::Sqs => Ok(Box::new(
+                self.create_sqs_ingestor(multiline_config)
+                    .await?
+                    .run(out, shutdown)
+                    .boxed()
+                    .compat(),
+            )),
+        }
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn source_type(&self) -> &'static str {
+        "aws_s3"
+    }
+}
+
+#[derive(Debug, snafu::Snafu)]
+enum CreateSqsIngestorError {
+    #[snafu(display("Unable to initialize: {}", source))]
+    Initialize { source: sqs::IngestorNewError },
+    #[snafu(display("Unable to create AWS client: {}", source))]
+    Client { source: crate::Error },
+    #[snafu(display("Unable to parse region: {}", source))]
+    RegionParse { source: crate::region::ParseError },
+    #[snafu(display("Configuration for `sqs` required when strategy=sqs"))]
+    ConfigMissing,
+}
+
+impl AwsS3Config {
+    async fn create_sqs_ingestor(
+        &self,
+        multiline: Option<line_agg::Config>,
+    ) -> Result<sqs::Ingestor, CreateSqsIngestorError> {
+        use snafu::ResultExt;
+
+        let region: Region = (&self.region).try_into().context(RegionParse)?;
+
+        let client = rusoto::client(Resolver).context(Client)?;
+        let creds = rusoto::AwsCredentialsProvider::new(&region, self.assume_role.clone())
+            .context(Client)?;
+        let s3_client = S3Client::new_with(client.clone(), creds, region.clone());
+
+        match self.sqs {
+            Some(ref sqs) => {
+                let creds = rusoto::AwsCredentialsProvider::new(&region, self.assume_role.clone())
+                    .context(Client)?;
+                let sqs_client = SqsClient::new_with(client, creds, region.clone());
+
+                sqs::Ingestor::new(
+                    region,
+                    sqs_client,
+                    s3_client,
+                    sqs.clone(),
+                    self.compression,
+                    multiline,
+                )
+                .context(Initialize)
+            }
+            None => Err(CreateSqsIngestorError::ConfigMissing),
+        }
+    }
+}
+
+impl TryFrom<&str> for Compression {
+    type Error = ();
+
+    /// Maps a `Content-Encoding` or `Content-Type` value onto the
+    /// compression it implies, if any.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "gzip" | "x-gzip" | "application/gzip" | "application/x-gzip" => Ok(Compression::Gzip),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn aws_s3_parse_config() {
+        let config: AwsS3Config = toml::from_str(
+            r#"
+            region = "us-east-1"
+            compression = "gzip"
+
+            [sqs]
+            queue_url = "https://sqs.us-east-1.amazonaws.com/123456789012/vector"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.strategy, Strategy::Sqs);
+        assert_eq!(config.compression, Compression::Gzip);
+        assert!(config.sqs.is_some());
+    }
+
+    #[test]
+    fn aws_s3_compression_from_content_encoding() {
+        assert_eq!(Compression::try_from("gzip"), Ok(Compression::Gzip));
+        assert_eq!(
+            Compression::try_from("application/x-gzip"),
+            Ok(Compression::Gzip)
+        );
+        assert_eq!(Compression::try_from("text/plain"), Err(()));
+    }
+}
+
+#[cfg(feature = "aws-s3-integration-tests")]
+#[cfg(test)]
+mod integration_tests {
+    use super::*;
+    use crate::{
+        config::log_schema,
+        test_util::{collect_n, random_lines, random_string, trace_init},
+    };
+    use flate2::{write::GzEncoder, Compression as GzCompression};
+    use pretty_assertions::assert_eq;
+    use rusoto_core::RusotoError;
+    use rusoto_s3::{CreateBucketError, CreateBucketRequest, PutObjectRequest, S3};
+    use rusoto_sqs::{CreateQueueRequest, GetQueueAttributesRequest, SendMessageRequest, Sqs};
+    use std::io::Write;
+    use tokio::time::{delay_for, Duration};
+
+    // The source uses one endpoint for both services, so go through the
+    // localstack edge port which routes requests to either of them.
+    const ENDPOINT: &str = "http://localhost:4566";
+
+    #[tokio::test]
+    async fn aws_s3_process_message() {
+        trace_init();
+
+        let lines = random_lines(100).take(10).collect::<Vec<_>>();
+        let body = lines.join("\n");
+
+        test_event("app.log", None, body.into_bytes(), lines).await;
+    }
+
+    #[tokio::test]
+    async fn aws_s3_process_gzip_message() {
+        trace_init();
+
+        let lines = random_lines(100).take(10).collect::<Vec<_>>();
+        let mut encoder = GzEncoder::new(Vec::new(), GzCompression::default());
+        encoder.write_all(lines.join("\n").as_bytes()).unwrap();
+        let body = encoder.finish().unwrap();
+
+        test_event("app.log.gz", Some("gzip"), body, lines).await;
+    }
+
+    async fn test_event(
+        key: &str,
+        content_encoding: Option<&str>,
+        body: Vec<u8>,
+        expected_lines: Vec<String>,
+    ) {
+        let bucket = create_bucket().await;
+        let queue_url = create_queue().await;
+
+        s3_client()
+            .put_object(PutObjectRequest {
+                bucket: bucket.clone(),
+                key: key.to_owned(),
+                body: Some(body.into()),
+                content_encoding: content_encoding.map(Into::into),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        // Localstack does not deliver bucket notifications, so send the
+        // message S3 would have sent.
+        sqs_client()
+            .send_message(SendMessageRequest {
+                queue_url: queue_url.clone(),
+                message_body: notification(&bucket, key),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let config: AwsS3Config = toml::from_str(&format!(
+            r#"
+            endpoint = "{}"
+
+            [sqs]
+            queue_url = "{}"
+            poll_secs = 1
+            "#,
+            ENDPOINT, queue_url
+        ))
+        .unwrap();
+
+        let (tx, rx) = Pipeline::new_test();
+        let source = config
+            .build(
+                "default",
+                &GlobalOptions::default(),
+                ShutdownSignal::noop(),
+                tx,
+            )
+            .await
+            .unwrap();
+        tokio::spawn(source.compat());
+
+        let events = collect_n(rx, expected_lines.len()).await.unwrap();
+        for (event, line) in events.iter().zip(expected_lines) {
+            let log = event.as_log();
+            assert_eq!(log[&log_schema().message_key()], line.into());
+            assert_eq!(log[&"bucket".into()], bucket.clone().into());
+            assert_eq!(log[&"object".into()], key.into());
+        }
+
+        // The message is deleted once its object has been processed.
+        delay_for(Duration::from_secs(1)).await;
+        let attributes = sqs_client()
+            .get_queue_attributes(GetQueueAttributesRequest {
+                queue_url,
+                attribute_names: Some(vec!["ApproximateNumberOfMessages".into()]),
+            })
+            .await
+            .unwrap()
+            .attributes
+            .unwrap();
+        assert_eq!(attributes["ApproximateNumberOfMessages"], "0");
+    }
+
+    fn notification(bucket: &str, key: &str) -> String {
+        serde_json::json!({
+            "Records": [{
+                "eventSource": "aws:s3",
+                "awsRegion": "us-east-1",
+                "eventName": "ObjectCreated:Put",
+                "s3": {
+                    "bucket": { "name": bucket },
+                    "object": { "key": key }
+                }
+            }]
+        })
+        .to_string()
+    }
+
+    fn region() -> Region {
+        Region::Custom {
+            name: "us-east-1".to_owned(),
+            endpoint: ENDPOINT.to_owned(),
+        }
+    }
+
+    fn s3_client() -> S3Client {
+        let client = rusoto::client(Resolver).unwrap();
+        let creds = rusoto::AwsCredentialsProvider::new(&region(), None).unwrap();
+        S3Client::new_with(client, creds, region())
+    }
+
+    fn sqs_client() -> SqsClient {
+        let client = rusoto::client(Resolver).unwrap();
+        let creds = rusoto::AwsCredentialsProvider::new(&region(), None).unwrap();
+        SqsClient::new_with(client, creds, region())
+    }
+
+    async fn create_bucket() -> String {
+        let bucket = format!("test-bucket-{}", random_string(10).to_lowercase());
+
+        match s3_client()
+            .create_bucket(CreateBucketRequest {
+                bucket: bucket.clone(),
+                ..Default::default()
+            })
+            .await
+        {
+            Ok(_) | Err(RusotoError::Service(CreateBucketError::BucketAlreadyOwnedByYou(_))) => {}
+            Err(error) => panic!("Couldn't create bucket: {}", error),
+        }
+
+        bucket
+    }
+
+    async fn create_queue() -> String {
+        sqs_client()
+            .create_queue(CreateQueueRequest {
+                queue_name: format!("test-queue-{}", random_string(10)),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .queue_url
+            .unwrap()
+    }
+}
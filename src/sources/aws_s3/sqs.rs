@@ -0,0 +1,538 @@
+use super::Compression;
+use crate::{
+    config::log_schema,
+    event::Event,
+    internal_events::{
+        AwsS3EventReceived, AwsS3SqsMessageDeleteFailed, AwsS3SqsMessageProcessingFailed,
+        AwsS3SqsMessageReceiveFailed,
+    },
+    line_agg::{self, LineAgg},
+    shutdown::ShutdownSignal,
+    Pipeline,
+};
+use async_compression::tokio_02::bufread::GzipDecoder;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use codec::BytesDelimitedCodec;
+use futures::{
+    compat::{Future01CompatExt, Sink01CompatExt},
+    future::ready,
+    stream::{BoxStream, StreamExt},
+    SinkExt,
+};
+use rusoto_core::{Region, RusotoError};
+use rusoto_s3::{GetObjectError, GetObjectRequest, S3Client, S3};
+use rusoto_sqs::{
+    DeleteMessageError, DeleteMessageRequest, Message, ReceiveMessageError, ReceiveMessageRequest,
+    Sqs, SqsClient,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{convert::TryFrom, time::Duration};
+use tokio::{
+    io::{AsyncRead, BufReader},
+    select,
+    time::delay_for,
+};
+use tokio_util::codec::FramedRead;
+
+/// SQS caps long polling at 20 seconds.
+const MAX_POLL_SECS: u32 = 20;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct Config {
+    pub queue_url: String,
+    #[serde(default = "default_poll_secs")]
+    pub poll_secs: u32,
+    #[serde(default = "default_visibility_timeout_secs")]
+    pub visibility_timeout_secs: u32,
+    #[serde(default = "default_true")]
+    pub delete_message: bool,
+}
+
+fn default_poll_secs() -> u32 {
+    15
+}
+
+fn default_visibility_timeout_secs() -> u32 {
+    300
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Snafu)]
+pub(super) enum IngestorNewError {
+    #[snafu(display(
+        "Invalid value for poll_secs: {}, must be at most {}",
+        poll_secs,
+        MAX_POLL_SECS
+    ))]
+    InvalidPollSecs { poll_secs: u32 },
+}
+
+#[derive(Debug, Snafu)]
+pub(super) enum ProcessingError {
+    #[snafu(display("Could not parse SQS message body as S3 notification: {}", source))]
+    InvalidSqsMessage { source: serde_json::Error },
+    #[snafu(display("Unexpected S3 notification event: {}", event))]
+    UnknownEvent { event: String },
+    #[snafu(display("Failed to fetch s3://{}/{}: {}", bucket, key, source))]
+    GetObject {
+        source: RusotoError<GetObjectError>,
+        bucket: String,
+        key: String,
+    },
+    #[snafu(display("Failed to read all of s3://{}/{}: {}", bucket, key, source))]
+    ReadObject {
+        source: std::io::Error,
+        bucket: String,
+        key: String,
+    },
+    #[snafu(display(
+        "Object notification for s3://{}/{} is from region {}, expected {}",
+        bucket,
+        key,
+        region,
+        expected_region
+    ))]
+    WrongRegion {
+        region: String,
+        expected_region: String,
+        bucket: String,
+        key: String,
+    },
+    #[snafu(display("Failed to send events to the pipeline"))]
+    PipelineSend,
+}
+
+pub(super) struct Ingestor {
+    region: Region,
+    s3_client: S3Client,
+    sqs_client: SqsClient,
+    config: Config,
+    compression: Compression,
+    multiline: Option<line_agg::Config>,
+}
+
+impl Ingestor {
+    pub(super) fn new(
+        region: Region,
+        sqs_client: SqsClient,
+        s3_client: S3Client,
+        config: Config,
+        compression: Compression,
+        multiline: Option<line_agg::Config>,
+    ) -> Result<Ingestor, IngestorNewError> {
+        if config.poll_secs > MAX_POLL_SECS {
+            return Err(IngestorNewError::InvalidPollSecs {
+                poll_secs: config.poll_secs,
+            });
+        }
+
+        Ok(Ingestor {
+            region,
+            s3_client,
+            sqs_client,
+            config,
+            compression,
+            multiline,
+        })
+    }
+
+    pub(super) async fn run(self, out: Pipeline, shutdown: ShutdownSignal) -> Result<(), ()> {
+        let mut shutdown = shutdown.compat();
+
+        loop {
+            let messages = select! {
+                messages = self.receive_messages() => messages,
+                _ = &mut shutdown => break,
+            };
+
+            let messages = match messages {
+                Ok(messages) => messages,
+                Err(error) => {
+                    emit!(AwsS3SqsMessageReceiveFailed {
+                        error,
+                        queue_url: &self.config.queue_url,
+                    });
+                    delay_for(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+
+            for message in messages {
+                let message_id = message.message_id.clone().unwrap_or_default();
+                let receipt_handle = message.receipt_handle.clone();
+
+                match self.handle_sqs_message(message, out.clone()).await {
+                    // A message is only deleted once all of its objects have
+                    // been handed to the pipeline. Otherwise it becomes
+                    // visible again after the visibility timeout and is retried.
+                    Ok(()) => {
+                        if let (true, Some(receipt_handle)) =
+                            (self.config.delete_message, receipt_handle)
+                        {
+                            if let Err(error) = self.delete_message(receipt_handle).await {
+                                emit!(AwsS3SqsMessageDeleteFailed {
+                                    error,
+                                    message_id: &message_id,
+                                });
+                            }
+                        }
+                    }
+                    Err(ProcessingError::PipelineSend) => return Err(()),
+                    Err(error) => emit!(AwsS3SqsMessageProcessingFailed {
+                        error,
+                        message_id: &message_id,
+                    }),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn receive_messages(&self) -> Result<Vec<Message>, RusotoError<ReceiveMessageError>> {
+        self.sqs_client
+            .receive_message(ReceiveMessageRequest {
+                queue_url: self.config.queue_url.clone(),
+                max_number_of_messages: Some(10),
+                wait_time_seconds: Some(self.config.poll_secs as i64),
+                visibility_timeout: Some(self.config.visibility_timeout_secs as i64),
+                ..Default::default()
+            })
+            .await
+            .map(|res| res.messages.unwrap_or_default())
+    }
+
+    async fn delete_message(
+        &self,
+        receipt_handle: String,
+    ) -> Result<(), RusotoError<DeleteMessageError>> {
+        self.sqs_client
+            .delete_message(DeleteMessageRequest {
+                queue_url: self.config.queue_url.clone(),
+                receipt_handle,
+            })
+            .await
+    }
+
+    async fn handle_sqs_message(
+        &self,
+        message: Message,
+        out: Pipeline,
+    ) -> Result<(), ProcessingError> {
+        let body = message.body.unwrap_or_default();
+        let notification: SqsNotification =
+            serde_json::from_str(&body).context(InvalidSqsMessage)?;
+
+        let records = match notification {
+            SqsNotification::Event(event) => event.records,
+            // Sent by S3 when a notification configuration is created.
+            SqsNotification::TestEvent(event) if event.event == "s3:TestEvent" => return Ok(()),
+            SqsNotification::TestEvent(event) => {
+                return Err(ProcessingError::UnknownEvent { event: event.event })
+            }
+        };
+
+        for record in records {
+            if record.event_source == "aws:s3" && record.event_name.starts_with("ObjectCreated:") {
+                self.handle_s3_event_record(record, out.clone()).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_s3_event_record(
+        &self,
+        record: S3EventRecord,
+        out: Pipeline,
+    ) -> Result<(), ProcessingError> {
+        let bucket = record.s3.bucket.name;
+        let key = record.s3.object.key;
+
+        // Objects are fetched with a client for the configured region. Custom
+        // endpoints are trusted to serve whatever region they are sent.
+        if !matches!(self.region, Region::Custom { .. }) && record.aws_region != self.region.name()
+        {
+            return Err(ProcessingError::WrongRegion {
+                region: record.aws_region,
+                expected_region: self.region.name().to_owned(),
+                bucket,
+                key,
+            });
+        }
+
+        let object = self
+            .s3_client
+            .get_object(GetObjectRequest {
+                bucket: bucket.clone(),
+                key: key.clone(),
+                ..Default::default()
+            })
+            .await
+            .with_context(|| GetObject {
+                bucket: bucket.clone(),
+                key: key.clone(),
+            })?;
+
+        let body = match object.body {
+            Some(body) => body,
+            // The object is empty.
+            None => return Ok(()),
+        };
+
+        let compression = match self.compression {
+            Compression::Auto => detect_compression(
+                object.content_encoding.as_deref(),
+                object.content_type.as_deref(),
+                &key,
+            ),
+            compression => compression,
+        };
+        let reader: Box<dyn AsyncRead + Send + Unpin> = match compression {
+            Compression::Gzip => Box::new(GzipDecoder::new(BufReader::new(body.into_async_read()))),
+            _ => Box::new(body.into_async_read()),
+        };
+
+        let timestamp = object
+            .last_modified
+            .and_then(|last_modified| DateTime::parse_from_rfc2822(&last_modified).ok())
+            .map(|timestamp| timestamp.with_timezone(&Utc));
+
+        let mut read_error = None;
+        let send_result = {
+            // Reading stops at the first error, which is reported once the
+            // lines read so far have been sent.
+            let lines: BoxStream<'_, Bytes> =
+                FramedRead::new(reader, BytesDelimitedCodec::new(b'\n'))
+                    .scan(&mut read_error, |read_error, line| {
+                        ready(match line {
+                            Ok(line) => Some(line),
+                            Err(error) => {
+                                **read_error = Some(error);
+                                None
+                            }
+                        })
+                    })
+                    .boxed();
+
+            let lines = match &self.multiline {
+                Some(config) => LineAgg::new(
+                    lines.map(|line| ((), line, ())),
+                    line_agg::Logic::new(config.clone()),
+                )
+                .map(|(_, line, _)| line)
+                .boxed(),
+                None => lines,
+            };
+
+            let region = self.region.name().to_owned();
+            let mut events = lines.map(|line| {
+                emit!(AwsS3EventReceived {
+                    byte_size: line.len()
+                });
+
+                Ok(create_event(line, &bucket, &key, &region, timestamp))
+            });
+
+            out.sink_compat().send_all(&mut events).await
+        };
+
+        if send_result.is_err() {
+            return Err(ProcessingError::PipelineSend);
+        }
+
+        match read_error {
+            Some(source) => Err(ProcessingError::ReadObject {
+                source,
+                bucket,
+                key,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+fn detect_compression(
+    content_encoding: Option<&str>,
+    content_type: Option<&str>,
+    key: &str,
+) -> Compression {
+    content_encoding
+        .and_then(|value| Compression::try_from(value).ok())
+        .or_else(|| content_type.and_then(|value| Compression::try_from(value).ok()))
+        .unwrap_or_else(|| {
+            if key.ends_with(".gz") {
+                Compression::Gzip
+            } else {
+                Compression::None
+            }
+        })
+}
+
+fn create_event(
+    line: Bytes,
+    bucket: &str,
+    key: &str,
+    region: &str,
+    timestamp: Option<DateTime<Utc>>,
+) -> Event {
+    let mut event = Event::from(line);
+
+    let log = event.as_mut_log();
+    log.insert("bucket", bucket.to_owned());
+    log.insert("object", key.to_owned());
+    log.insert("region", region.to_owned());
+    log.insert(log_schema().source_type_key(), Bytes::from("aws_s3"));
+    if let Some(timestamp) = timestamp {
+        log.insert(log_schema().timestamp_key().clone(), timestamp);
+    }
+
+    event
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SqsNotification {
+    Event(S3Event),
+    TestEvent(S3TestEvent),
+}
+
+// https://docs.aws.amazon.com/AmazonS3/latest/dev/notification-content-structure.html
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct S3TestEvent {
+    event: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct S3Event {
+    records: Vec<S3EventRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct S3EventRecord {
+    event_source: String,
+    aws_region: String,
+    event_name: String,
+    s3: S3Message,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Message {
+    bucket: S3Bucket,
+    object: S3Object,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Bucket {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Object {
+    // Object keys are URL encoded in notifications, with spaces as `+`.
+    #[serde(deserialize_with = "urlencoded_string")]
+    key: String,
+}
+
+fn urlencoded_string<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let s = String::deserialize(deserializer)?.replace('+', " ");
+    percent_encoding::percent_decode_str(&s)
+        .decode_utf8()
+        .map(Into::into)
+        .map_err(|error| D::Error::custom(format!("Invalid key {:?}: {}", s, error)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn aws_s3_parse_notification() {
+        let event: S3Event = serde_json::from_str(
+            r#"{
+            "Records": [{
+                "eventVersion": "2.1",
+                "eventSource": "aws:s3",
+                "awsRegion": "us-east-1",
+                "eventTime": "2020-10-20T19:06:01.123Z",
+                "eventName": "ObjectCreated:Put",
+                "s3": {
+                    "s3SchemaVersion": "1.0",
+                    "bucket": { "name": "logs", "arn": "arn:aws:s3:::logs" },
+                    "object": { "key": "AWSLogs/my+app/2020%3D10.log.gz", "size": 1024 }
+                }
+            }]
+        }"#,
+        )
+        .unwrap();
+
+        let record = &event.records[0];
+        assert_eq!(record.event_name, "ObjectCreated:Put");
+        assert_eq!(record.aws_region, "us-east-1");
+        assert_eq!(record.s3.bucket.name, "logs");
+        assert_eq!(record.s3.object.key, "AWSLogs/my app/2020=10.log.gz");
+    }
+
+    #[test]
+    fn aws_s3_parse_test_notification() {
+        let notification: SqsNotification = serde_json::from_str(
+            r#"{
+                "Service": "Amazon S3",
+                "Event": "s3:TestEvent",
+                "Time": "2020-10-20T19:06:01.123Z",
+                "Bucket": "logs",
+                "RequestId": "5582815E1AEA5ADF",
+                "HostId": "8cLeGAmw098X5cv4Zkwcmo8vvZa3eH3eKxsPzbB9wrR+YstdA6Knx4Ip8EXAMPLE"
+            }"#,
+        )
+        .unwrap();
+
+        assert!(matches!(notification, SqsNotification::TestEvent(_)));
+    }
+
+    #[test]
+    fn aws_s3_detect_compression() {
+        assert_eq!(
+            detect_compression(Some("gzip"), None, "file.log"),
+            Compression::Gzip
+        );
+        assert_eq!(
+            detect_compression(None, Some("application/x-gzip"), "file"),
+            Compression::Gzip
+        );
+        assert_eq!(
+            detect_compression(None, None, "file.log.gz"),
+            Compression::Gzip
+        );
+        assert_eq!(
+            detect_compression(None, Some("text/plain"), "file.log"),
+            Compression::None
+        );
+    }
+
+    #[test]
+    fn aws_s3_create_event() {
+        let event = create_event("hello world".into(), "logs", "app.log", "us-east-1", None);
+        let log = event.as_log();
+
+        assert_eq!(log[&log_schema().message_key()], "hello world".into());
+        assert_eq!(log[&"bucket".into()], "logs".into());
+        assert_eq!(log[&"object".into()], "app.log".into());
+        assert_eq!(log[&"region".into()], "us-east-1".into());
+        assert_eq!(log[log_schema().source_type_key()], "aws_s3".into());
+    }
+}
@@ -17,10 +17,10 @@ use bollard::{
     errors::Error as DockerError,
     service::{ContainerInspectResponse, SystemEventsResponse},
     system::EventsOptions,
-    Docker,
+    Docker, API_DEFAULT_VERSION,
 };
 use bytes::{Buf, Bytes};
-use chrono::{DateTime, FixedOffset, Local, ParseError, Utc};
+use chrono::{DateTime, FixedOffset, Local, ParseError, TimeZone, Utc};
 use futures::{
     compat::{Future01CompatExt, Sink01CompatExt},
     future,
@@ -29,16 +29,20 @@ use futures::{
 };
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
-use std::{collections::HashMap, convert::TryFrom, env};
+use std::{collections::HashMap, convert::TryFrom, env, path::PathBuf};
 use string_cache::DefaultAtom as Atom;
 use tokio::sync::mpsc;
 
 /// The beginning of image names of vector docker images packaged by vector.
 const VECTOR_IMAGE_NAME: &str = "timberio/vector";
 
+/// Timeout in seconds for the requests to the docker daemon.
+const DEFAULT_TIMEOUT: u64 = 120;
+
 lazy_static! {
     static ref STDERR: Bytes = "stderr".into();
     static ref STDOUT: Bytes = "stdout".into();
@@ -47,30 +51,79 @@ lazy_static! {
     static ref NAME: Atom = Atom::from("container_name");
     static ref STREAM: Atom = Atom::from("stream");
     static ref CONTAINER: Atom = Atom::from("container_id");
+    static ref EVENT: Atom = Atom::from("event");
+    static ref EXIT_CODE: Atom = Atom::from("exit_code");
+}
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("Invalid docker host {:?}: {}", host, source))]
+    InvalidHost {
+        host: String,
+        source: http::uri::InvalidUri,
+    },
+    #[snafu(display("Unsupported docker host scheme {:?}", scheme))]
+    UnsupportedScheme { scheme: String },
+    #[snafu(display(
+        "TLS is required for docker host {:?}, set either `tls` or DOCKER_CERT_PATH",
+        host
+    ))]
+    TlsRequired { host: String },
+    #[snafu(display("Unable to connect to docker: {}", source))]
+    Connect { source: DockerError },
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields, default)]
 pub struct DockerConfig {
+    docker_host: Option<String>,
+    tls: Option<DockerTlsConfig>,
     include_containers: Option<Vec<String>>, // Starts with actually, not include
+    exclude_containers: Option<Vec<String>>, // Starts with actually, not exclude
     include_labels: Option<Vec<String>>,
     include_images: Option<Vec<String>>,
     partial_event_marker_field: Option<Atom>,
     auto_partial_merge: bool,
     multiline: Option<MultilineConfig>,
     retry_backoff_secs: u64,
+    container_events: bool,
+    label_prefix: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DockerTlsConfig {
+    ca_file: PathBuf,
+    crt_file: PathBuf,
+    key_file: PathBuf,
+}
+
+impl DockerTlsConfig {
+    /// Use the files the docker CLI expects in the DOCKER_CERT_PATH directory.
+    fn from_cert_path(cert_path: PathBuf) -> Self {
+        Self {
+            ca_file: cert_path.join("ca.pem"),
+            crt_file: cert_path.join("cert.pem"),
+            key_file: cert_path.join("key.pem"),
+        }
+    }
 }
 
 impl Default for DockerConfig {
     fn default() -> Self {
         Self {
+            docker_host: None,
+            tls: None,
             include_containers: None,
+            exclude_containers: None,
             include_labels: None,
             include_images: None,
             partial_event_marker_field: Some(event::PARTIAL.clone()),
             auto_partial_merge: true,
             multiline: None,
             retry_backoff_secs: 2,
+            container_events: false,
+            label_prefix: "label.".to_owned(),
         }
     }
 }
@@ -81,18 +134,26 @@ impl DockerConfig {
         id: &str,
         names: impl IntoIterator<Item = &'a str>,
     ) -> bool {
-        if let Some(include_containers) = &self.include_containers {
-            let id_flag = include_containers
-                .iter()
-                .any(|include| id.starts_with(include));
+        let names: Vec<&str> = names.into_iter().collect();
+        let matches = |prefixes: &Vec<String>| {
+            let id_flag = prefixes.iter().any(|prefix| id.starts_with(prefix));
 
-            let name_flag = names.into_iter().any(|name| {
-                include_containers
-                    .iter()
-                    .any(|include| name.starts_with(include))
-            });
+            let name_flag = names
+                .iter()
+                .any(|name| prefixes.iter().any(|prefix| name.starts_with(prefix)));
 
             id_flag || name_flag
+        };
+
+        // Excludes take precedence over includes.
+        if let Some(exclude_containers) = &self.exclude_containers {
+            if matches(exclude_containers) {
+                return false;
+            }
+        }
+
+        if let Some(include_containers) = &self.include_containers {
+            matches(include_containers)
         } else {
             true
         }
@@ -172,7 +233,7 @@ impl DockerSourceCore {
     fn new(config: DockerConfig) -> crate::Result<Self> {
         // ?NOTE: Constructs a new Docker instance for a docker host listening at url specified by an env var DOCKER_HOST.
         // ?      Otherwise connects to unix socket which requires sudo privileges, or docker group membership.
-        let docker = docker()?;
+        let docker = docker(config.docker_host.clone(), config.tls.clone())?;
 
         // Only log events created at-or-after this moment are logged.
         let now = Local::now();
@@ -195,9 +256,10 @@ impl DockerSourceCore {
         })
     }
 
-    /// Returns event stream coming from docker.
+    /// Returns event stream coming from docker, starting at `since`.
     fn docker_event_stream(
         &self,
+        since: DateTime<Utc>,
     ) -> impl Stream<Item = Result<SystemEventsResponse, DockerError>> + Send {
         let mut filters = HashMap::new();
 
@@ -207,15 +269,17 @@ impl DockerSourceCore {
         // unpause | docker unpause
         // die    | docker restart, docker stop, docker kill, process exited, oom
         // pause  | docker pause
-        filters.insert(
-            "event".to_owned(),
-            vec![
-                "start".to_owned(),
-                "unpause".to_owned(),
-                "die".to_owned(),
-                "pause".to_owned(),
-            ],
-        );
+        // oom    | container ran out of memory, only reported as a log event
+        let mut events = vec![
+            "start".to_owned(),
+            "unpause".to_owned(),
+            "die".to_owned(),
+            "pause".to_owned(),
+        ];
+        if self.config.container_events {
+            events.push("oom".to_owned());
+        }
+        filters.insert("event".to_owned(), events);
         filters.insert("type".to_owned(), vec!["container".to_owned()]);
 
         // Apply include filters
@@ -228,7 +292,7 @@ impl DockerSourceCore {
         }
 
         self.docker.events(Some(EventsOptions {
-            since: Some(self.now_timestamp),
+            since: Some(since),
             until: None,
             filters,
         }))
@@ -259,6 +323,9 @@ struct DockerSource {
     /// True if self needs to be excluded
     exclude_self: bool,
     backoff_duration: Duration,
+    /// Time of the last received docker event, in nanoseconds.
+    /// Used to resume the event stream after reconnecting.
+    last_event_time_nano: Option<i64>,
 }
 
 impl DockerSource {
@@ -286,7 +353,7 @@ impl DockerSource {
         let core = DockerSourceCore::new(config)?;
 
         // main event stream, with whom only newly started/restarted containers will be logged.
-        let events = core.docker_event_stream();
+        let events = core.docker_event_stream(core.now_timestamp);
         info!(message = "Listening to docker events.");
 
         // Channel of communication between main future and event_stream futures
@@ -316,6 +383,7 @@ impl DockerSource {
             hostname: env::var("HOSTNAME").ok(),
             exclude_self,
             backoff_duration: Duration::from_secs(backoff_secs),
+            last_event_time_nano: None,
         })
     }
 
@@ -414,6 +482,16 @@ impl DockerSource {
                 value = self.events.next() => {
                     match value {
                         Some(Ok(mut event)) => {
+                            // Skip the events replayed after reconnecting.
+                            if let (Some(time_nano), Some(last)) = (event.time_nano, self.last_event_time_nano) {
+                                if time_nano <= last {
+                                    continue;
+                                }
+                            }
+                            if event.time_nano.is_some() {
+                                self.last_event_time_nano = event.time_nano;
+                            }
+
                             let action = event.action.unwrap();
                             let actor = event.actor.take().unwrap();
                             let id = actor.id.unwrap();
@@ -421,6 +499,10 @@ impl DockerSource {
 
                             emit!(DockerContainerEventReceived { container_id: &id, action: &action });
 
+                            if self.esb.core.config.container_events {
+                                self.esb.emit_container_event(&id, &action, &attributes, event.time_nano).await;
+                            }
+
                             let id = ContainerId::new(id);
 
                             // Update container status
@@ -456,10 +538,13 @@ impl DockerSource {
                         }
                         Some(Err(error)) => emit!(DockerCommunicationError{error,container_id:None}),
                         None => {
-                            // TODO: this could be fixed, but should be tried with some timeoff and exponential backoff
-                            error!(message = "docker event stream has ended unexpectedly.");
-                            info!(message = "shutting down docker source.");
-                            return;
+                            error!(message = "docker event stream has ended unexpectedly, reconnecting.");
+                            tokio::time::delay_for(self.backoff_duration).await;
+                            let since = self
+                                .last_event_time_nano
+                                .map(|time_nano| Utc.timestamp_nanos(time_nano))
+                                .unwrap_or(self.esb.core.now_timestamp);
+                            self.events = Box::pin(self.esb.core.docker_event_stream(since));
                         }
                     };
                 }
@@ -516,17 +601,19 @@ impl EventStreamBuilder {
                 .inspect_container(id.as_str(), None::<InspectContainerOptions>)
                 .await
             {
-                Ok(details) => match ContainerMetadata::from_details(details) {
-                    Ok(metadata) => {
-                        let info = ContainerLogInfo::new(id, metadata, this.core.now_timestamp);
-                        this.run_event_stream(info).await;
-                        return;
+                Ok(details) => {
+                    match ContainerMetadata::from_details(details, &this.core.config.label_prefix) {
+                        Ok(metadata) => {
+                            let info = ContainerLogInfo::new(id, metadata, this.core.now_timestamp);
+                            this.run_event_stream(info).await;
+                            return;
+                        }
+                        Err(error) => emit!(DockerTimestampParseFailed {
+                            error,
+                            container_id: id.as_str()
+                        }),
                     }
-                    Err(error) => emit!(DockerTimestampParseFailed {
-                        error,
-                        container_id: id.as_str()
-                    }),
-                },
+                }
                 Err(error) => emit!(DockerContainerMetadataFetchFailed {
                     error,
                     container_id: id.as_str()
@@ -541,6 +628,38 @@ impl EventStreamBuilder {
         ContainerState::new_running()
     }
 
+    /// Sends a container lifecycle event downstream as a log event.
+    async fn emit_container_event(
+        &self,
+        id: &str,
+        action: &str,
+        attributes: &HashMap<String, String>,
+        time_nano: Option<i64>,
+    ) {
+        let name = attributes.get("name").map(|s| s.as_str());
+        if !self.core.config.container_name_included(id, name) {
+            return;
+        }
+
+        let event = container_event_to_log(
+            id,
+            action,
+            attributes,
+            time_nano,
+            &self.core.config.label_prefix,
+        );
+        if let Err(()) = self
+            .out
+            .clone()
+            .sink_compat()
+            .sink_map_err(|_| ())
+            .send(event)
+            .await
+        {
+            error!(message = "unable to send container event.", container_id = %id);
+        }
+    }
+
     /// If info is present, restarts event stream which will run until shutdown.
     fn restart(&self, container: &mut ContainerState) {
         if let Some(info) = container.take_info() {
@@ -918,7 +1037,10 @@ struct ContainerMetadata {
 }
 
 impl ContainerMetadata {
-    fn from_details(details: ContainerInspectResponse) -> Result<Self, ParseError> {
+    fn from_details(
+        details: ContainerInspectResponse,
+        label_prefix: &str,
+    ) -> Result<Self, ParseError> {
         let config = details.config.unwrap();
         let name = details.name.unwrap();
         let created = details.created.unwrap();
@@ -930,7 +1052,7 @@ impl ContainerMetadata {
                 map.iter()
                     .map(|(key, value)| {
                         (
-                            ("label.".to_owned() + key).into(),
+                            (label_prefix.to_owned() + key).into(),
                             Value::from(value.to_owned()),
                         )
                     })
@@ -947,17 +1069,93 @@ impl ContainerMetadata {
     }
 }
 
-fn docker() -> Result<Docker, DockerError> {
-    let scheme = env::var("DOCKER_HOST").ok().and_then(|host| {
-        let uri = host.parse::<hyper::Uri>().expect("invalid url");
-        uri.into_parts().scheme
+/// Creates a log event from a container lifecycle event.
+/// Container attributes, other than the well known ones, are its labels.
+fn container_event_to_log(
+    id: &str,
+    action: &str,
+    attributes: &HashMap<String, String>,
+    time_nano: Option<i64>,
+    label_prefix: &str,
+) -> Event {
+    let mut log_event = LogEvent::default();
+
+    log_event.insert(log_schema().source_type_key(), Bytes::from("docker"));
+
+    let name = attributes.get("name").cloned().unwrap_or_default();
+    log_event.insert(
+        log_schema().message_key(),
+        format!("Container {} {}", name, action),
+    );
+    log_event.insert(EVENT.clone(), action.to_owned());
+    log_event.insert(
+        log_schema().timestamp_key(),
+        time_nano
+            .map(|time_nano| Utc.timestamp_nanos(time_nano))
+            .unwrap_or_else(Utc::now),
+    );
+    log_event.insert(CONTAINER.clone(), id.to_owned());
+    log_event.insert(NAME.clone(), name);
+
+    for (key, value) in attributes {
+        match key.as_str() {
+            "name" => {}
+            "image" => {
+                log_event.insert(IMAGE.clone(), value.clone());
+            }
+            "exitCode" => match value.parse::<i64>() {
+                Ok(exit_code) => {
+                    log_event.insert(EXIT_CODE.clone(), exit_code);
+                }
+                Err(_) => {
+                    log_event.insert(EXIT_CODE.clone(), value.clone());
+                }
+            },
+            _ => {
+                log_event.insert(label_prefix.to_owned() + key, value.clone());
+            }
+        }
+    }
+
+    Event::Log(log_event)
+}
+
+/// Connects to the docker daemon at `host`, or at DOCKER_HOST if not set.
+/// Falls back to the local socket.
+fn docker(host: Option<String>, tls: Option<DockerTlsConfig>) -> crate::Result<Docker> {
+    let host = match host.or_else(|| env::var("DOCKER_HOST").ok()) {
+        Some(host) => host,
+        None => return Ok(Docker::connect_with_local_defaults().context(Connect)?),
+    };
+    let tls = tls.or_else(|| {
+        env::var("DOCKER_CERT_PATH")
+            .ok()
+            .map(|cert_path| DockerTlsConfig::from_cert_path(cert_path.into()))
     });
 
-    match scheme.as_ref().map(|s| s.as_str()) {
-        Some("http") => Docker::connect_with_http_defaults(),
-        Some("https") => Docker::connect_with_ssl_defaults(),
-        _ => Docker::connect_with_local_defaults(),
-    }
+    let uri = host
+        .parse::<hyper::Uri>()
+        .context(InvalidHost { host: host.clone() })?;
+    let scheme = uri.scheme_str().unwrap_or_default().to_owned();
+
+    let docker = match (scheme.as_str(), tls) {
+        ("unix", _) => Docker::connect_with_unix(&host, DEFAULT_TIMEOUT, API_DEFAULT_VERSION),
+        ("http", _) | ("tcp", None) => {
+            Docker::connect_with_http(&host, DEFAULT_TIMEOUT, API_DEFAULT_VERSION)
+        }
+        ("https", Some(tls)) | ("tcp", Some(tls)) => Docker::connect_with_ssl(
+            &host,
+            &tls.key_file,
+            &tls.crt_file,
+            &tls.ca_file,
+            DEFAULT_TIMEOUT,
+            API_DEFAULT_VERSION,
+        ),
+        ("https", None) => return Err(BuildError::TlsRequired { host }.into()),
+        _ => return Err(BuildError::UnsupportedScheme { scheme }.into()),
+    };
+
+    Ok(docker.context(Connect)?)
 }
 
 fn line_agg_adapter(
@@ -1241,7 +1439,7 @@ mod tests {
 
         let out = source_with(&[name], None);
 
-        let docker = docker().unwrap();
+        let docker = docker(None, None).unwrap();
 
         let id = container_log_n(1, name, Some(label), message, &docker).await;
         let events = collect_n(out, 1).await.unwrap();
@@ -1269,7 +1467,7 @@ mod tests {
 
         let out = source_with(&[name], None);
 
-        let docker = docker().unwrap();
+        let docker = docker(None, None).unwrap();
 
         let id = container_log_n(2, name, None, message, &docker).await;
         let events = collect_n(out, 2).await.unwrap();
@@ -1295,7 +1493,7 @@ mod tests {
 
         let out = source_with(&[name1], None);
 
-        let docker = docker().unwrap();
+        let docker = docker(None, None).unwrap();
 
         let id0 = container_log_n(1, name0, None, "13", &docker).await;
         let id1 = container_log_n(1, name1, None, message, &docker).await;
@@ -1309,6 +1507,76 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn exclude_containers() {
+        trace_init();
+
+        let message = "14";
+        let name0 = "vector_test_exclude_container_0";
+        let name1 = "vector_test_exclude_container_1";
+
+        let out = source_with_config(DockerConfig {
+            include_containers: Some(vec!["vector_test_exclude_container_".to_owned()]),
+            exclude_containers: Some(vec![name0.to_owned()]),
+            ..DockerConfig::default()
+        });
+
+        let docker = docker(None, None).unwrap();
+
+        let id0 = container_log_n(1, name0, None, "15", &docker).await;
+        let id1 = container_log_n(1, name1, None, message, &docker).await;
+        let events = collect_n(out, 1).await.unwrap();
+        container_remove(&id0, &docker).await;
+        container_remove(&id1, &docker).await;
+
+        assert_eq!(
+            events[0].as_log()[&log_schema().message_key()],
+            message.into()
+        );
+    }
+
+    #[tokio::test]
+    async fn container_events() {
+        trace_init();
+
+        let message = "16";
+        let name = "vector_test_container_events";
+        let label = "vector_test_container_events_label";
+
+        let out = source_with_config(DockerConfig {
+            include_containers: Some(vec![name.to_owned()]),
+            container_events: true,
+            label_prefix: "docker.".to_owned(),
+            ..DockerConfig::default()
+        });
+
+        let docker = docker(None, None).unwrap();
+
+        let id = container_log_n(1, name, Some(label), message, &docker).await;
+        let events = collect_n(out, 3).await.unwrap();
+        container_remove(&id, &docker).await;
+
+        let actions = events
+            .iter()
+            .filter_map(|event| event.as_log().get(&super::EVENT))
+            .map(|action| action.to_string_lossy())
+            .collect::<Vec<_>>();
+        assert_eq!(actions, vec!["start".to_owned(), "die".to_owned()]);
+
+        for event in events.iter() {
+            let log = event.as_log();
+            assert_eq!(log[&super::CONTAINER], id.clone().into());
+            assert_eq!(log[&super::NAME], name.into());
+            assert!(log.get(&format!("docker.{}", label).into()).is_some());
+        }
+
+        let die = events
+            .iter()
+            .find(|event| event.as_log().get(&super::EVENT) == Some(&"die".into()))
+            .unwrap();
+        assert_eq!(die.as_log()[&super::EXIT_CODE], 0.into());
+    }
+
     #[tokio::test]
     async fn include_labels() {
         trace_init();
@@ -1320,7 +1588,7 @@ mod tests {
 
         let out = source_with(&[name0, name1], label);
 
-        let docker = docker().unwrap();
+        let docker = docker(None, None).unwrap();
 
         let id0 = container_log_n(1, name0, None, "13", &docker).await;
         let id1 = container_log_n(1, name1, Some(label), message, &docker).await;
@@ -1342,7 +1610,7 @@ mod tests {
         let name = "vector_test_currently_running";
         let label = "vector_test_label_currently_running";
 
-        let docker = docker().unwrap();
+        let docker = docker(None, None).unwrap();
         let id = running_container(name, Some(label), message, &docker).await;
         let out = source_with(&[name], None);
 
@@ -1377,7 +1645,7 @@ mod tests {
 
         let out = source_with_config(config);
 
-        let docker = docker().unwrap();
+        let docker = docker(None, None).unwrap();
 
         let id = container_log_n(1, name, None, message, &docker).await;
         let events = collect_n(out, 1).await.unwrap();
@@ -1402,7 +1670,7 @@ mod tests {
 
         let exclude_out = source_with_config(config_ex);
 
-        let docker = docker().unwrap();
+        let docker = docker(None, None).unwrap();
 
         let id = container_log_n(1, name, None, message, &docker).await;
         container_remove(&id, &docker).await;
@@ -1426,7 +1694,7 @@ mod tests {
             ..DockerConfig::default()
         };
 
-        let docker = docker().unwrap();
+        let docker = docker(None, None).unwrap();
 
         let id = running_container(name, None, message, &docker).await;
         let exclude_out = source_with_config(config_ex);
@@ -1451,7 +1719,7 @@ mod tests {
 
         let out = source_with(&[name], None);
 
-        let docker = docker().unwrap();
+        let docker = docker(None, None).unwrap();
 
         let id = container_log_n(1, name, None, message.as_str(), &docker).await;
         let events = collect_n(out, 1).await.unwrap();
@@ -1490,7 +1758,7 @@ mod tests {
 
         let out = source_with_config(config);
 
-        let docker = docker().unwrap();
+        let docker = docker(None, None).unwrap();
 
         let command = emitted_messages
             .into_iter()
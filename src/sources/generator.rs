@@ -5,13 +5,16 @@ use crate::{
     shutdown::ShutdownSignal,
     Pipeline,
 };
+use chrono::{DateTime, SecondsFormat, Utc};
 use futures::{
     compat::Future01CompatExt,
     future::{FutureExt, TryFutureExt},
     stream::StreamExt,
 };
 use futures01::{future::Future, stream::iter_ok, Sink};
+use rand::{rngs::SmallRng, FromEntropy, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use snafu::Snafu;
 use std::time::Duration;
 use tokio::time::interval;
 
@@ -20,13 +23,38 @@ use tokio::time::interval;
 pub struct GeneratorConfig {
     #[serde(default)]
     sequence: bool,
+    #[serde(default)]
     lines: Vec<String>,
     #[serde(default)]
+    format: Option<FakeFormat>,
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
     batch_interval: Option<f64>,
     #[serde(default = "usize::max_value")]
     count: usize,
 }
 
+/// The built-in formats of fake log lines.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FakeFormat {
+    ApacheCommon,
+    ApacheCombined,
+    Json,
+    #[serde(rename = "syslog_3164")]
+    Syslog3164,
+    #[serde(rename = "syslog_5424")]
+    Syslog5424,
+    Logfmt,
+}
+
+#[derive(Debug, PartialEq, Snafu)]
+enum BuildError {
+    #[snafu(display("Exactly one of `lines` or `format` must be set"))]
+    LinesOrFormat,
+}
+
 impl GeneratorConfig {
     #[allow(dead_code)] // to make check-component-features pass
     pub fn repeat(lines: Vec<String>, count: usize, batch_interval: Option<f64>) -> Self {
@@ -53,6 +81,9 @@ impl SourceConfig for GeneratorConfig {
         shutdown: ShutdownSignal,
         out: Pipeline,
     ) -> crate::Result<super::Source> {
+        if self.lines.is_empty() == self.format.is_none() {
+            return Err(BuildError::LinesOrFormat.into());
+        }
         Ok(self.clone().generator(shutdown, out))
     }
 
@@ -75,6 +106,10 @@ impl GeneratorConfig {
             .batch_interval
             .map(|i| interval(Duration::from_secs_f64(i)));
         let mut number: usize = 0;
+        let mut rng = match self.seed {
+            Some(seed) => seeded_rng(seed),
+            None => SmallRng::from_entropy(),
+        };
 
         for _ in 0..self.count {
             if shutdown.poll().expect("polling shutdown").is_ready() {
@@ -85,9 +120,12 @@ impl GeneratorConfig {
                 batch_interval.next().await;
             }
 
-            let events = self
-                .lines
-                .iter()
+            let lines = match self.format {
+                Some(format) => vec![format.generate(&mut rng, Utc::now())],
+                None => self.lines.clone(),
+            };
+            let events = lines
+                .into_iter()
                 .map(|line| {
                     emit!(GeneratorEventProcessed);
 
@@ -110,6 +148,141 @@ impl GeneratorConfig {
     }
 }
 
+fn seeded_rng(seed: u64) -> SmallRng {
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    bytes[8..].copy_from_slice(&seed.to_be_bytes());
+    SmallRng::from_seed(bytes)
+}
+
+const HOSTS: &[&str] = &["web-01", "web-02", "api-01", "db-01", "cache-01"];
+const APPS: &[&str] = &["nginx", "sshd", "cron", "kernel", "systemd", "postfix"];
+const USERS: &[&str] = &["-", "alice", "bob", "carol", "dave"];
+const METHODS: &[&str] = &["GET", "GET", "GET", "POST", "PUT", "DELETE", "HEAD"];
+const PATHS: &[&str] = &[
+    "/",
+    "/index.html",
+    "/login",
+    "/logout",
+    "/api/v1/users",
+    "/api/v1/orders",
+    "/static/app.js",
+    "/static/style.css",
+    "/images/logo.png",
+    "/search?q=vector",
+];
+const PROTOCOLS: &[&str] = &["HTTP/1.0", "HTTP/1.1", "HTTP/2.0"];
+const STATUSES: &[u16] = &[
+    200, 200, 200, 201, 204, 301, 302, 304, 400, 401, 403, 404, 500, 503,
+];
+const REFERERS: &[&str] = &[
+    "-",
+    "https://www.google.com/",
+    "https://duckduckgo.com/",
+    "https://example.com/",
+];
+const USER_AGENTS: &[&str] = &[
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/85.0.4183.102 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_6) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/14.0 Safari/605.1.15",
+    "Mozilla/5.0 (X11; Linux x86_64; rv:80.0) Gecko/20100101 Firefox/80.0",
+    "curl/7.68.0",
+    "Googlebot/2.1 (+http://www.google.com/bot.html)",
+];
+const LEVELS: &[&str] = &["debug", "info", "info", "info", "warn", "error"];
+const MESSAGES: &[&str] = &[
+    "Request handled.",
+    "User logged in.",
+    "User logged out.",
+    "Cache miss.",
+    "Retrying connection.",
+    "Connection refused.",
+    "Order created.",
+];
+
+impl FakeFormat {
+    fn generate<R: Rng>(self, rng: &mut R, now: DateTime<Utc>) -> String {
+        match self {
+            FakeFormat::ApacheCommon => apache_common(rng, now),
+            FakeFormat::ApacheCombined => format!(
+                "{} \"{}\" \"{}\"",
+                apache_common(rng, now),
+                pick(rng, REFERERS),
+                pick(rng, USER_AGENTS)
+            ),
+            FakeFormat::Json => serde_json::json!({
+                "timestamp": now.to_rfc3339_opts(SecondsFormat::Millis, true),
+                "level": pick(rng, LEVELS),
+                "host": pick(rng, HOSTS),
+                "message": pick(rng, MESSAGES),
+                "client_ip": ip_address(rng),
+                "method": pick(rng, METHODS),
+                "path": pick(rng, PATHS),
+                "status": pick(rng, STATUSES),
+                "duration_ms": rng.gen_range(1, 2000),
+            })
+            .to_string(),
+            FakeFormat::Syslog3164 => format!(
+                "<{}>{} {} {}[{}]: {}",
+                rng.gen_range(0, 192),
+                now.format("%b %e %T"),
+                pick(rng, HOSTS),
+                pick(rng, APPS),
+                rng.gen_range(1, 65536),
+                pick(rng, MESSAGES)
+            ),
+            FakeFormat::Syslog5424 => format!(
+                "<{}>1 {} {} {} {} ID{} - {}",
+                rng.gen_range(0, 192),
+                now.to_rfc3339_opts(SecondsFormat::Millis, true),
+                pick(rng, HOSTS),
+                pick(rng, APPS),
+                rng.gen_range(1, 65536),
+                rng.gen_range(0, 1000),
+                pick(rng, MESSAGES)
+            ),
+            FakeFormat::Logfmt => format!(
+                "time={} level={} host={} method={} path={} status={} duration={}ms msg={:?}",
+                now.to_rfc3339_opts(SecondsFormat::Millis, true),
+                pick(rng, LEVELS),
+                pick(rng, HOSTS),
+                pick(rng, METHODS),
+                pick(rng, PATHS),
+                pick(rng, STATUSES),
+                rng.gen_range(1, 2000),
+                pick(rng, MESSAGES)
+            ),
+        }
+    }
+}
+
+fn apache_common<R: Rng>(rng: &mut R, now: DateTime<Utc>) -> String {
+    format!(
+        "{} - {} [{}] \"{} {} {}\" {} {}",
+        ip_address(rng),
+        pick(rng, USERS),
+        now.format("%d/%b/%Y:%T %z"),
+        pick(rng, METHODS),
+        pick(rng, PATHS),
+        pick(rng, PROTOCOLS),
+        pick(rng, STATUSES),
+        rng.gen_range(0, 50_000)
+    )
+}
+
+fn ip_address<R: Rng>(rng: &mut R) -> String {
+    format!(
+        "{}.{}.{}.{}",
+        rng.gen_range(1, 255),
+        rng.gen::<u8>(),
+        rng.gen::<u8>(),
+        rng.gen_range(1, 255)
+    )
+}
+
+fn pick<R: Rng, T: Copy>(rng: &mut R, items: &[T]) -> T {
+    items[rng.gen_range(0, items.len())]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -211,4 +384,77 @@ mod tests {
         let duration = start.elapsed();
         assert!(duration >= Duration::from_secs(2));
     }
+
+    #[tokio::test]
+    async fn requires_lines_or_format() {
+        for config in &["count = 1", "lines = [\"one\"]\nformat = \"json\""] {
+            let config: GeneratorConfig = toml::from_str(config).unwrap();
+            assert!(config
+                .build(
+                    "default",
+                    &GlobalOptions::default(),
+                    ShutdownSignal::noop(),
+                    Pipeline::new_test().0
+                )
+                .await
+                .is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn generates_format() {
+        let mut rx = runit(
+            r#"format = "apache_common"
+               count = 3"#,
+        )
+        .await;
+
+        for _ in 0..3 {
+            assert!(matches!(rx.poll().unwrap(), Ready(Some(_))));
+        }
+        assert_eq!(rx.poll().unwrap(), Ready(None));
+    }
+
+    #[test]
+    fn seeded_formats_are_reproducible() {
+        let now = Utc::now();
+        for format in &[
+            FakeFormat::ApacheCommon,
+            FakeFormat::ApacheCombined,
+            FakeFormat::Json,
+            FakeFormat::Syslog3164,
+            FakeFormat::Syslog5424,
+            FakeFormat::Logfmt,
+        ] {
+            let mut first = seeded_rng(42);
+            let mut second = seeded_rng(42);
+            for _ in 0..10 {
+                assert_eq!(
+                    format.generate(&mut first, now),
+                    format.generate(&mut second, now)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn formats_are_parseable() {
+        let mut rng = seeded_rng(1);
+        let now = Utc::now();
+
+        let line = FakeFormat::Json.generate(&mut rng, now);
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert!(json["status"].is_u64());
+
+        let line = FakeFormat::ApacheCombined.generate(&mut rng, now);
+        assert_eq!(line.matches('"').count(), 6);
+
+        let line = FakeFormat::Syslog5424.generate(&mut rng, now);
+        assert!(line.starts_with('<'));
+        assert!(line.contains(">1 "));
+
+        let line = FakeFormat::Logfmt.generate(&mut rng, now);
+        assert!(line.starts_with("time="));
+        assert!(line.contains(" status="));
+    }
 }
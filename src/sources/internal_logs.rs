@@ -0,0 +1,160 @@
+use crate::{
+    config::{log_schema, DataType, GlobalOptions, SourceConfig, SourceDescription},
+    event::Event,
+    shutdown::ShutdownSignal,
+    trace::{self, Instrument},
+    Pipeline,
+};
+use bytes::Bytes;
+use futures::{
+    compat::Future01CompatExt,
+    future::{FutureExt, TryFutureExt},
+};
+use futures01::Sink;
+use serde::{Deserialize, Serialize};
+use tokio::{select, sync::broadcast};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct InternalLogsConfig;
+
+inventory::submit! {
+    SourceDescription::new::<InternalLogsConfig>("internal_logs")
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "internal_logs")]
+impl SourceConfig for InternalLogsConfig {
+    async fn build(
+        &self,
+        _name: &str,
+        _globals: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: Pipeline,
+    ) -> crate::Result<super::Source> {
+        let fut = run(trace::subscribe(), out, shutdown)
+            // Events raised under this span are excluded from the internal
+            // logs, so they don't feed back on themselves. The span is at the
+            // error level, so that it isn't disabled by a stricter log level.
+            .instrument(error_span!(trace::INTERNAL_LOGS_SPAN_NAME))
+            .boxed()
+            .compat();
+        Ok(Box::new(fut))
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn source_type(&self) -> &'static str {
+        "internal_logs"
+    }
+}
+
+async fn run(
+    mut rx: broadcast::Receiver<crate::event::LogEvent>,
+    mut out: Pipeline,
+    shutdown: ShutdownSignal,
+) -> Result<(), ()> {
+    let mut shutdown = shutdown.compat();
+
+    loop {
+        let mut log = select! {
+            received = rx.recv() => match received {
+                Ok(log) => log,
+                Err(broadcast::RecvError::Lagged(count)) => {
+                    warn!(message = "Internal logs were dropped.", %count);
+                    continue;
+                }
+                Err(broadcast::RecvError::Closed) => break,
+            },
+            _ = &mut shutdown => break,
+        };
+
+        log.try_insert(log_schema().source_type_key(), Bytes::from("internal_logs"));
+
+        out = out
+            .send(Event::from(log))
+            .compat()
+            .await
+            .map_err(|error| error!(message = "Error sending internal logs.", %error))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Value;
+    use futures::compat::Stream01CompatExt;
+    use futures::StreamExt;
+    use tokio::time::{delay_for, Duration};
+    use tracing_subscriber::{filter::LevelFilter, layer::SubscriberExt, Registry};
+
+    fn subscriber() -> impl tracing::Subscriber {
+        // Stricter than the info level, to check that the excluding span
+        // stays enabled.
+        Registry::default()
+            .with(LevelFilter::WARN)
+            .with(trace::BroadcastLayer)
+    }
+
+    #[tokio::test]
+    async fn receives_logs() {
+        let (tx, rx) = Pipeline::new_test();
+        let (trigger, shutdown, _) = ShutdownSignal::new_wired();
+
+        let source = InternalLogsConfig
+            .build("default", &GlobalOptions::default(), shutdown, tx)
+            .await
+            .unwrap();
+        tokio::spawn(source.compat());
+
+        tracing::subscriber::with_default(subscriber(), || {
+            let span = error_span!(
+                "source",
+                component_kind = "source",
+                component_name = "in",
+                component_type = "stdin",
+            );
+            let _enter = span.enter();
+            error!(message = "Something went wrong.", count = 3);
+
+            // Excluded to not feed back on itself.
+            let span = error_span!(trace::INTERNAL_LOGS_SPAN_NAME);
+            let _enter = span.enter();
+            error!(message = "Error sending internal logs.");
+        });
+
+        delay_for(Duration::from_millis(100)).await;
+        drop(trigger);
+
+        let events = rx.compat().collect::<Vec<_>>().await;
+        let logs = events
+            .into_iter()
+            .map(|event| event.unwrap().into_log())
+            // Other tests may log concurrently.
+            .filter(|log| {
+                let message = log[&log_schema().message_key()].to_string_lossy();
+                message == "Something went wrong." || message == "Error sending internal logs."
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(logs.len(), 1);
+
+        let log = &logs[0];
+        assert_eq!(
+            log[&log_schema().message_key()],
+            "Something went wrong.".into()
+        );
+        assert_eq!(log[&"count".into()], Value::Integer(3));
+        assert_eq!(log[&"metadata.level".into()], "ERROR".into());
+        assert_eq!(
+            log[&"metadata.target".into()],
+            "vector::sources::internal_logs::tests".into()
+        );
+        assert_eq!(log[&"vector.component_name".into()], "in".into());
+        assert_eq!(log[&"vector.component_type".into()], "stdin".into());
+        assert_eq!(log[&"vector.component_kind".into()], "source".into());
+        assert_eq!(log[log_schema().source_type_key()], "internal_logs".into());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{
     config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
+    event::Event,
     metrics::Controller,
     metrics::{capture_metrics, get_controller},
     shutdown::ShutdownSignal,
@@ -12,11 +13,32 @@ use futures::{
 };
 use futures01::Sink;
 use serde::{Deserialize, Serialize};
+use snafu::Snafu;
 use std::time::Duration;
-use tokio::{select, time::interval};
+use tokio::select;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct InternalMetricsConfig {
+    /// Prefixed to the metric names, separated by an underscore.
+    namespace: Option<String>,
+    scrape_interval_secs: u64,
+}
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("scrape_interval_secs must be greater than 0"))]
+    ZeroScrapeInterval,
+}
 
-#[derive(Deserialize, Serialize, Debug, Clone, Default)]
-pub struct InternalMetricsConfig;
+impl Default for InternalMetricsConfig {
+    fn default() -> Self {
+        Self {
+            namespace: None,
+            scrape_interval_secs: 2,
+        }
+    }
+}
 
 inventory::submit! {
     SourceDescription::new::<InternalMetricsConfig>("internal_metrics")
@@ -32,7 +54,14 @@ impl SourceConfig for InternalMetricsConfig {
         shutdown: ShutdownSignal,
         out: Pipeline,
     ) -> crate::Result<super::Source> {
-        let fut = run(get_controller()?, out, shutdown).boxed().compat();
+        if self.scrape_interval_secs == 0 {
+            return Err(BuildError::ZeroScrapeInterval.into());
+        }
+        let interval = Duration::from_secs(self.scrape_interval_secs);
+        let namespace = self.namespace.clone();
+        let fut = run(get_controller()?, interval, namespace, out, shutdown)
+            .boxed()
+            .compat();
         Ok(Box::new(fut))
     }
 
@@ -47,10 +76,12 @@ impl SourceConfig for InternalMetricsConfig {
 
 async fn run(
     controller: &Controller,
+    interval: Duration,
+    namespace: Option<String>,
     mut out: Pipeline,
     shutdown: ShutdownSignal,
 ) -> Result<(), ()> {
-    let mut interval = interval(Duration::from_secs(2)).map(|_| ());
+    let mut interval = tokio::time::interval(interval).map(|_| ());
     let mut shutdown = shutdown.compat();
 
     let mut run = true;
@@ -61,7 +92,10 @@ async fn run(
             else => false,
         };
 
-        let metrics = capture_metrics(controller);
+        let metrics = capture_metrics(controller).map(|event| match &namespace {
+            Some(namespace) => with_namespace(event, namespace),
+            None => event,
+        });
 
         let (sink, _) = out
             .send_all(futures01::stream::iter_ok(metrics))
@@ -74,9 +108,16 @@ async fn run(
     Ok(())
 }
 
+fn with_namespace(event: Event, namespace: &str) -> Event {
+    let mut metric = event.into_metric();
+    metric.name = format!("{}_{}", namespace, metric.name);
+    metric.into()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::event::metric::{Metric, MetricValue, StatisticKind};
+    use super::*;
+    use crate::event::metric::{Metric, MetricKind, MetricValue, StatisticKind};
     use crate::metrics::{capture_metrics, get_controller};
     use metrics::{counter, gauge, histogram};
     use std::collections::BTreeMap;
@@ -132,4 +173,38 @@ mod tests {
         labels.insert(String::from("host"), String::from("foo"));
         assert_eq!(Some(labels), output["quux"].tags);
     }
+
+    #[test]
+    fn config_defaults() {
+        let config: InternalMetricsConfig = toml::from_str("").unwrap();
+        assert_eq!(config.namespace, None);
+        assert_eq!(config.scrape_interval_secs, 2);
+    }
+
+    #[tokio::test]
+    async fn rejects_zero_scrape_interval() {
+        let config: InternalMetricsConfig = toml::from_str("scrape_interval_secs = 0").unwrap();
+        let (tx, _rx) = Pipeline::new_test();
+
+        let result = config
+            .build("in", &GlobalOptions::default(), ShutdownSignal::noop(), tx)
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn prefixes_namespace() {
+        let event: Event = Metric {
+            name: "events_in".into(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Absolute,
+            value: MetricValue::Counter { value: 1.0 },
+        }
+        .into();
+
+        let metric = with_namespace(event, "vector").into_metric();
+        assert_eq!(metric.name, "vector_events_in");
+    }
 }
@@ -26,17 +26,21 @@ use std::{
     process::Stdio,
     str::FromStr,
     task::{Context, Poll},
+    time::Duration,
 };
 use string_cache::DefaultAtom as Atom;
 use tokio::{
     fs::{File, OpenOptions},
     io::{self, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
     process::{ChildStdout, Command},
+    time::{timeout_at, Instant},
 };
 use tracing_futures::Instrument;
 
 const DEFAULT_BATCH_SIZE: usize = 16;
 
+const DEFAULT_CHECKPOINT_INTERVAL_SECS: u64 = 1;
+
 const CHECKPOINT_FILENAME: &str = "checkpoint.txt";
 
 lazy_static! {
@@ -56,12 +60,17 @@ enum BuildError {
     #[snafu(display("Cannot use both `units` and `include_units`"))]
     BothUnitsAndIncludeUnits,
     #[snafu(display(
-        "The unit {:?} is duplicated in both include_units and exclude_units",
-        unit
+        "The value {:?} of field {:?} is duplicated in both includes and excludes",
+        value,
+        field
     ))]
-    DuplicatedUnit { unit: String },
+    DuplicatedMatches { field: String, value: String },
+    #[snafu(display("Cannot use both `since` and `since_now`"))]
+    BothSinceAndSinceNow,
 }
 
+type Matches = HashMap<String, HashSet<String>>;
+
 #[derive(Deserialize, Serialize, Debug, Default)]
 #[serde(deny_unknown_fields, default)]
 pub struct JournaldConfig {
@@ -69,9 +78,15 @@ pub struct JournaldConfig {
     pub units: Vec<String>,
     pub include_units: Vec<String>,
     pub exclude_units: Vec<String>,
+    pub include_matches: HashMap<String, Vec<String>>,
+    pub exclude_matches: HashMap<String, Vec<String>>,
     pub data_dir: Option<PathBuf>,
     pub batch_size: Option<usize>,
+    pub checkpoint_interval_secs: Option<u64>,
     pub journalctl_path: Option<PathBuf>,
+    pub journal_directory: Option<PathBuf>,
+    pub since_now: Option<bool>,
+    pub since: Option<String>,
     #[serde(default)]
     pub remap_priority: bool,
 }
@@ -94,6 +109,14 @@ impl SourceConfig for JournaldConfig {
     ) -> crate::Result<super::Source> {
         let data_dir = globals.resolve_and_make_data_subdir(self.data_dir.as_ref(), name)?;
         let batch_size = self.batch_size.unwrap_or(DEFAULT_BATCH_SIZE);
+        let checkpoint_interval = Duration::from_secs(
+            self.checkpoint_interval_secs
+                .unwrap_or(DEFAULT_CHECKPOINT_INTERVAL_SECS),
+        );
+
+        if self.since.is_some() && self.since_now.unwrap_or(false) {
+            return Err(BuildError::BothSinceAndSinceNow.into());
+        }
 
         let include_units = match (!self.units.is_empty(), !self.include_units.is_empty()) {
             (true, true) => return Err(BuildError::BothUnitsAndIncludeUnits.into()),
@@ -104,15 +127,12 @@ impl SourceConfig for JournaldConfig {
             (false, _) => &self.include_units,
         };
 
-        let include_units: HashSet<String> = include_units.iter().map(|s| fixup_unit(&s)).collect();
-        let exclude_units: HashSet<String> =
-            self.exclude_units.iter().map(|s| fixup_unit(&s)).collect();
-        if let Some(unit) = include_units
-            .iter()
-            .find(|unit| exclude_units.contains(&unit[..]))
-        {
-            let unit = unit.into();
-            return Err(BuildError::DuplicatedUnit { unit }.into());
+        let mut include_matches = create_matches(&self.include_matches);
+        add_unit_matches(&mut include_matches, include_units);
+        let mut exclude_matches = create_matches(&self.exclude_matches);
+        add_unit_matches(&mut exclude_matches, &self.exclude_units);
+        if let Some((field, value)) = find_duplicate_match(&include_matches, &exclude_matches) {
+            return Err(BuildError::DuplicatedMatches { field, value }.into());
         }
 
         let mut checkpoint = data_dir;
@@ -127,9 +147,10 @@ impl SourceConfig for JournaldConfig {
             out,
             shutdown,
             checkpointer,
-            include_units,
-            exclude_units,
+            include_matches,
+            exclude_matches,
             batch_size,
+            checkpoint_interval,
             self.remap_priority,
         )
         .await
@@ -150,9 +171,10 @@ impl JournaldConfig {
         out: Pipeline,
         shutdown: ShutdownSignal,
         mut checkpointer: Checkpointer,
-        include_units: HashSet<String>,
-        exclude_units: HashSet<String>,
+        include_matches: Matches,
+        exclude_matches: Matches,
         batch_size: usize,
+        checkpoint_interval: Duration,
         remap_priority: bool,
     ) -> crate::Result<super::Source>
     where
@@ -177,12 +199,13 @@ impl JournaldConfig {
         let (journal, close) = J::new(self, cursor)?;
         let journald_server = JournaldServer {
             journal: Box::pin(journal),
-            include_units,
-            exclude_units,
+            include_matches,
+            exclude_matches,
             channel: out,
             shutdown: shutdown.clone(),
             checkpointer,
             batch_size,
+            checkpoint_interval,
             remap_priority,
         };
 
@@ -235,6 +258,36 @@ fn create_event(record: Record) -> Event {
     log.into()
 }
 
+/// Convert the configured field matches into sets of accepted values.
+fn create_matches(matches: &HashMap<String, Vec<String>>) -> Matches {
+    matches
+        .iter()
+        .map(|(field, values)| (field.clone(), values.iter().cloned().collect()))
+        .collect()
+}
+
+/// Merge the given unit names into the `_SYSTEMD_UNIT` field matches.
+fn add_unit_matches(matches: &mut Matches, units: &[String]) {
+    if units.is_empty() {
+        return;
+    }
+    matches
+        .entry(SYSTEMD_UNIT.to_string())
+        .or_default()
+        .extend(units.iter().map(|unit| fixup_unit(unit)));
+}
+
+/// Find a field value present in both the includes and the excludes.
+fn find_duplicate_match(includes: &Matches, excludes: &Matches) -> Option<(String, String)> {
+    includes.iter().find_map(|(field, include_values)| {
+        let exclude_values = excludes.get(field)?;
+        include_values
+            .iter()
+            .find(|value| exclude_values.contains(*value))
+            .map(|value| (field.clone(), value.clone()))
+    })
+}
+
 /// Map the given unit name into a valid systemd unit
 /// by appending ".service" if no extension is present.
 fn fixup_unit(unit: &str) -> String {
@@ -271,22 +324,7 @@ impl JournalSource for Journalctl {
         let journalctl = config.journalctl_path.as_ref().unwrap_or(&JOURNALCTL);
         let mut command = Command::new(journalctl);
         command.stdout(Stdio::piped());
-        command.arg("--follow");
-        command.arg("--all");
-        command.arg("--show-cursor");
-        command.arg("--output=json");
-
-        let current_boot = config.current_boot_only.unwrap_or(true);
-        if current_boot {
-            command.arg("--boot");
-        }
-
-        if let Some(cursor) = cursor {
-            command.arg(format!("--after-cursor={}", cursor));
-        } else {
-            // journalctl --follow only outputs a few lines without a starting point
-            command.arg("--since=2000-01-01");
-        }
+        command.args(journalctl_args(config, cursor));
 
         let mut child = command.spawn().context(JournalctlSpawn)?;
         let stdout = BufReader::new(child.stdout.take().unwrap());
@@ -306,6 +344,36 @@ impl JournalSource for Journalctl {
     }
 }
 
+fn journalctl_args(config: &JournaldConfig, cursor: Option<String>) -> Vec<String> {
+    let mut args = vec![
+        "--follow".to_owned(),
+        "--all".to_owned(),
+        "--show-cursor".to_owned(),
+        "--output=json".to_owned(),
+    ];
+
+    if let Some(dir) = &config.journal_directory {
+        args.push(format!("--directory={}", dir.display()));
+    }
+
+    let current_boot = config.current_boot_only.unwrap_or(true);
+    if current_boot {
+        args.push("--boot".to_owned());
+    }
+
+    if let Some(cursor) = cursor {
+        args.push(format!("--after-cursor={}", cursor));
+    } else if config.since_now.unwrap_or(false) {
+        args.push("--since=now".to_owned());
+    } else {
+        // journalctl --follow only outputs a few lines without a starting point
+        let since = config.since.as_deref().unwrap_or("2000-01-01");
+        args.push(format!("--since={}", since));
+    }
+
+    args
+}
+
 impl Stream for Journalctl {
     type Item = io::Result<String>;
 
@@ -320,12 +388,13 @@ impl Stream for Journalctl {
 
 struct JournaldServer<J, T> {
     journal: Pin<Box<J>>,
-    include_units: HashSet<String>,
-    exclude_units: HashSet<String>,
+    include_matches: Matches,
+    exclude_matches: Matches,
     channel: T,
     shutdown: ShutdownSignal,
     checkpointer: Checkpointer,
     batch_size: usize,
+    checkpoint_interval: Duration,
     remap_priority: bool,
 }
 
@@ -340,9 +409,16 @@ where
         loop {
             let mut saw_record = false;
             let mut cursor: Option<String> = None;
+            // End the batch early once the interval elapses, so that the
+            // checkpoint doesn't lag behind on a quiet journal.
+            let deadline = Instant::now() + self.checkpoint_interval;
 
             for _ in 0..self.batch_size {
-                let text = match self.journal.next().await {
+                let next = match timeout_at(deadline, self.journal.next()).await {
+                    Ok(next) => next,
+                    Err(_) => break,
+                };
+                let text = match next {
                     None => {
                         let _ = self.shutdown.compat().await;
                         return;
@@ -370,8 +446,7 @@ where
 
                 saw_record = true;
 
-                let unit = record.get(&SYSTEMD_UNIT);
-                if filter_unit(unit, &self.include_units, &self.exclude_units) {
+                if filter_matches(&record, &self.include_matches, &self.exclude_matches) {
                     continue;
                 }
 
@@ -449,21 +524,26 @@ fn remap_priority(priority: &mut JsonValue) {
     }
 }
 
-/// Should the given unit name be filtered (excluded)?
-fn filter_unit(
-    unit: Option<&String>,
-    includes: &HashSet<String>,
-    excludes: &HashSet<String>,
-) -> bool {
-    match (unit, includes.is_empty(), excludes.is_empty()) {
-        (None, empty, _) => !empty,
-        (Some(_), true, true) => false,
-        (Some(unit), false, true) => !includes.contains(unit),
-        (Some(unit), true, false) => excludes.contains(unit),
-        (Some(unit), false, false) => !includes.contains(unit) || excludes.contains(unit),
+/// Should the given record be filtered (excluded)?
+fn filter_matches(record: &Record, includes: &Matches, excludes: &Matches) -> bool {
+    match (includes.is_empty(), excludes.is_empty()) {
+        (true, true) => false,
+        (false, true) => !contains_match(record, includes),
+        (true, false) => contains_match(record, excludes),
+        (false, false) => !contains_match(record, includes) || contains_match(record, excludes),
     }
 }
 
+/// Does any of the record fields have one of the matching values?
+fn contains_match(record: &Record, matches: &Matches) -> bool {
+    record.iter().any(|(field, value)| {
+        matches
+            .get(&field[..])
+            .map(|values| values.contains(value))
+            .unwrap_or(false)
+    })
+}
+
 struct Checkpointer {
     file: File,
     filename: PathBuf,
@@ -549,10 +629,7 @@ mod tests {
     use super::*;
     use crate::Pipeline;
     use futures01::stream::Stream as _;
-    use std::{
-        io::{BufRead, BufReader, Cursor},
-        iter::FromIterator,
-    };
+    use std::io::{BufRead, BufReader, Cursor};
     use tempfile::tempdir;
     use tokio::{
         io,
@@ -614,6 +691,16 @@ mod tests {
     }
 
     async fn run_journal(iunits: &[&str], xunits: &[&str], cursor: Option<&str>) -> Vec<Event> {
+        let include_matches = create_unit_matches(iunits);
+        let exclude_matches = create_unit_matches(xunits);
+        run_journal_with_matches(include_matches, exclude_matches, cursor).await
+    }
+
+    async fn run_journal_with_matches(
+        include_matches: Matches,
+        exclude_matches: Matches,
+        cursor: Option<&str>,
+    ) -> Vec<Event> {
         let (tx, rx) = Pipeline::new_test();
         let (trigger, shutdown, _) = ShutdownSignal::new_wired();
         let tempdir = tempdir().unwrap();
@@ -622,8 +709,6 @@ mod tests {
         let mut checkpointer = Checkpointer::new(filename)
             .await
             .expect("Creating checkpointer failed!");
-        let include_units = HashSet::<String>::from_iter(iunits.iter().map(|&s| s.into()));
-        let exclude_units = HashSet::<String>::from_iter(xunits.iter().map(|&s| s.into()));
 
         if let Some(cursor) = cursor {
             checkpointer
@@ -638,9 +723,10 @@ mod tests {
                 tx,
                 shutdown,
                 checkpointer,
-                include_units,
-                exclude_units,
+                include_matches,
+                exclude_matches,
                 DEFAULT_BATCH_SIZE,
+                Duration::from_secs(DEFAULT_CHECKPOINT_INTERVAL_SECS),
                 true,
             )
             .await
@@ -724,26 +810,130 @@ mod tests {
         assert_eq!(timestamp(&received[1]), value_ts(1578529839, 140005000));
     }
 
+    #[tokio::test]
+    async fn includes_matches() {
+        let matches = create_matches_from(&[("PRIORITY", "INFO"), ("PRIORITY", "ERR")]);
+        let received = run_journal_with_matches(matches, HashMap::new(), None).await;
+        assert_eq!(received.len(), 2);
+        assert_eq!(
+            message(&received[0]),
+            Value::Bytes("System Initialization".into())
+        );
+        assert_eq!(
+            message(&received[1]),
+            Value::Bytes("Different timestamps".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn excludes_matches() {
+        let matches = create_matches_from(&[("PRIORITY", "INFO"), ("PRIORITY", "DEBUG")]);
+        let received = run_journal_with_matches(HashMap::new(), matches, None).await;
+        assert_eq!(received.len(), 3);
+        assert_eq!(message(&received[0]), Value::Bytes("¿Hello?".into()));
+    }
+
+    #[test]
+    fn filter_matches_works_correctly() {
+        let empty = Matches::new();
+        let includes = create_unit_matches(&["one", "two"]);
+        let excludes = create_unit_matches(&["foo", "bar"]);
+
+        let none = Record::new();
+        assert_eq!(filter_matches(&none, &empty, &empty), false);
+        assert_eq!(filter_matches(&none, &includes, &empty), true);
+        assert_eq!(filter_matches(&none, &empty, &excludes), false);
+        assert_eq!(filter_matches(&none, &includes, &excludes), true);
+        let one = create_record(&[("_SYSTEMD_UNIT", "one")]);
+        assert_eq!(filter_matches(&one, &empty, &empty), false);
+        assert_eq!(filter_matches(&one, &includes, &empty), false);
+        assert_eq!(filter_matches(&one, &empty, &excludes), false);
+        assert_eq!(filter_matches(&one, &includes, &excludes), false);
+        let two = create_record(&[("_SYSTEMD_UNIT", "bar")]);
+        assert_eq!(filter_matches(&two, &empty, &empty), false);
+        assert_eq!(filter_matches(&two, &includes, &empty), true);
+        assert_eq!(filter_matches(&two, &empty, &excludes), true);
+        assert_eq!(filter_matches(&two, &includes, &excludes), true);
+        let other = create_record(&[("_SYSTEMD_UNIT", "one"), ("_TRANSPORT", "kernel")]);
+        let transport = create_matches_from(&[("_TRANSPORT", "kernel")]);
+        assert_eq!(filter_matches(&other, &transport, &empty), false);
+        assert_eq!(filter_matches(&other, &includes, &transport), true);
+    }
+
+    #[test]
+    fn unit_matches_are_merged() {
+        let mut matches = create_matches_from(&[("_SYSTEMD_UNIT", "one.service")]);
+        add_unit_matches(&mut matches, &["two".to_owned(), "three.target".to_owned()]);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            matches["_SYSTEMD_UNIT"],
+            vec!["one.service", "two.service", "three.target"]
+                .into_iter()
+                .map(Into::into)
+                .collect::<HashSet<String>>()
+        );
+    }
+
+    #[test]
+    fn finds_duplicate_matches() {
+        let includes = create_matches_from(&[("PRIORITY", "ERR"), ("_TRANSPORT", "kernel")]);
+        let excludes = create_matches_from(&[("PRIORITY", "INFO"), ("_TRANSPORT", "kernel")]);
+        assert_eq!(
+            find_duplicate_match(&includes, &excludes),
+            Some(("_TRANSPORT".to_owned(), "kernel".to_owned()))
+        );
+        assert_eq!(find_duplicate_match(&includes, &Matches::new()), None);
+    }
+
     #[test]
-    fn filter_unit_works_correctly() {
-        let empty: HashSet<String> = vec![].into_iter().collect();
-        let includes: HashSet<String> = vec!["one", "two"].into_iter().map(Into::into).collect();
-        let excludes: HashSet<String> = vec!["foo", "bar"].into_iter().map(Into::into).collect();
-
-        assert_eq!(filter_unit(None, &empty, &empty), false);
-        assert_eq!(filter_unit(None, &includes, &empty), true);
-        assert_eq!(filter_unit(None, &empty, &excludes), false);
-        assert_eq!(filter_unit(None, &includes, &excludes), true);
-        let one = String::from("one");
-        assert_eq!(filter_unit(Some(&one), &empty, &empty), false);
-        assert_eq!(filter_unit(Some(&one), &includes, &empty), false);
-        assert_eq!(filter_unit(Some(&one), &empty, &excludes), false);
-        assert_eq!(filter_unit(Some(&one), &includes, &excludes), false);
-        let two = String::from("bar");
-        assert_eq!(filter_unit(Some(&two), &empty, &empty), false);
-        assert_eq!(filter_unit(Some(&two), &includes, &empty), true);
-        assert_eq!(filter_unit(Some(&two), &empty, &excludes), true);
-        assert_eq!(filter_unit(Some(&two), &includes, &excludes), true);
+    fn journalctl_args_start_point() {
+        let config = JournaldConfig::default();
+        let args = journalctl_args(&config, None);
+        assert!(args.contains(&"--since=2000-01-01".to_owned()));
+        assert!(args.contains(&"--boot".to_owned()));
+
+        let args = journalctl_args(&config, Some("cursor".into()));
+        assert!(args.contains(&"--after-cursor=cursor".to_owned()));
+        assert!(!args.iter().any(|arg| arg.starts_with("--since")));
+
+        let config = JournaldConfig {
+            since_now: Some(true),
+            journal_directory: Some("/var/log/journal/remote".into()),
+            ..Default::default()
+        };
+        let args = journalctl_args(&config, None);
+        assert!(args.contains(&"--since=now".to_owned()));
+        assert!(args.contains(&"--directory=/var/log/journal/remote".to_owned()));
+
+        let config = JournaldConfig {
+            since: Some("-1h".into()),
+            ..Default::default()
+        };
+        let args = journalctl_args(&config, None);
+        assert!(args.contains(&"--since=-1h".to_owned()));
+    }
+
+    fn create_unit_matches(units: &[&str]) -> Matches {
+        let pairs: Vec<_> = units.iter().map(|&unit| ("_SYSTEMD_UNIT", unit)).collect();
+        create_matches_from(&pairs)
+    }
+
+    fn create_matches_from(pairs: &[(&str, &str)]) -> Matches {
+        let mut matches = Matches::new();
+        for (field, value) in pairs {
+            matches
+                .entry((*field).into())
+                .or_insert_with(HashSet::new)
+                .insert((*value).into());
+        }
+        matches
+    }
+
+    fn create_record(pairs: &[(&str, &str)]) -> Record {
+        pairs
+            .iter()
+            .map(|(field, value)| (Atom::from(*field), (*value).into()))
+            .collect()
     }
 
     fn message(event: &Event) -> Value {
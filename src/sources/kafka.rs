@@ -1,7 +1,9 @@
 use crate::{
     config::{log_schema, DataType, GlobalOptions, SourceConfig, SourceDescription},
     event::{Event, Value},
-    internal_events::{KafkaEventFailed, KafkaEventReceived, KafkaOffsetUpdateFailed},
+    internal_events::{
+        KafkaConsumerLagUpdated, KafkaEventFailed, KafkaEventReceived, KafkaOffsetUpdateFailed,
+    },
     kafka::KafkaAuthConfig,
     shutdown::ShutdownSignal,
     Pipeline,
@@ -15,12 +17,14 @@ use futures::{
 use futures01::Sink;
 use rdkafka::{
     config::ClientConfig,
-    consumer::{Consumer, StreamConsumer},
-    message::Message,
+    consumer::{Consumer, ConsumerContext, StreamConsumer},
+    message::{BorrowedMessage, Headers, Message},
+    statistics::Statistics,
+    ClientContext,
 };
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
-use std::{collections::HashMap, sync::Arc};
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Snafu)]
 enum BuildError {
@@ -30,7 +34,22 @@ enum BuildError {
     KafkaSubscribeError { source: rdkafka::error::KafkaError },
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitMode {
+    /// Store the offset of a message as soon as an event has been built from
+    /// it and let librdkafka commit stored offsets every `commit_interval_ms`.
+    #[derivative(Default)]
+    Auto,
+    /// Store the offset of a message only once its event has been accepted
+    /// downstream, and let librdkafka commit stored offsets every
+    /// `commit_interval_ms`.
+    Manual,
+}
+
+#[derive(Clone, Debug, Derivative, Deserialize, Serialize)]
+#[derivative(Default)]
 #[serde(deny_unknown_fields)]
 pub struct KafkaSourceConfig {
     bootstrap_servers: String,
@@ -46,7 +65,13 @@ pub struct KafkaSourceConfig {
     fetch_wait_max_ms: u64,
     #[serde(default = "default_commit_interval_ms")]
     commit_interval_ms: u64,
+    #[serde(default)]
+    commit_mode: CommitMode,
     key_field: Option<String>,
+    topic_key: Option<String>,
+    partition_key: Option<String>,
+    offset_key: Option<String>,
+    headers_key: Option<String>,
     librdkafka_options: Option<HashMap<String, String>>,
     #[serde(flatten)]
     auth: KafkaAuthConfig,
@@ -98,101 +123,173 @@ impl SourceConfig for KafkaSourceConfig {
     }
 }
 
+/// Reports consumer lag from the statistics librdkafka emits every
+/// `statistics.interval.ms`.
+struct KafkaSourceContext;
+
+impl ClientContext for KafkaSourceContext {
+    fn stats(&self, statistics: Statistics) {
+        for (topic_id, topic) in &statistics.topics {
+            for (partition_id, partition) in &topic.partitions {
+                // Partition -1 is librdkafka's internal unassigned partition.
+                if *partition_id < 0 {
+                    continue;
+                }
+                emit!(KafkaConsumerLagUpdated {
+                    topic_id,
+                    partition_id: *partition_id,
+                    lag: partition.consumer_lag,
+                });
+            }
+        }
+    }
+}
+
+impl ConsumerContext for KafkaSourceContext {}
+
+#[derive(Clone)]
+struct Keys {
+    key_field: Option<String>,
+    topic_key: Option<String>,
+    partition_key: Option<String>,
+    offset_key: Option<String>,
+    headers_key: Option<String>,
+}
+
+impl From<&KafkaSourceConfig> for Keys {
+    fn from(config: &KafkaSourceConfig) -> Self {
+        Self {
+            key_field: config.key_field.clone(),
+            topic_key: config.topic_key.clone(),
+            partition_key: config.partition_key.clone(),
+            offset_key: config.offset_key.clone(),
+            headers_key: config.headers_key.clone(),
+        }
+    }
+}
+
 fn kafka_source(
     config: &KafkaSourceConfig,
     shutdown: ShutdownSignal,
     out: Pipeline,
 ) -> crate::Result<super::Source> {
-    let key_field = config.key_field.clone();
-    let consumer = Arc::new(create_consumer(config)?);
+    let keys = Keys::from(config);
+    let commit_mode = config.commit_mode;
+    let consumer = create_consumer(config)?;
 
     let fut = async move {
-        Arc::clone(&consumer)
-            .start()
-            .take_until(shutdown.clone().compat())
-            .then(move |message| {
-                let key_field = key_field.clone();
-                let consumer = Arc::clone(&consumer);
-
-                async move {
-                    match message {
-                        Err(error) => {
-                            emit!(KafkaEventFailed { error });
-                            Err(())
-                        }
-                        Ok(msg) => {
-                            emit!(KafkaEventReceived {
-                                byte_size: msg.payload_len()
-                            });
-
-                            let payload = match msg.payload() {
-                                None => return Err(()), // skip messages with empty payload
-                                Some(payload) => payload,
-                            };
-                            let mut event = Event::new_empty_log();
-                            let log = event.as_mut_log();
-
-                            log.insert(
-                                log_schema().message_key().clone(),
-                                Value::from(Bytes::from(payload.to_owned())),
-                            );
-
-                            // Extract timestamp from kafka message
-                            let timestamp = msg
-                                .timestamp()
-                                .to_millis()
-                                .and_then(|millis| Utc.timestamp_millis_opt(millis).latest())
-                                .unwrap_or_else(Utc::now);
-                            log.insert(log_schema().timestamp_key().clone(), timestamp);
-
-                            // Add source type
-                            log.insert(log_schema().source_type_key(), Bytes::from("kafka"));
-
-                            if let Some(key_field) = &key_field {
-                                match msg.key() {
-                                    None => (),
-                                    Some(key) => {
-                                        log.insert(
-                                            key_field.clone(),
-                                            Value::from(String::from_utf8_lossy(key).to_string()),
-                                        );
-                                    }
-                                }
-                            }
-
-                            consumer.store_offset(&msg).map_err(|error| {
-                                emit!(KafkaOffsetUpdateFailed { error });
-                            })?;
-
-                            Ok(event)
-                        }
-                    }
+        let mut out = out;
+        let mut messages = consumer.start().take_until(shutdown.clone().compat());
+
+        while let Some(message) = messages.next().await {
+            let msg = match message {
+                Err(error) => {
+                    emit!(KafkaEventFailed { error });
+                    continue;
                 }
-            })
-            // Try `forward` after removing old futures.
-            // Error: implementation of `futures_core::stream::Stream` is not general enough
-            // .forward(
-            //     out.sink_compat()
-            //         .sink_map_err(|e| error!(message = "Error sending to sink", error = ?e)),
-            // )
-            .for_each(|item| {
-                let out = out.clone();
-                async move {
-                    if let Ok(item) = item {
-                        if let Err(e) = out.send(item).compat().await {
-                            error!(message = "Error sending to sink", error = ?e);
-                        }
-                    }
+                Ok(msg) => msg,
+            };
+
+            emit!(KafkaEventReceived {
+                byte_size: msg.payload_len()
+            });
+
+            let event = match message_to_event(&msg, &keys) {
+                None => continue, // skip messages with empty payload
+                Some(event) => event,
+            };
+
+            if commit_mode == CommitMode::Auto {
+                store_offset(&consumer, &msg);
+            }
+
+            out = match out.send(event).compat().await {
+                Ok(out) => out,
+                Err(error) => {
+                    error!(message = "Error sending to sink", error = ?error);
+                    return Err(());
                 }
-            })
-            .await;
+            };
+
+            if commit_mode == CommitMode::Manual {
+                store_offset(&consumer, &msg);
+            }
+        }
+
         Ok(())
     };
 
     Ok(Box::new(Compat::new(fut.boxed())))
 }
 
-fn create_consumer(config: &KafkaSourceConfig) -> crate::Result<StreamConsumer> {
+/// Stores the offset of `msg`, to be committed with the next automatic
+/// commit.
+fn store_offset(consumer: &StreamConsumer<KafkaSourceContext>, msg: &BorrowedMessage<'_>) {
+    if let Err(error) = consumer.store_offset(msg) {
+        emit!(KafkaOffsetUpdateFailed { error });
+    }
+}
+
+fn message_to_event(msg: &BorrowedMessage<'_>, keys: &Keys) -> Option<Event> {
+    let payload = msg.payload()?;
+
+    let mut event = Event::new_empty_log();
+    let log = event.as_mut_log();
+
+    log.insert(
+        log_schema().message_key().clone(),
+        Value::from(Bytes::from(payload.to_owned())),
+    );
+
+    // Extract timestamp from kafka message
+    let timestamp = msg
+        .timestamp()
+        .to_millis()
+        .and_then(|millis| Utc.timestamp_millis_opt(millis).latest())
+        .unwrap_or_else(Utc::now);
+    log.insert(log_schema().timestamp_key().clone(), timestamp);
+
+    // Add source type
+    log.insert(log_schema().source_type_key(), Bytes::from("kafka"));
+
+    if let Some(key_field) = &keys.key_field {
+        if let Some(key) = msg.key() {
+            log.insert(
+                key_field.clone(),
+                Value::from(String::from_utf8_lossy(key).to_string()),
+            );
+        }
+    }
+
+    // Record where the message came from
+    if let Some(topic_key) = &keys.topic_key {
+        log.insert(topic_key.clone(), Value::from(msg.topic().to_owned()));
+    }
+    if let Some(partition_key) = &keys.partition_key {
+        log.insert(partition_key.clone(), Value::from(msg.partition()));
+    }
+    if let Some(offset_key) = &keys.offset_key {
+        log.insert(offset_key.clone(), Value::from(msg.offset()));
+    }
+
+    if let Some(headers_key) = &keys.headers_key {
+        let mut headers_map = BTreeMap::new();
+        if let Some(headers) = msg.headers() {
+            for i in 0..headers.count() {
+                if let Some((name, value)) = headers.get(i) {
+                    headers_map.insert(name.to_owned(), Value::from(Bytes::from(value.to_owned())));
+                }
+            }
+        }
+        log.insert(headers_key.clone(), Value::from(headers_map));
+    }
+
+    Some(event)
+}
+
+fn create_consumer(
+    config: &KafkaSourceConfig,
+) -> crate::Result<StreamConsumer<KafkaSourceContext>> {
     let mut client_config = ClientConfig::new();
     client_config
         .set("group.id", &config.group_id)
@@ -208,6 +305,7 @@ fn create_consumer(config: &KafkaSourceConfig) -> crate::Result<StreamConsumer>
             &config.commit_interval_ms.to_string(),
         )
         .set("enable.auto.offset.store", "false")
+        .set("statistics.interval.ms", "1000")
         .set("client.id", "vector");
 
     config.auth.apply(&mut client_config)?;
@@ -218,7 +316,9 @@ fn create_consumer(config: &KafkaSourceConfig) -> crate::Result<StreamConsumer>
         }
     }
 
-    let consumer: StreamConsumer = client_config.create().context(KafkaCreateError)?;
+    let consumer: StreamConsumer<KafkaSourceContext> = client_config
+        .create_with_context(KafkaSourceContext)
+        .context(KafkaCreateError)?;
     let topics: Vec<&str> = config.topics.iter().map(|s| s.as_str()).collect();
     consumer.subscribe(&topics).context(KafkaSubscribeError)?;
 
@@ -227,7 +327,7 @@ fn create_consumer(config: &KafkaSourceConfig) -> crate::Result<StreamConsumer>
 
 #[cfg(test)]
 mod test {
-    use super::{kafka_source, KafkaSourceConfig};
+    use super::{kafka_source, CommitMode, KafkaSourceConfig};
     use crate::{shutdown::ShutdownSignal, Pipeline};
 
     fn make_config() -> KafkaSourceConfig {
@@ -259,6 +359,33 @@ mod test {
         };
         assert!(kafka_source(&config, ShutdownSignal::noop(), Pipeline::new_test().0).is_err());
     }
+
+    #[test]
+    fn kafka_source_parse_config_defaults() {
+        let config: KafkaSourceConfig = toml::from_str(
+            r#"
+            bootstrap_servers = "localhost:9092"
+            topics = ["my-topic"]
+            group_id = "group-id"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.commit_mode, CommitMode::Auto);
+        assert_eq!(config.topic_key, None);
+        assert_eq!(config.partition_key, None);
+        assert_eq!(config.offset_key, None);
+        assert_eq!(config.headers_key, None);
+    }
+
+    #[test]
+    fn kafka_source_create_manual_commit_ok() {
+        let config = KafkaSourceConfig {
+            commit_mode: CommitMode::Manual,
+            ..make_config()
+        };
+        assert!(kafka_source(&config, ShutdownSignal::noop(), Pipeline::new_test().0).is_ok());
+    }
 }
 
 #[cfg(feature = "kafka-integration-tests")]
@@ -274,6 +401,7 @@ mod integration_test {
     use futures::compat::Future01CompatExt;
     use rdkafka::{
         config::ClientConfig,
+        message::OwnedHeaders,
         producer::{FutureProducer, FutureRecord},
         util::Timeout,
     };
@@ -292,7 +420,8 @@ mod integration_test {
         let record = FutureRecord::to(&topic)
             .payload(text)
             .key(key)
-            .timestamp(timestamp);
+            .timestamp(timestamp)
+            .headers(OwnedHeaders::new().add("has_header", "true"));
 
         if let Err(err) = producer.send(record, Timeout::Never).await {
             panic!("Cannot send event to Kafka: {:?}", err);
@@ -302,6 +431,16 @@ mod integration_test {
     #[ignore]
     #[tokio::test]
     async fn kafka_source_consume_event() {
+        consume_event(CommitMode::Auto).await;
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn kafka_source_consume_event_manual_commit() {
+        consume_event(CommitMode::Manual).await;
+    }
+
+    async fn consume_event(commit_mode: CommitMode) {
         let topic = format!("test-topic-{}", random_string(10));
         println!("Test topic name: {}", topic);
         let group_id = format!("test-group-{}", random_string(10));
@@ -317,6 +456,11 @@ mod integration_test {
             key_field: Some("message_key".to_string()),
             socket_timeout_ms: 60000,
             fetch_wait_max_ms: 100,
+            commit_mode,
+            topic_key: Some("topic".to_string()),
+            partition_key: Some("partition".to_string()),
+            offset_key: Some("offset".to_string()),
+            headers_key: Some("headers".to_string()),
             ..Default::default()
         };
 
@@ -351,5 +495,12 @@ mod integration_test {
             "kafka".into()
         );
         assert_eq!(events[0].as_log()[log_schema().timestamp_key()], now.into());
+        assert_eq!(events[0].as_log()[&Atom::from("topic")], topic.into());
+        assert_eq!(events[0].as_log()[&Atom::from("partition")], 0.into());
+        assert_eq!(events[0].as_log()[&Atom::from("offset")], 0.into());
+        assert_eq!(
+            events[0].as_log()[&Atom::from("headers.has_header")],
+            "true".into()
+        );
     }
 }
@@ -0,0 +1,649 @@
+//! This mod implements `kubernetes_events` source.
+//! The source watches the Kubernetes `Event` objects via the API and emits
+//! the new and updated ones as log events.
+
+#![deny(missing_docs)]
+
+use crate::{
+    config::{log_schema, DataType, GlobalOptions, SourceConfig, SourceDescription},
+    dns::Resolver,
+    event::{Event, LogEvent},
+    internal_events::{
+        KubernetesEventsCheckpointFailed, KubernetesEventsEventReceived,
+        KubernetesEventsEventSkipped,
+    },
+    kubernetes::{self as k8s, watch_request_builder::WatchRequestBuilder},
+    shutdown::ShutdownSignal,
+    sources, Pipeline,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::Utc;
+use futures::{
+    compat::{Compat01As03Sink, Future01CompatExt},
+    future::{select, BoxFuture, Either, FutureExt, TryFutureExt},
+    pin_mut,
+    sink::{Sink, SinkExt},
+};
+use k8s_openapi::{api::core::v1::Event as KubeEvent, http::Request, RequestError, WatchOptional};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::{delay_until, Instant};
+
+/// The name of the file the last seen resource version is persisted to.
+const CHECKPOINT_FILENAME: &str = "checkpoint.txt";
+
+const COMPONENT_NAME: &str = "kubernetes_events";
+
+/// How often the checkpoint is written at most.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Configuration for the `kubernetes_events` source.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct Config {
+    /// Only watch the `Event`s in this namespace.
+    /// All the namespaces are watched if unset.
+    namespace: Option<String>,
+
+    /// A field selector to narrow down the watched `Event`s.
+    field_selector: Option<String>,
+
+    /// A label selector to narrow down the watched `Event`s.
+    label_selector: Option<String>,
+
+    /// The directory used to persist the last seen resource version.
+    data_dir: Option<PathBuf>,
+}
+
+inventory::submit! {
+    SourceDescription::new_without_default::<Config>(COMPONENT_NAME)
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "kubernetes_events")]
+impl SourceConfig for Config {
+    async fn build(
+        &self,
+        name: &str,
+        globals: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: Pipeline,
+    ) -> crate::Result<sources::Source> {
+        let data_dir = globals.resolve_and_make_data_subdir(self.data_dir.as_ref(), name)?;
+
+        let k8s_config = k8s::client::config::Config::in_cluster()?;
+        let client = k8s::client::Client::new(k8s_config, Resolver)?;
+
+        let fut = run(
+            self.clone(),
+            client,
+            data_dir,
+            Compat01As03Sink::new(out),
+            shutdown,
+        );
+        Ok(Box::new(fut.boxed().compat()))
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn source_type(&self) -> &'static str {
+        COMPONENT_NAME
+    }
+}
+
+async fn run<O>(
+    config: Config,
+    client: k8s::client::Client,
+    data_dir: PathBuf,
+    out: O,
+    shutdown: ShutdownSignal,
+) -> Result<(), ()>
+where
+    O: Sink<Event> + Send + Unpin,
+    <O as Sink<Event>>::Error: fmt::Debug,
+{
+    let checkpointer = Arc::new(Checkpointer::new(data_dir.join(CHECKPOINT_FILENAME)));
+    let resource_version = checkpointer.get().await.map_err(|error| {
+        error!(message = "Unable to read checkpoint.", %error);
+    })?;
+
+    let request_builder = match config.namespace {
+        Some(namespace) => RequestBuilder::Namespaced(namespace),
+        None => RequestBuilder::AllNamespaces,
+    };
+    let watcher = k8s::api_watcher::ApiWatcher::new(client, request_builder);
+    let watcher = k8s::instrumenting_watcher::InstrumentingWatcher::new(watcher);
+    let state_writer =
+        EventsWriter::new(out, Arc::clone(&checkpointer), resource_version.as_deref());
+
+    let mut reflector = k8s::reflector::Reflector::new(
+        watcher,
+        state_writer,
+        config.field_selector,
+        config.label_selector,
+        Duration::from_secs(1),
+    );
+    if let Some(resource_version) = resource_version {
+        info!(message = "Resuming from checkpoint.", %resource_version);
+        reflector.resume_from(resource_version);
+    }
+
+    let reflector_process = reflector.run();
+    pin_mut!(reflector_process);
+    let result = match select(reflector_process, shutdown.compat()).await {
+        Either::Left((result, _)) => match result {
+            Ok(_infallible) => unreachable!("ok value is infallible, thus impossible to reach"),
+            Err(error) => {
+                error!(message = "Reflector process exited with an error.", ?error);
+                Err(())
+            }
+        },
+        Either::Right(_) => Ok(()),
+    };
+
+    // Persist what was sent since the last periodic checkpoint.
+    if let Err(error) = checkpointer.flush().await {
+        emit!(KubernetesEventsCheckpointFailed { error });
+    }
+
+    result
+}
+
+/// Builds the watch requests for either all or a single namespace.
+enum RequestBuilder {
+    AllNamespaces,
+    Namespaced(String),
+}
+
+impl WatchRequestBuilder for RequestBuilder {
+    type Object = KubeEvent;
+
+    fn build<'a>(
+        &self,
+        watch_optional: WatchOptional<'a>,
+    ) -> Result<Request<Vec<u8>>, RequestError> {
+        let (request, _) = match self {
+            RequestBuilder::AllNamespaces => {
+                KubeEvent::watch_event_for_all_namespaces(watch_optional)?
+            }
+            RequestBuilder::Namespaced(namespace) => {
+                KubeEvent::watch_namespaced_event(namespace, watch_optional)?
+            }
+        };
+        Ok(request)
+    }
+}
+
+/// A state writer that emits the reflected `Event`s downstream instead of
+/// keeping them, and checkpoints the highest resource version sent.
+///
+/// When the watch can't be resumed, for instance because the checkpointed
+/// resource version has expired, the watcher relists all the `Event`s.
+/// Those at or below the resource version sent before the restart or the
+/// resync were already emitted, and are skipped.
+struct EventsWriter<O> {
+    out: O,
+    checkpointer: Arc<Checkpointer>,
+    /// `Event`s at or below this resource version were already sent.
+    skip_up_to: Option<u64>,
+    /// The highest resource version sent so far.
+    latest: Option<u64>,
+    next_checkpoint: Instant,
+}
+
+impl<O> EventsWriter<O>
+where
+    O: Sink<Event> + Send + Unpin,
+    <O as Sink<Event>>::Error: fmt::Debug,
+{
+    fn new(out: O, checkpointer: Arc<Checkpointer>, checkpoint: Option<&str>) -> Self {
+        let skip_up_to = checkpoint.and_then(parse_resource_version);
+        Self {
+            out,
+            checkpointer,
+            skip_up_to,
+            latest: skip_up_to,
+            next_checkpoint: Instant::now(),
+        }
+    }
+
+    async fn emit(&mut self, kube_event: KubeEvent) {
+        // Resource versions are opaque strings to the API clients, but are
+        // numeric in practice. If one isn't, the `Event` is always sent.
+        let resource_version = kube_event.metadata.resource_version.clone();
+        let version = resource_version.as_deref().and_then(parse_resource_version);
+        if let (Some(version), Some(skip_up_to)) = (version, self.skip_up_to) {
+            if version <= skip_up_to {
+                emit!(KubernetesEventsEventSkipped);
+                return;
+            }
+        }
+
+        let event = create_event(kube_event);
+
+        emit!(KubernetesEventsEventReceived);
+
+        if let Err(error) = self.out.send(event).await {
+            error!(message = "Error sending event.", ?error);
+            return;
+        }
+
+        let checkpoint = match version {
+            Some(version) => {
+                let latest = self.latest.map_or(version, |latest| latest.max(version));
+                self.latest = Some(latest);
+                Some(latest.to_string())
+            }
+            None => resource_version,
+        };
+        if let Some(checkpoint) = checkpoint {
+            self.checkpointer.update(checkpoint);
+        }
+    }
+}
+
+fn parse_resource_version(resource_version: &str) -> Option<u64> {
+    resource_version.parse().ok()
+}
+
+#[async_trait]
+impl<O> k8s::state::Write for EventsWriter<O>
+where
+    O: Sink<Event> + Send + Unpin,
+    <O as Sink<Event>>::Error: fmt::Debug,
+{
+    type Item = KubeEvent;
+
+    async fn add(&mut self, item: Self::Item) {
+        self.emit(item).await;
+    }
+
+    async fn update(&mut self, item: Self::Item) {
+        self.emit(item).await;
+    }
+
+    async fn delete(&mut self, _item: Self::Item) {
+        // `Event`s are deleted when they expire, there's nothing new to
+        // report.
+    }
+
+    async fn resync(&mut self) {
+        // The relisted `Event`s come in no particular order, so only those
+        // sent before the resync can be skipped.
+        self.skip_up_to = self.latest;
+    }
+}
+
+#[async_trait]
+impl<O> k8s::state::MaintainedWrite for EventsWriter<O>
+where
+    O: Sink<Event> + Send + Unpin,
+    <O as Sink<Event>>::Error: fmt::Debug,
+{
+    fn maintenance_request(&mut self) -> Option<BoxFuture<'_, ()>> {
+        if self.checkpointer.is_dirty() {
+            Some(Box::pin(delay_until(self.next_checkpoint)))
+        } else {
+            None
+        }
+    }
+
+    async fn perform_maintenance(&mut self) {
+        self.next_checkpoint = Instant::now() + CHECKPOINT_INTERVAL;
+        if let Err(error) = self.checkpointer.flush().await {
+            emit!(KubernetesEventsCheckpointFailed { error });
+        }
+    }
+}
+
+/// Persists the resource version of the last sent `Event`, so that the
+/// watch can be resumed without duplicates after a restart.
+///
+/// Updates are only kept in memory until they are flushed, which the
+/// writer does at most every `CHECKPOINT_INTERVAL` and on shutdown.
+struct Checkpointer {
+    filename: PathBuf,
+    pending: Mutex<Option<String>>,
+}
+
+impl Checkpointer {
+    fn new(filename: PathBuf) -> Self {
+        Self {
+            filename,
+            pending: Mutex::new(None),
+        }
+    }
+
+    fn update(&self, resource_version: String) {
+        *self.pending.lock().unwrap() = Some(resource_version);
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.pending.lock().unwrap().is_some()
+    }
+
+    async fn flush(&self) -> Result<(), io::Error> {
+        let pending = self.pending.lock().unwrap().take();
+        match pending {
+            Some(resource_version) => self.set(&resource_version).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn set(&self, resource_version: &str) -> Result<(), io::Error> {
+        // Write to a temporary file first to never leave a partially
+        // written checkpoint behind.
+        let tmp_filename = self.filename.with_extension("tmp");
+        tokio::fs::write(&tmp_filename, format!("{}\n", resource_version)).await?;
+        tokio::fs::rename(&tmp_filename, &self.filename).await
+    }
+
+    async fn get(&self) -> Result<Option<String>, io::Error> {
+        match tokio::fs::read_to_string(&self.filename).await {
+            Ok(contents) => Ok(contents
+                .lines()
+                .next()
+                .filter(|line| !line.is_empty())
+                .map(Into::into)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+fn create_event(kube_event: KubeEvent) -> Event {
+    let mut log = LogEvent::default();
+
+    log.insert(
+        log_schema().message_key().clone(),
+        kube_event.message.unwrap_or_default(),
+    );
+
+    // Prefer the time the event was last observed.
+    let timestamp = kube_event
+        .last_timestamp
+        .as_ref()
+        .map(|time| time.0)
+        .or_else(|| kube_event.event_time.as_ref().map(|time| time.0))
+        .or_else(|| kube_event.first_timestamp.as_ref().map(|time| time.0))
+        .unwrap_or_else(Utc::now);
+    log.insert(log_schema().timestamp_key().clone(), timestamp);
+
+    log.insert(log_schema().source_type_key(), Bytes::from(COMPONENT_NAME));
+
+    let metadata = kube_event.metadata;
+    let involved_object = kube_event.involved_object;
+    for (key, value) in [
+        ("name", metadata.name),
+        ("namespace", metadata.namespace),
+        ("uid", metadata.uid),
+        ("reason", kube_event.reason),
+        ("type", kube_event.type_),
+        ("involved_object.kind", involved_object.kind),
+        ("involved_object.name", involved_object.name),
+        ("involved_object.namespace", involved_object.namespace),
+        ("involved_object.uid", involved_object.uid),
+        ("involved_object.api_version", involved_object.api_version),
+        ("involved_object.field_path", involved_object.field_path),
+        (
+            "source.component",
+            kube_event
+                .source
+                .as_ref()
+                .and_then(|source| source.component.clone()),
+        ),
+        (
+            "source.host",
+            kube_event
+                .source
+                .as_ref()
+                .and_then(|source| source.host.clone()),
+        ),
+    ]
+    .iter()
+    .cloned()
+    {
+        if let Some(value) = value {
+            log.insert(key, value);
+        }
+    }
+
+    if let Some(count) = kube_event.count {
+        log.insert("count", count);
+    }
+    if let Some(first_timestamp) = kube_event.first_timestamp {
+        log.insert("first_timestamp", first_timestamp.0);
+    }
+    if let Some(last_timestamp) = kube_event.last_timestamp {
+        log.insert("last_timestamp", last_timestamp.0);
+    }
+
+    Event::Log(log)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        kubernetes::{
+            mock_watcher::{self, MockWatcher},
+            reflector::Reflector,
+        },
+        test_util::trace_init,
+    };
+    use chrono::TimeZone;
+    use futures::{channel::mpsc, StreamExt};
+    use k8s_openapi::{
+        api::core::v1::{EventSource, ObjectReference},
+        apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time, WatchEvent},
+        WatchResponse,
+    };
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    fn make_event(uid: &str, resource_version: &str, count: i32) -> KubeEvent {
+        KubeEvent {
+            metadata: ObjectMeta {
+                name: Some("sandbox0-name.1234".to_owned()),
+                namespace: Some("sandbox0-ns".to_owned()),
+                uid: Some(uid.to_owned()),
+                resource_version: Some(resource_version.to_owned()),
+                ..ObjectMeta::default()
+            },
+            involved_object: ObjectReference {
+                kind: Some("Pod".to_owned()),
+                name: Some("sandbox0-name".to_owned()),
+                namespace: Some("sandbox0-ns".to_owned()),
+                ..ObjectReference::default()
+            },
+            message: Some("Pulling image \"busybox\"".to_owned()),
+            reason: Some("Pulling".to_owned()),
+            type_: Some("Normal".to_owned()),
+            count: Some(count),
+            first_timestamp: Some(Time(Utc.ymd(2020, 9, 1).and_hms(10, 0, 0))),
+            last_timestamp: Some(Time(Utc.ymd(2020, 9, 1).and_hms(10, 5, 0))),
+            source: Some(EventSource {
+                component: Some("kubelet".to_owned()),
+                host: Some("sandbox0-node".to_owned()),
+            }),
+            ..KubeEvent::default()
+        }
+    }
+
+    #[test]
+    fn create_event_test() {
+        let event = create_event(make_event("uid0", "10", 2));
+        let log = event.as_log();
+
+        assert_eq!(
+            log[&log_schema().message_key()],
+            "Pulling image \"busybox\"".into()
+        );
+        assert_eq!(
+            log[&log_schema().timestamp_key()],
+            Utc.ymd(2020, 9, 1).and_hms(10, 5, 0).into()
+        );
+        assert_eq!(log[&log_schema().source_type_key()], COMPONENT_NAME.into());
+        assert_eq!(log[&"name".into()], "sandbox0-name.1234".into());
+        assert_eq!(log[&"namespace".into()], "sandbox0-ns".into());
+        assert_eq!(log[&"reason".into()], "Pulling".into());
+        assert_eq!(log[&"type".into()], "Normal".into());
+        assert_eq!(log[&"count".into()], 2.into());
+        assert_eq!(log[&"involved_object.kind".into()], "Pod".into());
+        assert_eq!(log[&"involved_object.name".into()], "sandbox0-name".into());
+        assert_eq!(log[&"source.component".into()], "kubelet".into());
+        assert_eq!(
+            log[&"first_timestamp".into()],
+            Utc.ymd(2020, 9, 1).and_hms(10, 0, 0).into()
+        );
+        assert!(log.get(&"involved_object.uid".into()).is_none());
+    }
+
+    #[tokio::test]
+    async fn checkpointer_test() {
+        let tempdir = tempdir().unwrap();
+        let checkpointer = Checkpointer::new(tempdir.path().join(CHECKPOINT_FILENAME));
+
+        assert_eq!(checkpointer.get().await.unwrap(), None);
+
+        checkpointer.set("10").await.unwrap();
+        assert_eq!(checkpointer.get().await.unwrap(), Some("10".to_owned()));
+
+        checkpointer.set("20").await.unwrap();
+        assert_eq!(checkpointer.get().await.unwrap(), Some("20".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn reflect_events_test() {
+        trace_init();
+
+        let tempdir = tempdir().unwrap();
+        let checkpoint = tempdir.path().join(CHECKPOINT_FILENAME);
+
+        // Prepare the writer.
+        let (out, mut out_rx) = mpsc::channel(10);
+        let checkpointer = Arc::new(Checkpointer::new(checkpoint.clone()));
+        let state_writer = EventsWriter::new(out, Arc::clone(&checkpointer), Some("5"));
+
+        // Prepare watcher.
+        let (watcher_events_tx, mut watcher_events_rx) = mpsc::channel(0);
+        let (mut watcher_invocations_tx, watcher_invocations_rx) = mpsc::channel(0);
+        let watcher = MockWatcher::<KubeEvent>::new(watcher_events_tx, watcher_invocations_rx);
+
+        // Prepare reflector, resuming from an earlier checkpoint.
+        let mut reflector =
+            Reflector::new(watcher, state_writer, None, None, Duration::from_secs(1));
+        reflector.resume_from("5".to_owned());
+
+        // Run test logic.
+        let logic = tokio::spawn(async move {
+            let watch_optional = match watcher_events_rx.next().await.unwrap() {
+                mock_watcher::ScenarioEvent::Invocation(val) => val,
+                _ => panic!("Unexpected event from watcher mock"),
+            };
+            assert_eq!(watch_optional.resource_version, Some("5".to_owned()));
+
+            let (mut watch_stream_tx, watch_stream_rx) = mpsc::channel(0);
+            watcher_invocations_tx
+                .send(mock_watcher::ScenarioActionInvocation::Ok(watch_stream_rx))
+                .await
+                .unwrap();
+
+            for response in vec![
+                WatchEvent::Added(make_event("uid0", "10", 1)),
+                WatchEvent::Modified(make_event("uid0", "15", 2)),
+                WatchEvent::Deleted(make_event("uid0", "20", 2)),
+            ] {
+                assert_eq!(
+                    watcher_events_rx.next().await.unwrap(),
+                    mock_watcher::ScenarioEvent::Stream
+                );
+                watch_stream_tx
+                    .send(mock_watcher::ScenarioActionStream::Ok(WatchResponse::Ok(
+                        response,
+                    )))
+                    .await
+                    .unwrap();
+            }
+
+            // We're done with the test, terminate the stream and the
+            // reflector.
+            assert_eq!(
+                watcher_events_rx.next().await.unwrap(),
+                mock_watcher::ScenarioEvent::Stream
+            );
+            watch_stream_tx
+                .send(mock_watcher::ScenarioActionStream::Done)
+                .await
+                .unwrap();
+            assert!(matches!(
+                watcher_events_rx.next().await.unwrap(),
+                mock_watcher::ScenarioEvent::Invocation(_)
+            ));
+            watcher_invocations_tx
+                .send(mock_watcher::ScenarioActionInvocation::ErrOther)
+                .await
+                .unwrap();
+        });
+
+        let result = reflector.run().await;
+        logic.await.unwrap();
+        result.unwrap_err();
+        drop(reflector);
+        checkpointer.flush().await.unwrap();
+
+        // The added and the modified events are emitted, the deletion is not.
+        let events = out_rx.collect::<Vec<_>>().await;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].as_log()[&"count".into()], 1.into());
+        assert_eq!(events[1].as_log()[&"count".into()], 2.into());
+
+        // The last emitted event is checkpointed.
+        assert_eq!(
+            Checkpointer::new(checkpoint).get().await.unwrap(),
+            Some("15".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_already_sent_events_test() {
+        let tempdir = tempdir().unwrap();
+        let checkpointer = Arc::new(Checkpointer::new(tempdir.path().join(CHECKPOINT_FILENAME)));
+
+        let (out, out_rx) = mpsc::channel(10);
+        let mut writer = EventsWriter::new(out, Arc::clone(&checkpointer), Some("10"));
+
+        // Relisted after a restart with an expired checkpoint.
+        k8s::state::Write::add(&mut writer, make_event("uid0", "5", 1)).await;
+        k8s::state::Write::add(&mut writer, make_event("uid1", "14", 1)).await;
+        k8s::state::Write::add(&mut writer, make_event("uid2", "10", 1)).await;
+        k8s::state::Write::add(&mut writer, make_event("uid3", "12", 1)).await;
+
+        // Relisted again after a resync.
+        k8s::state::Write::resync(&mut writer).await;
+        k8s::state::Write::add(&mut writer, make_event("uid1", "14", 1)).await;
+        k8s::state::Write::add(&mut writer, make_event("uid3", "12", 1)).await;
+        k8s::state::Write::update(&mut writer, make_event("uid3", "16", 2)).await;
+
+        drop(writer);
+        let events = out_rx.collect::<Vec<_>>().await;
+        let uids = events
+            .iter()
+            .map(|event| event.as_log()[&"uid".into()].to_string_lossy())
+            .collect::<Vec<_>>();
+        assert_eq!(uids, vec!["uid1", "uid3", "uid3"]);
+
+        // Nothing is written until flushed, then the highest version is.
+        assert_eq!(checkpointer.get().await.unwrap(), None);
+        checkpointer.flush().await.unwrap();
+        assert_eq!(checkpointer.get().await.unwrap(), Some("16".to_owned()));
+    }
+}
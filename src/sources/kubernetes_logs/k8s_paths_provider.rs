@@ -6,19 +6,81 @@ use super::path_helpers::build_pod_logs_directory;
 use crate::kubernetes as k8s;
 use evmap::ReadHandle;
 use file_source::paths_provider::PathsProvider;
-use k8s_openapi::api::core::v1::Pod;
-use std::path::PathBuf;
+use glob::Pattern;
+use k8s_openapi::api::core::v1::{Namespace, Pod};
+use std::path::{Path, PathBuf};
 
 /// A paths provider implementation that uses the state obtained from the
 /// the k8s API.
 pub struct K8sPathsProvider {
     pods_state_reader: ReadHandle<String, k8s::state::evmap::Value<Pod>>,
+    namespaces_state_reader: Option<ReadHandle<String, k8s::state::evmap::Value<Namespace>>>,
+    container_filter: ContainerFilter,
 }
 
 impl K8sPathsProvider {
     /// Create a new [`K8sPathsProvider`].
-    pub fn new(pods_state_reader: ReadHandle<String, k8s::state::evmap::Value<Pod>>) -> Self {
-        Self { pods_state_reader }
+    ///
+    /// If `namespaces_state_reader` is passed, only the logs of the pods from
+    /// the namespaces present in that state are provided.
+    pub fn new(
+        pods_state_reader: ReadHandle<String, k8s::state::evmap::Value<Pod>>,
+        namespaces_state_reader: Option<ReadHandle<String, k8s::state::evmap::Value<Namespace>>>,
+        container_filter: ContainerFilter,
+    ) -> Self {
+        Self {
+            pods_state_reader,
+            namespaces_state_reader,
+            container_filter,
+        }
+    }
+
+    fn is_namespace_selected(&self, pod: &Pod) -> bool {
+        let namespaces_state_reader = match self.namespaces_state_reader {
+            Some(ref reader) => reader,
+            None => return true,
+        };
+        match pod.metadata.namespace {
+            Some(ref namespace) => namespaces_state_reader.contains_key(namespace.as_str()),
+            None => false,
+        }
+    }
+}
+
+/// Include and exclude globs matched against the container names.
+#[derive(Debug, Default)]
+pub struct ContainerFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl ContainerFilter {
+    /// Create a new [`ContainerFilter`].
+    /// An empty `include` list includes all the containers.
+    pub fn new(include: Vec<Pattern>, exclude: Vec<Pattern>) -> Self {
+        Self { include, exclude }
+    }
+
+    fn matches(&self, container_name: &str) -> bool {
+        (self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| pattern.matches(container_name)))
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| pattern.matches(container_name))
+    }
+
+    /// Check whether the log file at `path` belongs to a selected container.
+    /// The paths look like `<pod_logs_dir>/<container_name>/<n>.log`.
+    fn matches_path(&self, path: &Path) -> bool {
+        path.parent()
+            .and_then(Path::file_name)
+            .and_then(|name| name.to_str())
+            .map(|container_name| self.matches(container_name))
+            .unwrap_or(false)
     }
 }
 
@@ -41,13 +103,19 @@ impl PathsProvider for K8sPathsProvider {
 
         read_ref
             .into_iter()
-            .flat_map(|(uid, values)| {
+            .filter_map(|(uid, values)| {
                 let pod = values
                     .get_one()
                     .expect("we are supposed to be working with single-item values only");
+                if !self.is_namespace_selected(pod) {
+                    trace!(message = "Skipping pod from an unselected namespace", ?uid);
+                    return None;
+                }
                 trace!(message = "Providing log paths for pod", ?uid);
-                list_pod_log_paths(real_glob, pod)
+                Some(list_pod_log_paths(real_glob, pod))
             })
+            .flatten()
+            .filter(|path| self.container_filter.matches_path(path))
             .collect()
     }
 }
@@ -93,9 +161,10 @@ fn real_glob(pattern: &str) -> impl Iterator<Item = PathBuf> {
 
 #[cfg(test)]
 mod tests {
-    use super::{extract_pod_logs_directory, list_pod_log_paths};
+    use super::{extract_pod_logs_directory, list_pod_log_paths, ContainerFilter};
+    use glob::Pattern;
     use k8s_openapi::{api::core::v1::Pod, apimachinery::pkg::apis::meta::v1::ObjectMeta};
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn test_extract_pod_logs_directory() {
@@ -226,4 +295,60 @@ mod tests {
             assert_eq!(actual_paths, expected_paths)
         }
     }
+
+    #[test]
+    fn test_container_filter() {
+        let patterns = |patterns: &[&str]| {
+            patterns
+                .iter()
+                .map(|pattern| Pattern::new(pattern).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let cases = vec![
+            // No filters, everything is included.
+            (ContainerFilter::default(), "container1", true),
+            // Include only.
+            (
+                ContainerFilter::new(patterns(&["app-*"]), vec![]),
+                "app-main",
+                true,
+            ),
+            (
+                ContainerFilter::new(patterns(&["app-*"]), vec![]),
+                "istio-proxy",
+                false,
+            ),
+            // Exclude only.
+            (
+                ContainerFilter::new(vec![], patterns(&["istio-*"])),
+                "istio-proxy",
+                false,
+            ),
+            (
+                ContainerFilter::new(vec![], patterns(&["istio-*"])),
+                "app-main",
+                true,
+            ),
+            // Exclude takes precedence over include.
+            (
+                ContainerFilter::new(patterns(&["*"]), patterns(&["istio-*"])),
+                "istio-proxy",
+                false,
+            ),
+        ];
+
+        for (filter, container_name, expected) in cases {
+            let path = format!(
+                "/var/log/pods/sandbox0-ns_sandbox0-name_sandbox0-uid/{}/0.log",
+                container_name
+            );
+            assert_eq!(
+                filter.matches_path(Path::new(&path)),
+                expected,
+                "{}",
+                container_name
+            );
+        }
+    }
 }
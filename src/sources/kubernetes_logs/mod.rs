@@ -22,7 +22,7 @@ use crate::{
 use bytes::Bytes;
 use file_source::{FileServer, FileServerShutdown, Fingerprinter};
 use futures::{future::FutureExt, sink::Sink, stream::StreamExt};
-use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::api::core::v1::{Namespace, Pod};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::time::Duration;
@@ -36,7 +36,7 @@ mod pod_metadata_annotator;
 mod transform_utils;
 mod util;
 
-use k8s_paths_provider::K8sPathsProvider;
+use k8s_paths_provider::{ContainerFilter, K8sPathsProvider};
 use lifecycle::Lifecycle;
 use pod_metadata_annotator::PodMetadataAnnotator;
 
@@ -62,6 +62,26 @@ pub struct Config {
 
     /// Specifies the field names for metadata annotation.
     annotation_fields: pod_metadata_annotator::FieldsSpec,
+
+    /// A label selector to further narrow down the `Pod`s to collect the
+    /// logs from, in addition to the built-in exclusion label.
+    extra_label_selector: String,
+
+    /// A field selector to further narrow down the `Pod`s to collect the
+    /// logs from, in addition to the built-in `Node` selector.
+    extra_field_selector: String,
+
+    /// A label selector for the `Namespace`s to collect the `Pod` logs from.
+    /// All the namespaces are selected if it's empty.
+    extra_namespace_label_selector: String,
+
+    /// Glob patterns of the container names to collect the logs from.
+    /// All the containers are included if it's empty.
+    include_container_names: Vec<String>,
+
+    /// Glob patterns of the container names to not collect the logs from.
+    /// Takes precedence over `include_container_names`.
+    exclude_container_names: Vec<String>,
 }
 
 inventory::submit! {
@@ -110,10 +130,14 @@ impl SourceConfig for Config {
 #[derive(Clone)]
 struct Source {
     client: k8s::client::Client,
-    self_node_name: String,
     data_dir: PathBuf,
     auto_partial_merge: bool,
     fields_spec: pod_metadata_annotator::FieldsSpec,
+    field_selector: String,
+    label_selector: String,
+    namespace_label_selector: Option<String>,
+    include_container_names: Vec<glob::Pattern>,
+    exclude_container_names: Vec<glob::Pattern>,
 }
 
 impl Source {
@@ -145,12 +169,24 @@ impl Source {
 
         let data_dir = globals.resolve_and_make_data_subdir(None, name)?;
 
+        let field_selector = prepare_field_selector(&self_node_name, &config.extra_field_selector);
+        let label_selector = prepare_label_selector(&config.extra_label_selector);
+        let namespace_label_selector = if config.extra_namespace_label_selector.is_empty() {
+            None
+        } else {
+            Some(config.extra_namespace_label_selector.clone())
+        };
+
         Ok(Self {
             client,
-            self_node_name,
             data_dir,
             auto_partial_merge: config.auto_partial_merge,
             fields_spec: config.annotation_fields.clone(),
+            field_selector,
+            label_selector,
+            namespace_label_selector,
+            include_container_names: compile_globs(&config.include_container_names)?,
+            exclude_container_names: compile_globs(&config.exclude_container_names)?,
         })
     }
 
@@ -161,16 +197,18 @@ impl Source {
     {
         let Self {
             client,
-            self_node_name,
             data_dir,
             auto_partial_merge,
             fields_spec,
+            field_selector,
+            label_selector,
+            namespace_label_selector,
+            include_container_names,
+            exclude_container_names,
         } = self;
 
-        let field_selector = format!("spec.nodeName={}", self_node_name);
-        let label_selector = "vector.dev/exclude!=true".to_owned();
-
-        let watcher = k8s::api_watcher::ApiWatcher::new(client, Pod::watch_pod_for_all_namespaces);
+        let watcher =
+            k8s::api_watcher::ApiWatcher::new(client.clone(), Pod::watch_pod_for_all_namespaces);
         let watcher = k8s::instrumenting_watcher::InstrumentingWatcher::new(watcher);
         let (state_reader, state_writer) = evmap::new();
         let state_writer =
@@ -188,8 +226,47 @@ impl Source {
         );
         let reflector_process = reflector.run();
 
-        let paths_provider = K8sPathsProvider::new(state_reader.clone());
-        let annotator = PodMetadataAnnotator::new(state_reader, fields_spec);
+        // Namespaces are reflected separately, and are keyed by name so that
+        // they can be looked up by the `Pod`'s namespace.
+        let namespaces_watcher =
+            k8s::api_watcher::ApiWatcher::new(client, Namespace::watch_namespace);
+        let namespaces_watcher =
+            k8s::instrumenting_watcher::InstrumentingWatcher::new(namespaces_watcher);
+        let (namespaces_state_reader, namespaces_state_writer) = evmap::new();
+        let namespaces_state_writer = k8s::state::evmap::Writer::new_with_key_fn(
+            namespaces_state_writer,
+            Some(Duration::from_millis(10)),
+            k8s::state::evmap::key_by_name,
+        );
+        let namespaces_state_writer =
+            k8s::state::instrumenting::Writer::new(namespaces_state_writer);
+        let namespaces_state_writer = k8s::state::delayed_delete::Writer::new(
+            namespaces_state_writer,
+            Duration::from_secs(60),
+        );
+
+        // Only restrict the paths to the reflected namespaces when they're
+        // actually filtered.
+        let paths_namespaces_state_reader = namespace_label_selector
+            .as_ref()
+            .map(|_| namespaces_state_reader.clone());
+
+        let mut namespaces_reflector = k8s::reflector::Reflector::new(
+            namespaces_watcher,
+            namespaces_state_writer,
+            None,
+            namespace_label_selector,
+            Duration::from_secs(1),
+        );
+        let namespaces_reflector_process = namespaces_reflector.run();
+
+        let paths_provider = K8sPathsProvider::new(
+            state_reader.clone(),
+            paths_namespaces_state_reader,
+            ContainerFilter::new(include_container_names, exclude_container_names),
+        );
+        let annotator =
+            PodMetadataAnnotator::new(state_reader, namespaces_state_reader, fields_spec);
 
         // TODO: maybe some of the parameters have to be configurable.
 
@@ -282,6 +359,22 @@ impl Source {
                 });
             slot.bind(Box::pin(fut));
         }
+        {
+            let (slot, shutdown) = lifecycle.add();
+            let fut =
+                util::cancel_on_signal(namespaces_reflector_process, shutdown).map(|result| {
+                    match result {
+                        Ok(()) => {
+                            info!(message = "namespaces reflector process completed gracefully")
+                        }
+                        Err(error) => error!(
+                            message = "namespaces reflector process exited with an error",
+                            ?error
+                        ),
+                    }
+                });
+            slot.bind(Box::pin(fut));
+        }
         {
             let (slot, shutdown) = lifecycle.add();
             let fut = util::run_file_server(file_server, file_source_tx, shutdown).map(|result| {
@@ -336,8 +429,68 @@ fn create_event(line: Bytes, file: &str) -> Event {
     event
 }
 
+/// Prepares the field selector for the `Pod`s at our `Node`, merged with the
+/// user-supplied one.
+fn prepare_field_selector(self_node_name: &str, extra_field_selector: &str) -> String {
+    let field_selector = format!("spec.nodeName={}", self_node_name);
+    if extra_field_selector.is_empty() {
+        return field_selector;
+    }
+    format!("{},{}", field_selector, extra_field_selector)
+}
+
+/// Prepares the label selector excluding the opted out `Pod`s, merged with
+/// the user-supplied one.
+fn prepare_label_selector(extra_label_selector: &str) -> String {
+    let label_selector = "vector.dev/exclude!=true".to_owned();
+    if extra_label_selector.is_empty() {
+        return label_selector;
+    }
+    format!("{},{}", label_selector, extra_label_selector)
+}
+
+fn compile_globs(patterns: &[String]) -> crate::Result<Vec<glob::Pattern>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            glob::Pattern::new(pattern).map_err(|error| {
+                format!("invalid container name glob {:?}: {}", pattern, error).into()
+            })
+        })
+        .collect()
+}
+
 /// This function returns the default value for `self_node_name` variable
 /// as it should be at the generated config file.
 fn default_self_node_name_env_template() -> String {
     format!("${{{}}}", SELF_NODE_NAME_ENV_KEY.to_owned())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prepare_field_selector_test() {
+        assert_eq!(prepare_field_selector("node0", ""), "spec.nodeName=node0");
+        assert_eq!(
+            prepare_field_selector("node0", "metadata.name!=vector"),
+            "spec.nodeName=node0,metadata.name!=vector"
+        );
+    }
+
+    #[test]
+    fn prepare_label_selector_test() {
+        assert_eq!(prepare_label_selector(""), "vector.dev/exclude!=true");
+        assert_eq!(
+            prepare_label_selector("app=nginx"),
+            "vector.dev/exclude!=true,app=nginx"
+        );
+    }
+
+    #[test]
+    fn compile_globs_test() {
+        assert_eq!(compile_globs(&["app-*".to_owned()]).unwrap().len(), 1);
+        assert!(compile_globs(&["app-[".to_owned()]).is_err());
+    }
+}
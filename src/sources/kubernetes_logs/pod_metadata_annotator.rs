@@ -9,7 +9,7 @@ use crate::{
 };
 use evmap::ReadHandle;
 use k8s_openapi::{
-    api::core::v1::{Container, Pod, PodSpec},
+    api::core::v1::{Container, Namespace, Pod, PodSpec},
     apimachinery::pkg::apis::meta::v1::ObjectMeta,
 };
 use serde::{Deserialize, Serialize};
@@ -22,6 +22,7 @@ pub struct FieldsSpec {
     pub pod_uid: String,
     pub pod_labels: String,
     pub pod_node_name: String,
+    pub namespace_labels: String,
     pub container_name: String,
     pub container_image: String,
 }
@@ -34,6 +35,7 @@ impl Default for FieldsSpec {
             pod_uid: "kubernetes.pod_uid".to_owned(),
             pod_labels: "kubernetes.pod_labels".to_owned(),
             pod_node_name: "kubernetes.pod_node_name".to_owned(),
+            namespace_labels: "kubernetes.namespace_labels".to_owned(),
             container_name: "kubernetes.container_name".to_owned(),
             container_image: "kubernetes.container_image".to_owned(),
         }
@@ -43,17 +45,22 @@ impl Default for FieldsSpec {
 /// Annotate the event with pod metadata.
 pub struct PodMetadataAnnotator {
     pods_state_reader: ReadHandle<String, k8s::state::evmap::Value<Pod>>,
+    namespaces_state_reader: ReadHandle<String, k8s::state::evmap::Value<Namespace>>,
     fields_spec: FieldsSpec,
 }
 
 impl PodMetadataAnnotator {
     /// Create a new [`PodMetadataAnnotator`].
+    /// The `namespaces_state_reader` is expected to be keyed by the
+    /// namespace name.
     pub fn new(
         pods_state_reader: ReadHandle<String, k8s::state::evmap::Value<Pod>>,
+        namespaces_state_reader: ReadHandle<String, k8s::state::evmap::Value<Namespace>>,
         fields_spec: FieldsSpec,
     ) -> Self {
         Self {
             pods_state_reader,
+            namespaces_state_reader,
             fields_spec,
         }
     }
@@ -72,6 +79,16 @@ impl PodMetadataAnnotator {
 
         annotate_from_file_info(log, &self.fields_spec, &file_info);
         annotate_from_metadata(log, &self.fields_spec, &pod.metadata);
+        if let Some(ref namespace) = pod.metadata.namespace {
+            // Missing namespace metadata is not a reason to fail the
+            // annotation, the namespace state might just lag behind.
+            if let Some(guard) = self.namespaces_state_reader.get(namespace.as_str()) {
+                if let Some(entry) = guard.get_one() {
+                    let namespace: &Namespace = entry.as_ref();
+                    annotate_from_namespace(log, &self.fields_spec, &namespace.metadata);
+                }
+            }
+        }
         if let Some(ref pod_spec) = pod.spec {
             annotate_from_pod_spec(log, &self.fields_spec, pod_spec);
 
@@ -122,6 +139,18 @@ fn annotate_from_metadata(log: &mut LogEvent, fields_spec: &FieldsSpec, metadata
     }
 }
 
+fn annotate_from_namespace(log: &mut LogEvent, fields_spec: &FieldsSpec, metadata: &ObjectMeta) {
+    if let Some(labels) = &metadata.labels {
+        // Calculate and cache the prefix path.
+        let prefix_path = PathIter::new(fields_spec.namespace_labels.as_ref()).collect::<Vec<_>>();
+        for (key, val) in labels.iter() {
+            let mut path = prefix_path.clone();
+            path.push(PathComponent::Key(key.clone()));
+            log.insert_path(path, val.to_owned());
+        }
+    }
+}
+
 fn annotate_from_pod_spec(log: &mut LogEvent, fields_spec: &FieldsSpec, pod_spec: &PodSpec) {
     for (ref key, ref val) in [(&fields_spec.pod_node_name, &pod_spec.node_name)].iter() {
         if let Some(val) = val {
@@ -141,6 +170,14 @@ fn annotate_from_container(log: &mut LogEvent, fields_spec: &FieldsSpec, contain
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::kubernetes::{
+        mock_watcher::{self, MockWatcher},
+        reflector::Reflector,
+        state::{self, Write},
+    };
+    use futures::{channel::mpsc, SinkExt, StreamExt};
+    use k8s_openapi::{apimachinery::pkg::apis::meta::v1::WatchEvent, WatchResponse};
+    use std::time::Duration;
 
     #[test]
     fn test_annotate_from_metadata() {
@@ -248,6 +285,64 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_annotate_from_namespace() {
+        let cases = vec![
+            (
+                FieldsSpec::default(),
+                ObjectMeta::default(),
+                LogEvent::default(),
+            ),
+            (
+                FieldsSpec::default(),
+                ObjectMeta {
+                    name: Some("sandbox0-ns".to_owned()),
+                    labels: Some(
+                        vec![
+                            ("sandbox0-label0".to_owned(), "val0".to_owned()),
+                            ("nested0.label0".to_owned(), "val1".to_owned()),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    ),
+                    ..ObjectMeta::default()
+                },
+                {
+                    let mut log = LogEvent::default();
+                    log.insert("kubernetes.namespace_labels.sandbox0-label0", "val0");
+                    log.insert("kubernetes.namespace_labels.nested0\\.label0", "val1");
+                    log
+                },
+            ),
+            (
+                FieldsSpec {
+                    namespace_labels: "ns_labels".to_owned(),
+                    ..Default::default()
+                },
+                ObjectMeta {
+                    name: Some("sandbox0-ns".to_owned()),
+                    labels: Some(
+                        vec![("sandbox0-label0".to_owned(), "val0".to_owned())]
+                            .into_iter()
+                            .collect(),
+                    ),
+                    ..ObjectMeta::default()
+                },
+                {
+                    let mut log = LogEvent::default();
+                    log.insert("ns_labels.sandbox0-label0", "val0");
+                    log
+                },
+            ),
+        ];
+
+        for (fields_spec, metadata, expected) in cases.into_iter() {
+            let mut log = LogEvent::default();
+            annotate_from_namespace(&mut log, &fields_spec, &metadata);
+            assert_eq!(log, expected);
+        }
+    }
+
     #[test]
     fn test_annotate_from_file_info() {
         let cases = vec![(
@@ -366,4 +461,120 @@ mod tests {
             assert_eq!(log, expected);
         }
     }
+
+    #[tokio::test]
+    async fn test_annotate_with_reflected_namespace() {
+        // Prepare the pods state.
+        let (pods_state_reader, pods_state_writer) = evmap::new();
+        let mut pods_state_writer = state::evmap::Writer::new(pods_state_writer, None);
+        pods_state_writer
+            .add(Pod {
+                metadata: ObjectMeta {
+                    name: Some("sandbox0-name".to_owned()),
+                    namespace: Some("sandbox0-ns".to_owned()),
+                    uid: Some("sandbox0-uid".to_owned()),
+                    ..ObjectMeta::default()
+                },
+                ..Pod::default()
+            })
+            .await;
+
+        // Prepare the namespaces state, reflected from the mock watcher.
+        let (namespaces_state_reader, namespaces_state_writer) = evmap::new();
+        let namespaces_state_writer = state::evmap::Writer::new_with_key_fn(
+            namespaces_state_writer,
+            None,
+            state::evmap::key_by_name,
+        );
+        let (watcher_events_tx, mut watcher_events_rx) = mpsc::channel(0);
+        let (mut watcher_invocations_tx, watcher_invocations_rx) = mpsc::channel(0);
+        let watcher: MockWatcher<Namespace> =
+            MockWatcher::new(watcher_events_tx, watcher_invocations_rx);
+        let mut reflector = Reflector::new(
+            watcher,
+            namespaces_state_writer,
+            None,
+            None,
+            Duration::from_secs(1),
+        );
+
+        let logic = tokio::spawn(async move {
+            assert!(matches!(
+                watcher_events_rx.next().await.unwrap(),
+                mock_watcher::ScenarioEvent::Invocation(_)
+            ));
+            let (mut watch_stream_tx, watch_stream_rx) = mpsc::channel(0);
+            watcher_invocations_tx
+                .send(mock_watcher::ScenarioActionInvocation::Ok(watch_stream_rx))
+                .await
+                .unwrap();
+
+            assert_eq!(
+                watcher_events_rx.next().await.unwrap(),
+                mock_watcher::ScenarioEvent::Stream
+            );
+            watch_stream_tx
+                .send(mock_watcher::ScenarioActionStream::Ok(WatchResponse::Ok(
+                    WatchEvent::Added(Namespace {
+                        metadata: ObjectMeta {
+                            name: Some("sandbox0-ns".to_owned()),
+                            uid: Some("sandbox0-ns-uid".to_owned()),
+                            labels: Some(
+                                vec![("team".to_owned(), "sandbox".to_owned())]
+                                    .into_iter()
+                                    .collect(),
+                            ),
+                            ..ObjectMeta::default()
+                        },
+                        ..Namespace::default()
+                    }),
+                )))
+                .await
+                .unwrap();
+
+            // Terminate the stream and the reflector.
+            assert_eq!(
+                watcher_events_rx.next().await.unwrap(),
+                mock_watcher::ScenarioEvent::Stream
+            );
+            watch_stream_tx
+                .send(mock_watcher::ScenarioActionStream::Done)
+                .await
+                .unwrap();
+            assert!(matches!(
+                watcher_events_rx.next().await.unwrap(),
+                mock_watcher::ScenarioEvent::Invocation(_)
+            ));
+            watcher_invocations_tx
+                .send(mock_watcher::ScenarioActionInvocation::ErrOther)
+                .await
+                .unwrap();
+        });
+
+        reflector.run().await.unwrap_err();
+        logic.await.unwrap();
+
+        let annotator = PodMetadataAnnotator::new(
+            pods_state_reader,
+            namespaces_state_reader,
+            FieldsSpec::default(),
+        );
+        let mut event = Event::from("hello");
+        annotator
+            .annotate(
+                &mut event,
+                "/var/log/pods/sandbox0-ns_sandbox0-name_sandbox0-uid/sandbox0-container0-name/1.log",
+            )
+            .unwrap();
+
+        let log = event.as_log();
+        assert_eq!(
+            log[&"kubernetes.pod_namespace".into()],
+            "sandbox0-ns".into()
+        );
+        assert_eq!(
+            log[&"kubernetes.namespace_labels.team".into()],
+            "sandbox".into()
+        );
+    }
 }
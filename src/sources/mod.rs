@@ -3,6 +3,10 @@ use snafu::Snafu;
 
 #[cfg(feature = "sources-apache_metrics")]
 pub mod apache_metrics;
+#[cfg(feature = "sources-aws_kinesis_firehose")]
+pub mod aws_kinesis_firehose;
+#[cfg(feature = "sources-aws_s3")]
+pub mod aws_s3;
 #[cfg(feature = "sources-docker")]
 pub mod docker;
 #[cfg(feature = "sources-file")]
@@ -11,18 +15,28 @@ pub mod file;
 pub mod generator;
 #[cfg(feature = "sources-http")]
 pub mod http;
+#[cfg(feature = "sources-internal_logs")]
+pub mod internal_logs;
 #[cfg(feature = "sources-internal_metrics")]
 pub mod internal_metrics;
 #[cfg(all(unix, feature = "sources-journald"))]
 pub mod journald;
 #[cfg(all(feature = "sources-kafka", feature = "rdkafka"))]
 pub mod kafka;
+#[cfg(feature = "sources-kubernetes-events")]
+pub mod kubernetes_events;
 #[cfg(feature = "sources-kubernetes-logs")]
 pub mod kubernetes_logs;
 #[cfg(feature = "sources-logplex")]
 pub mod logplex;
+#[cfg(feature = "sources-mqtt")]
+pub mod mqtt;
+#[cfg(feature = "sources-nats")]
+pub mod nats;
 #[cfg(feature = "sources-prometheus")]
 pub mod prometheus;
+#[cfg(feature = "sources-redis")]
+pub mod redis;
 #[cfg(feature = "sources-socket")]
 pub mod socket;
 #[cfg(feature = "sources-splunk_hec")]
@@ -0,0 +1,304 @@
+use crate::{
+    config::{log_schema, DataType, GlobalOptions, SourceConfig, SourceDescription},
+    event::Event,
+    internal_events::{MqttConnectionError, MqttEventDecodeFailed, MqttEventReceived},
+    mqtt::{MqttConnectionConfig, MqttQos},
+    shutdown::ShutdownSignal,
+    Pipeline,
+};
+use bytes::Bytes;
+use chrono::Utc;
+use futures::{
+    compat::{Future01CompatExt, Sink01CompatExt},
+    future::{FutureExt, TryFutureExt},
+    stream, SinkExt, StreamExt,
+};
+use rumqttc::{AsyncClient, Event as MqttEvent, EventLoop, Incoming, Publish};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::time::Duration;
+use tokio::{select, time::delay_for};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MqttSourceConfig {
+    #[serde(flatten)]
+    connection: MqttConnectionConfig,
+    topics: Vec<String>,
+    #[serde(default)]
+    qos: MqttQos,
+    #[serde(default)]
+    encoding: Encoding,
+    #[serde(default = "default_topic_key")]
+    topic_key: String,
+}
+
+fn default_topic_key() -> String {
+    "topic".into()
+}
+
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone, Derivative, Copy)]
+#[serde(rename_all = "snake_case")]
+#[derivative(Default)]
+pub enum Encoding {
+    #[derivative(Default)]
+    Text,
+    Ndjson,
+    Json,
+}
+
+inventory::submit! {
+    SourceDescription::new_without_default::<MqttSourceConfig>("mqtt")
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "mqtt")]
+impl SourceConfig for MqttSourceConfig {
+    async fn build(
+        &self,
+        _name: &str,
+        _globals: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: Pipeline,
+    ) -> crate::Result<super::Source> {
+        let options = self.connection.build_options()?;
+        let (client, eventloop) = AsyncClient::new(options, 100);
+
+        Ok(Box::new(
+            mqtt_source(self.clone(), client, eventloop, shutdown, out)
+                .boxed()
+                .compat(),
+        ))
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn source_type(&self) -> &'static str {
+        "mqtt"
+    }
+}
+
+async fn mqtt_source(
+    config: MqttSourceConfig,
+    client: AsyncClient,
+    mut eventloop: EventLoop,
+    shutdown: ShutdownSignal,
+    out: Pipeline,
+) -> Result<(), ()> {
+    let mut out = out.sink_compat();
+    let mut shutdown = shutdown.compat();
+
+    loop {
+        let event = select! {
+            event = eventloop.poll() => event,
+            _ = &mut shutdown => break,
+        };
+
+        match event {
+            // Subscriptions are lost when a clean session reconnects, so
+            // (re)subscribe every time the broker acknowledges a connection.
+            Ok(MqttEvent::Incoming(Incoming::ConnAck(_))) => {
+                for topic in &config.topics {
+                    if let Err(error) = client.subscribe(topic, config.qos.into()).await {
+                        error!(message = "Failed to subscribe to topic.", %topic, %error);
+                        return Err(());
+                    }
+                }
+            }
+            Ok(MqttEvent::Incoming(Incoming::Publish(publish))) => {
+                emit!(MqttEventReceived {
+                    byte_size: publish.payload.len(),
+                    topic: &publish.topic,
+                });
+
+                let events = match decode_publish(publish, config.encoding, &config.topic_key) {
+                    Ok(events) => events,
+                    Err(error) => {
+                        emit!(MqttEventDecodeFailed { error });
+                        continue;
+                    }
+                };
+
+                let mut events = stream::iter(events).map(Ok);
+                out.send_all(&mut events).await.map_err(|error| {
+                    error!(message = "Error sending to sink.", %error);
+                })?;
+            }
+            Ok(_) => (),
+            Err(error) => {
+                emit!(MqttConnectionError { error });
+                // The event loop reconnects on the next poll.
+                delay_for(Duration::from_secs(1)).await;
+            }
+        }
+    }
+
+    let _ = client.disconnect().await;
+
+    Ok(())
+}
+
+fn decode_publish(
+    publish: Publish,
+    encoding: Encoding,
+    topic_key: &str,
+) -> Result<Vec<Event>, serde_json::Error> {
+    let payload = Bytes::from(publish.payload.to_vec());
+
+    let mut events = match encoding {
+        Encoding::Text => vec![Event::from(payload)],
+        Encoding::Ndjson => payload
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).map(json_to_event))
+            .collect::<Result<_, _>>()?,
+        Encoding::Json => match serde_json::from_slice(&payload)? {
+            JsonValue::Array(values) => values.into_iter().map(json_to_event).collect(),
+            value => vec![json_to_event(value)],
+        },
+    };
+
+    for event in events.iter_mut() {
+        let log = event.as_mut_log();
+        log.insert(topic_key, publish.topic.clone());
+        log.try_insert(log_schema().source_type_key(), Bytes::from("mqtt"));
+    }
+
+    Ok(events)
+}
+
+fn json_to_event(value: JsonValue) -> Event {
+    let mut event = Event::new_empty_log();
+    let log = event.as_mut_log();
+    log.insert(log_schema().timestamp_key().clone(), Utc::now());
+    match value {
+        JsonValue::Object(map) => {
+            for (k, v) in map {
+                log.insert(k, v);
+            }
+        }
+        value => {
+            log.insert(log_schema().message_key().clone(), value);
+        }
+    }
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rumqttc::QoS;
+
+    fn publish(payload: &str) -> Publish {
+        Publish::new("devices/sensor-1/telemetry", QoS::AtMostOnce, payload)
+    }
+
+    #[test]
+    fn mqtt_decode_text() {
+        let events = decode_publish(publish("hello world"), Encoding::Text, "topic").unwrap();
+
+        assert_eq!(events.len(), 1);
+        let log = events[0].as_log();
+        assert_eq!(log[&log_schema().message_key()], "hello world".into());
+        assert_eq!(log[&"topic".into()], "devices/sensor-1/telemetry".into());
+        assert_eq!(log[log_schema().source_type_key()], "mqtt".into());
+    }
+
+    #[test]
+    fn mqtt_decode_json() {
+        let events = decode_publish(
+            publish(r#"[{"temperature": 21}, {"temperature": 22}]"#),
+            Encoding::Json,
+            "mqtt_topic",
+        )
+        .unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].as_log()[&"temperature".into()], 21.into());
+        assert_eq!(events[1].as_log()[&"temperature".into()], 22.into());
+        assert_eq!(
+            events[1].as_log()[&"mqtt_topic".into()],
+            "devices/sensor-1/telemetry".into()
+        );
+    }
+
+    #[test]
+    fn mqtt_decode_ndjson() {
+        let events = decode_publish(
+            publish("{\"temperature\": 21}\n{\"temperature\": 22}\n"),
+            Encoding::Ndjson,
+            "topic",
+        )
+        .unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].as_log()[&"temperature".into()], 22.into());
+    }
+
+    #[test]
+    fn mqtt_decode_invalid_json() {
+        assert!(decode_publish(publish("{"), Encoding::Json, "topic").is_err());
+    }
+}
+
+#[cfg(feature = "mqtt-integration-tests")]
+#[cfg(test)]
+mod integration_tests {
+    use super::*;
+    use crate::test_util::{collect_n, random_string, trace_init};
+    use rumqttc::QoS;
+
+    fn make_config(topic: &str) -> MqttSourceConfig {
+        toml::from_str(&format!(
+            r#"
+            host = "127.0.0.1"
+            topics = ["{}/#"]
+            qos = 1
+            "#,
+            topic
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn mqtt_happy() {
+        trace_init();
+
+        let topic = format!("test-{}", random_string(10));
+        let config = make_config(&topic);
+
+        let (tx, rx) = Pipeline::new_test();
+        let source = config
+            .build(
+                "default",
+                &GlobalOptions::default(),
+                ShutdownSignal::noop(),
+                tx,
+            )
+            .await
+            .unwrap();
+        tokio::spawn(source.compat());
+
+        let options = config.connection.build_options().unwrap();
+        let (client, mut eventloop) = AsyncClient::new(options, 10);
+        tokio::spawn(async move { while eventloop.poll().await.is_ok() {} });
+
+        // Give the source some time to subscribe.
+        delay_for(Duration::from_secs(1)).await;
+        client
+            .publish(
+                format!("{}/sensor-1", topic),
+                QoS::AtLeastOnce,
+                false,
+                "my message",
+            )
+            .await
+            .unwrap();
+
+        let events = collect_n(rx, 1).await.unwrap();
+        let log = events[0].as_log();
+        assert_eq!(log[&log_schema().message_key()], "my message".into());
+        assert_eq!(log[&"topic".into()], format!("{}/sensor-1", topic).into());
+    }
+}
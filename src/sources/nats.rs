@@ -0,0 +1,287 @@
+use crate::{
+    config::{log_schema, DataType, GlobalOptions, SourceConfig, SourceDescription},
+    event::Event,
+    internal_events::{NatsEventDecodeFailed, NatsEventReceived},
+    shutdown::ShutdownSignal,
+    sinks::util::encoding::{EncodingConfig, EncodingConfigWithDefault, EncodingConfiguration},
+    Pipeline,
+};
+use bytes::Bytes;
+use chrono::Utc;
+use futures::{
+    compat::{Future01CompatExt, Sink01CompatExt},
+    future::{FutureExt, TryFutureExt},
+    stream, SinkExt, StreamExt,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use snafu::{ResultExt, Snafu};
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("NATS Connect Error: {}", source))]
+    Connect { source: std::io::Error },
+    #[snafu(display("NATS Subscribe Error: {}", source))]
+    Subscribe { source: std::io::Error },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NatsSourceConfig {
+    url: String,
+    #[serde(default = "default_name")]
+    name: String,
+    subject: String,
+    queue: Option<String>,
+    #[serde(default)]
+    encoding: EncodingConfigWithDefault<Encoding>,
+}
+
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    #[derivative(Default)]
+    Text,
+    Json,
+}
+
+fn default_name() -> String {
+    String::from("vector")
+}
+
+inventory::submit! {
+    SourceDescription::new_without_default::<NatsSourceConfig>("nats")
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "nats")]
+impl SourceConfig for NatsSourceConfig {
+    async fn build(
+        &self,
+        _name: &str,
+        _globals: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: Pipeline,
+    ) -> crate::Result<super::Source> {
+        let (connection, subscription) = create_subscription(self).await?;
+
+        Ok(Box::new(
+            nats_source(
+                connection,
+                subscription,
+                self.encoding.clone().into(),
+                shutdown,
+                out,
+            )
+            .boxed()
+            .compat(),
+        ))
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn source_type(&self) -> &'static str {
+        "nats"
+    }
+}
+
+impl NatsSourceConfig {
+    async fn connect(&self) -> crate::Result<nats::asynk::Connection> {
+        nats::Options::new()
+            .with_name(&self.name)
+            .connect_async(&self.url)
+            .await
+            .context(Connect)
+            .map_err(Into::into)
+    }
+}
+
+async fn create_subscription(
+    config: &NatsSourceConfig,
+) -> crate::Result<(nats::asynk::Connection, nats::asynk::Subscription)> {
+    let connection = config.connect().await?;
+
+    let subscription = match &config.queue {
+        None => connection.subscribe(&config.subject).await,
+        Some(queue) => connection.queue_subscribe(&config.subject, queue).await,
+    }
+    .context(Subscribe)?;
+
+    Ok((connection, subscription))
+}
+
+fn get_subscription_stream(
+    subscription: nats::asynk::Subscription,
+) -> impl stream::Stream<Item = nats::asynk::Message> {
+    stream::unfold(subscription, |subscription| async move {
+        subscription.next().await.map(|msg| (msg, subscription))
+    })
+}
+
+async fn nats_source(
+    // Keep the connection alive while the subscription is in use.
+    _connection: nats::asynk::Connection,
+    subscription: nats::asynk::Subscription,
+    encoding: EncodingConfig<Encoding>,
+    shutdown: ShutdownSignal,
+    out: Pipeline,
+) -> Result<(), ()> {
+    let mut out = out.sink_compat();
+    let mut stream = get_subscription_stream(subscription).take_until(shutdown.compat());
+
+    while let Some(msg) = stream.next().await {
+        emit!(NatsEventReceived {
+            byte_size: msg.data.len(),
+        });
+
+        let event = match create_event(msg.subject, msg.data, &encoding) {
+            Ok(event) => event,
+            Err(error) => {
+                emit!(NatsEventDecodeFailed { error });
+                continue;
+            }
+        };
+
+        out.send(event).await.map_err(|error| {
+            error!(message = "Error sending to sink.", %error);
+        })?;
+    }
+
+    Ok(())
+}
+
+fn create_event(
+    subject: String,
+    data: Vec<u8>,
+    encoding: &EncodingConfig<Encoding>,
+) -> Result<Event, serde_json::Error> {
+    let mut event = match encoding.codec() {
+        Encoding::Text => Event::from(Bytes::from(data)),
+        Encoding::Json => {
+            let mut event = Event::new_empty_log();
+            let log = event.as_mut_log();
+            log.insert(log_schema().timestamp_key().clone(), Utc::now());
+            match serde_json::from_slice(&data)? {
+                JsonValue::Object(map) => {
+                    for (key, value) in map {
+                        log.insert(key, value);
+                    }
+                }
+                value => {
+                    log.insert(log_schema().message_key().clone(), value);
+                }
+            }
+            event
+        }
+    };
+
+    let log = event.as_mut_log();
+    log.insert("subject", subject);
+    log.insert(log_schema().source_type_key(), Bytes::from("nats"));
+
+    encoding.apply_rules(&mut event);
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nats_create_event() {
+        let event = create_event(
+            "my.subject".into(),
+            b"hello world".to_vec(),
+            &EncodingConfig::from(Encoding::Text),
+        )
+        .unwrap();
+        let log = event.as_log();
+
+        assert_eq!(log[&log_schema().message_key()], "hello world".into());
+        assert_eq!(log[&"subject".into()], "my.subject".into());
+        assert_eq!(log[log_schema().source_type_key()], "nats".into());
+    }
+
+    #[test]
+    fn nats_create_event_json() {
+        let event = create_event(
+            "my.subject".into(),
+            br#"{"message":"hello world","key":"value"}"#.to_vec(),
+            &EncodingConfig::from(Encoding::Json),
+        )
+        .unwrap();
+        let log = event.as_log();
+
+        assert_eq!(log[&log_schema().message_key()], "hello world".into());
+        assert_eq!(log[&"key".into()], "value".into());
+        assert_eq!(log[&"subject".into()], "my.subject".into());
+    }
+
+    #[test]
+    fn nats_create_event_apply_rules() {
+        let event = create_event(
+            "my.subject".into(),
+            br#"{"message":"hello world","key":"value"}"#.to_vec(),
+            &EncodingConfigWithDefault {
+                codec: Encoding::Json,
+                except_fields: Some(vec!["key".into()]),
+                ..Default::default()
+            }
+            .into(),
+        )
+        .unwrap();
+
+        assert!(!event.as_log().contains("key"));
+    }
+
+    #[test]
+    fn nats_create_event_invalid_json() {
+        assert!(create_event(
+            "my.subject".into(),
+            b"{".to_vec(),
+            &EncodingConfig::from(Encoding::Json),
+        )
+        .is_err());
+    }
+}
+
+#[cfg(feature = "nats-integration-tests")]
+#[cfg(test)]
+mod integration_tests {
+    use super::*;
+    use crate::test_util::{collect_n, random_string};
+
+    #[tokio::test]
+    async fn nats_happy() {
+        let subject = format!("test-{}", random_string(10));
+
+        let conf = NatsSourceConfig {
+            url: "nats://127.0.0.1:4222".to_owned(),
+            name: "vector-test".to_owned(),
+            subject: subject.clone(),
+            queue: None,
+            encoding: EncodingConfigWithDefault::from(Encoding::Text),
+        };
+
+        let (nc, sub) = create_subscription(&conf).await.unwrap();
+        let nc_pub = nc.clone();
+
+        let (tx, rx) = Pipeline::new_test();
+        tokio::spawn(nats_source(
+            nc,
+            sub,
+            conf.encoding.clone().into(),
+            ShutdownSignal::noop(),
+            tx,
+        ));
+        let msg = "my message";
+        nc_pub.publish(&subject, msg).await.unwrap();
+
+        let events = collect_n(rx, 1).await.unwrap();
+        assert_eq!(events[0].as_log()[&log_schema().message_key()], msg.into());
+        assert_eq!(events[0].as_log()[&"subject".into()], subject.into());
+    }
+}
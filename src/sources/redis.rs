@@ -0,0 +1,528 @@
+use crate::{
+    config::{log_schema, DataType, GlobalOptions, SourceConfig, SourceDescription},
+    event::Event,
+    internal_events::{RedisEventReceived, RedisReceiveEventFailed},
+    shutdown::ShutdownSignal,
+    Pipeline,
+};
+use bytes::Bytes;
+use futures::{
+    compat::{Future01CompatExt, Sink01CompatExt},
+    future::{FutureExt, TryFutureExt},
+    stream, SinkExt, StreamExt,
+};
+use futures01::Future;
+use redis::{
+    aio::{Connection, ConnectionManager},
+    streams::{StreamReadOptions, StreamReadReply},
+    AsyncCommands, ErrorKind, RedisError, RedisResult,
+};
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::time::Duration;
+use tokio::time::delay_for;
+use tokio_retry::strategy::ExponentialBackoff;
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("Failed to create connection: {}", source))]
+    Connection { source: RedisError },
+    #[snafu(display("Failed to create consumer group {:?}: {}", group, source))]
+    CreateGroup { group: String, source: RedisError },
+}
+
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RedisDataType {
+    #[derivative(Default)]
+    List,
+    Channel,
+    Stream,
+}
+
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    #[derivative(Default)]
+    Lpop,
+    Rpop,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ListOption {
+    #[serde(default)]
+    method: Method,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct StreamOption {
+    #[serde(default = "default_group")]
+    group: String,
+    consumer: Option<String>,
+    #[serde(default = "default_batch_size")]
+    batch_size: usize,
+}
+
+fn default_group() -> String {
+    "vector".into()
+}
+
+fn default_batch_size() -> usize {
+    100
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RedisSourceConfig {
+    url: String,
+    key: String,
+    #[serde(default)]
+    data_type: RedisDataType,
+    list: Option<ListOption>,
+    stream: Option<StreamOption>,
+    redis_key: Option<String>,
+}
+
+inventory::submit! {
+    SourceDescription::new_without_default::<RedisSourceConfig>("redis")
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "redis")]
+impl SourceConfig for RedisSourceConfig {
+    async fn build(
+        &self,
+        _name: &str,
+        _globals: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: Pipeline,
+    ) -> crate::Result<super::Source> {
+        let client = redis::Client::open(self.url.as_str()).context(Connection)?;
+
+        // Lists and streams are read through a `ConnectionManager`, which
+        // reconnects after the connection fails. Subscriptions need a
+        // dedicated connection, which `channel_source` reopens itself.
+        let fut = match self.data_type {
+            RedisDataType::List => {
+                let conn = ConnectionManager::new(client).await.context(Connection)?;
+                let method = self.list.clone().unwrap_or_default().method;
+                list_source(self.clone(), conn, method, shutdown, out).boxed()
+            }
+            RedisDataType::Channel => {
+                let conn = client.get_async_connection().await.context(Connection)?;
+                channel_source(self.clone(), client, conn, shutdown, out).boxed()
+            }
+            RedisDataType::Stream => {
+                let mut conn = ConnectionManager::new(client).await.context(Connection)?;
+                let options = self.stream.clone().unwrap_or_else(|| StreamOption {
+                    group: default_group(),
+                    consumer: None,
+                    batch_size: default_batch_size(),
+                });
+                create_group(&mut conn, &self.key, &options.group).await?;
+                stream_source(self.clone(), conn, options, shutdown, out).boxed()
+            }
+        };
+
+        Ok(Box::new(fut.compat()))
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn source_type(&self) -> &'static str {
+        "redis"
+    }
+}
+
+/// Blocking commands are issued with this timeout so shutdown is noticed
+/// without cancelling a command that may already have popped an element.
+const BLOCK_TIMEOUT_SECS: usize = 1;
+
+fn fresh_backoff() -> ExponentialBackoff {
+    ExponentialBackoff::from_millis(2)
+        .factor(250)
+        .max_delay(Duration::from_secs(30))
+}
+
+/// Waits out the next backoff delay after a failure. Returns `true` if the
+/// source is shut down in the meantime.
+async fn backoff_or_shutdown(backoff: &mut ExponentialBackoff, shutdown: &ShutdownSignal) -> bool {
+    let delay = backoff.next().expect("Backoff never ends.");
+    tokio::select! {
+        _ = delay_for(delay) => false,
+        _ = shutdown.clone().compat() => true,
+    }
+}
+
+async fn list_source(
+    config: RedisSourceConfig,
+    mut conn: ConnectionManager,
+    method: Method,
+    mut shutdown: ShutdownSignal,
+    out: Pipeline,
+) -> Result<(), ()> {
+    let mut out = out.sink_compat();
+    let mut backoff = fresh_backoff();
+
+    loop {
+        if shutdown.poll().expect("polling shutdown").is_ready() {
+            break;
+        }
+
+        let res: RedisResult<Option<(String, Vec<u8>)>> = match method {
+            Method::Lpop => conn.blpop(&config.key, BLOCK_TIMEOUT_SECS).await,
+            Method::Rpop => conn.brpop(&config.key, BLOCK_TIMEOUT_SECS).await,
+        };
+
+        match res {
+            Ok(Some((_, payload))) => {
+                backoff = fresh_backoff();
+                let event = create_event(payload, &config.key, &config.redis_key);
+                out.send(event).await.map_err(|error| {
+                    error!(message = "Error sending to sink.", %error);
+                })?;
+            }
+            Ok(None) => backoff = fresh_backoff(),
+            Err(error) => {
+                emit!(RedisReceiveEventFailed { error });
+                if backoff_or_shutdown(&mut backoff, &shutdown).await {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Subscribes to the channel until shut down, subscribing again on a new
+/// connection whenever the subscription's connection is lost.
+async fn channel_source(
+    config: RedisSourceConfig,
+    client: redis::Client,
+    conn: Connection,
+    mut shutdown: ShutdownSignal,
+    out: Pipeline,
+) -> Result<(), ()> {
+    let mut out = out.sink_compat();
+    let mut backoff = fresh_backoff();
+    let mut conn = Some(conn);
+
+    loop {
+        let conn = match conn.take() {
+            Some(conn) => Ok(conn),
+            None => client.get_async_connection().await,
+        };
+
+        let error = match conn {
+            Ok(conn) => {
+                let mut pubsub = conn.into_pubsub();
+                match pubsub.subscribe(&config.key).await {
+                    Ok(()) => {
+                        backoff = fresh_backoff();
+                        let mut messages =
+                            pubsub.on_message().take_until(shutdown.clone().compat());
+                        while let Some(msg) = messages.next().await {
+                            let payload = msg.get_payload_bytes().to_vec();
+                            let event =
+                                create_event(payload, msg.get_channel_name(), &config.redis_key);
+                            out.send(event).await.map_err(|error| {
+                                error!(message = "Error sending to sink.", %error);
+                            })?;
+                        }
+                        None
+                    }
+                    Err(error) => Some(error),
+                }
+            }
+            Err(error) => Some(error),
+        };
+
+        if shutdown.poll().expect("polling shutdown").is_ready() {
+            break;
+        }
+
+        match error {
+            Some(error) => emit!(RedisReceiveEventFailed { error }),
+            None => warn!(message = "Subscription connection closed, reconnecting."),
+        }
+        if backoff_or_shutdown(&mut backoff, &shutdown).await {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+async fn create_group<C>(conn: &mut C, key: &str, group: &str) -> crate::Result<()>
+where
+    C: AsyncCommands,
+{
+    let res: RedisResult<()> = conn.xgroup_create_mkstream(key, group, "$").await;
+    match res {
+        Ok(()) => Ok(()),
+        // The group already exists, so continue from its last delivered ID.
+        Err(error)
+            if error.kind() == ErrorKind::ExtensionError && error.code() == Some("BUSYGROUP") =>
+        {
+            Ok(())
+        }
+        Err(source) => Err(BuildError::CreateGroup {
+            group: group.into(),
+            source,
+        }
+        .into()),
+    }
+}
+
+async fn stream_source(
+    config: RedisSourceConfig,
+    mut conn: ConnectionManager,
+    options: StreamOption,
+    mut shutdown: ShutdownSignal,
+    out: Pipeline,
+) -> Result<(), ()> {
+    let mut out = out.sink_compat();
+    let consumer = options
+        .consumer
+        .clone()
+        .or_else(|| crate::get_hostname().ok())
+        .unwrap_or_else(|| "vector".into());
+    let read_options = StreamReadOptions::default()
+        .group(&options.group, &consumer)
+        .count(options.batch_size)
+        .block(BLOCK_TIMEOUT_SECS * 1000);
+
+    // Entries delivered to this consumer but never acknowledged, for
+    // example because Vector stopped, are replayed before reading new ones.
+    let mut start_id = "0";
+    let mut backoff = fresh_backoff();
+
+    loop {
+        if shutdown.poll().expect("polling shutdown").is_ready() {
+            break;
+        }
+
+        let reply: RedisResult<StreamReadReply> = conn
+            .xread_options(&[&config.key], &[start_id], read_options.clone())
+            .await;
+        let reply = match reply {
+            Ok(reply) => {
+                backoff = fresh_backoff();
+                reply
+            }
+            Err(error) => {
+                // A restarted server may have lost the group.
+                if error.code() == Some("NOGROUP") {
+                    if let Err(error) = create_group(&mut conn, &config.key, &options.group).await {
+                        error!(message = "Failed to create consumer group.", %error);
+                    }
+                }
+                emit!(RedisReceiveEventFailed { error });
+                if backoff_or_shutdown(&mut backoff, &shutdown).await {
+                    break;
+                }
+                // Replay what was delivered but not acknowledged before the failure.
+                start_id = "0";
+                continue;
+            }
+        };
+
+        let entries = reply
+            .keys
+            .into_iter()
+            .flat_map(|key| key.ids)
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            start_id = ">";
+            continue;
+        }
+
+        let ids = entries
+            .iter()
+            .map(|entry| entry.id.clone())
+            .collect::<Vec<_>>();
+        let events = entries.into_iter().map(|entry| {
+            let mut event = Event::new_empty_log();
+            let log = event.as_mut_log();
+            log.insert(log_schema().timestamp_key().clone(), chrono::Utc::now());
+            let mut byte_size = 0;
+            for (field, value) in entry.map {
+                if let Ok(value) = redis::from_redis_value::<Vec<u8>>(&value) {
+                    byte_size += field.len() + value.len();
+                    log.insert(field, Bytes::from(value));
+                }
+            }
+            emit!(RedisEventReceived { byte_size });
+            finish_event(event, &config.key, &config.redis_key)
+        });
+
+        let mut events = stream::iter(events).map(Ok);
+        out.send_all(&mut events).await.map_err(|error| {
+            error!(message = "Error sending to sink.", %error);
+        })?;
+
+        // Only acknowledge the entries once they have been handed to the pipeline.
+        let res: RedisResult<usize> = conn.xack(&config.key, &options.group, &ids).await;
+        if let Err(error) = res {
+            emit!(RedisReceiveEventFailed { error });
+        }
+    }
+
+    Ok(())
+}
+
+fn create_event(payload: Vec<u8>, key: &str, redis_key: &Option<String>) -> Event {
+    emit!(RedisEventReceived {
+        byte_size: payload.len()
+    });
+
+    finish_event(Event::from(Bytes::from(payload)), key, redis_key)
+}
+
+fn finish_event(mut event: Event, key: &str, redis_key: &Option<String>) -> Event {
+    let log = event.as_mut_log();
+    log.insert(log_schema().source_type_key(), Bytes::from("redis"));
+    if let Some(redis_key) = redis_key {
+        log.insert(redis_key, key.to_owned());
+    }
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redis_parse_config() {
+        let config: RedisSourceConfig = toml::from_str(
+            r#"
+            url = "redis://127.0.0.1:6379/0"
+            key = "vector"
+            data_type = "list"
+            list.method = "rpop"
+            redis_key = "redis_key"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.data_type, RedisDataType::List);
+        assert_eq!(config.list.unwrap().method, Method::Rpop);
+    }
+
+    #[test]
+    fn redis_parse_stream_config() {
+        let config: RedisSourceConfig = toml::from_str(
+            r#"
+            url = "redis://127.0.0.1:6379/0"
+            key = "vector"
+            data_type = "stream"
+            stream.consumer = "vector-1"
+            "#,
+        )
+        .unwrap();
+
+        let stream = config.stream.unwrap();
+        assert_eq!(config.data_type, RedisDataType::Stream);
+        assert_eq!(stream.group, "vector");
+        assert_eq!(stream.batch_size, 100);
+    }
+
+    #[test]
+    fn redis_create_event() {
+        let event = create_event(b"hello".to_vec(), "vector", &Some("redis_key".into()));
+        let log = event.as_log();
+
+        assert_eq!(log[&log_schema().message_key()], "hello".into());
+        assert_eq!(log[&"redis_key".into()], "vector".into());
+        assert_eq!(log[log_schema().source_type_key()], "redis".into());
+    }
+}
+
+#[cfg(feature = "redis-integration-tests")]
+#[cfg(test)]
+mod integration_test {
+    use super::*;
+    use crate::test_util::{collect_n, random_string, trace_init};
+
+    const REDIS_SERVER: &str = "redis://127.0.0.1:6379/0";
+
+    async fn connect() -> Connection {
+        let client = redis::Client::open(REDIS_SERVER).unwrap();
+        client.get_async_connection().await.unwrap()
+    }
+
+    async fn run(config: RedisSourceConfig, n: usize) -> Vec<Event> {
+        let (tx, rx) = Pipeline::new_test();
+        let source = config
+            .build(
+                "default",
+                &GlobalOptions::default(),
+                ShutdownSignal::noop(),
+                tx,
+            )
+            .await
+            .unwrap();
+        tokio::spawn(source.compat());
+
+        collect_n(rx, n).await.unwrap()
+    }
+
+    fn make_config(key: &str, data_type: &str) -> RedisSourceConfig {
+        toml::from_str(&format!(
+            r#"
+            url = "{}"
+            key = "{}"
+            data_type = "{}"
+            redis_key = "redis_key"
+            "#,
+            REDIS_SERVER, key, data_type
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn redis_source_list() {
+        trace_init();
+
+        let key = format!("test-key-{}", random_string(10));
+        let mut conn = connect().await;
+        let _: usize = conn.rpush(&key, &["one", "two", "three"]).await.unwrap();
+
+        let events = run(make_config(&key, "list"), 3).await;
+
+        for (event, line) in events.iter().zip(&["one", "two", "three"]) {
+            assert_eq!(event.as_log()[&log_schema().message_key()], (*line).into());
+            assert_eq!(event.as_log()[&"redis_key".into()], key.clone().into());
+        }
+    }
+
+    #[tokio::test]
+    async fn redis_source_stream() {
+        trace_init();
+
+        let key = format!("test-key-{}", random_string(10));
+        let config = make_config(&key, "stream");
+        let mut conn = connect().await;
+        create_group(&mut conn, &key, "vector").await.unwrap();
+        let _: String = conn.xadd(&key, "*", &[("message", "one")]).await.unwrap();
+
+        let events = run(config, 1).await;
+        assert_eq!(
+            events[0].as_log()[&log_schema().message_key()],
+            "one".into()
+        );
+
+        // The entry is acknowledged once it has been handed to the pipeline.
+        delay_for(Duration::from_secs(1)).await;
+        let pending: redis::streams::StreamPendingReply =
+            conn.xpending(&key, "vector").await.unwrap();
+        assert_eq!(pending.count(), 0);
+    }
+}
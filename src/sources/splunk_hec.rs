@@ -20,8 +20,11 @@ use serde::{de, Deserialize, Serialize};
 use serde_json::{de::IoRead, json, Deserializer, Value as JsonValue};
 use snafu::Snafu;
 use std::{
+    collections::{HashMap, HashSet},
     io::Read,
     net::{Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 use string_cache::DefaultAtom as Atom;
 use warp::{filters::BoxedFilter, path, reject::Rejection, reply::Response, Filter, Reply};
@@ -32,6 +35,7 @@ lazy_static! {
     pub static ref INDEX: Atom = Atom::from("splunk_index");
     pub static ref SOURCE: Atom = Atom::from("splunk_source");
     pub static ref SOURCETYPE: Atom = Atom::from("splunk_sourcetype");
+    pub static ref HEC_TOKEN: Atom = Atom::from("splunk_hec_token");
 }
 
 /// Accepts HTTP requests.
@@ -43,7 +47,34 @@ pub struct SplunkConfig {
     address: SocketAddr,
     /// Splunk HEC token
     token: Option<String>,
+    /// Splunk HEC tokens, any of which is accepted
+    valid_tokens: Option<Vec<String>>,
+    /// Store the matched token in the `splunk_hec_token` field
+    store_hec_token: bool,
     tls: Option<TlsConfig>,
+    acknowledgements: HecAcknowledgementsConfig,
+}
+
+/// Indexer acknowledgement settings.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct HecAcknowledgementsConfig {
+    enabled: bool,
+    max_number_of_ack_channels: usize,
+    max_pending_acks_per_channel: usize,
+    /// Channels idle for longer are dropped, with their pending acks
+    max_idle_time_secs: u64,
+}
+
+impl Default for HecAcknowledgementsConfig {
+    fn default() -> Self {
+        HecAcknowledgementsConfig {
+            enabled: false,
+            max_number_of_ack_channels: 1_000_000,
+            max_pending_acks_per_channel: 1_000_000,
+            max_idle_time_secs: 300,
+        }
+    }
 }
 
 inventory::submit! {
@@ -65,7 +96,10 @@ impl Default for SplunkConfig {
         SplunkConfig {
             address: default_socket_address(),
             token: None,
+            valid_tokens: None,
+            store_hec_token: false,
             tls: None,
+            acknowledgements: HecAcknowledgementsConfig::default(),
         }
     }
 }
@@ -88,6 +122,7 @@ impl SourceConfig for SplunkConfig {
 
         let event_service = source.event_service(out.clone());
         let raw_service = source.raw_service(out.clone());
+        let ack_service = source.ack_service();
         let health_service = source.health_service(out);
         let options = SplunkSource::options();
 
@@ -105,6 +140,8 @@ impl SourceConfig for SplunkConfig {
                 event_service
                     .or(raw_service)
                     .unify()
+                    .or(ack_service)
+                    .unify()
                     .or(health_service)
                     .unify()
                     .or(options)
@@ -140,20 +177,37 @@ impl SourceConfig for SplunkConfig {
 
 /// Shared data for responding to requests.
 struct SplunkSource {
-    credentials: Option<Bytes>,
+    /// Accepted `Authorization` header values, any is accepted if empty.
+    valid_credentials: Vec<String>,
+    store_hec_token: bool,
+    idx_ack: Option<Arc<IndexerAcknowledgement>>,
 }
 
 impl SplunkSource {
     fn new(config: &SplunkConfig) -> Self {
+        let valid_credentials = config
+            .token
+            .iter()
+            .chain(config.valid_tokens.iter().flatten())
+            .map(|token| format!("Splunk {}", token))
+            .collect();
+
         SplunkSource {
-            credentials: config
-                .token
-                .as_ref()
-                .map(|token| format!("Splunk {}", token).into()),
+            valid_credentials,
+            store_hec_token: config.store_hec_token,
+            idx_ack: if config.acknowledgements.enabled {
+                Some(Arc::new(IndexerAcknowledgement::new(
+                    config.acknowledgements.clone(),
+                )))
+            } else {
+                None
+            },
         }
     }
 
     fn event_service(&self, out: Pipeline) -> BoxedFilter<(Response,)> {
+        let store_hec_token = self.store_hec_token;
+        let idx_ack = self.idx_ack.clone();
         warp::post()
             .and(path!("event").or(path!("event" / "1.0")))
             .and(self.authorization())
@@ -163,27 +217,44 @@ impl SplunkSource {
             .and(warp::body::bytes())
             .and_then(
                 move |_,
-                      _,
+                      token: Option<String>,
                       channel: Option<String>,
                       host: Option<String>,
                       gzip: bool,
                       body: Bytes| {
                     let out = out.clone();
+                    let idx_ack = idx_ack.clone();
+                    let token = token.filter(|_| store_hec_token);
                     async move {
+                        let ack_id = match &idx_ack {
+                            Some(idx_ack) => {
+                                let channel = channel.as_ref().ok_or(ApiError::MissingChannel)?;
+                                Some(idx_ack.get_ack_id(channel)?)
+                            }
+                            None => None,
+                        };
+
                         // Construct event parser
-                        if gzip {
-                            EventStream::new(GzDecoder::new(body.reader()), channel, host)
-                                .forward(out.clone().sink_map_err(|_| ApiError::ServerShutdown))
-                                .map(|_| ())
-                                .compat()
-                                .await
+                        let result = if gzip {
+                            EventStream::new(
+                                GzDecoder::new(body.reader()),
+                                channel.clone(),
+                                host,
+                                token,
+                            )
+                            .forward(out.clone().sink_map_err(|_| ApiError::ServerShutdown))
+                            .map(|_| ())
+                            .compat()
+                            .await
                         } else {
-                            EventStream::new(body.reader(), channel, host)
+                            EventStream::new(body.reader(), channel.clone(), host, token)
                                 .forward(out.clone().sink_map_err(|_| ApiError::ServerShutdown))
                                 .map(|_| ())
                                 .compat()
                                 .await
-                        }
+                        };
+
+                        finish_ack(idx_ack.as_deref(), channel.as_deref(), ack_id, result)
                     }
                 },
             )
@@ -192,6 +263,8 @@ impl SplunkSource {
     }
 
     fn raw_service(&self, out: Pipeline) -> BoxedFilter<(Response,)> {
+        let store_hec_token = self.store_hec_token;
+        let idx_ack = self.idx_ack.clone();
         warp::post()
             .and(path!("raw" / "1.0").or(path!("raw")))
             .and(self.authorization())
@@ -210,15 +283,35 @@ impl SplunkSource {
             .and(self.gzip())
             .and(warp::body::bytes())
             .and_then(
-                move |_, _, channel: String, host: Option<String>, gzip: bool, body: Bytes| {
+                move |_,
+                      token: Option<String>,
+                      channel: String,
+                      host: Option<String>,
+                      gzip: bool,
+                      body: Bytes| {
                     let out = out.clone();
+                    let idx_ack = idx_ack.clone();
+                    let token = token.filter(|_| store_hec_token);
                     async move {
+                        let ack_id = match &idx_ack {
+                            Some(idx_ack) => Some(idx_ack.get_ack_id(&channel)?),
+                            None => None,
+                        };
+
                         // Construct event parser
-                        futures01::stream::once(raw_event(body, gzip, channel, host))
-                            .forward(out.clone().sink_map_err(|_| ApiError::ServerShutdown))
-                            .map(|_| ())
-                            .compat()
-                            .await
+                        let result = futures01::stream::once(raw_event(
+                            body,
+                            gzip,
+                            channel.clone(),
+                            host,
+                            token,
+                        ))
+                        .forward(out.clone().sink_map_err(|_| ApiError::ServerShutdown))
+                        .map(|_| ())
+                        .compat()
+                        .await;
+
+                        finish_ack(idx_ack.as_deref(), Some(&channel), ack_id, result)
                     }
                 },
             )
@@ -226,17 +319,38 @@ impl SplunkSource {
             .boxed()
     }
 
+    fn ack_service(&self) -> BoxedFilter<(Response,)> {
+        let idx_ack = self.idx_ack.clone();
+        warp::post()
+            .and(path!("ack"))
+            .and(self.authorization())
+            .and(warp::header::optional::<String>("x-splunk-request-channel"))
+            .and(warp::body::json())
+            .and_then(
+                move |_, channel: Option<String>, request: HecAckStatusRequest| {
+                    let idx_ack = idx_ack.clone();
+                    async move {
+                        let idx_ack = idx_ack.ok_or(ApiError::AckIsDisabled)?;
+                        let channel = channel.ok_or(ApiError::MissingChannel)?;
+                        let acks = idx_ack.get_acks_status(&channel, &request.acks)?;
+                        Ok::<_, Rejection>(
+                            warp::reply::json(&HecAckStatusResponse { acks }).into_response(),
+                        )
+                    }
+                },
+            )
+            .boxed()
+    }
+
     fn health_service(&self, out: Pipeline) -> BoxedFilter<(Response,)> {
-        let credentials = self.credentials.clone();
+        let valid_credentials = self.valid_credentials.clone();
         let authorize =
             warp::header::optional("Authorization").and_then(move |token: Option<String>| {
-                let credentials = credentials.clone();
+                let valid_credentials = valid_credentials.clone();
                 async move {
-                    match (token, credentials) {
-                        (_, None) => Ok(()),
-                        (Some(token), Some(password)) if token.as_bytes() == password.as_ref() => {
-                            Ok(())
-                        }
+                    match token {
+                        _ if valid_credentials.is_empty() => Ok(()),
+                        Some(token) if valid_credentials.contains(&token) => Ok(()),
                         _ => Err(Rejection::from(ApiError::BadRequest)),
                     }
                 }
@@ -271,7 +385,8 @@ impl SplunkSource {
                 path!("event")
                     .or(path!("event" / "1.0"))
                     .or(path!("raw" / "1.0"))
-                    .or(path!("raw")),
+                    .or(path!("raw"))
+                    .or(path!("ack")),
             )
             .map(|_| warp::reply::with_header(warp::reply(), "Allow", "POST").into_response());
 
@@ -282,20 +397,23 @@ impl SplunkSource {
         post.or(get).unify().boxed()
     }
 
-    /// Authorize request
-    fn authorization(&self) -> BoxedFilter<((),)> {
-        let credentials = self.credentials.clone();
+    /// Authorize request, resolving to the token it was authorized with
+    fn authorization(&self) -> BoxedFilter<(Option<String>,)> {
+        let valid_credentials = self.valid_credentials.clone();
         warp::header::optional("Authorization")
             .and_then(move |token: Option<String>| {
-                let credentials = credentials.clone();
+                let valid_credentials = valid_credentials.clone();
                 async move {
-                    match (token, credentials) {
-                        (_, None) => Ok(()),
-                        (Some(token), Some(password)) if token.as_bytes() == password.as_ref() => {
-                            Ok(())
+                    match token {
+                        Some(token)
+                            if valid_credentials.is_empty()
+                                || valid_credentials.contains(&token) =>
+                        {
+                            Ok(Some(hec_token(token)))
                         }
-                        (Some(_), Some(_)) => Err(Rejection::from(ApiError::InvalidAuthorization)),
-                        (None, Some(_)) => Err(Rejection::from(ApiError::MissingAuthorization)),
+                        None if valid_credentials.is_empty() => Ok(None),
+                        Some(_) => Err(Rejection::from(ApiError::InvalidAuthorization)),
+                        None => Err(Rejection::from(ApiError::MissingAuthorization)),
                     }
                 }
             })
@@ -325,6 +443,8 @@ struct EventStream<R: Read> {
     events: usize,
     /// Optional channel from headers
     channel: Option<Value>,
+    /// Optional token the request was authorized with
+    token: Option<Value>,
     /// Default time
     time: Time,
     /// Remaining extracted default values
@@ -332,11 +452,12 @@ struct EventStream<R: Read> {
 }
 
 impl<R: Read> EventStream<R> {
-    fn new(data: R, channel: Option<String>, host: Option<String>) -> Self {
+    fn new(data: R, channel: Option<String>, host: Option<String>, token: Option<String>) -> Self {
         EventStream {
             data,
             events: 0,
             channel: channel.map(Value::from),
+            token: token.map(Value::from),
             time: Time::Now(Utc::now()),
             extractors: [
                 DefaultExtractor::new_with("host", &log_schema().host_key(), host.map(Value::from)),
@@ -434,6 +555,11 @@ impl<R: Read> Stream for EventStream<R> {
             log.insert(CHANNEL.clone(), guid.clone());
         }
 
+        // Add token
+        if let Some(token) = self.token.as_ref() {
+            log.insert(HEC_TOKEN.clone(), token.clone());
+        }
+
         // Process fields field
         if let Some(JsonValue::Object(object)) = json.get_mut("fields").map(JsonValue::take) {
             for (key, value) in object {
@@ -573,6 +699,7 @@ fn raw_event(
     gzip: bool,
     channel: String,
     host: Option<String>,
+    token: Option<String>,
 ) -> Result<Event, Rejection> {
     // Process gzip
     let message: Value = if gzip {
@@ -604,6 +731,11 @@ fn raw_event(
         log.insert(log_schema().host_key().clone(), host);
     }
 
+    // Add token
+    if let Some(token) = token {
+        log.insert(HEC_TOKEN.clone(), token);
+    }
+
     // Add timestamp
     log.insert(log_schema().timestamp_key().clone(), Utc::now());
 
@@ -617,6 +749,133 @@ fn raw_event(
     Ok(event)
 }
 
+/// Strips the `Splunk` scheme from the `Authorization` header value.
+fn hec_token(authorization: String) -> String {
+    const SCHEME: &str = "Splunk ";
+    if authorization.starts_with(SCHEME) {
+        authorization[SCHEME.len()..].to_owned()
+    } else {
+        authorization
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct HecAckStatusRequest {
+    acks: Vec<u64>,
+}
+
+#[derive(Serialize, Debug)]
+struct HecAckStatusResponse {
+    acks: HashMap<u64, bool>,
+}
+
+/// Tracks the indexer acknowledgements of the channels.
+///
+/// An ack id is handed out for every request on a channel, and is acked
+/// once all of the request's events are pushed into the `Pipeline`.
+struct IndexerAcknowledgement {
+    config: HecAcknowledgementsConfig,
+    channels: Mutex<HashMap<String, Channel>>,
+}
+
+#[derive(Default)]
+struct Channel {
+    next_ack_id: u64,
+    pending: HashSet<u64>,
+    acked: HashSet<u64>,
+    last_used: Option<Instant>,
+}
+
+impl IndexerAcknowledgement {
+    fn new(config: HecAcknowledgementsConfig) -> Self {
+        IndexerAcknowledgement {
+            config,
+            channels: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Hands out the ack id for the next request on the channel.
+    fn get_ack_id(&self, channel_id: &str) -> Result<u64, ApiError> {
+        let mut channels = self.channels.lock().unwrap();
+        let now = Instant::now();
+        self.expire_idle_channels(&mut channels, now);
+
+        if !channels.contains_key(channel_id)
+            && channels.len() >= self.config.max_number_of_ack_channels
+        {
+            return Err(ApiError::ServerBusy);
+        }
+
+        let channel = channels.entry(channel_id.to_owned()).or_default();
+        if channel.pending.len() + channel.acked.len() >= self.config.max_pending_acks_per_channel {
+            return Err(ApiError::ServerBusy);
+        }
+
+        let ack_id = channel.next_ack_id;
+        channel.next_ack_id += 1;
+        channel.pending.insert(ack_id);
+        channel.last_used = Some(now);
+        Ok(ack_id)
+    }
+
+    /// Settles a pending ack id. Undelivered requests are forgotten, so
+    /// their status stays `false` and the client resends them.
+    fn finish(&self, channel_id: &str, ack_id: u64, delivered: bool) {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(channel) = channels.get_mut(channel_id) {
+            if channel.pending.remove(&ack_id) && delivered {
+                channel.acked.insert(ack_id);
+            }
+        }
+    }
+
+    /// Reports the status of the ack ids, forgetting the acked ones.
+    fn get_acks_status(
+        &self,
+        channel_id: &str,
+        ack_ids: &[u64],
+    ) -> Result<HashMap<u64, bool>, ApiError> {
+        let mut channels = self.channels.lock().unwrap();
+        let now = Instant::now();
+        self.expire_idle_channels(&mut channels, now);
+
+        let channel = channels
+            .get_mut(channel_id)
+            .ok_or(ApiError::InvalidChannel)?;
+        channel.last_used = Some(now);
+
+        Ok(ack_ids
+            .iter()
+            .map(|ack_id| (*ack_id, channel.acked.remove(ack_id)))
+            .collect())
+    }
+
+    /// Drops the channels, with their pending acks, that have been idle for
+    /// longer than `max_idle_time_secs`.
+    fn expire_idle_channels(&self, channels: &mut HashMap<String, Channel>, now: Instant) {
+        let max_idle_time = Duration::from_secs(self.config.max_idle_time_secs);
+        channels.retain(|_, channel| {
+            channel
+                .last_used
+                .map_or(false, |last_used| now - last_used < max_idle_time)
+        });
+    }
+}
+
+/// Settles the ack id of the request, if any, with the outcome of pushing
+/// its events into the `Pipeline`.
+fn finish_ack(
+    idx_ack: Option<&IndexerAcknowledgement>,
+    channel: Option<&str>,
+    ack_id: Option<u64>,
+    result: Result<(), Rejection>,
+) -> Result<Option<u64>, Rejection> {
+    if let (Some(idx_ack), Some(channel), Some(ack_id)) = (idx_ack, channel, ack_id) {
+        idx_ack.finish(channel, ack_id, result.is_ok());
+    }
+    result.map(|()| ack_id)
+}
+
 #[derive(Clone, Copy, Debug, Snafu)]
 pub(crate) enum ApiError {
     MissingAuthorization,
@@ -629,6 +888,9 @@ pub(crate) enum ApiError {
     EmptyEventField { event: usize },
     MissingEventField { event: usize },
     BadRequest,
+    AckIsDisabled,
+    InvalidChannel,
+    ServerBusy,
 }
 
 impl From<ApiError> for Rejection {
@@ -664,11 +926,23 @@ mod splunk_response {
             json_to_bytes(json!({"text":"unsupported content encoding"}));
         pub static ref NO_CHANNEL: Bytes =
             json_to_bytes(json!({"text":"Data channel is missing","code":10}));
+        pub static ref INVALID_CHANNEL: Bytes =
+            json_to_bytes(json!({"text":"Invalid data channel","code":11}));
+        pub static ref ACK_IS_DISABLED: Bytes =
+            json_to_bytes(json!({"text":"ACK is disabled","code":14}));
+        pub static ref SERVER_BUSY: Bytes =
+            json_to_bytes(json!({"text":"Server is busy","code":9}));
     }
 }
 
-fn finish_ok(_: ()) -> Response {
-    response_json(StatusCode::OK, splunk_response::SUCCESS.as_ref())
+fn finish_ok(ack_id: Option<u64>) -> Response {
+    match ack_id {
+        Some(ack_id) => response_json(
+            StatusCode::OK,
+            json!({"text":"Success","code":0,"ackId":ack_id}),
+        ),
+        None => response_json(StatusCode::OK, splunk_response::SUCCESS.as_ref()),
+    }
 }
 
 async fn finish_err(rejection: Rejection) -> Result<(Response,), Rejection> {
@@ -706,6 +980,18 @@ async fn finish_err(rejection: Rejection) -> Result<(Response,), Rejection> {
                 event_error("Event field is required", 12, event)
             }
             ApiError::BadRequest => empty_response(StatusCode::BAD_REQUEST),
+            ApiError::AckIsDisabled => response_json(
+                StatusCode::BAD_REQUEST,
+                splunk_response::ACK_IS_DISABLED.as_ref(),
+            ),
+            ApiError::InvalidChannel => response_json(
+                StatusCode::BAD_REQUEST,
+                splunk_response::INVALID_CHANNEL.as_ref(),
+            ),
+            ApiError::ServerBusy => response_json(
+                StatusCode::SERVICE_UNAVAILABLE,
+                splunk_response::SERVER_BUSY.as_ref(),
+            ),
         },))
     } else {
         Err(rejection)
@@ -747,7 +1033,7 @@ fn event_error(text: &str, code: u16, event: usize) -> Response {
 #[cfg(feature = "sinks-splunk_hec")]
 #[cfg(test)]
 mod tests {
-    use super::{parse_timestamp, SplunkConfig};
+    use super::{parse_timestamp, HecAcknowledgementsConfig, IndexerAcknowledgement, SplunkConfig};
     use crate::{
         config::{log_schema, GlobalOptions, SinkConfig, SinkContext, SourceConfig},
         event::Event,
@@ -773,25 +1059,29 @@ mod tests {
     }
 
     async fn source_with(token: Option<String>) -> (mpsc::Receiver<Event>, SocketAddr) {
+        source_with_config(SplunkConfig {
+            token,
+            ..SplunkConfig::default()
+        })
+        .await
+    }
+
+    async fn source_with_config(config: SplunkConfig) -> (mpsc::Receiver<Event>, SocketAddr) {
         let (sender, recv) = Pipeline::new_test();
         let address = next_addr();
         tokio::spawn(async move {
-            SplunkConfig {
-                address,
-                token,
-                tls: None,
-            }
-            .build(
-                "default",
-                &GlobalOptions::default(),
-                ShutdownSignal::noop(),
-                sender,
-            )
-            .await
-            .unwrap()
-            .compat()
-            .await
-            .unwrap()
+            SplunkConfig { address, ..config }
+                .build(
+                    "default",
+                    &GlobalOptions::default(),
+                    ShutdownSignal::noop(),
+                    sender,
+                )
+                .await
+                .unwrap()
+                .compat()
+                .await
+                .unwrap()
         });
         wait_for_tcp(address).await;
         (recv, address)
@@ -847,6 +1137,24 @@ mod tests {
         send_with(address, api, message, TOKEN).await
     }
 
+    async fn send_with_response(
+        address: SocketAddr,
+        api: &str,
+        message: &str,
+        token: &str,
+    ) -> (u16, serde_json::Value) {
+        let response = reqwest::Client::new()
+            .post(&format!("http://{}/{}", address, api))
+            .header("Authorization", format!("Splunk {}", token))
+            .header("x-splunk-request-channel", "guid")
+            .body(message.to_owned())
+            .send()
+            .await
+            .unwrap();
+        let status = response.status().as_u16();
+        (status, response.json().await.unwrap())
+    }
+
     async fn send_with(address: SocketAddr, api: &str, message: &str, token: &str) -> u16 {
         reqwest::Client::new()
             .post(&format!("http://{}/{}", address, api))
@@ -1128,4 +1436,138 @@ mod tests {
 
         assert!(parse_timestamp(-1).is_none());
     }
+
+    #[tokio::test]
+    async fn valid_tokens() {
+        trace_init();
+
+        let (source, address) = source_with_config(SplunkConfig {
+            valid_tokens: Some(vec!["first".to_owned(), "second".to_owned()]),
+            store_hec_token: true,
+            ..SplunkConfig::default()
+        })
+        .await;
+
+        assert_eq!(
+            200,
+            send_with(address, "services/collector/raw", "raw", "second").await
+        );
+        assert_eq!(
+            401,
+            send_with(address, "services/collector/raw", "raw", TOKEN).await
+        );
+
+        let event = collect_n(source, 1).await.unwrap().remove(0);
+        assert_eq!(event.as_log()[&super::HEC_TOKEN], "second".into());
+    }
+
+    #[tokio::test]
+    async fn token_not_stored_by_default() {
+        trace_init();
+
+        let (source, address) = source().await;
+
+        assert_eq!(200, post(address, "services/collector/raw", "raw").await);
+
+        let event = collect_n(source, 1).await.unwrap().remove(0);
+        assert!(event.as_log().get(&super::HEC_TOKEN).is_none());
+    }
+
+    #[tokio::test]
+    async fn acknowledgements() {
+        trace_init();
+
+        let mut config = SplunkConfig {
+            token: Some(TOKEN.to_owned()),
+            ..SplunkConfig::default()
+        };
+        config.acknowledgements.enabled = true;
+        let (source, address) = source_with_config(config).await;
+
+        let (status, body) = send_with_response(
+            address,
+            "services/collector/event",
+            r#"{"event":"first"}"#,
+            TOKEN,
+        )
+        .await;
+        assert_eq!(200, status);
+        assert_eq!(body["ackId"], 0);
+
+        let (status, body) =
+            send_with_response(address, "services/collector/raw", "second", TOKEN).await;
+        assert_eq!(200, status);
+        assert_eq!(body["ackId"], 1);
+
+        collect_n(source, 2).await.unwrap();
+
+        let (status, body) = send_with_response(
+            address,
+            "services/collector/ack",
+            r#"{"acks":[0,1,2]}"#,
+            TOKEN,
+        )
+        .await;
+        assert_eq!(200, status);
+        assert_eq!(
+            body,
+            serde_json::json!({"acks":{"0":true,"1":true,"2":false}})
+        );
+
+        // Acked ids are reported only once.
+        let (_, body) =
+            send_with_response(address, "services/collector/ack", r#"{"acks":[0]}"#, TOKEN).await;
+        assert_eq!(body, serde_json::json!({"acks":{"0":false}}));
+    }
+
+    #[tokio::test]
+    async fn acknowledgements_disabled() {
+        trace_init();
+
+        let (_source, address) = source().await;
+
+        assert_eq!(
+            400,
+            post(address, "services/collector/ack", r#"{"acks":[0]}"#).await
+        );
+    }
+
+    #[test]
+    fn acknowledgement_limits() {
+        let idx_ack = IndexerAcknowledgement::new(HecAcknowledgementsConfig {
+            enabled: true,
+            max_number_of_ack_channels: 1,
+            max_pending_acks_per_channel: 2,
+            max_idle_time_secs: 300,
+        });
+
+        assert_eq!(idx_ack.get_ack_id("a").unwrap(), 0);
+        assert_eq!(idx_ack.get_ack_id("a").unwrap(), 1);
+        assert!(idx_ack.get_ack_id("a").is_err());
+        assert!(idx_ack.get_ack_id("b").is_err());
+        assert!(idx_ack.get_acks_status("b", &[0]).is_err());
+
+        idx_ack.finish("a", 0, true);
+        idx_ack.finish("a", 1, false);
+        let acks = idx_ack.get_acks_status("a", &[0, 1]).unwrap();
+        assert!(acks[&0]);
+        assert!(!acks[&1]);
+        assert_eq!(idx_ack.get_ack_id("a").unwrap(), 2);
+    }
+
+    #[test]
+    fn acknowledgement_idle_channels_expire() {
+        let idx_ack = IndexerAcknowledgement::new(HecAcknowledgementsConfig {
+            enabled: true,
+            max_number_of_ack_channels: 10,
+            max_pending_acks_per_channel: 10,
+            max_idle_time_secs: 0,
+        });
+
+        assert_eq!(idx_ack.get_ack_id("a").unwrap(), 0);
+        idx_ack.finish("a", 0, true);
+        // The channel expires although the channel limit was never reached.
+        assert!(idx_ack.get_acks_status("a", &[0]).is_err());
+        assert_eq!(idx_ack.get_ack_id("a").unwrap(), 0);
+    }
 }
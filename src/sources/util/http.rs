@@ -17,6 +17,7 @@ use warp::{
     filters::BoxedFilter,
     http::{HeaderMap, StatusCode},
     reject::Rejection,
+    reply::{Reply, Response},
     Filter,
 };
 
@@ -32,6 +33,10 @@ impl ErrorMessage {
             message,
         }
     }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
 }
 impl Error for ErrorMessage {}
 impl fmt::Display for ErrorMessage {
@@ -53,6 +58,12 @@ impl warp::reject::Reject for RejectShuttingDown {}
 pub trait HttpSource: Clone + Send + Sync + 'static {
     fn build_event(&self, body: Bytes, header_map: HeaderMap) -> Result<Vec<Event>, ErrorMessage>;
 
+    /// Builds the reply sent once the events of a request have been forwarded.
+    /// Protocols that expect an acknowledgement in the body override this.
+    fn build_response(&self, _header_map: &HeaderMap) -> Response {
+        warp::reply().into_response()
+    }
+
     fn run(
         self,
         address: SocketAddr,
@@ -79,7 +90,7 @@ pub trait HttpSource: Clone + Send + Sync + 'static {
 
                 async move {
                     let body_size = body.len();
-                    match this.build_event(body, headers) {
+                    match this.build_event(body, headers.clone()) {
                         Ok(events) => {
                             emit!(HTTPEventsReceived {
                                 events_count: events.len(),
@@ -94,7 +105,7 @@ pub trait HttpSource: Clone + Send + Sync + 'static {
                                     error!("Tried to send the following event: {:?}", e);
                                     warp::reject::custom(RejectShuttingDown)
                                 })
-                                .map_ok(|_| warp::reply())
+                                .map_ok(|_| this.build_response(&headers))
                                 .await
                         }
                         Err(err) => {
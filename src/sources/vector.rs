@@ -1,21 +1,25 @@
-use super::util::{SocketListenAddr, TcpSource};
+use super::util::{ErrorMessage, HttpSource, SocketListenAddr, TcpSource};
 use crate::{
     config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
     event::proto,
-    internal_events::{VectorEventReceived, VectorProtoDecodeError},
+    internal_events::{VectorBatchDecodeError, VectorEventReceived, VectorProtoDecodeError},
     shutdown::ShutdownSignal,
     tls::{MaybeTlsSettings, TlsConfig},
+    vector_protocol::{self, Compression, ProtocolVersion, V2_PATH},
     Event, Pipeline,
 };
 use bytes::{Bytes, BytesMut};
 use prost::Message;
 use serde::{Deserialize, Serialize};
 use tokio_util::codec::LengthDelimitedCodec;
+use warp::http::{HeaderMap, StatusCode};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct VectorConfig {
     pub address: SocketListenAddr,
+    #[serde(default)]
+    pub version: ProtocolVersion,
     #[serde(default = "default_shutdown_timeout_secs")]
     pub shutdown_timeout_secs: u64,
     tls: Option<TlsConfig>,
@@ -30,6 +34,7 @@ impl VectorConfig {
     pub fn new(address: SocketListenAddr, tls: Option<TlsConfig>) -> Self {
         Self {
             address,
+            version: ProtocolVersion::V1,
             shutdown_timeout_secs: default_shutdown_timeout_secs(),
             tls,
         }
@@ -50,9 +55,22 @@ impl SourceConfig for VectorConfig {
         shutdown: ShutdownSignal,
         out: Pipeline,
     ) -> crate::Result<super::Source> {
-        let vector = VectorSource;
-        let tls = MaybeTlsSettings::from_config(&self.tls, true)?;
-        vector.run(self.address, self.shutdown_timeout_secs, tls, shutdown, out)
+        match self.version {
+            ProtocolVersion::V1 => {
+                let vector = VectorSource;
+                let tls = MaybeTlsSettings::from_config(&self.tls, true)?;
+                vector.run(self.address, self.shutdown_timeout_secs, tls, shutdown, out)
+            }
+            ProtocolVersion::V2 => {
+                let address = match self.address {
+                    SocketListenAddr::SocketAddr(address) => address,
+                    SocketListenAddr::SystemdFd(_) => {
+                        return Err("Protocol version 2 does not support systemd sockets.".into())
+                    }
+                };
+                VectorV2Source.run(address, V2_PATH, &self.tls, out, shutdown)
+            }
+        }
     }
 
     fn output_type(&self) -> DataType {
@@ -90,6 +108,31 @@ impl TcpSource for VectorSource {
     }
 }
 
+/// Receives batches of events over HTTP. The response is only sent once
+/// the events are in the pipeline, which acknowledges them to the sink.
+#[derive(Debug, Clone)]
+struct VectorV2Source;
+
+impl HttpSource for VectorV2Source {
+    fn build_event(&self, body: Bytes, header_map: HeaderMap) -> Result<Vec<Event>, ErrorMessage> {
+        let encoding = header_map
+            .get("Content-Encoding")
+            .map(|value| value.to_str().unwrap_or_default());
+        let compression = Compression::from_content_encoding(encoding).ok_or_else(|| {
+            ErrorMessage::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("Unsupported encoding {:?}", encoding.unwrap_or_default()),
+            )
+        })?;
+
+        // The received events are counted by `HttpSource`.
+        vector_protocol::decode_batch(&body, compression).map_err(|error| {
+            emit!(VectorBatchDecodeError { error: &error });
+            ErrorMessage::new(StatusCode::BAD_REQUEST, error.to_string())
+        })
+    }
+}
+
 #[cfg(feature = "sinks-vector")]
 #[cfg(test)]
 mod test {
@@ -104,6 +147,7 @@ mod test {
         sinks::vector::VectorSinkConfig,
         test_util::{collect_ready, next_addr, wait_for_tcp},
         tls::{TlsConfig, TlsOptions},
+        vector_protocol::{Compression, ProtocolVersion},
         Event, Pipeline,
     };
     use futures::{compat::Future01CompatExt, stream};
@@ -161,10 +205,7 @@ mod test {
         stream_test(
             addr,
             VectorConfig::new(addr.into(), None),
-            VectorSinkConfig {
-                address: format!("localhost:{}", addr.port()),
-                tls: None,
-            },
+            VectorSinkConfig::new(format!("localhost:{}", addr.port())),
         )
         .await;
     }
@@ -186,7 +227,6 @@ mod test {
                 }),
             ),
             VectorSinkConfig {
-                address: format!("localhost:{}", addr.port()),
                 tls: Some(TlsConfig {
                     enabled: Some(true),
                     options: TlsOptions {
@@ -194,6 +234,44 @@ mod test {
                         ..Default::default()
                     },
                 }),
+                ..VectorSinkConfig::new(format!("localhost:{}", addr.port()))
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn it_works_with_vector_sink_v2() {
+        let addr = next_addr();
+        stream_test(
+            addr,
+            VectorConfig {
+                version: ProtocolVersion::V2,
+                ..VectorConfig::new(addr.into(), None)
+            },
+            VectorSinkConfig {
+                version: ProtocolVersion::V2,
+                ..VectorSinkConfig::new(format!("localhost:{}", addr.port()))
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn it_works_with_vector_sink_v2_compressed() {
+        let addr = next_addr();
+        let address = format!("localhost:{}", addr.port());
+        stream_test(
+            addr,
+            VectorConfig {
+                version: ProtocolVersion::V2,
+                ..VectorConfig::new(addr.into(), None)
+            },
+            VectorSinkConfig {
+                address: vec![address.clone(), address].into(),
+                version: ProtocolVersion::V2,
+                compression: Some(Compression::Zstd),
+                ..VectorSinkConfig::new(String::new())
             },
         )
         .await;
@@ -8,6 +8,7 @@ use crate::{
     config::{DataType, SinkContext, TransformContext},
     dns::Resolver,
     event::Event,
+    internal_events::{BufferUsageUpdated, EventIn, EventOut},
     shutdown::SourceShutdownCoordinator,
     Pipeline,
 };
@@ -17,7 +18,10 @@ use futures::{
 };
 use futures01::{sync::mpsc, Future, Stream};
 use std::collections::HashMap;
-use tokio::time::{timeout, Duration};
+use tokio::{
+    select,
+    time::{interval, timeout, Duration},
+};
 
 pub struct Pieces {
     pub inputs: HashMap<String, (buffers::BufferInputCloner, Vec<String>)>,
@@ -70,7 +74,11 @@ pub async fn build_pieces(
         };
 
         let (output, control) = Fanout::new();
-        let pump = rx.forward(output).map(|_| ()).compat();
+        let pump = rx
+            .inspect(|_| emit!(EventOut))
+            .forward(output)
+            .map(|_| ())
+            .compat();
         let pump = Task::new(name, typetag, pump);
 
         // The force_shutdown_tripwire is a Future that when it resolves means that this source
@@ -112,12 +120,16 @@ pub async fn build_pieces(
         };
 
         let (input_tx, input_rx) = futures01::sync::mpsc::channel(100);
-        let input_tx = buffers::BufferInputCloner::Memory(input_tx, buffers::WhenFull::Block);
+        let input_tx = buffers::BufferInputCloner::Memory(input_tx, buffers::WhenFull::Block, None);
 
         let (output, control) = Fanout::new();
 
         let transform = transform
-            .transform_stream(filter_event_type(input_rx, input_type))
+            .transform_stream(filter_event_type(
+                input_rx.inspect(|_| emit!(EventIn)),
+                input_type,
+            ))
+            .inspect(|_| emit!(EventOut))
             .forward(output)
             .map(|_| debug!("Finished"))
             .compat();
@@ -141,7 +153,7 @@ pub async fn build_pieces(
         let input_type = sink.inner.input_type();
 
         let buffer = sink.buffer.build(&config.global.data_dir, &name);
-        let (tx, rx, acker) = match buffer {
+        let (tx, rx, acker, buffer_usage) = match buffer {
             Err(error) => {
                 errors.push(format!("Sink \"{}\": {}", name, error));
                 continue;
@@ -161,12 +173,26 @@ pub async fn build_pieces(
 
         let sink = sink
             .run(
-                filter_event_type(rx, input_type)
+                filter_event_type(rx.inspect(|_| emit!(EventIn)), input_type)
                     .compat()
                     .take_while(|e| future::ready(e.is_ok()))
                     .map(|x| x.unwrap()),
             )
             .inspect(|_| debug!("Finished"));
+        // Report the buffer usage for as long as the sink runs.
+        let report_usage = interval(Duration::from_secs(1)).for_each(move |_| {
+            emit!(BufferUsageUpdated {
+                events: buffer_usage.events(),
+                byte_size: buffer_usage.byte_size(),
+            });
+            future::ready(())
+        });
+        let sink = async move {
+            select! {
+                result = sink => result,
+                () = report_usage => Ok(()),
+            }
+        };
         let task = Task::new(name, typetag, sink);
 
         let healthcheck_task = async move {
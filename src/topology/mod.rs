@@ -13,6 +13,7 @@ mod task;
 use crate::{
     buffers,
     config::{Config, ConfigDiff},
+    internal_events::ComponentFailed,
     shutdown::SourceShutdownCoordinator,
     topology::{builder::Pieces, task::Task},
 };
@@ -445,8 +446,9 @@ impl RunningTopology {
         let task = new_pieces.tasks.remove(name).unwrap();
         let span = error_span!(
             "sink",
-            topology_component_name = %task.name(),
-            topology_component_type = %task.typetag(),
+            component_kind = "sink",
+            component_name = %task.name(),
+            component_type = %task.typetag(),
         );
         let task = handle_errors(task.compat(), self.abort_tx.clone()).instrument(span);
         let spawned = tokio::spawn(task.compat());
@@ -459,8 +461,9 @@ impl RunningTopology {
         let task = new_pieces.tasks.remove(name).unwrap();
         let span = error_span!(
             "transform",
-            topology_component_name = %task.name(),
-            topology_component_type = %task.typetag(),
+            component_kind = "transform",
+            component_name = %task.name(),
+            component_type = %task.typetag(),
         );
         let task = handle_errors(task.compat(), self.abort_tx.clone()).instrument(span);
         let spawned = tokio::spawn(task.compat());
@@ -473,8 +476,9 @@ impl RunningTopology {
         let task = new_pieces.tasks.remove(name).unwrap();
         let span = error_span!(
             "source",
-            topology_component_name = %task.name(),
-            topology_component_type = %task.typetag(),
+            component_kind = "source",
+            component_name = %task.name(),
+            component_type = %task.typetag(),
         );
         let task = handle_errors(task.compat(), self.abort_tx.clone()).instrument(span.clone());
         let spawned = tokio::spawn(task.compat());
@@ -610,7 +614,7 @@ fn handle_errors(
         .map_err(|_| ())
         .flatten()
         .or_else(move |()| {
-            error!("An error occurred that vector couldn't handle.");
+            emit!(ComponentFailed);
             let _ = abort_tx.unbounded_send(());
             Err(())
         })
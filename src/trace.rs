@@ -1,11 +1,25 @@
+use crate::{
+    config::log_schema,
+    event::{LogEvent, Value},
+};
+use chrono::Utc;
 use metrics_tracing_context::MetricsLayer;
+use once_cell::sync::OnceCell;
+use std::{collections::BTreeMap, fmt};
+use tokio::sync::broadcast;
 use tracing::{
     dispatcher::{set_global_default, Dispatch},
-    span::Span,
+    field::{Field, Visit},
+    span::{self, Attributes, Span},
+    Event, Subscriber,
 };
 use tracing_limit::Limit;
 use tracing_log::LogTracer;
-use tracing_subscriber::{layer::SubscriberExt, FmtSubscriber};
+use tracing_subscriber::{
+    layer::{Context, Layer, SubscriberExt},
+    registry::LookupSpan,
+    FmtSubscriber,
+};
 
 pub use tracing_futures::Instrument;
 pub use tracing_tower::{InstrumentableService, InstrumentedService};
@@ -17,6 +31,7 @@ pub fn init(color: bool, json: bool, levels: &str) {
             .json()
             .flatten_event(true)
             .finish()
+            .with(BroadcastLayer)
             .with(Limit::default())
             .with(MetricsLayer::new());
 
@@ -26,6 +41,7 @@ pub fn init(color: bool, json: bool, levels: &str) {
             .with_ansi(color)
             .with_env_filter(levels)
             .finish()
+            .with(BroadcastLayer)
             .with(Limit::default())
             .with(MetricsLayer::new());
 
@@ -39,3 +55,134 @@ pub fn init(color: bool, json: bool, levels: &str) {
 pub fn current_span() -> Span {
     Span::current()
 }
+
+/// Name of the span the events raised while processing the internal logs
+/// are excluded under, to not feed them back into themselves.
+pub const INTERNAL_LOGS_SPAN_NAME: &str = "internal_logs";
+
+/// The capacity of the internal logs channel, the slow subscribers miss
+/// the oldest logs once it's exceeded.
+const INTERNAL_LOGS_CAPACITY: usize = 1000;
+
+static INTERNAL_LOGS_SENDER: OnceCell<broadcast::Sender<LogEvent>> = OnceCell::new();
+
+/// Subscribe to the log events produced from our own `tracing` output.
+pub fn subscribe() -> broadcast::Receiver<LogEvent> {
+    INTERNAL_LOGS_SENDER
+        .get_or_init(|| broadcast::channel(INTERNAL_LOGS_CAPACITY).0)
+        .subscribe()
+}
+
+/// A layer converting the `tracing` events into log events, and
+/// broadcasting them to the internal logs subscribers.
+///
+/// It's installed underneath the rate limiting layer, so the rate limited
+/// events never reach it.
+pub struct BroadcastLayer;
+
+impl<S> Layer<S> for BroadcastLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn new_span(&self, attrs: &Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        // Recorded even before there are subscribers, as the spans created
+        // until then, like the ones of the components, outlive that.
+        if let Some(span) = ctx.span(id) {
+            let mut fields = SpanFields::default();
+            attrs.record(&mut FieldsVisitor(&mut fields.0));
+            span.extensions_mut().insert(fields);
+        }
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(fields) = span.extensions_mut().get_mut::<SpanFields>() {
+                values.record(&mut FieldsVisitor(&mut fields.0));
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let sender = match INTERNAL_LOGS_SENDER.get() {
+            Some(sender) => sender,
+            None => return,
+        };
+
+        let mut log = LogEvent::default();
+
+        // Walk the spans from the root to the current one, so the fields of
+        // the innermost spans take precedence.
+        let mut spans = Vec::new();
+        let mut current = ctx.lookup_current();
+        while let Some(span) = current {
+            if span.name() == INTERNAL_LOGS_SPAN_NAME {
+                return;
+            }
+            current = span.parent();
+            spans.push(span);
+        }
+        for span in spans.iter().rev() {
+            if let Some(fields) = span.extensions().get::<SpanFields>() {
+                for (name, value) in fields.0.iter() {
+                    // The topology spans name the component the event
+                    // was raised by.
+                    if name.starts_with("component_") {
+                        log.insert(format!("vector.{}", name), value.clone());
+                    } else {
+                        log.insert(format!("span.{}", name), value.clone());
+                    }
+                }
+            }
+        }
+
+        let mut fields = BTreeMap::new();
+        event.record(&mut FieldsVisitor(&mut fields));
+        for (name, value) in fields {
+            if name == "message" {
+                log.insert(log_schema().message_key().clone(), value);
+            } else {
+                log.insert(name, value);
+            }
+        }
+
+        let metadata = event.metadata();
+        log.insert(log_schema().timestamp_key().clone(), Utc::now());
+        log.insert("metadata.level", metadata.level().to_string());
+        log.insert("metadata.target", metadata.target());
+        if let Some(module_path) = metadata.module_path() {
+            log.insert("metadata.module_path", module_path);
+        }
+
+        // Fails only if there are no subscribers left, which is fine.
+        let _ = sender.send(log);
+    }
+}
+
+#[derive(Default)]
+struct SpanFields(BTreeMap<String, Value>);
+
+struct FieldsVisitor<'a>(&'a mut BTreeMap<String, Value>);
+
+impl Visit for FieldsVisitor<'_> {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0
+            .insert(field.name().to_owned(), (value as i64).into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_owned(), format!("{:?}", value).into());
+    }
+}
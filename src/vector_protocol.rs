@@ -0,0 +1,179 @@
+//! Parts of the protocol shared by the `vector` sink and source.
+//!
+//! Version 1 streams length-delimited `EventWrapper` messages over TCP,
+//! without any acknowledgement. Version 2 sends batches of events as
+//! `EventBatch` messages in HTTP requests to `V2_PATH`, optionally
+//! compressed. The source only responds once the events are in its
+//! pipeline, so a successful response acknowledges the whole batch.
+
+use crate::{event::proto, Event};
+use flate2::{read::MultiGzDecoder, write::GzEncoder};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::io::{self, Read, Write};
+
+pub const V2_PATH: &str = "v2/events";
+pub const V2_CONTENT_TYPE: &str = "application/x-protobuf";
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Eq, PartialEq, Derivative)]
+#[derivative(Default)]
+pub enum ProtocolVersion {
+    #[derivative(Default)]
+    #[serde(rename = "1")]
+    V1,
+    #[serde(rename = "2")]
+    V2,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Eq, PartialEq, Derivative)]
+#[serde(rename_all = "snake_case")]
+#[derivative(Default)]
+pub enum Compression {
+    #[derivative(Default)]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn content_encoding(self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gzip"),
+            Compression::Zstd => Some("zstd"),
+        }
+    }
+
+    pub fn from_content_encoding(encoding: Option<&str>) -> Option<Self> {
+        match encoding {
+            None | Some("identity") => Some(Compression::None),
+            Some("gzip") => Some(Compression::Gzip),
+            Some("zstd") => Some(Compression::Zstd),
+            Some(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum DecodeError {
+    #[snafu(display("Failed to decompress batch: {}", source))]
+    Decompress { source: io::Error },
+    #[snafu(display("Failed to decode batch: {}", source))]
+    Decode { source: prost::DecodeError },
+}
+
+/// Encodes the events as an `EventBatch` message, compressed with
+/// `compression`.
+pub fn encode_batch(
+    events: Vec<proto::EventWrapper>,
+    compression: Compression,
+) -> io::Result<Vec<u8>> {
+    let batch = proto::EventBatch { events };
+    let mut body = Vec::with_capacity(batch.encoded_len());
+    batch
+        .encode(&mut body)
+        .expect("Vec has enough capacity for the batch");
+
+    match compression {
+        Compression::None => Ok(body),
+        Compression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&body)?;
+            encoder.finish()
+        }
+        Compression::Zstd => zstd::stream::encode_all(&body[..], 0),
+    }
+}
+
+/// Decodes a batch produced by `encode_batch`.
+pub fn decode_batch(body: &[u8], compression: Compression) -> Result<Vec<Event>, DecodeError> {
+    let body = match compression {
+        Compression::None => body.to_vec(),
+        Compression::Gzip => {
+            let mut decoded = Vec::new();
+            MultiGzDecoder::new(body)
+                .read_to_end(&mut decoded)
+                .context(Decompress)?;
+            decoded
+        }
+        Compression::Zstd => zstd::stream::decode_all(body).context(Decompress)?,
+    };
+
+    let batch = proto::EventBatch::decode(&body[..]).context(Decode)?;
+    Ok(batch.events.into_iter().map(Event::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::metric::{Metric, MetricKind, MetricValue};
+
+    fn events() -> Vec<Event> {
+        vec![
+            Event::from("first"),
+            Event::from("second"),
+            Event::Metric(Metric {
+                name: "counter".into(),
+                timestamp: None,
+                tags: None,
+                kind: MetricKind::Incremental,
+                value: MetricValue::Counter { value: 1.0 },
+            }),
+        ]
+    }
+
+    fn round_trip(compression: Compression) {
+        let events = events();
+        let wrapped = events
+            .clone()
+            .into_iter()
+            .map(proto::EventWrapper::from)
+            .collect();
+
+        let body = encode_batch(wrapped, compression).unwrap();
+        let decoded = decode_batch(&body, compression).unwrap();
+
+        assert_eq!(decoded, events);
+    }
+
+    #[test]
+    fn round_trip_uncompressed() {
+        round_trip(Compression::None);
+    }
+
+    #[test]
+    fn round_trip_gzip() {
+        round_trip(Compression::Gzip);
+    }
+
+    #[test]
+    fn round_trip_zstd() {
+        round_trip(Compression::Zstd);
+    }
+
+    #[test]
+    fn rejects_mismatched_compression() {
+        let wrapped = events()
+            .into_iter()
+            .map(proto::EventWrapper::from)
+            .collect();
+        let body = encode_batch(wrapped, Compression::Zstd).unwrap();
+
+        assert!(matches!(
+            decode_batch(&body, Compression::Gzip),
+            Err(DecodeError::Decompress { .. })
+        ));
+    }
+
+    #[test]
+    fn parses_content_encoding() {
+        for compression in &[Compression::None, Compression::Gzip, Compression::Zstd] {
+            assert_eq!(
+                Compression::from_content_encoding(compression.content_encoding()),
+                Some(*compression)
+            );
+        }
+        assert_eq!(Compression::from_content_encoding(Some("br")), None);
+    }
+}